@@ -2,7 +2,7 @@
 //! a C99-compatible syntax, and implements it for the various `llir` constructs.
 //!
 //! In the future, it should define additional shared constructs for C-based backends such as the
-//! x86 and MPPA backends.
+//! x86, MPPA and OpenCL backends.
 
 use std::fmt;
 
@@ -98,6 +98,7 @@ impl C99Display for llir::Operand<'_> {
                     signchar, mantissa, exponent, floating_suffix
                 )
             }
+            Bf16Literal(_) => panic!("Cannot print bf16 values in C99"),
         }
     }
 }
@@ -145,7 +146,8 @@ fn is_infix(binop: llir::BinOp) -> bool {
         | Set { .. }
         | And { .. }
         | Or { .. }
-        | Xor { .. } => true,
+        | Xor { .. }
+        | Shl { .. } => true,
         _ => false,
     }
 }
@@ -180,6 +182,7 @@ impl C99Display for llir::BinOp {
             And { .. } => write!(fmt, "&"),
             Or { .. } => write!(fmt, "|"),
             Xor { .. } => write!(fmt, "^"),
+            Shl { .. } => write!(fmt, "<<"),
         }
     }
 }
@@ -310,6 +313,7 @@ impl C99Display for llir::Instruction<'_> {
                 a = a.c99(),
                 b = b.c99()
             ),
+            Mma(..) => panic!("{}: no C99 equivalent", self),
             Jump(label) => write!(fmt, "goto {label}", label = label.name()),
             Sync => write!(fmt, "__sync()"),
         }
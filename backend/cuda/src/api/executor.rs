@@ -114,4 +114,6 @@ pub enum DeviceAttribute {
     GlobalL1CacheSupported = 79,
     /// Maximum shared memory available per multiprocessor in bytes.
     MaxSharedMemoryPerSmx = 81,
+    /// Maximum number of 32-bit registers available per multiprocessor.
+    MaxRegistersPerSmx = 82,
 }
@@ -0,0 +1,110 @@
+//! Descriptions of GPUs shipped with the crate.
+//!
+//! The descriptions allow computing bounds, running model-guided searches and generating PTX for
+//! GPUs that are not available on the host.  They are derived from the public specifications of
+//! each GPU and from the characterization of GPUs of the same compute capability, following the
+//! formulas of `characterize::gpu`.  Performance parameters are thus approximate; running
+//! `characterize` on the actual GPU gives a more accurate description.
+//!
+//! Descriptions are versioned: a new version is added whenever the parameters of a GPU are
+//! updated, so that results computed with an older description can be reproduced.
+use std::fmt;
+
+use utils::unwrap;
+
+use crate::Gpu;
+
+/// A GPU description shipped with the crate.
+pub struct BundledGpu {
+    /// The name used to select the GPU.
+    pub name: &'static str,
+    /// The version of the description.
+    pub version: u32,
+    /// The description, serialized in JSON.
+    desc: &'static str,
+}
+
+impl BundledGpu {
+    /// Deserializes the description of the GPU.
+    pub fn gpu(&self) -> Gpu {
+        unwrap!(
+            serde_json::from_str(self.desc),
+            "invalid description for {}",
+            self
+        )
+    }
+}
+
+impl fmt::Display for BundledGpu {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}@v{}", self.name, self.version)
+    }
+}
+
+/// The GPU descriptions shipped with the crate.
+static BUNDLED_GPUS: &[BundledGpu] = &[
+    BundledGpu {
+        name: "tesla_k40",
+        version: 1,
+        desc: include_str!("../gpus/tesla_k40.v1.json"),
+    },
+    BundledGpu {
+        name: "gtx_980",
+        version: 1,
+        desc: include_str!("../gpus/gtx_980.v1.json"),
+    },
+    BundledGpu {
+        name: "gtx_1080",
+        version: 1,
+        desc: include_str!("../gpus/gtx_1080.v1.json"),
+    },
+    BundledGpu {
+        name: "tesla_v100",
+        version: 1,
+        desc: include_str!("../gpus/tesla_v100.v1.json"),
+    },
+];
+
+/// Lists the GPU descriptions shipped with the crate.
+pub fn bundled_gpus() -> &'static [BundledGpu] {
+    BUNDLED_GPUS
+}
+
+/// Finds a bundled GPU description.  `name` is either the name of the GPU, in which case the
+/// latest version of its description is returned, or `<name>@v<version>`.
+pub fn find_bundled_gpu(name: &str) -> Option<&'static BundledGpu> {
+    let mut parts = name.splitn(2, "@v");
+    let name = parts.next()?;
+    let version = match parts.next() {
+        Some(version) => Some(version.parse::<u32>().ok()?),
+        None => None,
+    };
+    BUNDLED_GPUS
+        .iter()
+        .filter(|gpu| gpu.name == name && version.map_or(true, |v| gpu.version == v))
+        .max_by_key(|gpu| gpu.version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ensures all the bundled descriptions can be deserialized.
+    #[test]
+    fn deserialize_all() {
+        for bundled in bundled_gpus() {
+            let gpu = bundled.gpu();
+            assert!(gpu.num_smx > 0 && gpu.smx_clock > 0., "{}", bundled);
+        }
+    }
+
+    /// Ensures descriptions are found by name and version.
+    #[test]
+    fn find_by_name() {
+        assert_eq!(find_bundled_gpu("gtx_1080").map(|g| g.version), Some(1));
+        assert!(find_bundled_gpu("gtx_1080@v1").is_some());
+        assert!(find_bundled_gpu("gtx_1080@v0").is_none());
+        assert!(find_bundled_gpu("gtx_1080@vx").is_none());
+        assert!(find_bundled_gpu("unknown").is_none());
+    }
+}
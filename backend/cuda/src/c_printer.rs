@@ -0,0 +1,568 @@
+//! Provides functions to print CUDA C++ code.
+use std::fmt::Write as WriteFmt;
+
+use itertools::Itertools;
+use telamon::codegen::*;
+use telamon::ir::Type;
+use telamon::search_space::{DimKind, Domain};
+use telamon_c::C99Display as _;
+use utils::*;
+
+use crate::printer::PTXDisplay;
+use crate::NameGenerator;
+
+/// Scratch variables holding half-precision literals, which have no C99 syntax.
+const HALF_LITERAL_VARS: [&str; 6] = ["hl0", "hl1", "hl2", "hl3", "hl4", "hl5"];
+
+/// Indicates if values of type `t` are half-precision floats.
+fn is_half(t: Type) -> bool {
+    t == Type::F(16) || t == Type::Bf16
+}
+
+/// Returns the CUDA type of values of type `t`.  Half-precision types come from
+/// `cuda_fp16.h` and `cuda_bf16.h`.
+fn cuda_t(t: Type) -> String {
+    match t {
+        Type::F(16) => "__half".to_string(),
+        Type::Bf16 => "__nv_bfloat16".to_string(),
+        t => t.c99().to_string(),
+    }
+}
+
+/// Returns the CUDA vector type holding `len` values of type `t`, such as `float4`.
+fn vector_t(t: Type, len: u32) -> String {
+    let scalar = match t {
+        Type::F(16) => "half",
+        Type::Bf16 => "nv_bfloat16",
+        Type::I(8) => "char",
+        Type::I(16) => "short",
+        Type::I(32) => "int",
+        Type::I(64) => "longlong",
+        Type::F(32) => "float",
+        Type::F(64) => "double",
+        _ => panic!("no CUDA vector type for {}", t),
+    };
+    format!("{}{}", scalar, len)
+}
+
+/// Returns the name of the components of CUDA vector types.
+fn lanes() -> impl Iterator<Item = &'static str> {
+    ["x", "y", "z", "w"].iter().cloned()
+}
+
+/// Returns a pointer to `t` pointing to the given address.  Shared memory addresses are
+/// offsets in the shared memory window and must be converted to generic addresses.
+fn pointer(t: &str, ss: llir::StateSpace, addr: llir::Address<'_>) -> String {
+    match ss {
+        llir::StateSpace::Global => format!("({}*)({})", t, addr.c99()),
+        llir::StateSpace::Shared => {
+            format!("({}*)__cvta_shared_to_generic({})", t, addr.c99())
+        }
+    }
+}
+
+/// Returns the type of a kernel parameter.
+fn param_t(param: &ParamVal) -> String {
+    match param {
+        &ParamVal::External(ref param, par_type) => {
+            if let Some(elem_t) = param.elem_t {
+                format!("{}*", cuda_t(elem_t))
+            } else {
+                cuda_t(par_type)
+            }
+        }
+        ParamVal::Size(_) => "int32_t".to_string(),
+        ParamVal::GlobalMem(..) => "int8_t*".to_string(),
+    }
+}
+
+/// Prints a `Function` as a CUDA C++ `__global__` kernel, that can be compiled with NVRTC or
+/// `nvcc` instead of going through PTX.
+///
+/// Dimensions are unrolled by Telamon itself, so sequential loops are marked with
+/// `#pragma unroll 1` to prevent the CUDA compiler from unrolling them again.
+#[derive(Default)]
+pub(crate) struct CudaCPrinter {
+    buffer: String,
+}
+
+impl CudaCPrinter {
+    /// Declares all parameters of the function with the appropriate type.
+    fn param_decl(&self, param: &ParamVal) -> String {
+        format!("{} {}", param_t(param), param.key().ident())
+    }
+
+    /// Declares all variables that have been required from the namegen.
+    fn var_decls(&self, namegen: &NameGenerator) -> String {
+        namegen
+            .num_var
+            .iter()
+            .sorted_by_key(|&(&t, _)| NameGenerator::gen_prefix(t))
+            .map(|(&t, &n)| {
+                let prefix = NameGenerator::gen_prefix(t);
+                format!(
+                    "  {} {};\n",
+                    cuda_t(t),
+                    (0..n).format_with(", ", |i, f| f(&format_args!("{}{}", prefix, i)))
+                )
+            })
+            .collect()
+    }
+
+    /// Declares block and thread indexes.
+    fn decl_par_indexes(&mut self, function: &Function, name_map: &NameMap<'_>) {
+        for (dim, dir) in function.block_dims().iter().zip(lanes()) {
+            unwrap!(writeln!(
+                self.buffer,
+                "  {} = blockIdx.{};",
+                name_map.name_index(dim.id()).c99(),
+                dir
+            ));
+        }
+        for (dim, dir) in function.thread_dims().iter().rev().zip(lanes()) {
+            unwrap!(writeln!(
+                self.buffer,
+                "  {} = threadIdx.{};",
+                name_map.name_index(dim.id()).c99(),
+                dir
+            ));
+        }
+    }
+
+    /// Declares a shared memory block.
+    fn shared_mem_decl(&mut self, block: &MemoryRegion, name_map: &NameMap<'_>) {
+        let addr = name_map.name_addr(block.id());
+        unwrap!(writeln!(
+            self.buffer,
+            "  __shared__ __align__(16) int8_t shmem{id}[{size}];
+  {name} = ({t})__cvta_generic_to_shared(shmem{id});",
+            id = block.id().0,
+            name = addr.c99(),
+            t = addr.t().c99(),
+            size = unwrap!(block.alloc_size().as_int())
+        ));
+    }
+
+    /// Prints a load, using `__ldg` for non-coherent loads from global memory.
+    fn load(
+        spec: llir::LoadSpec,
+        d: &llir::RegVec<'_>,
+        addr: llir::Address<'_>,
+    ) -> String {
+        let vector_factor = spec.vector_factor().get();
+        let t = if vector_factor > 1 {
+            vector_t(spec.t(), vector_factor)
+        } else {
+            cuda_t(spec.t())
+        };
+        let ptr = pointer(&t, spec.state_space(), addr);
+        let value = match (spec.state_space(), spec.cache_operator()) {
+            (llir::StateSpace::Global, llir::LoadCacheOperator::CacheAllAndTexture) => {
+                format!("__ldg({})", ptr)
+            }
+            _ => format!("*{}", ptr),
+        };
+        match d {
+            llir::ScalarOrVector::Scalar(d) => format!("{} = {};", d.c99(), value),
+            llir::ScalarOrVector::Vector(regs) => format!(
+                "{{ {} v = {}; {} }}",
+                t,
+                value,
+                regs.iter()
+                    .zip(lanes())
+                    .format_with(" ", |(reg, lane), f| f(&format_args!(
+                        "{} = v.{};",
+                        reg.c99(),
+                        lane
+                    )))
+            ),
+        }
+    }
+
+    /// Prints a store.
+    fn store(
+        spec: llir::StoreSpec,
+        addr: llir::Address<'_>,
+        val: &llir::OpVec<'_>,
+    ) -> String {
+        match val {
+            llir::ScalarOrVector::Scalar(val) => format!(
+                "*{} = {};",
+                pointer(&cuda_t(spec.t()), spec.state_space(), addr),
+                val.c99()
+            ),
+            llir::ScalarOrVector::Vector(vals) => {
+                let t = vector_t(spec.t(), spec.vector_factor().get());
+                format!(
+                    "*{} = make_{}({});",
+                    pointer(&t, spec.state_space(), addr),
+                    t,
+                    vals.iter().map(|val| val.c99()).format(", ")
+                )
+            }
+        }
+    }
+
+    /// Moves the half-precision literal operands of an instruction into scratch variables,
+    /// and returns the declarations of the variables.
+    fn materialize_half_literals(inst: &mut llir::Instruction<'_>) -> Vec<String> {
+        let operands: &mut [llir::OpVec<'_>] = match inst {
+            llir::Instruction::Unary(_, _, ops) => ops,
+            llir::Instruction::Binary(_, _, ops) => ops,
+            llir::Instruction::Ternary(_, _, ops) => ops,
+            llir::Instruction::Store(_, _, ops) => ops,
+            llir::Instruction::Mma(_, _, ops) => ops,
+            _ => return vec![],
+        };
+        let mut decls = vec![];
+        for operand in operands {
+            let scalars = match operand {
+                llir::ScalarOrVector::Scalar(scalar) => std::slice::from_mut(scalar),
+                llir::ScalarOrVector::Vector(scalars) => &mut scalars[..],
+            };
+            for scalar in scalars {
+                let init = match scalar {
+                    llir::Operand::FloatLiteral(val, 16) => format!(
+                        "__float2half({})",
+                        llir::Operand::FloatLiteral(val.clone(), 32).c99()
+                    ),
+                    llir::Operand::Bf16Literal(bits) => {
+                        format!("__ushort_as_bfloat16({:#06x})", bits)
+                    }
+                    _ => continue,
+                };
+                let var = llir::Register::new(HALF_LITERAL_VARS[decls.len()], scalar.t());
+                decls.push(format!("{} {} = {};", cuda_t(var.t()), var.c99(), init));
+                *scalar = var.into();
+            }
+        }
+        decls
+    }
+
+    /// Prints packed half-precision arithmetic with the `half2` intrinsics.
+    fn half2(op: &str, d: &[llir::Register<'_>], args: &[&llir::OpVec<'_>]) -> String {
+        let t = vector_t(d[0].t(), 2);
+        let args = args.iter().format_with(", ", |arg, f| match arg {
+            llir::ScalarOrVector::Vector(arg) => f(&format_args!(
+                "{}({})",
+                t,
+                arg.iter().map(|arg| arg.c99()).format(", ")
+            )),
+            llir::ScalarOrVector::Scalar(arg) => panic!("{}: expected a vector", arg),
+        });
+        format!(
+            "{{ {} v = {}({}); {} }}",
+            t,
+            op,
+            args,
+            d.iter()
+                .zip(lanes())
+                .format_with(" ", |(reg, lane), f| f(&format_args!(
+                    "{} = v.{};",
+                    reg.c99(),
+                    lane
+                )))
+        )
+    }
+
+    /// Prints a warp-level matrix multiply-accumulate as inline PTX.  The `a` and `b`
+    /// fragments are packed by pairs into 32-bit registers.
+    fn mma(
+        spec: llir::MmaSpec,
+        d: &llir::RegVec<'_>,
+        [a, b, c]: &[llir::OpVec<'_>; 3],
+    ) -> String {
+        let d = match d {
+            llir::ScalarOrVector::Vector(d) => d.iter().map(|d| d.c99()).collect_vec(),
+            llir::ScalarOrVector::Scalar(d) => vec![d.c99()],
+        };
+        let scalars = |ops: &llir::OpVec<'_>| match ops {
+            llir::ScalarOrVector::Vector(ops) => {
+                ops.iter().map(|op| op.c99().to_string()).collect_vec()
+            }
+            llir::ScalarOrVector::Scalar(op) => vec![op.c99().to_string()],
+        };
+        let pack = |ops| {
+            scalars(ops)
+                .into_iter()
+                .tuples()
+                .map(|(lo, hi)| format!("__pack_half2({}, {})", lo, hi))
+                .collect_vec()
+        };
+        let (a, b, c) = (pack(a), pack(b), scalars(c));
+        // Operands are numbered in order, starting with the outputs.
+        let mut num_operands = 0;
+        let mut operands = |len: usize| {
+            num_operands += len;
+            (num_operands - len..num_operands)
+                .format_with(", ", |i, f| f(&format_args!("%{}", i)))
+                .to_string()
+        };
+        let code = format!(
+            "{} {{{}}}, {{{}}}, {{{}}}, {{{}}};",
+            spec.ptx(),
+            operands(d.len()),
+            operands(a.len()),
+            operands(b.len()),
+            operands(c.len())
+        );
+        format!(
+            "asm volatile(\"{}\" : {} : {}, {});",
+            code,
+            d.iter()
+                .format_with(", ", |d, f| f(&format_args!("\"=f\"({})", d))),
+            a.iter()
+                .chain(&b)
+                .format_with(", ", |ab, f| f(&format_args!("\"r\"({})", ab))),
+            c.iter()
+                .format_with(", ", |c, f| f(&format_args!("\"f\"({})", c))),
+        )
+    }
+
+    /// Prints a `Function`.
+    pub fn function(&mut self, function: &Function) -> String {
+        let mut namegen = NameGenerator::default();
+        let interner = Interner::default();
+        let name_map = &mut NameMap::new(&interner, function, &mut namegen);
+        // INDEX LOADS
+        self.decl_par_indexes(function, name_map);
+        // LOAD PARAM
+        for val in function.device_code_args() {
+            let var_name = name_map.name_param_val(val.key());
+            unwrap!(writeln!(
+                self.buffer,
+                "  {var_name} = {cast}{name}; // {param}",
+                cast = if val.elem_t().is_some() {
+                    format!("({})", var_name.t().c99())
+                } else {
+                    "".to_string()
+                },
+                var_name = var_name.c99(),
+                name = val.key().ident(),
+                param = val.key(),
+            ));
+        }
+        // MEM DECL
+        for block in function.mem_blocks() {
+            match block.alloc_scheme() {
+                AllocationScheme::Shared => self.shared_mem_decl(block, name_map),
+                AllocationScheme::PrivatisedGlobal => {
+                    Printer::new(self, name_map).privatise_global_block(block, function)
+                }
+                AllocationScheme::Global => (),
+            }
+        }
+        // Compute size casts
+        for dim in function.dimensions() {
+            if !dim.kind().intersects(DimKind::UNROLL | DimKind::LOOP) {
+                continue;
+            }
+            for level in dim.induction_levels() {
+                if let Some((_, ref incr)) = level.increment {
+                    let reg = name_map.declare_size_cast(incr, level.t());
+                    if let Some(reg) = reg {
+                        let old_name = name_map.name_size(incr, Type::I(32));
+                        self.print_inst(
+                            llir::Instruction::cast(level.t(), reg, old_name)
+                                .unwrap()
+                                .into(),
+                        );
+                    }
+                }
+            }
+        }
+        // INIT
+        let ind_levels = function.init_induction_levels().iter().chain(
+            function
+                .block_dims()
+                .iter()
+                .flat_map(|d| d.induction_levels()),
+        );
+        let mut insts = InstBuffer::default();
+        for level in ind_levels {
+            Printer::new(&mut insts, name_map).parallel_induction_level(level);
+        }
+        // BODY
+        Printer::new(&mut insts, name_map).cfg(function, function.cfg());
+        insts.optimize();
+        insts.print(self);
+        let mut body = self.var_decls(&namegen);
+        body.push_str(&self.buffer);
+        format!(
+            include_str!("template/kernel.cu"),
+            name = function.name(),
+            num_thread = function.num_threads(),
+            params = function
+                .device_code_args()
+                .format_with("", |v, f| f(&format_args!("\n    {},", self.param_decl(v))))
+                .to_string()
+                .trim_end_matches(','),
+            body = body,
+        )
+    }
+}
+
+impl<'a> InstPrinter<'a> for CudaCPrinter {
+    fn print_label(&mut self, label: llir::Label<'a>) {
+        unwrap!(writeln!(self.buffer, "{}", label.c99()))
+    }
+
+    fn print_inst(&mut self, mut inst: llir::PredicatedInstruction<'a>) {
+        use llir::ScalarOrVector::Vector;
+
+        let literals = Self::materialize_half_literals(&mut inst.instruction);
+        // Memory accesses may be vectorized, barriers are CUDA builtins and half-precision
+        // instructions need CUDA types and intrinsics, all other instructions are printed as
+        // C99.
+        let op = match &inst.instruction {
+            llir::Instruction::Load(spec, d, a) => Self::load(*spec, d, *a),
+            llir::Instruction::Store(spec, a, [b]) => Self::store(*spec, *a, b),
+            llir::Instruction::Sync => "__syncthreads();".to_string(),
+            llir::Instruction::Unary(llir::UnOp::Cast { src_t, dst_t }, d, [a])
+                if is_half(*src_t) || is_half(*dst_t) =>
+            {
+                format!("{} = ({})({});", d.c99(), cuda_t(*dst_t), a.c99())
+            }
+            llir::Instruction::Binary(op, Vector(d), [a, b]) => {
+                let op = match op {
+                    llir::BinOp::FAdd { .. } => "__hadd2",
+                    llir::BinOp::FSub { .. } => "__hsub2",
+                    llir::BinOp::FMul { .. } => "__hmul2",
+                    llir::BinOp::FMax { .. } => "__hmax2",
+                    llir::BinOp::FMin { .. } => "__hmin2",
+                    _ => panic!("{}: no packed CUDA equivalent", inst.instruction),
+                };
+                Self::half2(op, d, &[a, b])
+            }
+            llir::Instruction::Ternary(
+                llir::TernOp::FFma { .. },
+                Vector(d),
+                [a, b, c],
+            ) => Self::half2("__hfma2", d, &[a, b, c]),
+            llir::Instruction::Mma(spec, d, ops) => Self::mma(*spec, d, ops),
+            _ => inst.instruction.c99().to_string() + ";",
+        };
+        let op = match inst.predicate {
+            Some(predicate) => format!("if ({}) {}", predicate.c99(), op),
+            None => op,
+        };
+        if literals.is_empty() {
+            unwrap!(writeln!(self.buffer, "  {}", op));
+        } else {
+            unwrap!(writeln!(
+                self.buffer,
+                "  {{ {} {} }}",
+                literals.join(" "),
+                op
+            ));
+        }
+    }
+
+    fn print_loop_begin(&mut self, _: llir::Label<'a>) {
+        unwrap!(writeln!(self.buffer, "  #pragma unroll 1\n  do {{"));
+    }
+
+    fn print_loop_end(&mut self, _: llir::Label<'a>, cond: llir::Register<'a>) {
+        unwrap!(writeln!(self.buffer, "  }} while ({});", cond.c99()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{find_bundled_gpu, Gpu, ModelContext};
+    use telamon::device::{Bf16, Context, F16};
+    use telamon::search_space::{Action, InstFlag, SearchSpace};
+    use telamon::{helper, ir};
+
+    /// Prints the first implementation of a search space.
+    fn print_first(context: &ModelContext, space: SearchSpace) -> String {
+        telamon_kernels::print_first(context, space, |function| {
+            CudaCPrinter::default().function(function)
+        })
+    }
+
+    /// Ensures parallel dimensions are mapped to CUDA indexes and that sequential loops are
+    /// not unrolled by the CUDA compiler.
+    #[test]
+    fn loops_and_indexes() {
+        let context = ModelContext::new(unwrap!(find_bundled_gpu("gtx_1080")).gpu());
+        let signature = ir::Signature::new("loops");
+        let mut builder = helper::Builder::new(signature.into(), context.device());
+        let d0 = builder.open_dim_ex(builder.cst_size(16), DimKind::BLOCK);
+        let d1 = builder.open_dim_ex(builder.cst_size(32), DimKind::THREAD);
+        let d2 = builder.open_dim_ex(builder.cst_size(8), DimKind::LOOP);
+        builder.mov(&d0);
+        builder.mov(&d1);
+        builder.mov(&d2);
+        let code = print_first(&context, builder.get());
+        assert!(
+            code.contains("extern \"C\" __global__ void __launch_bounds__(32) loops()")
+        );
+        assert!(code.contains("= blockIdx.x;"));
+        assert!(code.contains("= threadIdx.x;"));
+        assert!(code.contains("#pragma unroll 1\n  do {"));
+        assert!(code.contains("} while ("));
+        assert!(!code.contains("goto"));
+    }
+
+    /// Ensures vectorized non-coherent loads use `__ldg` on CUDA vector types.
+    #[test]
+    fn vector_accesses() {
+        let mut context = ModelContext::new(unwrap!(find_bundled_gpu("gtx_1080")).gpu());
+        let signature = {
+            let mut builder = helper::SignatureBuilder::new("vector", &mut context);
+            builder.array::<f32>("x", 4);
+            builder.array::<f32>("y", 4);
+            builder.get()
+        };
+        let mut builder = helper::Builder::new(signature.into(), context.device());
+        // Vector dimensions can only contain a single instruction.
+        let d0 = builder.open_dim_ex(builder.cst_size(4), DimKind::VECTOR);
+        let (addr, pattern) = builder.tensor_access(&"x", None, Type::F(32), &[&d0]);
+        let ld = builder.ld_ex(Type::F(32), &addr, pattern, InstFlag::CACHE_READ_ONLY);
+        let d1 = builder.open_mapped_dim(&d0);
+        builder.action(Action::DimKind(d1[0], DimKind::VECTOR));
+        let (addr, pattern) = builder.tensor_access(&"y", None, Type::F(32), &[&d1]);
+        builder.st(&addr, &ld, pattern);
+        let code = print_first(&context, builder.get());
+        assert!(code.contains("__ldg((float4*)("), "{}", code);
+        assert!(code.contains("= make_float4("), "{}", code);
+    }
+
+    /// Ensures half-precision values use the CUDA types and that their literals are
+    /// converted from single-precision ones.
+    #[test]
+    fn half_arithmetic() {
+        let gpu = unwrap!(find_bundled_gpu("tesla_v100")).gpu();
+        let mut context = ModelContext::new(Gpu {
+            sm_major: 8,
+            sm_minor: 0,
+            ..gpu
+        });
+        let signature = {
+            let mut builder = helper::SignatureBuilder::new("half", &mut context);
+            builder.array::<F16>("x", 1);
+            builder.array::<Bf16>("y", 1);
+            builder.get()
+        };
+        let mut builder = helper::Builder::new(signature.into(), context.device());
+        let (x_addr, x_pattern) = builder.tensor_access(&"x", None, Type::F(16), &[]);
+        let x = builder.ld(Type::F(16), &x_addr, x_pattern.clone());
+        let x = builder.add(&x, &F16::from_f32(1.));
+        builder.st(&x_addr, &x, x_pattern);
+        let (y_addr, y_pattern) = builder.tensor_access(&"y", None, Type::Bf16, &[]);
+        let y = builder.ld(Type::Bf16, &y_addr, y_pattern.clone());
+        let y = builder.mad(&y, &y, &Bf16::from_f32(2.));
+        builder.st(&y_addr, &y, y_pattern);
+        let code = print_first(&context, builder.get());
+        assert!(code.contains("__half* x"), "{}", code);
+        assert!(code.contains("__nv_bfloat16 hb0"), "{}", code);
+        assert!(code.contains("__half hl0 = __float2half(0x"), "{}", code);
+        assert!(
+            code.contains("__nv_bfloat16 hl0 = __ushort_as_bfloat16(0x4000)"),
+            "{}",
+            code
+        );
+    }
+}
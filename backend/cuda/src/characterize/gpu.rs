@@ -66,6 +66,8 @@ pub fn functional_desc(executor: &Executor) -> Gpu {
         shared_bank_stride: shared_bank_stride(sm_major, sm_minor),
         num_smx: executor.device_attribute(SmxCount) as u32,
         max_block_per_smx: block_per_smx(sm_major, sm_minor),
+        registers_per_smx: executor.device_attribute(MaxRegistersPerSmx) as u32,
+        max_registers_per_thread: max_registers_per_thread(sm_major, sm_minor),
         smx_clock: f64::from(executor.device_attribute(ClockRate)) / 1.0E+6,
 
         thread_rates: EMPTY_INST_DESC,
@@ -95,6 +97,10 @@ pub fn functional_desc(executor: &Executor) -> Gpu {
         max_i32_inst: EMPTY_INST_DESC,
         max_i64_inst: EMPTY_INST_DESC,
         exp_f32_inst: EMPTY_INST_DESC,
+        add_f16_inst: EMPTY_INST_DESC,
+        mul_f16_inst: EMPTY_INST_DESC,
+        mad_f16_inst: EMPTY_INST_DESC,
+        mma_f16_inst: EMPTY_INST_DESC,
         syncthread_inst: EMPTY_INST_DESC,
         loop_init_overhead: EMPTY_INST_DESC,
         loop_iter_overhead: EMPTY_INST_DESC,
@@ -148,6 +154,16 @@ fn block_per_smx(sm_major: i32, sm_minor: i32) -> u32 {
     }
 }
 
+/// Returns the maximum number of 32-bit registers per thread.
+/// From line "Maximum number of 32-bit registers per thread" on Table 14.
+fn max_registers_per_thread(sm_major: i32, sm_minor: i32) -> u32 {
+    match (sm_major, sm_minor) {
+        (2, _) | (3, 0) => 63,
+        (3, _) | (5, _) | (6, _) | (7, _) | (8, _) | (9, _) => 255,
+        _ => panic!("Unkown compute capability: {}.{}", sm_major, sm_minor),
+    }
+}
+
 /// Returns the size of the L1 cache.
 /// From the "Architecture" subsection of each compute capability.
 fn l1_cache_size(sm_major: i32, sm_minor: i32) -> u32 {
@@ -364,6 +380,14 @@ pub fn performance_desc(executor: &Executor, gpu: &mut Gpu) {
     gpu.max_i32_inst = instruction::max_i32(gpu, executor);
     gpu.max_i64_inst = instruction::max_i64(gpu, executor);
     gpu.exp_f32_inst = instruction::exp_f32(gpu, executor);
+    // TODO(model): benchmark half-precision instructions.
+    gpu.add_f16_inst = gpu.add_f32_inst;
+    gpu.mul_f16_inst = gpu.mul_f32_inst;
+    gpu.mad_f16_inst = gpu.mad_f32_inst;
+    gpu.mma_f16_inst = InstDesc {
+        issue: 1.0,
+        ..EMPTY_INST_DESC
+    };
     gpu.mul_wide_inst = gpu.mul_i32_inst; // TODO(model): benchmark mul wide.
                                           // Compute memory accesses overhead.
     gpu.load_l2_latency = instruction::load_l2(gpu, executor);
@@ -5,16 +5,16 @@ use fxhash::FxHashMap;
 use log::warn;
 use serde::{Deserialize, Serialize};
 
-use telamon::codegen::Function;
+use telamon::codegen::{Function, ResourceReport};
 use telamon::device::{self, Device};
 use telamon::ir::{self, Operator, Type};
 use telamon::model::{self, HwPressure};
-use telamon::search_space::{DimKind, Domain, InstFlag, MemSpace, SearchSpace};
+use telamon::search_space::{self, DimKind, Domain, InstFlag, MemSpace, SearchSpace};
 
 #[cfg(feature = "real_gpu")]
 use crate::characterize;
 use crate::mem_model::{self, MemInfo};
-use crate::{printer::CudaPrinter, Executor};
+use crate::{c_printer::CudaCPrinter, printer::CudaPrinter, Executor};
 
 // FIXME: fix performance model
 // - l1_lines constraint for stores ?
@@ -125,6 +125,12 @@ pub struct Gpu {
     pub num_smx: u32,
     /// Maximum number of block per SMX.
     pub max_block_per_smx: u32,
+    /// The number of 32-bit registers in an SMX.
+    #[serde(default = "default_registers_per_smx")]
+    pub registers_per_smx: u32,
+    /// The maximal number of 32-bit registers a thread can use.
+    #[serde(default = "default_max_registers_per_thread")]
+    pub max_registers_per_thread: u32,
     /// The clock of an SMX, in GHz.
     pub smx_clock: f64,
 
@@ -159,6 +165,18 @@ pub struct Gpu {
     pub max_i32_inst: InstDesc,
     pub max_i64_inst: InstDesc,
     pub exp_f32_inst: InstDesc,
+    // Half-precision instructions, which also describe their `bf16` and packed `f16x2`
+    // variants.  They default to zero for descriptions that predate them, which keeps
+    // the model a lower bound.
+    #[serde(default)]
+    pub add_f16_inst: InstDesc,
+    #[serde(default)]
+    pub mul_f16_inst: InstDesc,
+    #[serde(default)]
+    pub mad_f16_inst: InstDesc,
+    /// Warp-level `m16n8k8` matrix multiply-accumulate, per thread.
+    #[serde(default)]
+    pub mma_f16_inst: InstDesc,
     pub syncthread_inst: InstDesc,
 
     /// Overhead for entring the loop.
@@ -169,6 +187,23 @@ pub struct Gpu {
     pub loop_end_latency: f64,
 }
 
+/// The number of registers per SMX on compute capabilities 3.x to 7.x, used for descriptions that
+/// predate the field.
+fn default_registers_per_smx() -> u32 {
+    65536
+}
+
+/// The maximal number of registers per thread on compute capabilities 3.5 to 7.x, used for
+/// descriptions that predate the field.
+fn default_max_registers_per_thread() -> u32 {
+    255
+}
+
+/// Indicates if values of type `t` are half-precision floats.
+fn is_half(t: Option<Type>) -> bool {
+    t == Some(Type::F(16)) || t == Some(Type::Bf16)
+}
+
 impl Gpu {
     /// Returns the GPU model corresponding to `name.
     #[cfg(feature = "real_gpu")]
@@ -182,6 +217,13 @@ impl Gpu {
         match *executor {}
     }
 
+    /// Prints a `Function` as a CUDA C++ kernel, which can be compiled with NVRTC instead of
+    /// the PTX produced by `Device::print`.  Host code is not generated.
+    pub fn print_cuda_c(&self, fun: &Function, out: &mut dyn Write) {
+        let code = CudaCPrinter::default().function(fun);
+        write!(out, "{}", code).unwrap();
+    }
+
     /// Creates a dummy GPU, to use for tests and benchmarks without evaluation.
     pub fn dummy() -> Self {
         Gpu {
@@ -203,6 +245,8 @@ impl Gpu {
             shared_bank_stride: 8,
             num_smx: 4,
             max_block_per_smx: 16,
+            registers_per_smx: 65536,
+            max_registers_per_thread: 255,
 
             smx_clock: -1.,
             load_l2_latency: -1.,
@@ -236,6 +280,10 @@ impl Gpu {
             max_i32_inst: InstDesc::default(),
             max_i64_inst: InstDesc::default(),
             exp_f32_inst: InstDesc::default(),
+            add_f16_inst: InstDesc::default(),
+            mul_f16_inst: InstDesc::default(),
+            mad_f16_inst: InstDesc::default(),
+            mma_f16_inst: InstDesc::default(),
             syncthread_inst: InstDesc::default(),
             loop_init_overhead: InstDesc::default(),
             loop_iter_overhead: InstDesc::default(),
@@ -315,6 +363,7 @@ impl Gpu {
         &self,
         space: &SearchSpace,
         dim_sizes: &FxHashMap<ir::DimId, model::size::Range>,
+        nesting: Option<&model::Nesting>,
         inst: &ir::Instruction,
         ctx: &dyn device::Context,
     ) -> HwPressure {
@@ -363,14 +412,29 @@ impl Gpu {
             (&BinOp(ir::BinOp::Max, ..), Some(Type::F(64))) => self.max_f64_inst.into(),
             (&BinOp(ir::BinOp::Max, ..), Some(Type::I(32))) => self.max_i32_inst.into(),
             (&BinOp(ir::BinOp::Max, ..), Some(Type::I(64))) => self.max_i64_inst.into(),
+            (&BinOp(ir::BinOp::Add, ..), Some(Type::F(16)))
+            | (&BinOp(ir::BinOp::Sub, ..), Some(Type::F(16)))
+            | (&BinOp(ir::BinOp::Max, ..), Some(Type::F(16)))
+            | (&BinOp(ir::BinOp::Add, ..), Some(Type::Bf16))
+            | (&BinOp(ir::BinOp::Sub, ..), Some(Type::Bf16))
+            | (&BinOp(ir::BinOp::Max, ..), Some(Type::Bf16)) => self.add_f16_inst.into(),
+            (&Mul(..), Some(Type::F(16))) | (&Mul(..), Some(Type::Bf16)) => {
+                self.mul_f16_inst.into()
+            }
+            (&Mad(..), Some(Type::F(16))) | (&Mad(..), Some(Type::Bf16)) => {
+                self.mad_f16_inst.into()
+            }
+            (&MmaSync(..), _) => self.mma_f16_inst.into(),
             (&Ld(..), _) | (&TmpLd(..), _) => {
                 let flag = space.domain().get_inst_flag(inst.id());
-                let mem_info = mem_model::analyse(space, self, inst, dim_sizes, ctx);
+                let mem_info =
+                    mem_model::analyse(space, self, inst, dim_sizes, nesting, ctx);
                 self.load_desc(&mem_info, flag).into()
             }
             (&St(..), _) | (&TmpSt(..), _) => {
                 let flag = space.domain().get_inst_flag(inst.id());
-                let mem_info = mem_model::analyse(space, self, inst, dim_sizes, ctx);
+                let mem_info =
+                    mem_model::analyse(space, self, inst, dim_sizes, nesting, ctx);
                 self.store_desc(&mem_info, flag).into()
             }
             (&UnaryOp(ir::UnaryOp::Exp(..), ..), Some(Type::F(32))) => {
@@ -382,6 +446,27 @@ impl Gpu {
         }
     }
 
+    /// Returns the share of a divergent branch around a region of side-effects attributed to
+    /// each of its instructions. The branch is issued once for the whole region and delays
+    /// the threads that take it. As a region may hold every side-effect of the function, each
+    /// instruction is only charged for a fraction of the branch so the model stays a lower
+    /// bound.
+    fn branch_share(&self, space: &SearchSpace) -> HwPressure {
+        let num_side_effects = space
+            .ir_instance()
+            .insts()
+            .filter(|inst| inst.has_side_effects())
+            .count();
+        let mut pressure: HwPressure = InstDesc {
+            issue: 1.0,
+            latency: self.loop_end_latency,
+            ..InstDesc::default()
+        }
+        .into();
+        pressure.repeat_sequential(1.0 / num_side_effects as f64);
+        pressure
+    }
+
     /// Computes the number of blocks that can fit in an smx.
     pub fn blocks_per_smx(&self, space: &SearchSpace) -> u32 {
         let mut block_per_smx = self.max_block_per_smx;
@@ -394,6 +479,13 @@ impl Gpu {
                 self.shared_mem_per_smx / shared_mem_used,
             );
         }
+        // Registers are allocated by chunks, which we ignore to keep a lower bound. Blocks using
+        // more registers than available fail to launch, which is reported at evaluation.
+        let num_registers = search_space::register_pressure(space) * num_thread;
+        if num_registers != 0 {
+            let max_blocks = std::cmp::max(self.registers_per_smx / num_registers, 1);
+            min_assign(&mut block_per_smx, max_blocks);
+        }
         assert!(
             block_per_smx > 0,
             "not enough resources per block: shared mem used = {}, num threads = {}",
@@ -434,6 +526,10 @@ impl device::Device for Gpu {
     fn check_type(&self, t: Type) -> Result<(), ir::TypeError> {
         match t {
             Type::I(i) | Type::F(i) if i == 32 || i == 64 => Ok(()),
+            // Half-precision arithmetic requires compute capability 5.3 for `f16` and 8.0
+            // for `bf16`.
+            Type::F(16) if (self.sm_major, self.sm_minor) >= (5, 3) => Ok(()),
+            Type::Bf16 if self.sm_major >= 8 => Ok(()),
             Type::PtrTo(_) => Ok(()),
             t => Err(ir::TypeError::InvalidType { t }),
         }
@@ -474,14 +570,27 @@ impl device::Device for Gpu {
                     .map(|sizes| !sizes.contains(&3))
                     .unwrap_or(false)
             }
+            // Half-precision arithmetic is vectorized into packed `f16x2` instructions.
+            Operator::BinOp(ir::BinOp::Add, ..)
+            | Operator::BinOp(ir::BinOp::Sub, ..)
+            | Operator::BinOp(ir::BinOp::Max, ..)
+            | Operator::Mul(..)
+            | Operator::Mad(..) => is_half(op.t()),
+            // Fragment dimensions index the registers held by each thread.
+            Operator::MmaSync(.., ref dims) => {
+                dim.id() == dims.frag_rows || dim.id() == dims.frag_cols
+            }
             _ => false,
         }
     }
 
-    fn max_vectorization(&self, _: &ir::Operator) -> [u32; 2] {
-        // No need to discriminate on the operator since this is already handled by
-        // `can_vectorize`.
-        [1, 4]
+    fn max_vectorization(&self, op: &ir::Operator) -> [u32; 2] {
+        // Besides packed half-precision arithmetic, there is no need to discriminate on
+        // the operator since this is already handled by `can_vectorize`.
+        match *op {
+            Operator::BinOp(..) | Operator::Mul(..) | Operator::Mad(..) => [1, 2],
+            _ => [1, 4],
+        }
     }
 
     fn has_vector_registers(&self) -> bool {
@@ -540,12 +649,19 @@ impl device::Device for Gpu {
         &self,
         space: &SearchSpace,
         dim_sizes: &FxHashMap<ir::DimId, model::size::Range>,
-        _nesting: &FxHashMap<ir::StmtId, model::Nesting>,
+        nesting: &FxHashMap<ir::StmtId, model::Nesting>,
         stmt: &dyn ir::Statement,
         ctx: &dyn device::Context,
     ) -> model::HwPressure {
         if let Some(inst) = stmt.as_inst() {
-            self.inst_pressure(space, dim_sizes, inst, ctx)
+            let nesting = nesting.get(&stmt.stmt_id());
+            let mut pressure = self.inst_pressure(space, dim_sizes, nesting, inst, ctx);
+            let is_branched_around =
+                nesting.map(|n| n.is_branched_around).unwrap_or(false);
+            if is_branched_around && inst.has_side_effects() {
+                pressure.add_sequential(&self.branch_share(space));
+            }
+            pressure
         } else if let Some(dim) = stmt.as_dim() {
             let kind = space.domain().get_dim_kind(dim.id());
             self.dim_pressure(kind, dim_sizes[&dim.id()])
@@ -568,6 +684,10 @@ impl device::Device for Gpu {
         }
     }
 
+    fn barrier_pressure(&self) -> HwPressure {
+        self.syncthread_inst.into()
+    }
+
     fn thread_rates(&self) -> HwPressure {
         self.thread_rates.into()
     }
@@ -593,6 +713,32 @@ impl device::Device for Gpu {
         ]
     }
 
+    fn max_registers_per_thread(&self) -> Option<u32> {
+        Some(self.max_registers_per_thread)
+    }
+
+    /// `mma.m16n8k8` requires compute capability 7.5 for `f16` and 8.0 for `bf16` inputs.
+    fn supports_mma(&self, t: Type) -> bool {
+        let min_sm = if t == Type::Bf16 { (8, 0) } else { (7, 5) };
+        (self.sm_major, self.sm_minor) >= min_sm
+    }
+
+    /// `add.bf16` and `mul.bf16` require compute capability 9.0, while `fma.bf16` is
+    /// available from 8.0.
+    fn supports_add_mul(&self, t: Type) -> bool {
+        t != Type::Bf16 || self.sm_major >= 9
+    }
+
+    fn resource_report(&self, fun: &Function) -> ResourceReport {
+        let mut printer = CudaPrinter::default();
+        printer.function(fun, self);
+        ResourceReport {
+            occupancy: Some(self.blocks_per_smx(fun.space())),
+            optimization: Some(printer.optimization_stats()),
+            ..ResourceReport::new(fun)
+        }
+    }
+
     fn block_parallelism(&self, space: &SearchSpace) -> u32 {
         self.blocks_per_smx(space) * self.num_smx
     }
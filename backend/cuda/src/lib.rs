@@ -11,11 +11,15 @@ mod api {
     pub use self::error::*;
     pub use self::fake::*;
 }
+mod bundled;
+mod c_printer;
 mod context;
 mod gpu;
 mod kernel;
 mod mem_model;
+mod model_context;
 mod printer;
+mod ptx_checker;
 
 #[cfg(feature = "real_gpu")]
 pub mod characterize;
@@ -25,9 +29,12 @@ pub mod characterize;
 pub use self::api::{Array, Executor, JITDaemon};
 #[cfg(feature = "real_gpu")]
 pub use self::api::{DeviceAttribute, PerfCounter, PerfCounterSet};
+pub use self::bundled::{bundled_gpus, find_bundled_gpu, BundledGpu};
 pub use self::context::Context;
 pub use self::gpu::{Gpu, InstDesc};
 pub use self::kernel::Kernel;
+pub use self::model_context::ModelContext;
+pub use self::ptx_checker::{check_ptx, PtxError};
 
 use fxhash::FxHashMap;
 use telamon::{codegen, ir};
@@ -47,6 +54,7 @@ impl NameGenerator {
             ir::Type::I(32) => "r",
             ir::Type::I(64) => "rd",
             ir::Type::F(16) => "h",
+            ir::Type::Bf16 => "hb",
             ir::Type::F(32) => "f",
             ir::Type::F(64) => "d",
             _ => panic!("invalid PTX type"),
@@ -7,13 +7,10 @@ use log::trace;
 use num::Integer;
 use telamon::device::{Context, Device};
 use telamon::ir;
-use telamon::model::size;
+use telamon::model::{size, Nesting};
 use telamon::search_space::*;
 use utils::*;
 
-// TODO(model): the pressure changes depending on the list of outer dimensions. Try to
-// take this into account be computing the pressure incrementatly when applying levels.
-
 /// Result of the memory analysis for one instruction. Vector instructions are considered
 /// as a single instance and predicated dimensions are not considered to compute the
 /// average pressure.
@@ -35,12 +32,13 @@ pub struct MemInfo {
     pub access_global: bool,
 }
 
-/// Runs the memory analysis.
+/// Runs the memory analysis. `nesting` is the nesting of the instruction, if known.
 pub fn analyse(
     space: &SearchSpace,
     gpu: &Gpu,
     inst: &ir::Instruction,
     sizes: &FxHashMap<ir::DimId, size::Range>,
+    nesting: Option<&Nesting>,
     ctx: &dyn Context,
 ) -> MemInfo {
     let flag = space.domain().get_inst_flag(inst.id());
@@ -52,9 +50,24 @@ pub fn analyse(
                 _ if flag.intersects(InstFlag::CACHE_READ_ONLY) => {
                     unknown_info(inst, is_shared, gpu)
                 }
+                // Dimensions missing from a tensor pattern have a zero stride, so an unknown
+                // pattern must not be analysed as a tensor without dimensions.
                 ir::AccessPattern::Unknown { .. } => unknown_info(inst, is_shared, gpu),
                 ir::AccessPattern::Tensor { ref dims, .. } => {
-                    info(space, inst, dims, is_shared, gpu, sizes, ctx)
+                    let mut info = info(space, inst, dims, is_shared, gpu, sizes, ctx);
+                    let is_load = if let ir::Operator::Ld(..) = inst.operator() {
+                        true
+                    } else {
+                        false
+                    };
+                    if let Some(nesting) = nesting {
+                        if is_load && flag.intersects(InstFlag::CACHE_SHARED) {
+                            let reuse = l1_reuse_factor(space, dims, nesting, sizes);
+                            info.l1_coalescing /= reuse;
+                            info.l2_coalescing /= reuse;
+                        }
+                    }
+                    info
                 }
             }
         }
@@ -125,7 +138,7 @@ fn info(
     info.memory_transactions = std::f64::INFINITY;
     if is_shared_access.maybe_true() {
         let replay =
-            shared_memory_transactions(thread_dims.clone(), dims, sizes, space, gpu);
+            shared_memory_transactions(thread_dims.clone(), dims, sizes, space, gpu, ctx);
         info.memory_transactions = f64::min(replay, info.memory_transactions);
         info.access_shared = true;
     }
@@ -165,6 +178,28 @@ fn info(
     info
 }
 
+/// Returns the number of consecutive instances of a load that read the same addresses, along
+/// the dimensions nested outside of it.  Only the first instance loads the lines from L2 if the
+/// load is cached in L1.  The maximal sizes of dimensions are used to keep a lower bound.
+fn l1_reuse_factor(
+    space: &SearchSpace,
+    tensor_dims: &FxHashMap<ir::DimId, ir::PartialSize>,
+    nesting: &Nesting,
+    sizes: &FxHashMap<ir::DimId, size::Range>,
+) -> f64 {
+    // Instances along thread and block dimensions are executed by different threads, that may
+    // not share the L1 cache.
+    let sequential_kinds = DimKind::LOOP | DimKind::UNROLL;
+    nesting
+        .outer_dims
+        .iter()
+        .filter(|&dim| !tensor_dims.contains_key(dim))
+        .filter(|&&dim| sequential_kinds.contains(space.domain().get_dim_kind(dim)))
+        .flat_map(|dim| sizes.get(dim))
+        .map(|size| size.max as f64)
+        .product()
+}
+
 #[derive(Debug, Copy, Clone)]
 struct ThreadDimInfo {
     id: ir::DimId,
@@ -220,13 +255,10 @@ fn tensor_thread_dims(
     let mut out = Vec::new();
     for (id, is_active_thread) in dims {
         let size = sizes[&id];
-        let stride_size = tensor_dims.get(&id);
-        let stride = stride_size
-            .map(|s| size::bounds(s, space, ctx))
-            .unwrap_or(size::Range::ZERO);
-        let stride_factors = stride_size
-            .map(|s| size::factors(s, space, ctx))
-            .unwrap_or(size::FactorRange::ZERO);
+        let (stride, stride_factors) = tensor_dims
+            .get(&id)
+            .map(|s| stride_bounds(s, space, ctx))
+            .unwrap_or((size::Range::ZERO, size::FactorRange::ZERO));
         let info = ThreadDimInfo {
             is_partial_dim: false,
             stride,
@@ -246,6 +278,40 @@ fn tensor_thread_dims(
     out
 }
 
+/// Bounds the stride of a tensor dimension.  Strides that depend on parameters whose value is
+/// not known in the context are bounded by the stride obtained when those parameters are one.
+fn stride_bounds(
+    stride: &ir::PartialSize,
+    space: &SearchSpace,
+    ctx: &dyn Context,
+) -> (size::Range, size::FactorRange) {
+    let (factor, params, dims) = stride.factors();
+    if params.iter().all(|p| ctx.param_as_size(&p.name).is_some()) {
+        return (
+            size::bounds(stride, space, ctx),
+            size::factors(stride, space, ctx),
+        );
+    }
+    let known_params = params
+        .iter()
+        .filter(|p| ctx.param_as_size(&p.name).is_some())
+        .cloned()
+        .collect();
+    let mut known = ir::PartialSize::new(factor, known_params);
+    for &dim in dims {
+        known *= &ir::PartialSize::new_dim_size(dim);
+    }
+    known.add_divisors(&VecSet::new(stride.divisors().to_vec()));
+    let min = size::bounds(&known, space, ctx).min;
+    let gcd = size::factors(&known, space, ctx).gcd;
+    // No multiple of the stride is known.
+    let range = size::Range {
+        min,
+        max: std::u64::MAX,
+    };
+    (range, size::FactorRange { gcd, lcm: 0 })
+}
+
 /// Returns the thread dimensions that are mapped outside an instruction but not active
 /// under this instruction. The returned boolean indicates if the thread dimension cannot
 /// be mapped to an active dimension and if the dimension is predicated.
@@ -415,6 +481,7 @@ fn shared_memory_transactions(
     dim_sizes: &FxHashMap<ir::DimId, size::Range>,
     space: &SearchSpace,
     gpu: &Gpu,
+    ctx: &dyn Context,
 ) -> f64 {
     let thread_dims = sort_thread_dims(thread_dims, true, space, gpu);
     // Handle replays caused by offsets.
@@ -439,13 +506,13 @@ fn shared_memory_transactions(
         .map(|offsets| offsets_shared_memory_transactions(offsets, gpu))
         .min()
         .unwrap();
-    // Handle the case where a single thread must access two banks.
+    // Handle the case where a single thread must access two banks. Strides that are not
+    // statically known are bounded by their minimal value.
     let vector_replay = tensor_dims
         .iter()
-        .flat_map(|(&d, stride)| stride.as_int().map(|s| (d, s)))
-        .filter(|&(d, _)| space.domain().get_dim_kind(d).intersects(DimKind::VECTOR))
-        .map(|(d, stride)| dim_sizes[&d].min as u32 * stride)
-        .map(|size| div_ceil(size, gpu.shared_bank_stride))
+        .filter(|&(&d, _)| space.domain().get_dim_kind(d).intersects(DimKind::VECTOR))
+        .map(|(d, stride)| dim_sizes[d].min * stride_bounds(stride, space, ctx).0.min)
+        .map(|size| div_ceil(size, u64::from(gpu.shared_bank_stride)) as u32)
         .min()
         .unwrap_or(1);
     let replay = std::cmp::max(replay, vector_replay);
@@ -453,19 +520,19 @@ fn shared_memory_transactions(
     f64::from(replay)
 }
 
-/// Computes the replay factor for a list of shared memory access.
+/// Computes the replay factor for a list of shared memory access. Accesses to different words
+/// of the same bank are serialized, while accesses to the same word are broadcast.
 fn offsets_shared_memory_transactions(offsets: &[u64], gpu: &Gpu) -> u32 {
-    // We only need to account for hits on the first bank. Other banks will have a smaller
-    // replay factor.
-    let mut hits: FxHashSet<_> = std::iter::once(0).collect();
+    // Threads in a wrap may be spread across multiple thread dimensions, so that the first
+    // bank is not necessarily the most accessed one.
+    let mut hits: FxHashMap<_, FxHashSet<_>> = FxHashMap::default();
+    hits.entry(0).or_default().insert(0);
     for &offset in offsets {
-        let num_bank_stride = offset / u64::from(gpu.shared_bank_stride);
-        let (hit_id, rem) = num_bank_stride.div_rem(&(u64::from(gpu.wrap_size)));
-        if rem == 0 {
-            hits.insert(hit_id);
-        }
+        let word = offset / u64::from(gpu.shared_bank_stride);
+        let bank = word % u64::from(gpu.wrap_size);
+        hits.entry(bank).or_default().insert(word);
     }
-    hits.len() as u32
+    hits.values().map(|words| words.len() as u32).max().unwrap()
 }
 
 /// Computes the L1, L2 coalescing and replay factor for a global memory access.
@@ -638,20 +705,21 @@ else { None }
 */
 
 #[cfg(test)]
-#[cfg(feature = "real_gpu")]
 #[allow(clippy::float_cmp)]
 mod tests {
     use super::*;
-    use crate::{Context, Executor, Gpu};
+    #[cfg(feature = "real_gpu")]
+    use crate::{Context, Executor};
+    use crate::{Gpu, ModelContext};
     use env_logger;
     use std::sync::Arc;
     use telamon::device::Device;
     use telamon::model::size::Range;
-    use telamon::search_space::Order;
     use telamon::{helper, ir};
 
     /// Generates function with a load in two thread dimensions, with non-coalesced
     /// accessed on the first one.
+    #[cfg(feature = "real_gpu")]
     fn gen_function<'a>(
         signature: Arc<ir::Signature>,
         gpu: &'a Gpu,
@@ -679,6 +747,31 @@ mod tests {
         (builder.get(), ld, size_map)
     }
 
+    /// Generates a function with a load in a thread dimension, nested in a loop along which the
+    /// accessed addresses do not change.
+    fn gen_reused_load(
+        gpu: &Gpu,
+        flag: InstFlag,
+    ) -> (SearchSpace, ir::InstId, FxHashMap<ir::DimId, Range>) {
+        let signature = Arc::new(gen_signature());
+        let mut builder = helper::Builder::new(signature, Arc::new(gpu.clone()));
+        let t = ir::Type::F(32);
+        let addr_base = builder.cast(&0i64, gpu.pointer_type(MemSpace::GLOBAL));
+        let thread_size = builder.cst_size(gpu.wrap_size);
+        let loop_size = builder.cst_size(4);
+        let d0 = builder.open_dim_ex(thread_size, DimKind::THREAD);
+        let d1 = builder.open_dim_ex(loop_size, DimKind::LOOP);
+        let addr = builder.mad(&d0, &4i32, &addr_base);
+        let stride = ir::Size::new_const(4);
+        let pattern = builder.tensor_access_pattern(None, vec![(&d0, stride)]);
+        let ld = builder.ld_ex(t, &addr, pattern, flag);
+
+        let mut size_map = FxHashMap::default();
+        size_map.insert(d0[0], Range::new_fixed(gpu.wrap_size.into()));
+        size_map.insert(d1[0], Range::new_fixed(4));
+        (builder.get(), ld, size_map)
+    }
+
     /// Generates a dummy signature.
     fn gen_signature() -> ir::Signature {
         ir::Signature {
@@ -689,6 +782,7 @@ mod tests {
 
     /// Tests `MemInfo` for global loads without coalescing.
     #[test]
+    #[cfg(feature = "real_gpu")]
     fn global_full_coalescing() {
         let _ = env_logger::try_init();
         let executor = Executor::init();
@@ -697,7 +791,8 @@ mod tests {
         let base = gen_signature();
         let (space, inst, size_map) = gen_function(base.into(), &gpu, Order::OUTER);
         let inst = space.ir_instance().inst(inst);
-        let inst_info = analyse(&space, &gpu, &inst, &size_map, &ctx);
+        let nesting = Nesting::compute(&space, inst.id().into());
+        let inst_info = analyse(&space, &gpu, &inst, &size_map, Some(&nesting), &ctx);
         assert_eq!(inst_info.l1_coalescing, 1.0 / f64::from(gpu.wrap_size));
         assert_eq!(inst_info.l2_coalescing, 1.0 / f64::from(gpu.wrap_size));
         assert_eq!(inst_info.memory_transactions, 1.0);
@@ -705,6 +800,7 @@ mod tests {
 
     /// Tests `MemInfo` for global loads with full coalescing.
     #[test]
+    #[cfg(feature = "real_gpu")]
     fn global_no_coalescing() {
         let _ = env_logger::try_init();
         let executor = Executor::init();
@@ -713,12 +809,69 @@ mod tests {
         let base = gen_signature();
         let (space, inst, size_map) = gen_function(base.into(), &gpu, Order::INNER);
         let inst = space.ir_instance().inst(inst);
-        let inst_info = analyse(&space, &gpu, &inst, &size_map, &ctx);
+        let nesting = Nesting::compute(&space, inst.id().into());
+        let inst_info = analyse(&space, &gpu, &inst, &size_map, Some(&nesting), &ctx);
         assert_eq!(inst_info.l1_coalescing, 1.0);
         assert_eq!(inst_info.l2_coalescing, 1.0);
         assert_eq!(inst_info.memory_transactions, f64::from(gpu.wrap_size));
     }
 
+    /// Tests that lines loaded from L2 are amortized over the outer loops along which the
+    /// accessed addresses do not change, if the load is cached in L1.
+    #[test]
+    fn global_l1_reuse() {
+        let _ = env_logger::try_init();
+        let gpu = Gpu {
+            allow_l1_for_global_mem: true,
+            ..Gpu::dummy()
+        };
+        let ctx = ModelContext::new(gpu.clone());
+        let infos = [InstFlag::CACHE_GLOBAL, InstFlag::CACHE_SHARED]
+            .iter()
+            .map(|&flag| {
+                let (space, inst, size_map) = gen_reused_load(&gpu, flag);
+                let inst = space.ir_instance().inst(inst);
+                let nesting = Nesting::compute(&space, inst.id().into());
+                analyse(&space, &gpu, &inst, &size_map, Some(&nesting), &ctx)
+            })
+            .collect_vec();
+        assert_eq!(infos[0].l2_coalescing, 4.0 * infos[1].l2_coalescing);
+        assert_eq!(infos[0].l1_coalescing, 4.0 * infos[1].l1_coalescing);
+        assert_eq!(infos[0].memory_transactions, infos[1].memory_transactions);
+    }
+
+    /// Tests that loads with an unknown access pattern are not analysed as a tensor access
+    /// without dimensions.
+    #[test]
+    fn unknown_pattern() {
+        let _ = env_logger::try_init();
+        let gpu = Gpu {
+            sm_major: 5,
+            ..Gpu::dummy()
+        };
+        let ctx = ModelContext::new(gpu.clone());
+        let signature = Arc::new(gen_signature());
+        let mut builder = helper::Builder::new(signature, Arc::new(gpu.clone()));
+        let addr_base = builder.cast(&0i64, gpu.pointer_type(MemSpace::GLOBAL));
+        let size = builder.cst_size(gpu.wrap_size);
+        let d0 = builder.open_dim_ex(size, DimKind::THREAD);
+        let addr = builder.mad(&d0, &4i32, &addr_base);
+        let pattern = ir::AccessPattern::Unknown(None);
+        let ld = builder.ld_ex(ir::Type::F(32), &addr, pattern, InstFlag::CACHE_GLOBAL);
+        let space = builder.get();
+        let mut size_map = FxHashMap::default();
+        size_map.insert(d0[0], Range::new_fixed(gpu.wrap_size.into()));
+
+        let inst = space.ir_instance().inst(ld);
+        let inst_info = analyse(&space, &gpu, inst, &size_map, None, &ctx);
+        let expected = unknown_info(inst, Trivalent::False, &gpu);
+        assert_eq!(inst_info.l1_coalescing, expected.l1_coalescing);
+        assert_eq!(inst_info.l2_coalescing, expected.l2_coalescing);
+        assert_eq!(inst_info.memory_transactions, expected.memory_transactions);
+        assert_eq!(inst_info.issue_replays, expected.issue_replays);
+        assert!(inst_info.access_global);
+    }
+
     fn thread_dim_info(
         id: u32,
         partial: bool,
@@ -783,4 +936,35 @@ mod tests {
             vec![0, 1, 0, 1, 2, 2, 3, 3, 0, 1, 2, 3, 0, 1, 2, 3]
         );
     }
+
+    /// Tests shared memory replays when the threads of a wrap span two dimensions, so that
+    /// the most accessed bank is not the first one.
+    #[test]
+    fn shared_bank_conflicts_2d() {
+        let _ = env_logger::try_init();
+        let gpu = Gpu::dummy();
+        let word = u64::from(gpu.shared_bank_stride);
+        let num_banks = u64::from(gpu.wrap_size);
+        // A 2x16 block of threads accessing a padded array, with rows of 33 words.
+        let cols = thread_dim_info(0, false, 2, 2, word);
+        let rows = thread_dim_info(1, false, 16, 16, (num_banks + 1) * word);
+        let offsets = wrap_access_offsets(&[cols, rows], true, &gpu);
+        assert_eq!(offsets_shared_memory_transactions(&offsets, &gpu), 2);
+        // Without padding, the threads of a column access the same bank.
+        let rows = thread_dim_info(1, false, 16, 16, num_banks * word);
+        let offsets = wrap_access_offsets(&[cols, rows], true, &gpu);
+        assert_eq!(offsets_shared_memory_transactions(&offsets, &gpu), 16);
+    }
+
+    /// Tests that accesses to the same word of a bank are broadcast.
+    #[test]
+    fn shared_bank_broadcast() {
+        let _ = env_logger::try_init();
+        let gpu = Gpu::dummy();
+        let word = u64::from(gpu.shared_bank_stride);
+        let broadcast = thread_dim_info(0, false, 8, 8, 0);
+        let consecutive = thread_dim_info(1, false, 4, 4, word);
+        let offsets = wrap_access_offsets(&[broadcast, consecutive], true, &gpu);
+        assert_eq!(offsets_shared_memory_transactions(&offsets, &gpu), 1);
+    }
 }
@@ -0,0 +1,186 @@
+//! Defines a CUDA context that relies on the performance model instead of a GPU.
+use std::fmt;
+use std::sync::Arc;
+
+use fxhash::FxHashMap;
+use telamon::device::{
+    self, ArrayArgument, AsyncCallback, EvalMode, KernelEvaluator, ScalarArgument,
+};
+use telamon::{codegen, explorer, ir, model};
+
+use crate::bundled::find_bundled_gpu;
+use crate::Gpu;
+
+/// A CUDA context for GPUs that are not available on the host.
+///
+/// Code is generated for the GPU, but never run: the evaluation of an implementation is the lower
+/// bound given by the performance model.  Scalar arguments are only kept to compute sizes, and
+/// arrays are not allocated: they read as zeros and ignore writes, so that output checks
+/// trivially succeed.
+pub struct ModelContext {
+    gpu_model: Arc<Gpu>,
+    parameters: FxHashMap<String, Option<u32>>,
+}
+
+impl ModelContext {
+    /// Creates a context modeling the given GPU.
+    pub fn new(gpu: Gpu) -> Self {
+        ModelContext {
+            gpu_model: Arc::new(gpu),
+            parameters: FxHashMap::default(),
+        }
+    }
+
+    /// Creates a context modeling a GPU bundled with the crate.  `name` is either the name of the
+    /// GPU or `<name>@v<version>`.
+    pub fn from_bundled(name: &str) -> Option<Self> {
+        find_bundled_gpu(name).map(|bundled| Self::new(bundled.gpu()))
+    }
+
+    /// Returns the GPU description.
+    pub fn gpu(&self) -> &Arc<Gpu> {
+        &self.gpu_model
+    }
+
+    /// Evaluates a candidate with the performance model.
+    fn bound(&self, function: &codegen::Function) -> f64 {
+        model::bound(function.space(), self).value()
+    }
+}
+
+impl<'a> device::ArgMap<'a> for ModelContext {
+    fn bind_erased_scalar(
+        &mut self,
+        param: &ir::Parameter,
+        value: Box<dyn ScalarArgument>,
+    ) {
+        assert_eq!(param.t, value.get_type());
+        self.parameters.insert(param.name.clone(), value.as_size());
+    }
+
+    fn bind_erased_array(
+        &mut self,
+        _: &ir::Parameter,
+        t: ir::Type,
+        len: usize,
+    ) -> Arc<dyn ArrayArgument + 'a> {
+        Arc::new(ModelArray {
+            num_bytes: len * t.len_byte().unwrap_or(1) as usize,
+        })
+    }
+}
+
+impl device::Context for ModelContext {
+    fn device(&self) -> Arc<dyn device::Device> {
+        Arc::<Gpu>::clone(&self.gpu_model)
+    }
+
+    fn param_as_size(&self, name: &str) -> Option<u32> {
+        self.parameters[name]
+    }
+
+    fn evaluate(&self, function: &codegen::Function, _: EvalMode) -> Result<f64, ()> {
+        Ok(self.bound(function))
+    }
+
+    fn benchmark(&self, function: &codegen::Function, num_samples: usize) -> Vec<f64> {
+        vec![self.bound(function); num_samples]
+    }
+
+    fn async_eval<'c>(
+        &self,
+        _: usize,
+        _: EvalMode,
+        inner: &(dyn Fn(&mut dyn device::AsyncEvaluator<'c>) + Sync),
+    ) {
+        inner(&mut ModelEvaluator { _priv: () });
+    }
+}
+
+/// Evaluates candidates in the calling thread, with the performance model.
+struct ModelEvaluator {
+    _priv: (),
+}
+
+impl<'c> device::AsyncEvaluator<'c> for ModelEvaluator {
+    fn add_dyn_kernel(
+        &mut self,
+        candidate: explorer::Candidate,
+        callback: AsyncCallback<'c>,
+    ) {
+        // Candidates reaching the evaluator are fully specified, so their bound is the model's
+        // estimate of their runtime.
+        let mut code = ModelCode {
+            bound: candidate.bound.value(),
+        };
+        callback.call(candidate, &mut code);
+    }
+}
+
+/// An implementation evaluated by the performance model.
+struct ModelCode {
+    bound: f64,
+}
+
+impl fmt::Display for ModelCode {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "<model: {:.4e}ns>", self.bound)
+    }
+}
+
+impl KernelEvaluator for ModelCode {
+    fn evaluate(&mut self) -> Option<f64> {
+        Some(self.bound)
+    }
+}
+
+/// An array that is never allocated.
+struct ModelArray {
+    num_bytes: usize,
+}
+
+impl ArrayArgument for ModelArray {
+    fn read_i8(&self) -> Vec<i8> {
+        vec![0; self.num_bytes]
+    }
+
+    fn write_i8(&self, _: &[i8]) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use telamon::device::Context;
+    use telamon::helper;
+    use telamon::search_space::{DimKind, Order};
+    use utils::unwrap;
+
+    /// Ensures the barrier ending instructions outside of thread dimensions is accounted for
+    /// in each iteration of a loop containing thread dimensions.
+    #[test]
+    fn barriers_in_loop() {
+        let _ = env_logger::try_init();
+        let mut gpu = unwrap!(find_bundled_gpu("gtx_1080")).gpu();
+        gpu.syncthread_inst.latency = 1000.;
+        let thread_latency_rate = gpu.thread_rates.latency;
+        let context = ModelContext::new(gpu);
+        let signature = ir::Signature::new("barriers");
+        let mut builder = helper::Builder::new(Arc::new(signature), context.device());
+        let loop_size = builder.cst_size(16);
+        let thread_size = builder.cst_size(32);
+        let d0 = builder.open_dim_ex(loop_size, DimKind::LOOP);
+        let init = builder.mov(&0f32);
+        let d1 = builder.open_dim_ex(thread_size, DimKind::THREAD);
+        builder.mov(&0f32);
+        builder.close_dim(&d1);
+        builder.close_dim(&d0);
+        builder.order(&d0, &d1, Order::OUTER);
+        builder.order(&init, &d1, Order::BEFORE);
+        let bound = model::bound(&builder.get(), &context);
+        assert!(
+            bound.value() >= 2. * 16. * 1000. / thread_latency_rate,
+            "{}",
+            bound
+        );
+    }
+}
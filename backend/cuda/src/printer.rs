@@ -76,6 +76,14 @@ impl PTXDisplay for llir::Operand<'_> {
                 assert!(bits <= 64);
                 fmt::Display::fmt(val, fmt)
             }
+            &FloatLiteral(ref val, 16) => {
+                use num::ToPrimitive;
+
+                // Half-precision literals are only used to initialize `.b16` registers.
+                let val = val.numer().to_f32().unwrap() / val.denom().to_f32().unwrap();
+                write!(fmt, "0x{:04X}", telamon::device::F16::from_f32(val).0)
+            }
+            Bf16Literal(bits) => write!(fmt, "0x{:04X}", bits),
             &FloatLiteral(ref val, bits) => {
                 use num::ToPrimitive;
                 assert!(bits <= 64);
@@ -104,24 +112,81 @@ impl<T: PTXDisplay> PTXDisplay for llir::ScalarOrVector<T> {
     }
 }
 
+/// Scratch registers holding pairs of packed half-precision values.
+const HALF2_REGS: [&str; 4] = ["hx0", "hx1", "hx2", "hx3"];
+/// Scratch registers holding half-precision literals, which arithmetic instructions do not
+/// accept as immediate operands.
+const HALF_LITERAL_REGS: [&str; 6] = ["hl0", "hl1", "hl2", "hl3", "hl4", "hl5"];
+
 #[derive(Default)]
 pub(crate) struct CudaPrinter {
     buffer: String,
+    /// Indicates if the scratch registers in `HALF2_REGS` are used.
+    uses_half2_regs: bool,
+    /// Indicates if the scratch registers in `HALF_LITERAL_REGS` are used.
+    uses_half_literal_regs: bool,
+    /// Effect of the optimizations on the last printed function body.
+    optimization_stats: OptimizationStats,
 }
 
 impl CudaPrinter {
+    /// Returns the effect of the optimizations on the last printed function body.
+    pub fn optimization_stats(&self) -> OptimizationStats {
+        self.optimization_stats
+    }
+
     /// Prints the variables declared by the `NameGenerator`.
     fn var_decls(&mut self, namegen: &NameGenerator) -> String {
         let print_decl = |(&t, n)| {
             let prefix = NameGenerator::gen_prefix(t);
-            format!(".reg.{} %{}<{}>;", t.ptx(), prefix, n)
+            format!(".reg.{} %{}<{}>;", storage_type(t), prefix, n)
         };
-        namegen
-            .num_var
-            .iter()
-            .map(print_decl)
-            .collect_vec()
-            .join("\n  ")
+        let mut decls = namegen.num_var.iter().map(print_decl).collect_vec();
+        if self.uses_half2_regs {
+            decls.push(format!(".reg.b32 %hx<{}>;", HALF2_REGS.len()));
+        }
+        if self.uses_half_literal_regs {
+            decls.push(format!(".reg.b16 %hl<{}>;", HALF_LITERAL_REGS.len()));
+        }
+        decls.join("\n  ")
+    }
+
+    /// Moves the half-precision literals operands of an arithmetic instruction into
+    /// scratch registers.
+    fn materialize_half_literals(&mut self, inst: &mut llir::Instruction<'_>) {
+        let operands: &mut [llir::OpVec<'_>] = match inst {
+            llir::Instruction::Binary(_, d, ops) if is_half(d.t()) => ops,
+            llir::Instruction::Ternary(_, d, ops) if is_half(d.t()) => ops,
+            llir::Instruction::Mma(_, _, ops) => &mut ops[..2],
+            _ => return,
+        };
+        let mut num_literals = 0;
+        for operand in operands {
+            let scalars = match operand {
+                llir::ScalarOrVector::Scalar(scalar) => std::slice::from_mut(scalar),
+                llir::ScalarOrVector::Vector(scalars) => &mut scalars[..],
+            };
+            for scalar in scalars {
+                let is_half_literal = match scalar {
+                    llir::Operand::FloatLiteral(_, 16)
+                    | llir::Operand::Bf16Literal(_) => true,
+                    _ => false,
+                };
+                if is_half_literal {
+                    let reg =
+                        llir::Register::new(HALF_LITERAL_REGS[num_literals], scalar.t());
+                    num_literals += 1;
+                    unwrap!(writeln!(
+                        self.buffer,
+                        "mov.b16 {}, {};",
+                        reg.ptx(),
+                        scalar.ptx()
+                    ));
+                    *scalar = reg.into();
+                }
+            }
+        }
+        self.uses_half_literal_regs |= num_literals > 0;
     }
 
     /// Declares block and thread indexes.
@@ -160,6 +225,7 @@ impl CudaPrinter {
     fn host_type(t: Type) -> &'static str {
         match t {
             Type::PtrTo(..) => "CUdeviceptr",
+            Type::F(16) | Type::Bf16 => "uint16_t",
             Type::F(32) => "float",
             Type::F(64) => "double",
             Type::I(8) => "int8_t",
@@ -198,7 +264,7 @@ impl CudaPrinter {
     fn param_decl(&mut self, param: &ParamVal) -> String {
         format!(
             ".param .{t} {name}",
-            t = param.t().ptx(),
+            t = storage_type(param.t()),
             name = param.key().ident(),
         )
     }
@@ -217,7 +283,7 @@ impl CudaPrinter {
             unwrap!(writeln!(
                 self.buffer,
                 "  ld.param.{t} {var_name}, [{name}];",
-                t = val.t().ptx(),
+                t = storage_type(val.t()),
                 var_name = name_map.name_param_val(val.key()).ptx(),
                 name = val.key().ident(),
             ));
@@ -262,10 +328,13 @@ impl CudaPrinter {
                 .iter()
                 .flat_map(|d| d.induction_levels()),
         );
+        let mut insts = InstBuffer::default();
         for level in ind_levels {
-            Printer::new(self, name_map).parallel_induction_level(level);
+            Printer::new(&mut insts, name_map).parallel_induction_level(level);
         }
-        Printer::new(self, name_map).cfg(function, function.cfg());
+        Printer::new(&mut insts, name_map).cfg(function, function.cfg());
+        self.optimization_stats = insts.optimize();
+        insts.print(self);
         let var_decls = self.var_decls(&namegen);
         let mut body = String::new();
         body.push_str(&var_decls);
@@ -273,6 +342,7 @@ impl CudaPrinter {
         body.push_str(&self.buffer);
         format!(
             include_str!("template/device.ptx"),
+            ptx_version = ptx_version(gpu),
             sm_major = gpu.sm_major,
             sm_minor = gpu.sm_minor,
             addr_size = gpu.addr_size,
@@ -356,14 +426,26 @@ impl CudaPrinter {
     }
 }
 
-impl InstPrinter for CudaPrinter {
-    fn print_label(&mut self, label: llir::Label<'_>) {
+impl<'a> InstPrinter<'a> for CudaPrinter {
+    fn print_label(&mut self, label: llir::Label<'a>) {
         unwrap!(writeln!(self.buffer, "{}:", label.name()));
     }
 
-    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'_>) {
+    fn print_inst(&mut self, mut inst: llir::PredicatedInstruction<'a>) {
+        self.materialize_half_literals(&mut inst.instruction);
+        self.uses_half2_regs |= uses_half2_regs(&inst.instruction);
         writeln!(self.buffer, "{};", inst.ptx()).unwrap();
     }
+
+    fn print_branch(&mut self, label: llir::Label<'a>, cond: llir::Register<'a>) {
+        // Threads may diverge on `cond`, so the branch cannot be marked as uniform.
+        unwrap!(writeln!(
+            self.buffer,
+            "@{} bra {};",
+            cond.ptx(),
+            label.ptx()
+        ));
+    }
 }
 
 impl PTXDisplay for llir::UnOp {
@@ -371,7 +453,7 @@ impl PTXDisplay for llir::UnOp {
         use llir::UnOp;
 
         match self {
-            UnOp::Move { t } => write!(fmt, "mov.{}", t.ptx()),
+            UnOp::Move { t } => write!(fmt, "mov.{}", storage_type(*t)),
             UnOp::Cast { src_t, dst_t } => {
                 // Integer rounding is required for float-to-integer conversions, and for
                 // same-size float-to-float conversions where the value is rounded to an
@@ -389,11 +471,13 @@ impl PTXDisplay for llir::UnOp {
                 //
                 // [1]:
                 // https://docs.nvidia.com/cuda/parallel-thread-execution/index.html#data-movement-and-conversion-instructions-cvt
-                let rnd = match (src_t, dst_t) {
-                    (ir::Type::F(_), ir::Type::I(_)) => ".rni",
-                    (ir::Type::I(_), ir::Type::F(_)) => ".rn",
-                    (ir::Type::F(src_bits), ir::Type::F(dst_bits))
-                        if dst_bits < src_bits =>
+                let rnd = match (src_t.is_float(), dst_t.is_float()) {
+                    (true, false) => ".rni",
+                    (false, true) => ".rn",
+                    (true, true)
+                        if dst_t.bitwidth() < src_t.bitwidth()
+                            || (dst_t.bitwidth() == src_t.bitwidth()
+                                && dst_t != src_t) =>
                     {
                         ".rn"
                     }
@@ -427,9 +511,10 @@ impl PTXDisplay for llir::BinOp {
             // Comparison and Selection Instructions
             Set { op, arg_t } => write!(fmt, "setp.{}.{}", op.ptx(), arg_t.ptx()),
             // Logic and Shift Instructions
-            And { t } => write!(fmt, "and.{}", t.ptx()),
-            Or { t } => write!(fmt, "or.{}", t.ptx()),
-            Xor { t } => write!(fmt, "xor.{}", t.ptx()),
+            And { t } => write!(fmt, "and.{}", logic_type(*t)),
+            Or { t } => write!(fmt, "or.{}", logic_type(*t)),
+            Xor { t } => write!(fmt, "xor.{}", logic_type(*t)),
+            Shl { t } => write!(fmt, "shl.{}", logic_type(*t)),
         }
     }
 }
@@ -542,7 +627,7 @@ impl PTXDisplay for llir::LoadSpec {
         if self.vector_factor().get() > 1 {
             write!(fmt, ".v{}", self.vector_factor())?;
         }
-        write!(fmt, ".{}", self.t().ptx())
+        write!(fmt, ".{}", storage_type(self.t()))
     }
 }
 
@@ -552,23 +637,31 @@ impl PTXDisplay for llir::StoreSpec {
         if self.vector_factor().get() > 1 {
             write!(fmt, ".v{}", self.vector_factor())?;
         }
-        write!(fmt, ".{}", self.t().ptx())
+        write!(fmt, ".{}", storage_type(self.t()))
     }
 }
 
 impl PTXDisplay for llir::PredicatedInstruction<'_> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            fmt,
-            "{}{}",
-            self.predicate
-                .into_iter()
-                .format_with("", |predicate, f| f(&format_args!(
-                    "@{} ",
-                    predicate.ptx()
-                ))),
-            self.instruction.ptx()
-        )
+        match self.predicate {
+            None => PTXDisplay::fmt(&self.instruction, fmt),
+            // Some instructions expand to several PTX instructions, which must all be
+            // predicated.
+            Some(predicate) => {
+                let instruction = self.instruction.ptx().to_string();
+                write!(
+                    fmt,
+                    "{}",
+                    instruction
+                        .lines()
+                        .format_with("\n", |line, f| f(&format_args!(
+                            "@{} {}",
+                            predicate.ptx(),
+                            line
+                        )))
+                )
+            }
+        }
     }
 }
 
@@ -593,6 +686,35 @@ impl PTXDisplay for llir::Instruction<'_> {
                 write!(fmt, "ex2.approx.{t} {d}, {d}", t = t.ptx(), d = d.ptx())
             }
             Unary(op, d, [a]) => write!(fmt, "{} {}, {}", op.ptx(), d.ptx(), a.ptx()),
+            // Pairs of half-precision values are packed into a single register.
+            Binary(op, d @ llir::ScalarOrVector::Vector(_), [a, b]) => {
+                writeln!(fmt, "mov.b32 %{}, {};", HALF2_REGS[0], a.ptx())?;
+                writeln!(fmt, "mov.b32 %{}, {};", HALF2_REGS[1], b.ptx())?;
+                writeln!(
+                    fmt,
+                    "{}x2 %{}, %{}, %{};",
+                    op.ptx(),
+                    HALF2_REGS[2],
+                    HALF2_REGS[0],
+                    HALF2_REGS[1]
+                )?;
+                write!(fmt, "mov.b32 {}, %{}", d.ptx(), HALF2_REGS[2])
+            }
+            Ternary(op, d @ llir::ScalarOrVector::Vector(_), [a, b, c]) => {
+                writeln!(fmt, "mov.b32 %{}, {};", HALF2_REGS[0], a.ptx())?;
+                writeln!(fmt, "mov.b32 %{}, {};", HALF2_REGS[1], b.ptx())?;
+                writeln!(fmt, "mov.b32 %{}, {};", HALF2_REGS[2], c.ptx())?;
+                writeln!(
+                    fmt,
+                    "{}x2 %{}, %{}, %{}, %{};",
+                    op.ptx(),
+                    HALF2_REGS[3],
+                    HALF2_REGS[0],
+                    HALF2_REGS[1],
+                    HALF2_REGS[2]
+                )?;
+                write!(fmt, "mov.b32 {}, %{}", d.ptx(), HALF2_REGS[3])
+            }
             Binary(op, d, [a, b]) => {
                 write!(fmt, "{} {}, {}, {}", op.ptx(), d.ptx(), a.ptx(), b.ptx())
             }
@@ -609,19 +731,133 @@ impl PTXDisplay for llir::Instruction<'_> {
             Store(spec, a, [b]) => {
                 write!(fmt, "st{} {}, {}", spec.ptx(), a.ptx(), b.ptx())
             }
+            // The `a` and `b` fragments are packed by pairs into 32-bit registers.
+            Mma(spec, d, [a, b, c]) => {
+                let (a, b) = match (a, b) {
+                    (
+                        llir::ScalarOrVector::Vector(a),
+                        llir::ScalarOrVector::Vector(b),
+                    ) => (a, b),
+                    _ => panic!("{}: expected vector fragments", self),
+                };
+                let pack =
+                    |fmt: &mut fmt::Formatter<'_>, reg, pair: &[llir::Operand<'_>]| {
+                        writeln!(
+                            fmt,
+                            "mov.b32 %{}, {{{}}};",
+                            reg,
+                            pair.iter().map(PTXDisplay::ptx).format(", ")
+                        )
+                    };
+                pack(fmt, HALF2_REGS[0], &a[..2])?;
+                pack(fmt, HALF2_REGS[1], &a[2..])?;
+                pack(fmt, HALF2_REGS[2], &b[..])?;
+                write!(
+                    fmt,
+                    "{} {}, {{%{}, %{}}}, {{%{}}}, {}",
+                    spec.ptx(),
+                    d.ptx(),
+                    HALF2_REGS[0],
+                    HALF2_REGS[1],
+                    HALF2_REGS[2],
+                    c.ptx()
+                )
+            }
             Jump(label) => write!(fmt, "bra.uni {}", label.ptx()),
             Sync => write!(fmt, "bar.sync 0"),
         }
     }
 }
 
+impl PTXDisplay for llir::MmaSpec {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "mma.sync.aligned.m16n8k8.row.col.{cd}.{ab}.{ab}.{cd}",
+            ab = self.ab_t.ptx(),
+            cd = self.cd_t.ptx()
+        )
+    }
+}
+
+/// Indicates if values of type `t` are half-precision floats.
+fn is_half(t: ir::Type) -> bool {
+    t == Type::F(16) || t == Type::Bf16
+}
+
+/// Indicates if printing the instruction uses the scratch registers in `HALF2_REGS`.
+fn uses_half2_regs(inst: &llir::Instruction<'_>) -> bool {
+    match inst {
+        llir::Instruction::Binary(_, d @ llir::ScalarOrVector::Vector(_), _)
+        | llir::Instruction::Ternary(_, d @ llir::ScalarOrVector::Vector(_), _) => {
+            is_half(d.t())
+        }
+        llir::Instruction::Mma(..) => true,
+        _ => false,
+    }
+}
+
+/// Returns the type used to declare, move, load and store values of type `t`.  Half-precision
+/// values live in untyped registers, since only arithmetic instructions accept the `.f16` and
+/// `.bf16` types.
+fn storage_type(t: ir::Type) -> String {
+    if is_half(t) {
+        "b16".to_string()
+    } else {
+        t.ptx().to_string()
+    }
+}
+
+/// Returns the oldest PTX ISA version that supports the targeted GPU, and at least the
+/// version 6.1.
+fn ptx_version(gpu: &Gpu) -> &'static str {
+    match (gpu.sm_major, gpu.sm_minor) {
+        (major, _) if major >= 9 => "7.8",
+        (8, minor) if minor >= 9 => "7.8",
+        (8, minor) if minor >= 6 => "7.1",
+        (8, _) => "7.0",
+        (7, minor) if minor >= 5 => "6.5",
+        _ => "6.1",
+    }
+}
+
+/// Returns the type of logic instructions, that operate on untyped bits and predicates.
+fn logic_type(t: ir::Type) -> String {
+    match t {
+        Type::I(size) if size > 1 => format!("b{}", size),
+        t => t.ptx().to_string(),
+    }
+}
+
 impl PTXDisplay for ir::Type {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Type::I(1) => write!(fmt, "pred"),
             Type::I(size) => write!(fmt, "s{size}", size = size),
             Type::F(size) => write!(fmt, "f{size}", size = size),
+            Type::Bf16 => write!(fmt, "bf16"),
             _ => panic!("unexpected PTX type: {}", self),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ensures the predicate guards every PTX instruction an instruction expands to.
+    #[test]
+    fn predicate_all_lines() {
+        let pred = llir::Register::new("p0", Type::I(1));
+        let d = llir::Register::new("f0", Type::F(32));
+        let a = llir::Register::new("f1", Type::F(32));
+        let inst = llir::Instruction::exp(d, a.into_operand())
+            .unwrap()
+            .predicated(pred);
+        let ptx = inst.ptx().to_string();
+        assert_eq!(ptx.lines().count(), 2);
+        for line in ptx.lines() {
+            assert!(line.starts_with("@%p0 "), "unpredicated line: {}", line);
+        }
+    }
+}
@@ -0,0 +1,923 @@
+//! Checks the well-formedness of the PTX code generated by the printer, without `ptxas`.
+//!
+//! The checker only understands the subset of PTX emitted by `CudaPrinter`: it validates that
+//! registers are declared before being used with a type matching the instruction suffixes,
+//! that vector operands have the width of the instruction, that predicates are predicate
+//! registers, that memory accesses use valid state spaces and cache operators and that
+//! branches target existing labels.
+use failure::Fail;
+use fxhash::{FxHashMap, FxHashSet};
+use std::fmt;
+
+/// An error found in PTX code.
+#[derive(Debug, Fail)]
+#[fail(display = "line {}: {}", line, message)]
+pub struct PtxError {
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    /// Describes the error.
+    pub message: String,
+}
+
+/// Checks that PTX code generated by the printer is well-formed.
+pub fn check_ptx(code: &str) -> Result<(), PtxError> {
+    let mut checker = Checker::default();
+    for (line_idx, line) in code.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        checker.line(line).map_err(|message| PtxError {
+            line: line_idx + 1,
+            message,
+        })?;
+    }
+    checker.finish().map_err(|message| PtxError {
+        line: code.lines().count(),
+        message,
+    })
+}
+
+/// A PTX type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PtxType {
+    Pred,
+    /// Untyped bits.
+    B(u32),
+    S(u32),
+    U(u32),
+    F(u32),
+    /// 16-bit brain floating point.
+    Bf16,
+    /// Pair of packed half-precision floats.
+    F16x2,
+    /// Pair of packed 16-bit brain floating points.
+    Bf16x2,
+}
+
+impl PtxType {
+    /// Parses a type suffix, without the leading dot.
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pred" => return Some(PtxType::Pred),
+            "bf16" => return Some(PtxType::Bf16),
+            "f16x2" => return Some(PtxType::F16x2),
+            "bf16x2" => return Some(PtxType::Bf16x2),
+            _ => (),
+        }
+        let (kind, bits) = (s.get(..1)?, s.get(1..)?.parse().ok()?);
+        let t = match (kind, bits) {
+            ("b", 8) | ("b", 16) | ("b", 32) | ("b", 64) => PtxType::B(bits),
+            ("s", 8) | ("s", 16) | ("s", 32) | ("s", 64) => PtxType::S(bits),
+            ("u", 8) | ("u", 16) | ("u", 32) | ("u", 64) => PtxType::U(bits),
+            ("f", 16) | ("f", 32) | ("f", 64) => PtxType::F(bits),
+            _ => return None,
+        };
+        Some(t)
+    }
+
+    /// Returns the number of bits of the type.
+    fn bits(self) -> u32 {
+        match self {
+            PtxType::Pred => 1,
+            PtxType::B(bits) | PtxType::S(bits) | PtxType::U(bits) | PtxType::F(bits) => {
+                bits
+            }
+            PtxType::Bf16 => 16,
+            PtxType::F16x2 | PtxType::Bf16x2 => 32,
+        }
+    }
+
+    fn is_int(self) -> bool {
+        match self {
+            PtxType::S(_) | PtxType::U(_) => true,
+            _ => false,
+        }
+    }
+
+    fn is_float(self) -> bool {
+        match self {
+            PtxType::F(_) | PtxType::Bf16 | PtxType::F16x2 | PtxType::Bf16x2 => true,
+            _ => false,
+        }
+    }
+
+    /// Indicates if a register of type `self` can be used as an operand of type `t`.  As in
+    /// `ptxas`, integer and bit types of the same size are interchangeable and bit types can
+    /// hold floats.
+    fn fits(self, t: PtxType) -> bool {
+        match (self, t) {
+            (PtxType::Pred, PtxType::Pred) => true,
+            (PtxType::Pred, _) | (_, PtxType::Pred) => false,
+            _ if self.is_float() || t.is_float() => {
+                self == t || self == PtxType::B(t.bits()) || t == PtxType::B(self.bits())
+            }
+            _ => self.bits() == t.bits(),
+        }
+    }
+
+    /// Returns the type twice as wide.
+    fn wide(self) -> Result<Self, String> {
+        match self {
+            PtxType::S(bits) if bits < 64 => Ok(PtxType::S(2 * bits)),
+            PtxType::U(bits) if bits < 64 => Ok(PtxType::U(2 * bits)),
+            _ => Err(format!("no wide version of {}", self)),
+        }
+    }
+}
+
+impl fmt::Display for PtxType {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PtxType::Pred => write!(fmt, ".pred"),
+            PtxType::B(bits) => write!(fmt, ".b{}", bits),
+            PtxType::S(bits) => write!(fmt, ".s{}", bits),
+            PtxType::U(bits) => write!(fmt, ".u{}", bits),
+            PtxType::F(bits) => write!(fmt, ".f{}", bits),
+            PtxType::Bf16 => write!(fmt, ".bf16"),
+            PtxType::F16x2 => write!(fmt, ".f16x2"),
+            PtxType::Bf16x2 => write!(fmt, ".bf16x2"),
+        }
+    }
+}
+
+/// Parses a type suffix, failing with an error message.
+fn parse_type(s: &str) -> Result<PtxType, String> {
+    PtxType::parse(s).ok_or_else(|| format!("invalid type .{}", s))
+}
+
+/// Parses an instruction modifier among a list of accepted values.
+fn parse_modifier<'a>(
+    modifiers: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+    accepted: &[&str],
+) -> Option<&'a str> {
+    let modifier = *modifiers.peek()?;
+    if accepted.contains(&modifier) {
+        modifiers.next()
+    } else {
+        None
+    }
+}
+
+/// Rounding modifiers of floating point results.
+const FLOAT_ROUNDINGS: &[&str] = &["rn", "rz", "rm", "rp"];
+/// Rounding modifiers of integer results.
+const INT_ROUNDINGS: &[&str] = &["rni", "rzi", "rmi", "rpi"];
+/// Comparison operators of `setp`.
+const CMP_OPS: &[&str] = &[
+    "eq", "ne", "lt", "le", "gt", "ge", "lo", "ls", "hi", "hs", "equ", "neu", "ltu",
+    "leu", "gtu", "geu", "num", "nan",
+];
+
+/// Splits a list of operands on commas that are not within braces or brackets.
+fn split_operands(operands: &str) -> Vec<&str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut result = vec![];
+    for (pos, c) in operands.char_indices() {
+        match c {
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                result.push(operands[start..pos].trim());
+                start = pos + 1;
+            }
+            _ => (),
+        }
+    }
+    let last = operands[start..].trim();
+    if !last.is_empty() || !result.is_empty() {
+        result.push(last);
+    }
+    result
+}
+
+/// Indicates if `s` is an integer literal.
+fn is_int_literal(s: &str) -> bool {
+    let s = s.trim_start_matches('-');
+    if s.starts_with("0x") || s.starts_with("0X") {
+        s.len() > 2 && s[2..].chars().all(|c| c.is_ascii_hexdigit())
+    } else {
+        !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
+    }
+}
+
+/// Indicates if `s` is a floating point literal, given in hexadecimal.
+fn is_float_literal(s: &str) -> bool {
+    let digits = match s.get(..2) {
+        Some("0f") | Some("0F") => 8,
+        Some("0d") | Some("0D") => 16,
+        _ => return false,
+    };
+    s.len() == 2 + digits && s[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Indicates if `s` is a valid PTX identifier.
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    let first_ok = chars
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_' || c == '$' || c == '%')
+        .unwrap_or(false);
+    first_ok && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// The part of the kernel the checker is in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Section {
+    Header,
+    Params,
+    Attributes,
+    Body,
+    End,
+}
+
+impl Default for Section {
+    fn default() -> Self {
+        Section::Header
+    }
+}
+
+/// Checks PTX code line by line.
+#[derive(Default)]
+struct Checker<'a> {
+    section: Section,
+    address_size: Option<u32>,
+    /// Type of the kernel parameters.
+    params: FxHashMap<&'a str, PtxType>,
+    /// Type of the declared registers.
+    registers: FxHashMap<String, PtxType>,
+    /// Variables declared in shared memory.
+    shared_vars: FxHashSet<&'a str>,
+    labels: FxHashSet<&'a str>,
+    /// Labels used by branches, with the line they are used at.
+    label_uses: Vec<&'a str>,
+}
+
+impl<'a> Checker<'a> {
+    /// Checks a line of code, stripped of comments and whitespaces.
+    fn line(&mut self, line: &'a str) -> Result<(), String> {
+        if line.is_empty() {
+            return Ok(());
+        }
+        match self.section {
+            Section::Header => self.header(line),
+            Section::Params => self.param(line),
+            Section::Attributes => match line {
+                "{" => {
+                    self.section = Section::Body;
+                    Ok(())
+                }
+                _ if line.starts_with(".reqntid ") || line.starts_with(".maxntid ") => {
+                    Ok(())
+                }
+                _ => Err(format!("unexpected kernel attribute: {}", line)),
+            },
+            Section::Body => self.body(line),
+            Section::End => Err(format!("unexpected code after the kernel: {}", line)),
+        }
+    }
+
+    /// Checks that the whole kernel has been parsed.
+    fn finish(&self) -> Result<(), String> {
+        if self.section != Section::End {
+            return Err("incomplete kernel".to_string());
+        }
+        if let Some(label) = self.label_uses.iter().find(|l| !self.labels.contains(*l)) {
+            return Err(format!("undefined label {}", label));
+        }
+        Ok(())
+    }
+
+    /// Checks the module directives preceding the kernel.
+    fn header(&mut self, line: &'a str) -> Result<(), String> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some(".version") | Some(".target") => Ok(()),
+            Some(".address_size") => {
+                let size = words.next().and_then(|s| s.parse().ok());
+                match size {
+                    Some(32) | Some(64) => {
+                        self.address_size = size;
+                        Ok(())
+                    }
+                    _ => Err(format!("invalid address size: {}", line)),
+                }
+            }
+            Some(".entry") if line.ends_with('(') => {
+                let name = &line[".entry".len()..line.len() - 1].trim();
+                if !is_identifier(name) {
+                    return Err(format!("invalid kernel name: {}", name));
+                }
+                if self.address_size.is_none() {
+                    return Err("missing .address_size directive".to_string());
+                }
+                self.section = Section::Params;
+                Ok(())
+            }
+            _ => Err(format!("unexpected directive: {}", line)),
+        }
+    }
+
+    /// Checks the declaration of a kernel parameter.
+    fn param(&mut self, line: &'a str) -> Result<(), String> {
+        if line == ")" {
+            self.section = Section::Attributes;
+            return Ok(());
+        }
+        let decl = line.trim_end_matches(',');
+        let words = decl.split_whitespace().collect::<Vec<_>>();
+        match words[..] {
+            [".param", t, name] if t.starts_with('.') && is_identifier(name) => {
+                let t = parse_type(&t[1..])?;
+                if self.params.insert(name, t).is_some() {
+                    return Err(format!("parameter {} declared twice", name));
+                }
+                Ok(())
+            }
+            _ => Err(format!("invalid parameter declaration: {}", line)),
+        }
+    }
+
+    /// Checks a line of the kernel body.
+    fn body(&mut self, line: &'a str) -> Result<(), String> {
+        if line == "}" {
+            self.section = Section::End;
+            Ok(())
+        } else if line.trim_end_matches(':').len() + 1 == line.len() {
+            let label = &line[..line.len() - 1];
+            if !is_identifier(label) {
+                return Err(format!("invalid label: {}", label));
+            }
+            if !self.labels.insert(label) {
+                return Err(format!("label {} defined twice", label));
+            }
+            Ok(())
+        } else if !line.ends_with(';') {
+            Err(format!("missing semicolon: {}", line))
+        } else if line.starts_with(".reg") {
+            self.reg_decl(&line[..line.len() - 1])
+        } else if line.starts_with(".shared") {
+            self.shared_decl(&line[..line.len() - 1])
+        } else {
+            self.instruction(&line[..line.len() - 1])
+        }
+    }
+
+    /// Checks a register declaration of the form `.reg.<type> %<prefix><<num>>`.
+    fn reg_decl(&mut self, decl: &str) -> Result<(), String> {
+        let invalid = || format!("invalid register declaration: {}", decl);
+        let mut words = decl.split_whitespace();
+        let (t, regs) = match (words.next(), words.next(), words.next()) {
+            (Some(t), Some(regs), None) => (t, regs),
+            _ => return Err(invalid()),
+        };
+        let t = parse_type(t.trim_start_matches(".reg."))?;
+        let lt = regs.find('<').ok_or_else(invalid)?;
+        let (prefix, num) = (&regs[..lt], &regs[lt + 1..]);
+        let num: usize = num.trim_end_matches('>').parse().map_err(|_| invalid())?;
+        if !prefix.starts_with('%') || !is_identifier(&prefix[1..]) {
+            return Err(invalid());
+        }
+        for i in 0..num {
+            let name = format!("{}{}", prefix, i);
+            if self.registers.insert(name, t).is_some() {
+                return Err(format!("register {}{} declared twice", prefix, i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a shared memory declaration of the form
+    /// `.shared.align <n> .<type> %<name>[<size>]`.
+    fn shared_decl(&mut self, decl: &'a str) -> Result<(), String> {
+        let invalid = || format!("invalid shared memory declaration: {}", decl);
+        let words = decl.split_whitespace().collect::<Vec<_>>();
+        let (align, t, var) = match words[..] {
+            [".shared.align", align, t, var] => (align, t, var),
+            _ => return Err(invalid()),
+        };
+        match align.parse::<u32>() {
+            Ok(align) if align.is_power_of_two() => (),
+            _ => return Err(invalid()),
+        }
+        parse_type(t.trim_start_matches('.'))?;
+        let bracket = var.find('[').ok_or_else(invalid)?;
+        let (name, size) = (&var[..bracket], &var[bracket + 1..]);
+        if !size.ends_with(']') || !is_int_literal(&size[..size.len() - 1]) {
+            return Err(invalid());
+        }
+        if !is_identifier(name) || !self.shared_vars.insert(name) {
+            return Err(invalid());
+        }
+        Ok(())
+    }
+
+    /// Returns the type of a register, or the type of special registers and shared memory
+    /// variables.
+    fn register_t(&self, reg: &str) -> Result<PtxType, String> {
+        if let Some(&t) = self.registers.get(reg) {
+            return Ok(t);
+        }
+        match reg {
+            "%tid.x" | "%tid.y" | "%tid.z" | "%ctaid.x" | "%ctaid.y" | "%ctaid.z" => {
+                Ok(PtxType::U(32))
+            }
+            // The address of a variable is taken in the shared memory window.
+            _ if self.shared_vars.contains(reg) => Ok(PtxType::U(32)),
+            _ => Err(format!("undeclared register {}", reg)),
+        }
+    }
+
+    /// Checks that a register holds values of type `t`.
+    fn check_register(&self, reg: &str, t: PtxType) -> Result<(), String> {
+        let reg_t = self.register_t(reg)?;
+        if reg_t.fits(t) {
+            Ok(())
+        } else {
+            Err(format!("register {} of type {} used as {}", reg, reg_t, t))
+        }
+    }
+
+    /// Checks a destination operand.
+    fn check_dest(&self, operand: &str, t: PtxType) -> Result<(), String> {
+        if operand.starts_with('%') {
+            self.check_register(operand, t)
+        } else {
+            Err(format!("invalid destination operand: {}", operand))
+        }
+    }
+
+    /// Checks a source operand, which may be a register or an immediate.
+    fn check_source(&self, operand: &str, t: PtxType) -> Result<(), String> {
+        if operand.starts_with('%') {
+            self.check_register(operand, t)
+        } else if is_int_literal(operand) {
+            if t == PtxType::Pred || t.is_float() {
+                Err(format!("integer literal {} used as {}", operand, t))
+            } else {
+                Ok(())
+            }
+        } else if is_float_literal(operand) {
+            if t.is_float() {
+                Ok(())
+            } else {
+                Err(format!("float literal {} used as {}", operand, t))
+            }
+        } else {
+            Err(format!("invalid operand: {}", operand))
+        }
+    }
+
+    /// Checks an operand that is either a scalar or a vector of `width` elements.
+    fn check_vector(
+        &self,
+        operand: &str,
+        t: PtxType,
+        width: usize,
+        is_dest: bool,
+    ) -> Result<(), String> {
+        let check = |op| {
+            if is_dest {
+                self.check_dest(op, t)
+            } else {
+                self.check_source(op, t)
+            }
+        };
+        if width == 1 {
+            return check(operand);
+        }
+        if !operand.starts_with('{') || !operand.ends_with('}') {
+            return Err(format!(
+                "expected a vector of {} elements: {}",
+                width, operand
+            ));
+        }
+        let elems = split_operands(&operand[1..operand.len() - 1]);
+        if elems.len() != width {
+            return Err(format!(
+                "vector {} has {} elements instead of {}",
+                operand,
+                elems.len(),
+                width
+            ));
+        }
+        elems.into_iter().try_for_each(check)
+    }
+
+    /// Checks an `mma` fragment, given as a braced list of `width` packed registers.
+    fn check_fragment(&self, operand: &str, width: usize) -> Result<(), String> {
+        if !operand.starts_with('{') || !operand.ends_with('}') {
+            return Err(format!("expected a fragment: {}", operand));
+        }
+        if width == 1 {
+            self.check_source(operand[1..operand.len() - 1].trim(), PtxType::B(32))
+        } else {
+            self.check_vector(operand, PtxType::B(32), width, false)
+        }
+    }
+
+    /// Checks an address operand in the given state space.
+    fn check_address(&self, operand: &str, state_space: &str) -> Result<(), String> {
+        if !operand.starts_with('[') || !operand.ends_with(']') {
+            return Err(format!("invalid address: {}", operand));
+        }
+        let addr = &operand[1..operand.len() - 1];
+        if state_space == "param" {
+            return if self.params.contains_key(addr) {
+                Ok(())
+            } else {
+                Err(format!("undeclared parameter {}", addr))
+            };
+        }
+        let (base, offset) = match addr.find(&['+', '-'][..]) {
+            Some(pos) => (&addr[..pos], Some(&addr[pos + 1..])),
+            None => (addr, None),
+        };
+        if offset.map(|o| !is_int_literal(o)).unwrap_or(false) {
+            return Err(format!("invalid address offset: {}", operand));
+        }
+        let base_t = self.register_t(base)?;
+        let valid_size = match state_space {
+            "global" => Some(base_t.bits()) == self.address_size,
+            _ => base_t.bits() == 32 || Some(base_t.bits()) == self.address_size,
+        };
+        if base_t.is_float() || base_t == PtxType::Pred || !valid_size {
+            return Err(format!(
+                "register {} of type {} cannot hold a .{} address",
+                base, base_t, state_space
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks an instruction, without the trailing semicolon.
+    fn instruction(&mut self, inst: &'a str) -> Result<(), String> {
+        let inst = if inst.starts_with('@') {
+            let space = inst
+                .find(' ')
+                .ok_or("missing instruction after predicate")?;
+            let predicate = inst[1..space].trim_start_matches('!');
+            self.check_register(predicate, PtxType::Pred)?;
+            inst[space..].trim_start()
+        } else {
+            inst
+        };
+        let (opcode, operands) = match inst.find(char::is_whitespace) {
+            Some(pos) => (&inst[..pos], split_operands(&inst[pos..])),
+            None => (inst, vec![]),
+        };
+        let mut parts = opcode.split('.');
+        let name = parts.next().unwrap_or("");
+        let mut modifiers = parts.peekable();
+        let expected_operands = match name {
+            "mov" => {
+                let t = parse_type(modifiers.next().unwrap_or(""))?;
+                self.with_operands(&operands, 2)?;
+                // Vectors of two values are packed into, or unpacked from, bit types.
+                let is_packing = operands.iter().any(|op| op.starts_with('{'));
+                if is_packing && t != PtxType::B(32) && t != PtxType::B(64) {
+                    return Err(format!("cannot pack vectors into {}", t));
+                }
+                let half_t = PtxType::B(t.bits() / 2);
+                if operands[0].starts_with('{') {
+                    self.check_vector(operands[0], half_t, 2, true)?;
+                } else {
+                    self.check_dest(operands[0], t)?;
+                }
+                if operands[1].starts_with('{') {
+                    self.check_vector(operands[1], half_t, 2, false)?;
+                } else {
+                    self.check_source(operands[1], t)?;
+                }
+                2
+            }
+            "ld" | "st" => self.memory(name, &mut modifiers, &operands)?,
+            "cvt" => {
+                let int_rnd = parse_modifier(&mut modifiers, INT_ROUNDINGS);
+                let float_rnd = parse_modifier(&mut modifiers, FLOAT_ROUNDINGS);
+                let dst_t = parse_type(modifiers.next().unwrap_or(""))?;
+                let src_t = parse_type(modifiers.next().unwrap_or(""))?;
+                let needs_int_rnd = dst_t.is_int() && src_t.is_float();
+                let needs_float_rnd = dst_t.is_float()
+                    && (src_t.is_int()
+                        || src_t.is_float() && dst_t.bits() < src_t.bits()
+                        || src_t.is_float()
+                            && dst_t.bits() == src_t.bits()
+                            && dst_t != src_t);
+                let rounding_ok = match (int_rnd, float_rnd) {
+                    (None, None) => !needs_int_rnd && !needs_float_rnd,
+                    (Some(_), None) => {
+                        needs_int_rnd || dst_t.is_float() && dst_t == src_t
+                    }
+                    (None, Some(_)) => needs_float_rnd,
+                    (Some(_), Some(_)) => false,
+                };
+                if !rounding_ok {
+                    return Err(format!("invalid rounding in {}", opcode));
+                }
+                self.with_operands(&operands, 2)?;
+                self.check_dest(operands[0], dst_t)?;
+                self.check_source(operands[1], src_t)?;
+                2
+            }
+            "add" | "sub" | "mul" | "div" | "max" | "min" | "mad" | "fma" => {
+                self.arithmetic(name, &mut modifiers, &operands)?
+            }
+            "mma" => {
+                for &modifier in &["sync", "aligned", "m16n8k8", "row", "col"] {
+                    parse_modifier(&mut modifiers, &[modifier])
+                        .ok_or_else(|| format!("invalid modifiers in {}", opcode))?;
+                }
+                let types = (0..4)
+                    .map(|_| parse_type(modifiers.next().unwrap_or("")))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let valid_types = types[0] == PtxType::F(32)
+                    && types[3] == PtxType::F(32)
+                    && types[1] == types[2]
+                    && (types[1] == PtxType::F(16) || types[1] == PtxType::Bf16);
+                if !valid_types {
+                    return Err(format!("invalid types in {}", opcode));
+                }
+                self.with_operands(&operands, 4)?;
+                self.check_vector(operands[0], PtxType::F(32), 4, true)?;
+                self.check_fragment(operands[1], 2)?;
+                self.check_fragment(operands[2], 1)?;
+                self.check_vector(operands[3], PtxType::F(32), 4, false)?;
+                4
+            }
+            "setp" => {
+                parse_modifier(&mut modifiers, CMP_OPS)
+                    .ok_or_else(|| format!("invalid comparison in {}", opcode))?;
+                let t = parse_type(modifiers.next().unwrap_or(""))?;
+                self.with_operands(&operands, 3)?;
+                self.check_dest(operands[0], PtxType::Pred)?;
+                self.check_source(operands[1], t)?;
+                self.check_source(operands[2], t)?;
+                3
+            }
+            "and" | "or" | "xor" => {
+                let t = parse_type(modifiers.next().unwrap_or(""))?;
+                match t {
+                    PtxType::Pred | PtxType::B(16) | PtxType::B(32) | PtxType::B(64) => {}
+                    _ => return Err(format!("invalid type {} for {}", t, name)),
+                }
+                self.with_operands(&operands, 3)?;
+                self.check_dest(operands[0], t)?;
+                self.check_source(operands[1], t)?;
+                self.check_source(operands[2], t)?;
+                3
+            }
+            "shl" => {
+                let t = parse_type(modifiers.next().unwrap_or(""))?;
+                match t {
+                    PtxType::B(16) | PtxType::B(32) | PtxType::B(64) => (),
+                    _ => return Err(format!("invalid type {} for {}", t, name)),
+                }
+                self.with_operands(&operands, 3)?;
+                self.check_dest(operands[0], t)?;
+                self.check_source(operands[1], t)?;
+                self.check_source(operands[2], PtxType::U(32))?;
+                3
+            }
+            "ex2" => {
+                parse_modifier(&mut modifiers, &["approx"])
+                    .ok_or_else(|| format!("{} must be approximate", opcode))?;
+                let t = parse_type(modifiers.next().unwrap_or(""))?;
+                if t != PtxType::F(32) {
+                    return Err(format!("invalid type {} for ex2", t));
+                }
+                self.with_operands(&operands, 2)?;
+                self.check_dest(operands[0], t)?;
+                self.check_source(operands[1], t)?;
+                2
+            }
+            "bra" => {
+                parse_modifier(&mut modifiers, &["uni"]);
+                self.with_operands(&operands, 1)?;
+                if !is_identifier(operands[0]) {
+                    return Err(format!("invalid label: {}", operands[0]));
+                }
+                self.label_uses.push(operands[0]);
+                1
+            }
+            "bar" => {
+                parse_modifier(&mut modifiers, &["sync"])
+                    .ok_or_else(|| format!("invalid barrier: {}", opcode))?;
+                self.with_operands(&operands, 1)?;
+                if !is_int_literal(operands[0]) {
+                    return Err(format!("invalid barrier id: {}", operands[0]));
+                }
+                1
+            }
+            "ret" => 0,
+            _ => return Err(format!("unknown instruction: {}", opcode)),
+        };
+        if let Some(modifier) = modifiers.next() {
+            return Err(format!("unexpected modifier .{} in {}", modifier, opcode));
+        }
+        self.with_operands(&operands, expected_operands)
+    }
+
+    /// Checks the number of operands of an instruction.
+    fn with_operands(&self, operands: &[&str], num: usize) -> Result<(), String> {
+        if operands.len() == num {
+            Ok(())
+        } else {
+            Err(format!(
+                "expected {} operands, found {}: {}",
+                num,
+                operands.len(),
+                operands.join(", ")
+            ))
+        }
+    }
+
+    /// Checks a `ld` or `st` instruction.  Returns the number of operands.
+    fn memory<'b>(
+        &self,
+        name: &str,
+        modifiers: &mut std::iter::Peekable<impl Iterator<Item = &'b str>>,
+        operands: &[&str],
+    ) -> Result<usize, String> {
+        let state_space = modifiers.next().unwrap_or("");
+        let cache_ops: &[&str] = match (name, state_space) {
+            (_, "param") => &[],
+            ("ld", "global") => &["ca", "cg", "cs", "lu", "cv", "nc"],
+            ("ld", "shared") => &["ca", "cg", "cs", "lu", "cv"],
+            ("st", "global") | ("st", "shared") => &["wb", "cg", "cs", "wt"],
+            _ => {
+                return Err(format!("invalid state space .{} for {}", state_space, name))
+            }
+        };
+        parse_modifier(modifiers, cache_ops);
+        let width = match parse_modifier(modifiers, &["v2", "v4"]) {
+            Some(vector) => vector[1..].parse().unwrap(),
+            None => 1,
+        };
+        let t = parse_type(modifiers.next().unwrap_or(""))?;
+        if t == PtxType::Pred {
+            return Err(format!("cannot access memory with type {}", t));
+        }
+        self.with_operands(operands, 2)?;
+        if name == "ld" {
+            self.check_vector(operands[0], t, width, true)?;
+            self.check_address(operands[1], state_space)?;
+        } else {
+            self.check_address(operands[0], state_space)?;
+            self.check_vector(operands[1], t, width, false)?;
+        }
+        Ok(2)
+    }
+
+    /// Checks an arithmetic instruction.  Returns the number of operands.
+    fn arithmetic<'b>(
+        &self,
+        name: &str,
+        modifiers: &mut std::iter::Peekable<impl Iterator<Item = &'b str>>,
+        operands: &[&str],
+    ) -> Result<usize, String> {
+        let mul_spec = match name {
+            "mul" | "mad" => parse_modifier(modifiers, &["lo", "hi", "wide"]),
+            _ => None,
+        };
+        let rounding = parse_modifier(modifiers, FLOAT_ROUNDINGS);
+        let t = parse_type(modifiers.next().unwrap_or(""))?;
+        let valid = if t.is_float() {
+            mul_spec.is_none()
+                && match name {
+                    "max" | "min" => rounding.is_none(),
+                    // Fused multiply-adds must specify the rounding.
+                    "fma" => rounding.is_some(),
+                    "mad" => false,
+                    // There is no half-precision division, and divisions must specify
+                    // the rounding.
+                    "div" => {
+                        rounding.is_some() && (t == PtxType::F(32) || t == PtxType::F(64))
+                    }
+                    _ => true,
+                }
+        } else {
+            t.is_int()
+                && rounding.is_none()
+                && name != "fma"
+                && (mul_spec.is_some() || name != "mul" && name != "mad")
+        };
+        if !valid {
+            return Err(format!("invalid modifiers for {}{}", name, t));
+        }
+        let dst_t = if mul_spec == Some("wide") {
+            t.wide()?
+        } else {
+            t
+        };
+        let num_operands = if name == "mad" || name == "fma" { 4 } else { 3 };
+        self.with_operands(operands, num_operands)?;
+        self.check_dest(operands[0], dst_t)?;
+        self.check_source(operands[1], t)?;
+        self.check_source(operands[2], t)?;
+        if num_operands == 4 {
+            self.check_source(operands[3], dst_t)?;
+        }
+        Ok(num_operands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps instructions in a kernel.
+    fn kernel(body: &str) -> String {
+        format!(
+            ".version 6.1
+.target sm_60
+.address_size 64
+
+.entry test(
+  .param .s64 x,
+  .param .s32 n
+)
+.reqntid 32
+{{
+  .reg.s32 %r<4>;
+  .reg.s64 %rd<2>;
+  .reg.f32 %f<4>;
+  .reg.pred %p<2>;
+{}
+  ret;
+}}",
+            body
+        )
+    }
+
+    /// Ensures valid code is accepted.
+    #[test]
+    fn valid_kernel() {
+        let code = kernel(
+            "  ld.param.s64 %rd0, [x];
+  mov.u32 %r0, %ctaid.x;
+  .shared.align 16 .u8 %shmem0[64];
+  mov.u32 %r1, %shmem0;
+LOOP_0:
+  ld.global.nc.v4.f32 {%f0, %f1, %f2, %f3}, [%rd0+0x10];
+  st.shared.wb.f32 [%r1], %f0;
+  mul.wide.s32 %rd1, %r0, 4;
+  fma.rn.f32 %f0, %f1, 0D3FF0000000000000, %f2;
+  setp.lt.s32 %p0, %r0, %r2;
+  and.pred %p1, %p0, %p0;
+  cvt.rn.f32.s32 %f0, %r0;
+  @%p0 bra.uni LOOP_0;
+  bar.sync 0;
+  .reg.b16 %h<4>;
+  .reg.b32 %hx<3>;
+  mov.b16 %h0, 0x3C00;
+  add.rn.f16 %h1, %h0, %h0;
+  cvt.rn.f16.f32 %h2, %f0;
+  cvt.f32.bf16 %f0, %h2;
+  mov.b32 %hx0, {%h0, %h1};
+  fma.rn.f16x2 %hx1, %hx0, %hx0, %hx0;
+  mov.b32 {%h2, %h3}, %hx1;
+  mma.sync.aligned.m16n8k8.row.col.f32.f16.f16.f32 {%f0, %f1, %f2, %f3}, {%hx0, %hx1}, {%hx2}, {%f0, %f1, %f2, %f3};",
+        );
+        if let Err(err) = check_ptx(&code) {
+            panic!("{}\n{}", err, code);
+        }
+    }
+
+    /// Ensures common errors are detected.
+    #[test]
+    fn invalid_instructions() {
+        let invalid = [
+            // Undeclared register.
+            "  mov.s32 %r4, 0;",
+            // Type mismatch.
+            "  add.s32 %r0, %f0, %r1;",
+            // Vector width.
+            "  ld.global.v4.f32 {%f0, %f1}, [%rd0];",
+            // Predicate register.
+            "  @%r0 mov.s32 %r1, 0;",
+            // State space.
+            "  ld.local.s32 %r0, [%rd0];",
+            // Non-coherent loads from shared memory.
+            "  ld.shared.nc.s32 %r0, [%r1];",
+            // Global addresses must have the size of pointers.
+            "  ld.global.s32 %r0, [%r1];",
+            // Undefined label.
+            "  bra.uni LOOP_1;",
+            // Missing integer rounding.
+            "  cvt.s32.f32 %r0, %f0;",
+            // Logic operators work on bits.
+            "  and.s32 %r0, %r1, %r2;",
+            // There is no half-precision division.
+            "  .reg.b16 %h<2>;\n  div.rn.f16 %h0, %h1, %h1;",
+            // Floating-point divisions must specify the rounding.
+            "  div.f32 %f0, %f1, %f1;",
+            // Only 16-bit values can be packed into 32 bits.
+            "  .reg.b32 %hx<1>;\n  mov.b32 %hx0, {%f0, %f1};",
+            // Matrix multiply-accumulate only takes half-precision inputs.
+            "  mma.sync.aligned.m16n8k8.row.col.f32.f32.f32.f32 {%f0, %f1, %f2, %f3}, \
+             {%r0, %r1}, {%r2}, {%f0, %f1, %f2, %f3};",
+        ];
+        for body in &invalid {
+            assert!(check_ptx(&kernel(body)).is_err(), "{}", body);
+        }
+    }
+}
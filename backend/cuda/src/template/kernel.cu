@@ -0,0 +1,62 @@
+#ifdef __CUDACC_RTC__
+typedef signed char int8_t;
+typedef short int16_t;
+typedef int int32_t;
+typedef long long int64_t;
+#else
+#include <stdint.h>
+#endif
+#include <cuda_fp16.h>
+#include <cuda_bf16.h>
+
+#define __max(a, b) max(a, b)
+#define __min(a, b) min(a, b)
+
+#define __mul32Hi(a, b) __mulhi(a, b)
+#define __mul64Hi(a, b) __mul64hi(a, b)
+#define __mul32Wide(a, b) ((int64_t)(a) * (int64_t)(b))
+
+#define __mad32(a, b, c) (((a) * (b)) + (c))
+#define __mad64(a, b, c) (((a) * (b)) + (c))
+#define __mad32Hi(a, b, c) (__mul32Hi(a, b) + (c))
+#define __mad32Wide(a, b, c) (__mul32Wide(a, b) + (c))
+
+#define __fma(a, b, c) fma(a, b, c)
+
+// Half-precision overloads of the math functions used above.
+__device__ inline __half max(__half a, __half b) {{ return __hmax(a, b); }}
+__device__ inline __half min(__half a, __half b) {{ return __hmin(a, b); }}
+__device__ inline __half fma(__half a, __half b, __half c) {{ return __hfma(a, b, c); }}
+__device__ inline __nv_bfloat16 max(__nv_bfloat16 a, __nv_bfloat16 b) {{ return __hmax(a, b); }}
+__device__ inline __nv_bfloat16 min(__nv_bfloat16 a, __nv_bfloat16 b) {{ return __hmin(a, b); }}
+__device__ inline __nv_bfloat16 fma(__nv_bfloat16 a, __nv_bfloat16 b, __nv_bfloat16 c) {{
+  return __hfma(a, b, c);
+}}
+
+// Vectors of half-precision values, for vectorized memory accesses.
+struct __align__(8) half4 {{ __half x, y, z, w; }};
+struct __align__(8) nv_bfloat164 {{ __nv_bfloat16 x, y, z, w; }};
+__device__ inline nv_bfloat162 make_nv_bfloat162(__nv_bfloat16 x, __nv_bfloat16 y) {{
+  return nv_bfloat162(x, y);
+}}
+__device__ inline half4 make_half4(__half x, __half y, __half z, __half w) {{
+  half4 v = {{x, y, z, w}};
+  return v;
+}}
+__device__ inline nv_bfloat164 make_nv_bfloat164(
+    __nv_bfloat16 x, __nv_bfloat16 y, __nv_bfloat16 z, __nv_bfloat16 w) {{
+  nv_bfloat164 v = {{x, y, z, w}};
+  return v;
+}}
+
+// Packs two half-precision values into a 32-bit register, the first one in the low bits.
+__device__ inline unsigned __pack_half2(__half lo, __half hi) {{
+  return (unsigned)__half_as_ushort(lo) | (unsigned)__half_as_ushort(hi) << 16;
+}}
+__device__ inline unsigned __pack_half2(__nv_bfloat16 lo, __nv_bfloat16 hi) {{
+  return (unsigned)__bfloat16_as_ushort(lo) | (unsigned)__bfloat16_as_ushort(hi) << 16;
+}}
+
+extern "C" __global__ void __launch_bounds__({num_thread}) {name}({params})
+{{
+{body}}}
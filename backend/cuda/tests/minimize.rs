@@ -0,0 +1,52 @@
+//! Checks the minimization of bound violations on the performance model of a GPU, without
+//! a GPU.
+use telamon::explorer::choice::ActionEx;
+use telamon::explorer::local_selection;
+use telamon::explorer::minimize::minimize_bound_violation;
+use telamon::model::bound;
+use telamon::search_space::SearchSpace;
+use telamon_cuda as cuda;
+use telamon_kernels::{linalg, Kernel, KernelBuilder};
+
+/// Indicates if the bound of the candidate obtained by applying `actions` is above `runtime`.
+fn violates(
+    context: &cuda::ModelContext,
+    space: &SearchSpace,
+    actions: &[ActionEx],
+    runtime: f64,
+) -> bool {
+    actions
+        .iter()
+        .try_fold(space.clone(), |space, action| action.apply_to(space))
+        .map(|space| bound(&space, context).value() > runtime)
+        .unwrap_or(false)
+}
+
+/// Ensures the decisions of a matrix-vector product are shrunk to a smaller subset that still
+/// violates a runtime below the bound of the implementation.
+#[test]
+fn shrink_matvec() {
+    let _ = env_logger::try_init();
+    let mut context = cuda::ModelContext::from_bundled("gtx_1080").unwrap();
+    let (signature, kernel, context) = KernelBuilder::new()
+        .build::<linalg::MatVec<f32>, _>((64, 32, true), &mut context);
+    let space = kernel.build_body(signature.into(), context).remove(0).space;
+    let implementation =
+        local_selection::first_implementation(context, space.clone()).unwrap();
+    let mut actions = implementation.actions.iter().cloned().collect::<Vec<_>>();
+    // The actions of a candidate are listed from the last one.
+    actions.reverse();
+    let runtime = 0.9 * implementation.bound.value();
+    assert!(violates(context, &space, &actions, runtime));
+
+    let violation = minimize_bound_violation(context, &space, &actions, runtime)
+        .unwrap()
+        .expect("no violation found");
+    assert!(violation.actions.len() < actions.len());
+    assert!(violation.bound_after.value() > runtime);
+    assert!(violates(context, &space, &violation.actions, runtime));
+
+    // The bounds along the path are all below an infinite runtime.
+    let none = minimize_bound_violation(context, &space, &actions, std::f64::INFINITY);
+    assert!(none.unwrap().is_none());
+}
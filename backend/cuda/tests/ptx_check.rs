@@ -0,0 +1,366 @@
+//! Checks the PTX and CUDA C generated for random implementations of the kernels, without
+//! a GPU.
+use rand::prelude::*;
+use telamon::codegen;
+use telamon::device::Context;
+use telamon::explorer::choice::{self, fix_order};
+use telamon::explorer::config::ChoiceOrdering;
+use telamon::explorer::Candidate;
+use telamon::search_space::*;
+use telamon::{helper, ir, model};
+use telamon_cuda as cuda;
+use telamon_kernels::{linalg, Kernel, KernelBuilder};
+
+/// Number of implementations to check for each kernel.
+const NUM_DESCENTS: usize = 4;
+
+/// Descends to a random implementation of `candidate`, or returns `None` on a dead-end.
+/// Children are picked by a generator seeded with `seed`, so that failures can be
+/// reproduced.
+fn random_descent(
+    context: &dyn Context,
+    mut candidate: Candidate,
+    seed: u8,
+) -> Option<Candidate> {
+    let mut rng = StdRng::from_seed([seed; 32]);
+    let ordering = ChoiceOrdering::default();
+    loop {
+        let choice = choice::list(&ordering, &candidate.space).next();
+        let choice = match choice {
+            Some(choice) => choice,
+            None => return Some(candidate),
+        };
+        let mut children = candidate.apply_choice(context, choice);
+        if children.is_empty() {
+            return None;
+        }
+        let index = rng.gen_range(0, children.len());
+        candidate = children.swap_remove(index);
+    }
+}
+
+/// Performs sanity checks on CUDA C code, that cannot be compiled without the CUDA
+/// toolkit.
+fn check_cuda_c(code: &str) -> Result<(), String> {
+    if !code.contains("extern \"C\" __global__ void") {
+        return Err("missing kernel entry point".to_string());
+    }
+    for &(open, close) in &[('{', '}'), ('(', ')'), ('[', ']')] {
+        let mut depth = 0i32;
+        for c in code.chars() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+            }
+            if depth < 0 {
+                return Err(format!("unmatched `{}`", close));
+            }
+        }
+        if depth != 0 {
+            return Err(format!("unmatched `{}`", open));
+        }
+    }
+    Ok(())
+}
+
+/// Generates random implementations of a kernel and checks the generated PTX and CUDA C.
+fn check_kernel<'a, K: Kernel<'a>>(
+    params: K::Parameters,
+    context: &'a mut cuda::ModelContext,
+) {
+    let gpu = context.gpu().clone();
+    let (signature, kernel, context) =
+        KernelBuilder::new().build::<K, _>(params, context);
+    let candidates = kernel.build_body(signature.into(), context);
+    for i in 0..NUM_DESCENTS {
+        let candidate = candidates[i % candidates.len()].clone();
+        let leaf = match random_descent(context, candidate, i as u8) {
+            Some(leaf) => leaf,
+            // Dead-ends are expected, they just do not generate code.
+            None => continue,
+        };
+        // Orders between instructions are fixed by the explorer before generating code.
+        let space = fix_order(leaf.space);
+        let function = codegen::Function::build(&space);
+        let ptx = gpu.print_ptx(&function);
+        if let Err(err) = cuda::check_ptx(&ptx) {
+            panic!(
+                "invalid PTX for kernel {}, seed {}, with actions {:?}: {}\n{}",
+                K::name(),
+                i,
+                leaf.actions,
+                err,
+                ptx
+            );
+        }
+        let mut code = Vec::new();
+        gpu.print_cuda_c(&function, &mut code);
+        let code = String::from_utf8(code).unwrap();
+        if let Err(err) = check_cuda_c(&code) {
+            panic!(
+                "invalid CUDA C for kernel {}, seed {}, with actions {:?}: {}\n{}",
+                K::name(),
+                i,
+                leaf.actions,
+                err,
+                code
+            );
+        }
+    }
+}
+
+macro_rules! test_ptx {
+    ($name:ident, $kernel:ty, $params:expr) => {
+        #[test]
+        fn $name() {
+            let _ = env_logger::try_init();
+            let mut context = cuda::ModelContext::from_bundled("gtx_1080").unwrap();
+            check_kernel::<$kernel>($params, &mut context);
+        }
+    };
+}
+
+test_ptx!(axpy, linalg::Axpy<f32>, (1 << 15, true));
+test_ptx!(mv, linalg::MatVec<f32>, (1 << 4, 1 << 2, true));
+test_ptx!(gesummv, linalg::Gesummv<f32>, (1 << 4, 1 << 4, true));
+test_ptx!(
+    fused_mm_identity,
+    linalg::FusedMM<f32>,
+    linalg::FusedMMP::new(16, 16, 16)
+);
+test_ptx!(
+    fused_mm_relu,
+    linalg::FusedMM<f32>,
+    linalg::FusedMMP::new(16, 16, 16).activation_fun(linalg::ActivationFunction::ReLU)
+);
+test_ptx!(
+    fused_mm_sigmoid,
+    linalg::FusedMM<f32>,
+    linalg::FusedMMP::new(16, 16, 16).activation_fun(linalg::ActivationFunction::Sigmoid)
+);
+test_ptx!(
+    batch_mm,
+    linalg::BatchMM<f32>,
+    linalg::BatchMMP::new(4, 16, 16, 16)
+);
+
+/// Ensures warp-level matrix multiply-accumulate instructions generate valid PTX.  None of
+/// the bundled GPUs supports them, so the test uses a V100 with the compute capability of
+/// an A100.
+#[test]
+fn mixed_mm() {
+    let _ = env_logger::try_init();
+    let context = cuda::ModelContext::from_bundled("tesla_v100").unwrap();
+    let mut gpu = cuda::Gpu::clone(context.gpu());
+    gpu.sm_major = 8;
+    gpu.sm_minor = 0;
+    let mut context = cuda::ModelContext::new(gpu);
+    check_kernel::<linalg::MixedMM>((32, 16, 16), &mut context);
+}
+
+/// Indicates if a CFG contains a software-pipelined loop.
+fn has_pipeline(cfg: &codegen::Cfg) -> bool {
+    match cfg {
+        codegen::Cfg::Root(body)
+        | codegen::Cfg::Loop(_, body)
+        | codegen::Cfg::Threads(_, _, body) => body.iter().any(has_pipeline),
+        codegen::Cfg::Pipeline(..) => true,
+        codegen::Cfg::Instruction(..) => false,
+    }
+}
+
+/// Ensures loops around double-buffered temporary memory are pipelined.
+#[test]
+fn pipelined_tmp_mem() {
+    let _ = env_logger::try_init();
+    let context = cuda::ModelContext::from_bundled("gtx_1080").unwrap();
+    let signature = ir::Signature::new("pipelined_tmp_mem");
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    let d0 = builder.open_dim_ex(ir::Size::new_const(16), DimKind::LOOP);
+    let d1 = builder.open_dim_ex(ir::Size::new_const(32), DimKind::THREAD);
+    let inst0 = builder.mov(&0f32);
+    let d2 = builder.open_mapped_dim(&d1);
+    builder.action(Action::DimKind(d2[0], DimKind::THREAD));
+    builder.mov(&helper::TmpArray(inst0));
+    builder.order(&d1, &d2, Order::BEFORE);
+    // Threads must communicate through memory.
+    builder.action(Action::ThreadMapping(
+        d1[0],
+        d2[0],
+        ThreadMapping::MAPPED_OUT,
+    ));
+    builder.close_dim(&d2);
+    builder.close_dim(&d0);
+    let mut space = builder.get();
+    let mem = space.ir_instance().mem_blocks().next().unwrap().mem_id();
+    let num_stages = NumericSet::new_eq(&ir::mem::NUM_STAGES, 2, &());
+    space
+        .apply_decisions(vec![Action::NumStages(mem, num_stages)])
+        .unwrap();
+    let bound = model::bound(&space, &context);
+    let candidate = Candidate::new(space, bound);
+    for i in 0..NUM_DESCENTS {
+        let leaf = random_descent(&context, candidate.clone(), i as u8).unwrap();
+        let space = fix_order(leaf.space);
+        let function = codegen::Function::build(&space);
+        let block = function.mem_blocks().next().unwrap();
+        assert_eq!(block.alloc_size().as_int(), Some(2 * 32 * 4));
+        assert!(has_pipeline(function.cfg()), "{:?}", function.cfg());
+        let ptx = context.gpu().print_ptx(&function);
+        if let Err(err) = cuda::check_ptx(&ptx) {
+            panic!("invalid PTX with seed {}: {}\n{}", i, err, ptx);
+        }
+    }
+}
+
+/// Lists the loads prefetched by the pipelined loops of a CFG.
+fn prefetched_loads(cfg: &codegen::Cfg) -> Vec<ir::InstId> {
+    match cfg {
+        codegen::Cfg::Root(body)
+        | codegen::Cfg::Loop(_, body)
+        | codegen::Cfg::Threads(_, _, body) => {
+            body.iter().flat_map(prefetched_loads).collect()
+        }
+        codegen::Cfg::Pipeline(_, pipeline) => pipeline.prefetch.clone(),
+        codegen::Cfg::Instruction(..) => vec![],
+    }
+}
+
+/// Ensures the loads of multi-stage variables feeding a pipelined loop are prefetched.
+#[test]
+fn pipelined_variable() {
+    let _ = env_logger::try_init();
+    let mut context = cuda::ModelContext::from_bundled("gtx_1080").unwrap();
+    let signature = {
+        let mut builder = helper::SignatureBuilder::new("pipelined_var", &mut context);
+        builder.array::<f32>("in", 16 * 32);
+        builder.get()
+    };
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    let d0 = builder.open_dim_ex(ir::Size::new_const(16), DimKind::LOOP);
+    let d1 = builder.open_dim_ex(ir::Size::new_const(32), DimKind::THREAD);
+    let (addr, pattern) =
+        builder.tensor_access(&"in", None, ir::Type::F(32), &[&d0, &d1]);
+    let ld = builder.ld(ir::Type::F(32), &addr, pattern);
+    let var = builder.get_inst_variable(ld);
+    let d2 = builder.open_mapped_dim(&d1);
+    builder.action(Action::DimKind(d2[0], DimKind::THREAD));
+    builder.mov(&helper::TmpArray(ld));
+    builder.order(&d1, &d2, Order::BEFORE);
+    builder.action(Action::ThreadMapping(
+        d1[0],
+        d2[0],
+        ThreadMapping::MAPPED_OUT,
+    ));
+    builder.close_dim(&d2);
+    builder.close_dim(&d0);
+    let mut space = builder.get();
+    let mem = space.ir_instance().mem_blocks().next().unwrap().mem_id();
+    let num_stages = NumericSet::new_eq(&ir::mem::NUM_STAGES, 2, &());
+    space
+        .apply_decisions(vec![
+            Action::NumStages(mem, num_stages),
+            Action::VarNumStages(var, num_stages),
+        ])
+        .unwrap();
+    let bound = model::bound(&space, &context);
+    let candidate = Candidate::new(space, bound);
+    for i in 0..NUM_DESCENTS {
+        let leaf = random_descent(&context, candidate.clone(), i as u8).unwrap();
+        let space = fix_order(leaf.space);
+        let function = codegen::Function::build(&space);
+        assert_eq!(
+            prefetched_loads(function.cfg()),
+            vec![ld],
+            "{:?}",
+            function.cfg()
+        );
+        let ptx = context.gpu().print_ptx(&function);
+        if let Err(err) = cuda::check_ptx(&ptx) {
+            panic!("invalid PTX with seed {}: {}\n{}", i, err, ptx);
+        }
+    }
+}
+
+/// Ensures threads can branch around a region of side-effects instead of executing it
+/// under a predicate.
+#[test]
+fn branch_around_threads() {
+    let _ = env_logger::try_init();
+    let mut context = cuda::ModelContext::from_bundled("gtx_1080").unwrap();
+    let signature = {
+        let mut builder = helper::SignatureBuilder::new("branch_around", &mut context);
+        builder.array::<f32>("out", 1);
+        builder.get()
+    };
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    let d0 = builder.open_dim_ex(ir::Size::new_const(32), DimKind::THREAD);
+    builder.mov(&0f32);
+    builder.close_dim(&d0);
+    let pattern = ir::AccessPattern::Unknown(None);
+    builder.st(&"out", &1f32, pattern);
+    builder.action(Action::ThreadGuard(d0[0], ThreadGuard::BRANCH));
+    let space = builder.get();
+    let bound = model::bound(&space, &context);
+    let candidate = Candidate::new(space, bound);
+    for i in 0..NUM_DESCENTS {
+        let leaf = random_descent(&context, candidate.clone(), i as u8).unwrap();
+        let space = fix_order(leaf.space);
+        let ptx = context.gpu().print_ptx(&codegen::Function::build(&space));
+        assert!(ptx.contains(" bra SKIP"), "{}", ptx);
+        if let Err(err) = cuda::check_ptx(&ptx) {
+            panic!("invalid PTX with seed {}: {}\n{}", i, err, ptx);
+        }
+    }
+}
+
+/// Ensures the scaling by `alpha` in `Fused2MM` is not recomputed in sequential loops of
+/// the second matrix multiplication that it does not depend on.
+#[test]
+fn fused_2mm_loop_invariants() {
+    let _ = env_logger::try_init();
+    let mut context = cuda::ModelContext::from_bundled("gtx_1080").unwrap();
+    let params = linalg::Fused2MMP::new(16, 32, 16, 64, 2., 3.);
+    let (signature, kernel, context) =
+        KernelBuilder::new().build::<linalg::Fused2MM<f32>, _>(params, &mut context);
+    let space = kernel.build_body(signature.into(), context).remove(0).space;
+    let fun = space.ir_instance();
+    let is_alpha = |op: &ir::Operand| match op {
+        ir::Operand::Param(p) => p.name == "alpha",
+        _ => false,
+    };
+    let scale = fun
+        .insts()
+        .find(|inst| inst.operands().into_iter().any(is_alpha))
+        .unwrap();
+    let uses_scale = |op: &ir::Operand| match op {
+        ir::Operand::Inst(src, ..) => *src == scale.id(),
+        _ => false,
+    };
+    let second_mm = fun
+        .insts()
+        .find(|inst| inst.operands().into_iter().any(uses_scale))
+        .unwrap();
+    // Dimensions of the second multiplication along `p`, the only size equal to 64.
+    let p_dims = second_mm
+        .iteration_dims()
+        .iter()
+        .filter(|&&dim| {
+            let logical_dim = fun.dim(dim).logical_dim().unwrap();
+            fun.logical_dim(logical_dim).total_size().max() == 64
+        })
+        .collect::<Vec<_>>();
+    assert!(!p_dims.is_empty());
+    for &dim in p_dims {
+        let mut space = space.clone();
+        if space
+            .apply_decisions(vec![Action::DimKind(dim, DimKind::LOOP)])
+            .is_err()
+        {
+            continue;
+        }
+        let order = space.domain().get_order(scale.id().into(), dim.into());
+        assert!(!order.intersects(Order::INNER), "{:?}", order);
+    }
+}
@@ -0,0 +1,251 @@
+//! Describes the targets of the LLVM backend.
+use crate::printer::LlvmPrinter;
+use fxhash::FxHashMap;
+use std::io::Write;
+use telamon::codegen::{Function, ResourceReport};
+use telamon::device;
+use telamon::ir::{self, Type};
+use telamon::model::{self, HwPressure};
+use telamon::search_space::{DimKind, InstFlag, MemSpace, SearchSpace};
+use utils::unwrap;
+
+/// A GPU architecture supported by LLVM.  The target defines the intrinsics used to retrieve
+/// block and thread indexes and to synchronize threads.  In both cases, global memory lives in
+/// address space 1 and shared memory in address space 3.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// AMD GPUs, through the AMDGPU backend.
+    Amdgcn,
+    /// Nvidia GPUs, through the NVPTX backend.
+    Nvptx,
+}
+
+impl Target {
+    /// Returns the LLVM target triple.
+    pub fn triple(self) -> &'static str {
+        match self {
+            Target::Amdgcn => "amdgcn-amd-amdhsa",
+            Target::Nvptx => "nvptx64-nvidia-cuda",
+        }
+    }
+
+    /// Returns the intrinsic returning the index of the block along a dimension.
+    pub fn block_index(self, dim: usize) -> String {
+        match self {
+            Target::Amdgcn => format!("llvm.amdgcn.workgroup.id.{}", Self::axis(dim)),
+            Target::Nvptx => format!("llvm.nvvm.read.ptx.sreg.ctaid.{}", Self::axis(dim)),
+        }
+    }
+
+    /// Returns the intrinsic returning the index of the thread along a dimension.
+    pub fn thread_index(self, dim: usize) -> String {
+        match self {
+            Target::Amdgcn => format!("llvm.amdgcn.workitem.id.{}", Self::axis(dim)),
+            Target::Nvptx => format!("llvm.nvvm.read.ptx.sreg.tid.{}", Self::axis(dim)),
+        }
+    }
+
+    /// Returns the intrinsic synchronizing the threads of a block.
+    pub fn barrier(self) -> &'static str {
+        match self {
+            Target::Amdgcn => "llvm.amdgcn.s.barrier",
+            Target::Nvptx => "llvm.nvvm.barrier0",
+        }
+    }
+
+    /// Returns the name of an axis of the grid.
+    fn axis(dim: usize) -> &'static str {
+        ["x", "y", "z"][dim]
+    }
+}
+
+/// Describes a GPU targeted through textual LLVM IR.
+#[derive(Clone, Debug)]
+pub struct Llvm {
+    /// The architecture the code is generated for.
+    pub target: Target,
+    /// Maximal number of threads in a block.
+    pub max_threads: u32,
+    /// Amount of shared memory available to a block, in bytes.
+    pub shared_mem: u32,
+}
+
+impl Llvm {
+    /// Creates a device for the given target, with limits supported by all its GPUs.
+    pub fn new(target: Target) -> Self {
+        let shared_mem = match target {
+            Target::Amdgcn => 64 * 1024,
+            Target::Nvptx => 48 * 1024,
+        };
+        Llvm {
+            target,
+            max_threads: 1024,
+            shared_mem,
+        }
+    }
+}
+
+impl device::Device for Llvm {
+    fn print(&self, fun: &Function, out: &mut dyn Write) {
+        unwrap!(write!(out, "{}", LlvmPrinter::new(self.target).module(fun)));
+    }
+
+    fn resource_report(&self, fun: &Function) -> ResourceReport {
+        let mut printer = LlvmPrinter::new(self.target);
+        printer.module(fun);
+        ResourceReport {
+            optimization: Some(printer.optimization_stats()),
+            ..ResourceReport::new(fun)
+        }
+    }
+
+    fn check_type(&self, t: Type) -> Result<(), ir::TypeError> {
+        match t {
+            Type::I(i) | Type::F(i) if i == 32 || i == 64 => Ok(()),
+            Type::I(i) if i == 1 || i == 8 || i == 16 => Ok(()),
+            Type::PtrTo(_) => Ok(()),
+            t => Err(ir::TypeError::InvalidType { t }),
+        }
+    }
+
+    fn max_block_dims(&self) -> u32 {
+        3
+    }
+
+    fn max_inner_block_size(&self) -> u32 {
+        65535
+    }
+
+    fn max_threads(&self) -> u32 {
+        self.max_threads
+    }
+
+    fn max_unrolling(&self) -> u32 {
+        512
+    }
+
+    fn has_vector_registers(&self) -> bool {
+        true
+    }
+
+    /// Memory accesses are vectorized along dimensions where they are consecutive and
+    /// arithmetic operators along any dimension, as LLVM supports vectors of any size.
+    fn can_vectorize(&self, dim: &ir::Dimension, op: &ir::Operator) -> bool {
+        match *op {
+            ir::Operator::TmpLd(..) | ir::Operator::TmpSt(..) => false,
+            ir::Operator::Ld(t, _, ref pattern) => pattern.is_consecutive(dim.id(), t),
+            ir::Operator::St(_, ref operand, _, ref pattern) => {
+                pattern.is_consecutive(dim.id(), operand.t())
+            }
+            ir::Operator::BinOp(..)
+            | ir::Operator::Mul(..)
+            | ir::Operator::Mad(..)
+            | ir::Operator::UnaryOp(..) => true,
+            ir::Operator::MmaSync(..) => false,
+        }
+    }
+
+    /// Matrix multiply-accumulate instructions have no LLVM equivalent, so they are rejected
+    /// when added to a function instead of failing when the function is printed.
+    fn supports_mma(&self, _: Type) -> bool {
+        false
+    }
+
+    fn max_vectorization(&self, _: &ir::Operator) -> [u32; 2] {
+        // Outer vectorization is not supported by `llir`.
+        [1, 4]
+    }
+
+    fn shared_mem(&self) -> u32 {
+        self.shared_mem
+    }
+
+    fn pointer_type(&self, _: MemSpace) -> ir::Type {
+        // Addresses are stored in 64-bit integers and converted to pointers in the right
+        // address space when accessing memory.
+        ir::Type::I(64)
+    }
+
+    fn supported_mem_flags(&self, op: &ir::Operator) -> InstFlag {
+        match op {
+            ir::Operator::Ld(..)
+            | ir::Operator::St(..)
+            | ir::Operator::TmpLd(..)
+            | ir::Operator::TmpSt(..) => InstFlag::BLOCK_COHERENT,
+            _ => panic!("not a memory operation"),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self.target {
+            Target::Amdgcn => "LLVM (amdgcn)",
+            Target::Nvptx => "LLVM (nvptx)",
+        }
+    }
+
+    fn hw_pressure(
+        &self,
+        _: &SearchSpace,
+        _: &FxHashMap<ir::DimId, model::size::Range>,
+        _: &FxHashMap<ir::StmtId, model::Nesting>,
+        _: &dyn ir::Statement,
+        _: &dyn device::Context,
+    ) -> model::HwPressure {
+        // TODO(model): implement model
+        model::HwPressure::zero(self)
+    }
+
+    fn loop_iter_pressure(&self, _kind: DimKind) -> (HwPressure, HwPressure) {
+        //TODO(model): implement minimal model
+        (HwPressure::zero(self), HwPressure::zero(self))
+    }
+
+    fn thread_rates(&self) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::new(1.0, vec![])
+    }
+
+    fn block_rates(&self) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::new(1.0, vec![])
+    }
+
+    fn total_rates(&self) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::new(1.0, vec![])
+    }
+
+    fn bottlenecks(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn block_parallelism(&self, _space: &SearchSpace) -> u32 {
+        1
+    }
+
+    fn additive_indvar_pressure(&self, _t: &ir::Type) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::zero(self)
+    }
+
+    fn multiplicative_indvar_pressure(&self, _t: &ir::Type) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::zero(self)
+    }
+
+    fn add_block_overhead(
+        &self,
+        _: model::size::FactorRange,
+        _: model::size::FactorRange,
+        _: model::size::Range,
+        _pressure: &mut HwPressure,
+    ) {
+    }
+
+    fn lower_type(&self, t: ir::Type, _space: &SearchSpace) -> Option<ir::Type> {
+        match t {
+            Type::PtrTo(_) => Some(Type::I(64)),
+            _ => Some(t),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Defines a backend printing kernels as textual LLVM IR.
+//!
+//! The generated module can be fed to LLVM-based toolchains for targets Telamon does not
+//! support directly.  Registers are stored in stack slots allocated in the entry block, which
+//! keeps the code in SSA form without computing phi nodes; the `mem2reg` pass of LLVM promotes
+//! them back to registers.
+#![deny(bare_trait_objects, unused_lifetimes)]
+#![warn(clippy::all)]
+
+mod device;
+pub mod printer;
+
+pub use crate::device::{Llvm, Target};
+
+use fxhash::FxHashMap;
+use telamon::{codegen, ir};
+
+#[derive(Default)]
+pub(crate) struct NameGenerator {
+    num_var: FxHashMap<ir::Type, usize>,
+}
+
+impl NameGenerator {
+    /// Generate a variable name prefix from a type.
+    fn gen_prefix(t: ir::Type) -> &'static str {
+        match t {
+            ir::Type::I(1) => "p",
+            ir::Type::I(8) => "c",
+            ir::Type::I(16) => "s",
+            ir::Type::I(32) => "r",
+            ir::Type::I(64) => "rd",
+            ir::Type::F(32) => "f",
+            ir::Type::F(64) => "d",
+            _ => panic!("invalid LLVM type"),
+        }
+    }
+}
+
+impl codegen::NameGenerator for NameGenerator {
+    fn name(&mut self, t: ir::Type) -> String {
+        let prefix = NameGenerator::gen_prefix(t);
+        let entry = self.num_var.entry(t).or_insert(0);
+        let name = format!("{}{}", prefix, *entry);
+        *entry += 1;
+        name
+    }
+}
@@ -0,0 +1,763 @@
+//! Prints functions as textual LLVM IR modules.
+use crate::{NameGenerator, Target};
+use itertools::Itertools;
+use num::ToPrimitive;
+use std::collections::BTreeSet;
+use std::fmt::{self, Write as WriteFmt};
+use telamon::codegen::*;
+use telamon::ir::Type;
+use telamon::search_space::{DimKind, Domain};
+use utils::unwrap;
+
+/// Address space of global memory.
+const GLOBAL_ADDR_SPACE: u32 = 1;
+/// Address space of shared memory.
+const SHARED_ADDR_SPACE: u32 = 3;
+
+/// Returns the address space of a state space.
+fn addr_space(ss: llir::StateSpace) -> u32 {
+    match ss {
+        llir::StateSpace::Global => GLOBAL_ADDR_SPACE,
+        llir::StateSpace::Shared => SHARED_ADDR_SPACE,
+    }
+}
+
+/// Returns the LLVM name of a scalar type.
+fn scalar_t(t: Type) -> &'static str {
+    match t {
+        Type::I(1) => "i1",
+        Type::I(8) => "i8",
+        Type::I(16) => "i16",
+        Type::I(32) => "i32",
+        Type::I(64) => "i64",
+        Type::F(32) => "float",
+        Type::F(64) => "double",
+        _ => panic!("invalid LLVM type: {}", t),
+    }
+}
+
+/// Returns the type of `len` values of type `t`, which is a vector type if `len > 1`.
+fn vector_t(t: Type, len: usize) -> String {
+    if len == 1 {
+        scalar_t(t).to_string()
+    } else {
+        format!("<{} x {}>", len, scalar_t(t))
+    }
+}
+
+/// Returns the suffix of overloaded intrinsics operating on `len` values of type `t`.
+fn intrinsic_suffix(t: Type, len: usize) -> String {
+    let scalar = match t {
+        Type::F(bits) => format!("f{}", bits),
+        Type::I(bits) => format!("i{}", bits),
+        _ => panic!("invalid LLVM type: {}", t),
+    };
+    if len == 1 {
+        scalar
+    } else {
+        format!("v{}{}", len, scalar)
+    }
+}
+
+/// Returns a constant with the same value in all the `len` lanes of a value of type `t`.
+fn splat(t: Type, len: usize, value: &str) -> String {
+    if len == 1 {
+        value.to_string()
+    } else {
+        let lane = format!("{} {}", scalar_t(t), value);
+        format!("<{}>", (0..len).map(|_| &lane).format(", "))
+    }
+}
+
+/// Returns the double-width integer type.
+fn wide_t(t: Type) -> Type {
+    match t {
+        Type::I(bits) => Type::I(2 * bits),
+        _ => panic!("not an integer type: {}", t),
+    }
+}
+
+/// Returns the number of lanes of a possibly vectorized operand or register.
+fn num_lanes<T>(value: &llir::ScalarOrVector<T>) -> usize {
+    match value {
+        llir::ScalarOrVector::Scalar(_) => 1,
+        llir::ScalarOrVector::Vector(values) => values.len(),
+    }
+}
+
+/// Prints a global name, quoting it if needed.
+fn global_name(name: &str) -> String {
+    if name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+    {
+        format!("@{}", name)
+    } else {
+        format!("@\"{}\"", name.escape_default())
+    }
+}
+
+/// Prints a literal operand.
+fn literal(operand: &llir::Operand<'_>) -> String {
+    match operand {
+        llir::Operand::Register(reg) => panic!("register {} is not a literal", reg),
+        llir::Operand::IntLiteral(value, 1) => {
+            let is_true = value.to_u8() != Some(0);
+            is_true.to_string()
+        }
+        llir::Operand::IntLiteral(value, _) => value.to_string(),
+        &llir::Operand::FloatLiteral(ref value, bits) => {
+            let f = value.numer().to_f64().unwrap() / value.denom().to_f64().unwrap();
+            // LLVM expects the hexadecimal representation of a double that is exactly
+            // representable in the type of the literal.
+            let f = match bits {
+                32 => f64::from(f as f32),
+                64 => f,
+                _ => panic!("cannot print floating point value with {} bits", bits),
+            };
+            format!("0x{:016X}", f.to_bits())
+        }
+        llir::Operand::Bf16Literal(bits) => format!("0xR{:04X}", bits),
+    }
+}
+
+/// Prints a `Function` as an LLVM module.
+///
+/// The registers of the function are stored in stack slots allocated in the entry block: each
+/// instruction loads its operands from the slots and stores its results back.  Labels start
+/// new basic blocks and predicated instructions are guarded by conditional branches.
+pub struct LlvmPrinter {
+    target: Target,
+    /// Instructions of the function body
+    buffer: String,
+    /// Declarations of module-level variables
+    globals: Vec<String>,
+    /// Declarations of the intrinsics called by the function
+    declarations: BTreeSet<String>,
+    /// Number of SSA values created so far
+    num_values: usize,
+    /// Number of basic blocks created by the printer so far
+    num_blocks: usize,
+    /// Indicates if the current basic block is terminated
+    terminated: bool,
+    /// Effect of the optimizations on the last printed function body
+    optimization_stats: OptimizationStats,
+}
+
+impl LlvmPrinter {
+    /// Creates a printer generating code for `target`.
+    pub fn new(target: Target) -> Self {
+        LlvmPrinter {
+            target,
+            buffer: String::new(),
+            globals: Vec::new(),
+            declarations: BTreeSet::new(),
+            num_values: 0,
+            num_blocks: 0,
+            terminated: false,
+            optimization_stats: OptimizationStats::default(),
+        }
+    }
+
+    /// Returns the effect of the optimizations on the last printed function body.
+    pub fn optimization_stats(&self) -> OptimizationStats {
+        self.optimization_stats
+    }
+
+    /// Returns the name of a new SSA value.
+    fn new_value(&mut self) -> String {
+        self.num_values += 1;
+        format!("%v{}", self.num_values - 1)
+    }
+
+    /// Returns the name of a new basic block.
+    fn new_block(&mut self, prefix: &str) -> String {
+        self.num_blocks += 1;
+        format!("{}.{}", prefix, self.num_blocks - 1)
+    }
+
+    /// Appends an instruction to the current basic block.  A new block is started if the
+    /// current one is already terminated, in which case the instruction is unreachable.
+    fn emit(&mut self, inst: fmt::Arguments<'_>) {
+        if self.terminated {
+            let block = self.new_block("bb");
+            unwrap!(writeln!(self.buffer, "{}:", block));
+            self.terminated = false;
+        }
+        unwrap!(writeln!(self.buffer, "  {}", inst));
+    }
+
+    /// Appends a terminator to the current basic block.
+    fn terminate(&mut self, inst: fmt::Arguments<'_>) {
+        self.emit(inst);
+        self.terminated = true;
+    }
+
+    /// Starts a new basic block, falling through from the current one.
+    fn start_block(&mut self, name: &str) {
+        if !self.terminated {
+            unwrap!(writeln!(self.buffer, "  br label %{}", name));
+        }
+        unwrap!(writeln!(self.buffer, "{}:", name));
+        self.terminated = false;
+    }
+
+    /// Calls a function, declaring it in the module.  Returns the value of the result, if any.
+    fn call(&mut self, ret_t: &str, name: &str, args: &[(String, String)]) -> String {
+        self.declarations.insert(format!(
+            "declare {} @{}({})",
+            ret_t,
+            name,
+            args.iter().map(|(t, _)| t).format(", ")
+        ));
+        let args = args
+            .iter()
+            .format_with(", ", |(t, v), f| f(&format_args!("{} {}", t, v)));
+        if ret_t == "void" {
+            self.emit(format_args!("call void @{}({})", name, args));
+            String::new()
+        } else {
+            let value = self.new_value();
+            self.emit(format_args!(
+                "{} = call {} @{}({})",
+                value, ret_t, name, args
+            ));
+            value
+        }
+    }
+
+    /// Returns the value of an operand, loading it from its slot if it is a register.
+    fn read(&mut self, operand: &llir::Operand<'_>) -> String {
+        match operand {
+            llir::Operand::Register(reg) => {
+                let value = self.new_value();
+                self.emit(format_args!(
+                    "{} = load {}, ptr %{}",
+                    value,
+                    scalar_t(reg.t()),
+                    reg.name()
+                ));
+                value
+            }
+            _ => literal(operand),
+        }
+    }
+
+    /// Returns the value of a possibly vectorized operand.  Vectors are built lane by lane.
+    fn read_vec(&mut self, operands: &llir::OpVec<'_>) -> String {
+        match operands {
+            llir::ScalarOrVector::Scalar(operand) => self.read(operand),
+            llir::ScalarOrVector::Vector(operands) => {
+                let t = operands[0].t();
+                let vec_t = vector_t(t, operands.len());
+                let mut vector = "undef".to_string();
+                for (lane, operand) in operands.iter().enumerate() {
+                    let scalar = self.read(operand);
+                    let value = self.new_value();
+                    self.emit(format_args!(
+                        "{} = insertelement {} {}, {} {}, i32 {}",
+                        value,
+                        vec_t,
+                        vector,
+                        scalar_t(t),
+                        scalar,
+                        lane
+                    ));
+                    vector = value;
+                }
+                vector
+            }
+        }
+    }
+
+    /// Stores a value in the slots of possibly vectorized registers.
+    fn write(&mut self, regs: &llir::RegVec<'_>, value: &str) {
+        match regs {
+            llir::ScalarOrVector::Scalar(reg) => self.emit(format_args!(
+                "store {} {}, ptr %{}",
+                scalar_t(reg.t()),
+                value,
+                reg.name()
+            )),
+            llir::ScalarOrVector::Vector(regs) => {
+                let vec_t = vector_t(regs[0].t(), regs.len());
+                for (lane, reg) in regs.iter().enumerate() {
+                    let scalar = self.new_value();
+                    self.emit(format_args!(
+                        "{} = extractelement {} {}, i32 {}",
+                        scalar, vec_t, value, lane
+                    ));
+                    self.emit(format_args!(
+                        "store {} {}, ptr %{}",
+                        scalar_t(reg.t()),
+                        scalar,
+                        reg.name()
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Returns a pointer to the given address in the given state space.
+    fn pointer(&mut self, addr: &llir::Address<'_>, ss: llir::StateSpace) -> String {
+        let llir::Address::Register(reg, offset) = *addr;
+        let mut base = self.read(&reg.into_operand());
+        if offset != 0 {
+            let value = self.new_value();
+            self.emit(format_args!(
+                "{} = add {} {}, {}",
+                value,
+                scalar_t(reg.t()),
+                base,
+                offset
+            ));
+            base = value;
+        }
+        let ptr = self.new_value();
+        self.emit(format_args!(
+            "{} = inttoptr {} {} to ptr addrspace({})",
+            ptr,
+            scalar_t(reg.t()),
+            base,
+            addr_space(ss)
+        ));
+        ptr
+    }
+
+    /// Emits an instruction computing `op` on `args` and returns the result.
+    fn op(&mut self, op: &str, t: Type, len: usize, args: &[&str]) -> String {
+        let value = self.new_value();
+        self.emit(format_args!(
+            "{} = {} {} {}",
+            value,
+            op,
+            vector_t(t, len),
+            args.iter().format(", ")
+        ));
+        value
+    }
+
+    /// Emits a conversion of `arg` from `src_t` to `dst_t`.
+    fn convert(
+        &mut self,
+        op: &str,
+        src_t: Type,
+        dst_t: Type,
+        len: usize,
+        arg: &str,
+    ) -> String {
+        let value = self.new_value();
+        self.emit(format_args!(
+            "{} = {} {} {} to {}",
+            value,
+            op,
+            vector_t(src_t, len),
+            arg,
+            vector_t(dst_t, len)
+        ));
+        value
+    }
+
+    /// Calls an intrinsic overloaded on the type of its arguments.
+    fn intrinsic(&mut self, name: &str, t: Type, len: usize, args: &[&str]) -> String {
+        let name = format!("llvm.{}.{}", name, intrinsic_suffix(t, len));
+        let args = args
+            .iter()
+            .map(|&arg| (vector_t(t, len), arg.to_string()))
+            .collect_vec();
+        self.call(&vector_t(t, len), &name, &args)
+    }
+
+    /// Emits a `select` between `lhs` and `rhs`.
+    fn select(
+        &mut self,
+        cond: &str,
+        t: Type,
+        len: usize,
+        lhs: &str,
+        rhs: &str,
+    ) -> String {
+        let value = self.new_value();
+        self.emit(format_args!(
+            "{} = select {} {}, {t} {}, {t} {}",
+            value,
+            vector_t(Type::I(1), len),
+            cond,
+            lhs,
+            rhs,
+            t = vector_t(t, len)
+        ));
+        value
+    }
+
+    /// Emits an integer multiplication.
+    fn imul(
+        &mut self,
+        spec: llir::MulSpec,
+        arg_t: Type,
+        len: usize,
+        lhs: &str,
+        rhs: &str,
+    ) -> String {
+        if spec == llir::MulSpec::Low {
+            return self.op("mul", arg_t, len, &[lhs, rhs]);
+        }
+        let wide_t = wide_t(arg_t);
+        let lhs = self.convert("sext", arg_t, wide_t, len, lhs);
+        let rhs = self.convert("sext", arg_t, wide_t, len, rhs);
+        let wide = self.op("mul", wide_t, len, &[&lhs, &rhs]);
+        if spec == llir::MulSpec::Wide {
+            return wide;
+        }
+        let bits = match arg_t {
+            Type::I(bits) => bits,
+            _ => unreachable!(),
+        };
+        let shift = splat(wide_t, len, &bits.to_string());
+        let high = self.op("lshr", wide_t, len, &[&wide, &shift]);
+        self.convert("trunc", wide_t, arg_t, len, &high)
+    }
+
+    /// Emits a unary operator and returns the result.
+    fn unary(&mut self, op: llir::UnOp, len: usize, arg: &str) -> String {
+        match op {
+            llir::UnOp::Move { .. } => arg.to_string(),
+            llir::UnOp::Cast { src_t, dst_t } => {
+                let op = match (src_t, dst_t) {
+                    (Type::I(src), Type::I(dst)) if src == dst => return arg.to_string(),
+                    // Predicates are converted to 0 or 1.
+                    (Type::I(1), Type::I(_)) => "zext",
+                    (Type::I(src), Type::I(dst)) if src < dst => "sext",
+                    (Type::I(_), Type::I(_)) => "trunc",
+                    (Type::I(_), Type::F(_)) => "sitofp",
+                    (Type::F(_), Type::I(_)) => "fptosi",
+                    (Type::F(src), Type::F(dst)) if src == dst => return arg.to_string(),
+                    (Type::F(src), Type::F(dst)) if src < dst => "fpext",
+                    (Type::F(_), Type::F(_)) => "fptrunc",
+                    _ => panic!("invalid cast from {} to {}", src_t, dst_t),
+                };
+                self.convert(op, src_t, dst_t, len, arg)
+            }
+            llir::UnOp::Exp { t } => self.intrinsic("exp", t, len, &[arg]),
+        }
+    }
+
+    /// Emits a binary operator and returns the result.
+    fn binary(&mut self, op: llir::BinOp, len: usize, lhs: &str, rhs: &str) -> String {
+        use llir::BinOp::*;
+
+        let args = &[lhs, rhs];
+        match op {
+            IAdd { arg_t } => self.op("add", arg_t, len, args),
+            ISub { arg_t } => self.op("sub", arg_t, len, args),
+            IDiv { arg_t } => self.op("sdiv", arg_t, len, args),
+            IMul { arg_t, spec } => self.imul(spec, arg_t, len, lhs, rhs),
+            IMax { arg_t } => {
+                let cond = self.op("icmp sgt", arg_t, len, args);
+                self.select(&cond, arg_t, len, lhs, rhs)
+            }
+            FAdd { t, .. } => self.op("fadd", t, len, args),
+            FSub { t, .. } => self.op("fsub", t, len, args),
+            FMul { t, .. } => self.op("fmul", t, len, args),
+            FDiv { t, .. } => self.op("fdiv", t, len, args),
+            FMax { t } => self.intrinsic("maxnum", t, len, args),
+            FMin { t } => self.intrinsic("minnum", t, len, args),
+            Set { op, arg_t } => {
+                let cmp = if arg_t.is_float() {
+                    match op {
+                        llir::CmpOp::Eq => "fcmp oeq",
+                        llir::CmpOp::Ne => "fcmp une",
+                        llir::CmpOp::Lt => "fcmp olt",
+                        llir::CmpOp::Le => "fcmp ole",
+                        llir::CmpOp::Gt => "fcmp ogt",
+                        llir::CmpOp::Ge => "fcmp oge",
+                    }
+                } else {
+                    match op {
+                        llir::CmpOp::Eq => "icmp eq",
+                        llir::CmpOp::Ne => "icmp ne",
+                        llir::CmpOp::Lt => "icmp slt",
+                        llir::CmpOp::Le => "icmp sle",
+                        llir::CmpOp::Gt => "icmp sgt",
+                        llir::CmpOp::Ge => "icmp sge",
+                    }
+                };
+                self.op(cmp, arg_t, len, args)
+            }
+            And { t } => self.op("and", t, len, args),
+            Or { t } => self.op("or", t, len, args),
+            Xor { t } => self.op("xor", t, len, args),
+            Shl { t } => self.op("shl", t, len, args),
+        }
+    }
+
+    /// Emits a ternary operator and returns the result.
+    fn ternary(&mut self, op: llir::TernOp, len: usize, args: [&str; 3]) -> String {
+        match op {
+            llir::TernOp::IMad { arg_t, spec } => {
+                let mul = self.imul(spec, arg_t, len, args[0], args[1]);
+                let ret_t = if spec == llir::MulSpec::Wide {
+                    wide_t(arg_t)
+                } else {
+                    arg_t
+                };
+                self.op("add", ret_t, len, &[&mul, args[2]])
+            }
+            llir::TernOp::FFma { t, .. } => self.intrinsic("fma", t, len, &args),
+        }
+    }
+
+    /// Emits an instruction.
+    fn instruction(&mut self, inst: &llir::Instruction<'_>) {
+        use llir::Instruction::*;
+
+        match inst {
+            Unary(op, d, [a]) => {
+                let a = self.read_vec(a);
+                let result = self.unary(*op, num_lanes(d), &a);
+                self.write(d, &result);
+            }
+            Binary(op, d, [a, b]) => {
+                let (a, b) = (self.read_vec(a), self.read_vec(b));
+                let result = self.binary(*op, num_lanes(d), &a, &b);
+                self.write(d, &result);
+            }
+            Ternary(op, d, [a, b, c]) => {
+                let (a, b, c) = (self.read_vec(a), self.read_vec(b), self.read_vec(c));
+                let result = self.ternary(*op, num_lanes(d), [&a, &b, &c]);
+                self.write(d, &result);
+            }
+            Load(spec, d, addr) => {
+                let ptr = self.pointer(addr, spec.state_space());
+                let value = self.new_value();
+                self.emit(format_args!(
+                    "{} = load {}, ptr addrspace({}) {}",
+                    value,
+                    vector_t(spec.t(), spec.vector_factor().get() as usize),
+                    addr_space(spec.state_space()),
+                    ptr
+                ));
+                self.write(d, &value);
+            }
+            Store(spec, addr, [val]) => {
+                let val = self.read_vec(val);
+                let ptr = self.pointer(addr, spec.state_space());
+                self.emit(format_args!(
+                    "store {} {}, ptr addrspace({}) {}",
+                    vector_t(spec.t(), spec.vector_factor().get() as usize),
+                    val,
+                    addr_space(spec.state_space()),
+                    ptr
+                ));
+            }
+            // Rejected when added to the function, as `Llvm` does not support it.
+            Mma(..) => unreachable!("{}: no LLVM equivalent", inst),
+            Jump(label) => self.terminate(format_args!("br label %{}", label.name())),
+            Sync => {
+                self.call("void", self.target.barrier(), &[]);
+            }
+        }
+    }
+
+    /// Loads block and thread indexes.  Thread dimensions are listed from the outermost to the
+    /// innermost, so the innermost one is mapped to the `x` axis.
+    fn decl_par_indexes(&mut self, function: &Function, name_map: &NameMap<'_>) {
+        for (axis, dim) in function.block_dims().iter().enumerate() {
+            let index = self.call("i32", &self.target.block_index(axis), &[]);
+            self.write(&name_map.name_index(dim.id()).into(), &index);
+        }
+        for (axis, dim) in function.thread_dims().iter().rev().enumerate() {
+            let index = self.call("i32", &self.target.thread_index(axis), &[]);
+            self.write(&name_map.name_index(dim.id()).into(), &index);
+        }
+    }
+
+    /// Declares a shared memory block.
+    fn shared_mem_decl(&mut self, block: &MemoryRegion, name_map: &NameMap<'_>) {
+        let name = format!("@shmem{}", block.id().0);
+        self.globals.push(format!(
+            "{} = internal addrspace({}) global [{} x i8] undef, align 16",
+            name,
+            SHARED_ADDR_SPACE,
+            unwrap!(block.alloc_size().as_int())
+        ));
+        let addr = name_map.name_addr(block.id());
+        let value = self.convert_ptr(&name, SHARED_ADDR_SPACE, addr.t());
+        self.write(&addr.into(), &value);
+    }
+
+    /// Converts a pointer into an integer.
+    fn convert_ptr(&mut self, ptr: &str, addr_space: u32, t: Type) -> String {
+        let value = self.new_value();
+        self.emit(format_args!(
+            "{} = ptrtoint ptr addrspace({}) {} to {}",
+            value,
+            addr_space,
+            ptr,
+            scalar_t(t)
+        ));
+        value
+    }
+
+    /// Returns the declaration of a parameter.
+    fn param_decl(param: &ParamVal) -> String {
+        let t = match param {
+            ParamVal::External(..) | ParamVal::GlobalMem(..)
+                if param.elem_t().is_some() =>
+            {
+                format!("ptr addrspace({})", GLOBAL_ADDR_SPACE)
+            }
+            _ => scalar_t(param.t()).to_string(),
+        };
+        format!("{} %arg.{}", t, param.key().ident())
+    }
+
+    /// Declares the stack slots of the registers required from the namegen.
+    fn var_decls(namegen: &NameGenerator) -> String {
+        let mut decls = String::new();
+        let vars = namegen
+            .num_var
+            .iter()
+            .sorted_by_key(|&(&t, _)| NameGenerator::gen_prefix(t));
+        for (&t, &n) in vars {
+            for i in 0..n {
+                unwrap!(writeln!(
+                    decls,
+                    "  %{}{} = alloca {}",
+                    NameGenerator::gen_prefix(t),
+                    i,
+                    scalar_t(t)
+                ));
+            }
+        }
+        decls
+    }
+
+    /// Prints a `Function` as an LLVM module containing a single kernel.
+    pub fn module(&mut self, function: &Function) -> String {
+        let mut namegen = NameGenerator::default();
+        let interner = Interner::default();
+        let name_map = &mut NameMap::new(&interner, function, &mut namegen);
+        // INDEX LOADS
+        self.decl_par_indexes(function, name_map);
+        // LOAD PARAM
+        for val in function.device_code_args() {
+            let reg = name_map.name_param_val(val.key());
+            let arg = format!("%arg.{}", val.key().ident());
+            let value = if val.elem_t().is_some() {
+                self.convert_ptr(&arg, GLOBAL_ADDR_SPACE, reg.t())
+            } else {
+                arg
+            };
+            self.write(&reg.into(), &value);
+        }
+        // MEM DECL
+        for block in function.mem_blocks() {
+            match block.alloc_scheme() {
+                AllocationScheme::Shared => self.shared_mem_decl(block, name_map),
+                AllocationScheme::PrivatisedGlobal => {
+                    Printer::new(self, name_map).privatise_global_block(block, function)
+                }
+                AllocationScheme::Global => (),
+            }
+        }
+        // Compute size casts
+        for dim in function.dimensions() {
+            if !dim.kind().intersects(DimKind::UNROLL | DimKind::LOOP) {
+                continue;
+            }
+            for level in dim.induction_levels() {
+                if let Some((_, ref incr)) = level.increment {
+                    let reg = name_map.declare_size_cast(incr, level.t());
+                    if let Some(reg) = reg {
+                        let old_name = name_map.name_size(incr, Type::I(32));
+                        self.print_inst(
+                            llir::Instruction::cast(level.t(), reg, old_name)
+                                .unwrap()
+                                .into(),
+                        );
+                    }
+                }
+            }
+        }
+        // INIT
+        let ind_levels = function.init_induction_levels().iter().chain(
+            function
+                .block_dims()
+                .iter()
+                .flat_map(|d| d.induction_levels()),
+        );
+        let mut insts = InstBuffer::default();
+        for level in ind_levels {
+            Printer::new(&mut insts, name_map).parallel_induction_level(level);
+        }
+        // BODY
+        Printer::new(&mut insts, name_map).cfg(function, function.cfg());
+        self.optimization_stats = insts.optimize();
+        insts.print(self);
+        self.terminate(format_args!("ret void"));
+
+        let name = global_name(function.name());
+        let (calling_conv, metadata) = match self.target {
+            Target::Amdgcn => ("amdgpu_kernel ", String::new()),
+            Target::Nvptx => (
+                "",
+                format!(
+                    "\n!nvvm.annotations = !{{!0}}\n!0 = !{{ptr {}, !\"kernel\", i32 1}}\n",
+                    name
+                ),
+            ),
+        };
+        format!(
+            include_str!("template/module.ll.template"),
+            module_id = function.name().escape_default(),
+            triple = self.target.triple(),
+            globals = self
+                .globals
+                .iter()
+                .format_with("", |global, f| { f(&format_args!("{}\n\n", global)) }),
+            calling_conv = calling_conv,
+            name = name,
+            params = function
+                .device_code_args()
+                .map(Self::param_decl)
+                .format(", "),
+            var_decls = Self::var_decls(&namegen),
+            body = self.buffer,
+            declarations = self.declarations.iter().format("\n"),
+            metadata = metadata,
+        )
+    }
+}
+
+impl<'a> InstPrinter<'a> for LlvmPrinter {
+    fn print_label(&mut self, label: llir::Label<'a>) {
+        self.start_block(label.name());
+    }
+
+    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'a>) {
+        let predicate = match inst.predicate {
+            Some(predicate) => predicate,
+            None => return self.instruction(&inst.instruction),
+        };
+        let cond = self.read(&predicate.into_operand());
+        let cont = self.new_block("cont");
+        if let llir::Instruction::Jump(label) = inst.instruction {
+            self.terminate(format_args!(
+                "br i1 {}, label %{}, label %{}",
+                cond,
+                label.name(),
+                cont
+            ));
+        } else {
+            let then = self.new_block("then");
+            self.terminate(format_args!(
+                "br i1 {}, label %{}, label %{}",
+                cond, then, cont
+            ));
+            self.start_block(&then);
+            self.instruction(&inst.instruction);
+        }
+        self.start_block(&cont);
+    }
+}
@@ -0,0 +1,257 @@
+//! Checks the LLVM modules generated for a few kernels are structurally well formed, without
+//! requiring an LLVM installation.
+//!
+//! Each kernel is lowered to a single implementation by always taking the first valid decision.
+use std::collections::{HashMap, HashSet};
+use telamon::device::{fake, Context};
+use telamon::search_space::{Action, DimKind, SearchSpace};
+use telamon::{helper, ir};
+use telamon_kernels::{linalg, Kernel, KernelBuilder};
+use telamon_llvm::{printer::LlvmPrinter, Llvm, Target};
+
+/// Generates the code of the first implementation of a kernel.
+fn kernel_code<'a, K>(target: Target, name: &'a str, params: K::Parameters) -> String
+where
+    K: Kernel<'a>,
+{
+    let mut context = fake::Context::new(Llvm::new(target));
+    let code = KernelBuilder::new().name(name).print_first::<K, _, _>(
+        params,
+        &mut context,
+        |function| LlvmPrinter::new(target).module(function),
+    );
+    checked(code)
+}
+
+/// Generates the code of the first implementation of a search space.
+fn space_code(target: Target, context: &dyn Context, space: SearchSpace) -> String {
+    let code = telamon_kernels::print_first(context, space, |function| {
+        LlvmPrinter::new(target).module(function)
+    });
+    checked(code)
+}
+
+/// Checks a module is well formed.
+fn checked(code: String) -> String {
+    if let Err(err) = check_module(&code) {
+        panic!("{}, in module:\n{}", err, code);
+    }
+    code
+}
+
+/// Returns the names of the local values, including labels, referenced by an instruction.
+/// Label references are prefixed by `label`.
+fn local_refs(line: &str) -> Vec<(bool, &str)> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+    let mut refs = vec![];
+    for (pos, _) in line.match_indices('%') {
+        let name = &line[pos + 1..];
+        let end = name.find(|c| !is_name_char(c)).unwrap_or(name.len());
+        refs.push((line[..pos].ends_with("label "), &name[..end]));
+    }
+    refs
+}
+
+/// Returns the global symbols referenced by an instruction.
+fn global_refs(line: &str) -> Vec<&str> {
+    line.match_indices('@')
+        .map(|(pos, _)| {
+            let name = &line[pos + 1..];
+            let end = name
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(name.len());
+            &name[..end]
+        })
+        .collect()
+}
+
+/// Checks that a module is well formed:
+/// - each basic block ends with a single terminator,
+/// - local values are defined exactly once and before being used in straight-line code,
+/// - branches target existing blocks,
+/// - called functions and referenced globals are declared.
+fn check_module(code: &str) -> Result<(), String> {
+    let mut globals = HashSet::new();
+    let mut global_uses = vec![];
+    let mut lines = code.lines();
+    // Module header and globals.
+    let define = loop {
+        let line = lines.next().ok_or("missing function definition")?;
+        if line.starts_with("define ") {
+            break line;
+        }
+        if line.starts_with('@') {
+            let name = global_refs(line)[0];
+            if !globals.insert(name) {
+                return Err(format!("global @{} defined twice", name));
+            }
+        }
+    };
+    if !define.ends_with(" {") {
+        return Err(format!("invalid function definition: {}", define));
+    }
+    globals.insert(global_refs(define)[0]);
+    let mut values: HashSet<&str> =
+        local_refs(define).into_iter().map(|(_, v)| v).collect();
+    // Function body.
+    let mut blocks = HashSet::new();
+    let mut label_uses = vec![];
+    let mut value_uses = vec![];
+    let mut current_block: Option<&str> = None;
+    let mut terminated = false;
+    for line in &mut lines {
+        if line == "}" {
+            break;
+        }
+        let label = line.trim_end_matches(':');
+        if label.len() < line.len() {
+            if current_block.is_some() && !terminated {
+                return Err(format!("block before {} is not terminated", label));
+            }
+            if !blocks.insert(label) {
+                return Err(format!("block {} defined twice", label));
+            }
+            current_block = Some(label);
+            terminated = false;
+            continue;
+        }
+        if !line.starts_with("  ") {
+            return Err(format!("unexpected line: {}", line));
+        }
+        let inst = &line[2..];
+        if current_block.is_none() {
+            return Err(format!("instruction outside of a block: {}", inst));
+        }
+        if terminated {
+            return Err(format!("instruction after a terminator: {}", inst));
+        }
+        let (def, rhs) = match inst.find(" = ") {
+            Some(pos) if inst.starts_with('%') => (Some(&inst[1..pos]), &inst[pos + 3..]),
+            _ => (None, inst),
+        };
+        for (is_label, name) in local_refs(rhs) {
+            if is_label {
+                label_uses.push(name);
+            } else {
+                value_uses.push(name);
+            }
+        }
+        global_uses.extend(global_refs(rhs));
+        if let Some(def) = def {
+            if !values.insert(def) {
+                return Err(format!("value %{} defined twice", def));
+            }
+        }
+        terminated = inst.starts_with("br ") || inst == "ret void";
+    }
+    if !terminated {
+        return Err("the last block is not terminated".to_string());
+    }
+    // Declarations and metadata.
+    let mut metadata = HashMap::new();
+    for line in lines {
+        if line.starts_with("declare ") {
+            let name = global_refs(line)[0];
+            if !globals.insert(name) {
+                return Err(format!("function @{} declared twice", name));
+            }
+        } else if line.starts_with('!') {
+            let pos = line
+                .find(" = ")
+                .ok_or_else(|| format!("invalid line: {}", line))?;
+            metadata.insert(&line[..pos], &line[pos + 3..]);
+        } else if !line.is_empty() {
+            return Err(format!("unexpected line: {}", line));
+        }
+    }
+    if let Some(value) = value_uses.into_iter().find(|v| !values.contains(v)) {
+        return Err(format!("value %{} is not defined", value));
+    }
+    if let Some(label) = label_uses.into_iter().find(|l| !blocks.contains(l)) {
+        return Err(format!("block %{} is not defined", label));
+    }
+    if let Some(global) = global_uses.into_iter().find(|g| !globals.contains(g)) {
+        return Err(format!("@{} is not declared", global));
+    }
+    Ok(())
+}
+
+/// Generates the code of a kernel with block and thread dimensions.
+fn block_thread_dims(target: Target) -> String {
+    let context = fake::Context::new(Llvm::new(target));
+    let space = telamon_kernels::block_thread_dims(&context);
+    space_code(target, &context, space)
+}
+
+/// Ensures block and thread dimensions are mapped to the index intrinsics of AMD GPUs.
+#[test]
+fn block_thread_dims_amdgcn() {
+    let code = block_thread_dims(Target::Amdgcn);
+    assert!(code.contains("define amdgpu_kernel void @block_thread_dims()"));
+    assert!(code.contains("call i32 @llvm.amdgcn.workgroup.id.x()"));
+    assert!(code.contains("call i32 @llvm.amdgcn.workitem.id.x()"));
+    assert!(code.contains("call i32 @llvm.amdgcn.workitem.id.y()"));
+}
+
+/// Ensures block and thread dimensions are mapped to the index intrinsics of Nvidia GPUs and
+/// that the function is annotated as a kernel.
+#[test]
+fn block_thread_dims_nvptx() {
+    let code = block_thread_dims(Target::Nvptx);
+    assert!(code.contains("call i32 @llvm.nvvm.read.ptx.sreg.ctaid.x()"));
+    assert!(code.contains("call i32 @llvm.nvvm.read.ptx.sreg.tid.y()"));
+    assert!(code.contains("!{ptr @block_thread_dims, !\"kernel\", i32 1}"));
+}
+
+/// Ensures vectorized instructions operate on vector types.
+#[test]
+fn vector_accesses() {
+    let mut context = fake::Context::new(Llvm::new(Target::Amdgcn));
+    let signature = {
+        let mut builder = helper::SignatureBuilder::new("vector_accesses", &mut context);
+        builder.array::<f32>("x", 4);
+        builder.get()
+    };
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    // Vector dimensions can only contain a single instruction.
+    let d0 = builder.open_dim_ex(builder.cst_size(4), DimKind::VECTOR);
+    let (addr, pattern) = builder.tensor_access(&"x", None, ir::Type::F(32), &[&d0]);
+    let ld = builder.ld(ir::Type::F(32), &addr, pattern);
+    let d1 = builder.open_mapped_dim(&d0);
+    builder.action(Action::DimKind(d1[0], DimKind::VECTOR));
+    let sum = builder.add(&ld, &ld);
+    let d2 = builder.open_mapped_dim(&d1);
+    builder.action(Action::DimKind(d2[0], DimKind::VECTOR));
+    let (addr, pattern) = builder.tensor_access(&"x", None, ir::Type::F(32), &[&d2]);
+    builder.st(&addr, &sum, pattern);
+    let code = space_code(Target::Amdgcn, &context, builder.get());
+    assert!(code.contains("load <4 x float>, ptr addrspace(1)"));
+    assert!(code.contains("fadd <4 x float>"));
+    assert!(code.contains("store <4 x float>"));
+}
+
+#[test]
+fn axpy() {
+    let code = kernel_code::<linalg::Axpy<f32>>(Target::Amdgcn, "axpy", (1 << 16, true));
+    assert!(code.contains("ptr addrspace(1) %arg."));
+}
+
+#[test]
+fn matmul() {
+    let code = kernel_code::<linalg::FusedMM<f32>>(
+        Target::Nvptx,
+        "matmul",
+        linalg::FusedMMP::new(32, 32, 32),
+    );
+    // The first implementation stores tiles in shared memory.
+    assert!(code.contains("addrspace(3) global"));
+    assert!(code.contains("call void @llvm.nvvm.barrier0()"));
+}
+
+/// Ensures kernels with matrix multiply-accumulate instructions are rejected when they are
+/// built, as the printer has no LLVM equivalent for them.
+#[test]
+#[should_panic(expected = "InvalidType { t: F(16) }")]
+fn mma_rejected() {
+    kernel_code::<linalg::MixedMM>(Target::Nvptx, "mixed_mm", (32, 16, 16));
+}
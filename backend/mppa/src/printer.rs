@@ -152,11 +152,14 @@ impl MppaPrinter {
                 .iter()
                 .flat_map(|d| d.induction_levels()),
         );
+        let mut insts = InstBuffer::default();
         for level in ind_levels {
-            Printer::new(self, name_map).parallel_induction_level(level);
+            Printer::new(&mut insts, name_map).parallel_induction_level(level);
         }
         // BODY
-        Printer::new(self, name_map).cfg(function, function.cfg());
+        Printer::new(&mut insts, name_map).cfg(function, function.cfg());
+        insts.optimize();
+        insts.print(self);
         let var_decls = self.var_decls(&namegen);
         return_string.push_str(&var_decls);
         return_string.push_str(&self.buffer);
@@ -392,12 +395,12 @@ impl MppaPrinter {
     }
 }
 
-impl InstPrinter for MppaPrinter {
-    fn print_label(&mut self, label: llir::Label<'_>) {
+impl<'a> InstPrinter<'a> for MppaPrinter {
+    fn print_label(&mut self, label: llir::Label<'a>) {
         writeln!(self.buffer, "{}", label.c99()).unwrap()
     }
 
-    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'_>) {
+    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'a>) {
         writeln!(self.buffer, "{}", inst.c99()).unwrap();
     }
 }
@@ -0,0 +1,178 @@
+//! Describes OpenCL devices.
+use crate::printer::OpenClPrinter;
+use fxhash::FxHashMap;
+use std::io::Write;
+use telamon::codegen::{Function, ResourceReport};
+use telamon::device;
+use telamon::ir::{self, Type};
+use telamon::model::{self, HwPressure};
+use telamon::search_space::{DimKind, InstFlag, MemSpace, SearchSpace};
+use utils::unwrap;
+
+/// Describes a generic OpenCL device.  The default limits are the minimal values the OpenCL
+/// 1.2 specification requires from GPU devices.
+#[derive(Clone, Debug)]
+pub struct OpenCl {
+    /// The name of the device.
+    pub name: String,
+    /// Maximal number of work-items in a work-group.
+    pub max_work_group_size: u32,
+    /// Maximal number of work-groups along a dimension.
+    pub max_num_groups: u32,
+    /// Size of the local memory of a work-group, in bytes.
+    pub local_mem_size: u32,
+}
+
+impl Default for OpenCl {
+    fn default() -> Self {
+        OpenCl {
+            name: String::from("OpenCL"),
+            max_work_group_size: 256,
+            max_num_groups: 65535,
+            local_mem_size: 32 * 1024,
+        }
+    }
+}
+
+impl device::Device for OpenCl {
+    fn print(&self, fun: &Function, out: &mut dyn Write) {
+        unwrap!(write!(out, "{}", OpenClPrinter::default().function(fun)));
+    }
+
+    fn resource_report(&self, fun: &Function) -> ResourceReport {
+        let mut printer = OpenClPrinter::default();
+        printer.function(fun);
+        ResourceReport {
+            optimization: Some(printer.optimization_stats()),
+            ..ResourceReport::new(fun)
+        }
+    }
+
+    fn check_type(&self, t: Type) -> Result<(), ir::TypeError> {
+        match t {
+            Type::I(i) | Type::F(i) if i == 32 || i == 64 => Ok(()),
+            Type::I(i) if i == 1 || i == 8 || i == 16 => Ok(()),
+            Type::PtrTo(_) => Ok(()),
+            t => Err(ir::TypeError::InvalidType { t }),
+        }
+    }
+
+    fn max_block_dims(&self) -> u32 {
+        3
+    }
+
+    fn max_inner_block_size(&self) -> u32 {
+        self.max_num_groups
+    }
+
+    fn max_threads(&self) -> u32 {
+        self.max_work_group_size
+    }
+
+    fn max_unrolling(&self) -> u32 {
+        512
+    }
+
+    // The C printer does not support vector types yet.
+    fn has_vector_registers(&self) -> bool {
+        false
+    }
+
+    fn can_vectorize(&self, _: &ir::Dimension, _: &ir::Operator) -> bool {
+        false
+    }
+
+    fn max_vectorization(&self, _: &ir::Operator) -> [u32; 2] {
+        [1, 1]
+    }
+
+    fn shared_mem(&self) -> u32 {
+        self.local_mem_size
+    }
+
+    fn pointer_type(&self, _: MemSpace) -> ir::Type {
+        // Addresses are stored in 64-bit integers and cast to pointers to the right address
+        // space when accessing memory.
+        ir::Type::I(64)
+    }
+
+    fn supported_mem_flags(&self, op: &ir::Operator) -> InstFlag {
+        match op {
+            ir::Operator::Ld(..)
+            | ir::Operator::St(..)
+            | ir::Operator::TmpLd(..)
+            | ir::Operator::TmpSt(..) => InstFlag::BLOCK_COHERENT,
+            _ => panic!("not a memory operation"),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn hw_pressure(
+        &self,
+        _: &SearchSpace,
+        _: &FxHashMap<ir::DimId, model::size::Range>,
+        _: &FxHashMap<ir::StmtId, model::Nesting>,
+        _: &dyn ir::Statement,
+        _: &dyn device::Context,
+    ) -> model::HwPressure {
+        // TODO(model): implement model
+        model::HwPressure::zero(self)
+    }
+
+    fn loop_iter_pressure(&self, _kind: DimKind) -> (HwPressure, HwPressure) {
+        //TODO(model): implement minimal model
+        (HwPressure::zero(self), HwPressure::zero(self))
+    }
+
+    fn thread_rates(&self) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::new(1.0, vec![])
+    }
+
+    fn block_rates(&self) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::new(1.0, vec![])
+    }
+
+    fn total_rates(&self) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::new(1.0, vec![])
+    }
+
+    fn bottlenecks(&self) -> &[&'static str] {
+        &[]
+    }
+
+    fn block_parallelism(&self, _space: &SearchSpace) -> u32 {
+        1
+    }
+
+    fn additive_indvar_pressure(&self, _t: &ir::Type) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::zero(self)
+    }
+
+    fn multiplicative_indvar_pressure(&self, _t: &ir::Type) -> HwPressure {
+        //TODO(model): implement minimal model
+        HwPressure::zero(self)
+    }
+
+    fn add_block_overhead(
+        &self,
+        _: model::size::FactorRange,
+        _: model::size::FactorRange,
+        _: model::size::Range,
+        _pressure: &mut HwPressure,
+    ) {
+    }
+
+    fn lower_type(&self, t: ir::Type, _space: &SearchSpace) -> Option<ir::Type> {
+        match t {
+            Type::PtrTo(_) => Some(Type::I(64)),
+            _ => Some(t),
+        }
+    }
+}
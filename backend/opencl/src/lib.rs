@@ -0,0 +1,48 @@
+//! Defines the OpenCL C target.
+//!
+//! The generated code is OpenCL C source built on the shared `telamon-c` printer: block and
+//! thread dimensions are mapped to work-groups and work-items, shared memory to `__local`
+//! memory and barriers to `barrier(CLK_LOCAL_MEM_FENCE)`.  The crate does not depend on an
+//! OpenCL runtime, so kernels can only be evaluated through a fake context.
+#![deny(bare_trait_objects, unused_lifetimes)]
+#![warn(clippy::all)]
+
+mod device;
+pub mod printer;
+
+pub use crate::device::OpenCl;
+
+use fxhash::FxHashMap;
+use telamon::{codegen, ir};
+
+#[derive(Default)]
+pub(crate) struct NameGenerator {
+    num_var: FxHashMap<ir::Type, usize>,
+}
+
+impl NameGenerator {
+    /// Generate a variable name prefix from a type.
+    fn gen_prefix(t: ir::Type) -> &'static str {
+        match t {
+            ir::Type::I(1) => "p",
+            ir::Type::I(8) => "c",
+            ir::Type::I(16) => "s",
+            ir::Type::I(32) => "r",
+            ir::Type::I(64) => "rd",
+            ir::Type::F(16) => "h",
+            ir::Type::F(32) => "f",
+            ir::Type::F(64) => "d",
+            _ => panic!("invalid OpenCL type"),
+        }
+    }
+}
+
+impl codegen::NameGenerator for NameGenerator {
+    fn name(&mut self, t: ir::Type) -> String {
+        let prefix = NameGenerator::gen_prefix(t);
+        let entry = self.num_var.entry(t).or_insert(0);
+        let name = format!("{}{}", prefix, *entry);
+        *entry += 1;
+        name
+    }
+}
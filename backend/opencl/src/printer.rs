@@ -0,0 +1,221 @@
+//! Prints OpenCL C kernels.
+use crate::NameGenerator;
+use itertools::Itertools;
+use std::fmt::Write as WriteFmt;
+use telamon::codegen::*;
+use telamon::ir::Type;
+use telamon::search_space::{DimKind, Domain};
+use telamon_c::C99Display as _;
+use utils::unwrap;
+
+/// Prints a `Function` as an OpenCL C kernel.
+#[derive(Default)]
+pub struct OpenClPrinter {
+    buffer: String,
+    /// Effect of the optimizations on the last printed function body
+    optimization_stats: OptimizationStats,
+}
+
+/// Returns the address space qualifier of pointers to a state space.
+fn address_space(ss: llir::StateSpace) -> &'static str {
+    match ss {
+        llir::StateSpace::Global => "__global",
+        llir::StateSpace::Shared => "__local",
+    }
+}
+
+/// Returns the type of a kernel parameter.
+fn param_t(param: &ParamVal) -> String {
+    match param {
+        &ParamVal::External(ref param, par_type) => {
+            if let Some(elem_t) = param.elem_t {
+                format!("__global {}*", elem_t.c99())
+            } else {
+                par_type.c99().to_string()
+            }
+        }
+        ParamVal::Size(_) => "uint32_t".to_string(),
+        ParamVal::GlobalMem(..) => "__global int8_t*".to_string(),
+    }
+}
+
+impl OpenClPrinter {
+    /// Returns the effect of the optimizations on the last printed function body.
+    pub fn optimization_stats(&self) -> OptimizationStats {
+        self.optimization_stats
+    }
+
+    /// Declares all parameters of the function with the appropriate type
+    fn param_decl(&self, param: &ParamVal) -> String {
+        format!("{} {}", param_t(param), param.key().ident())
+    }
+
+    /// Declares all variables that have been required from the namegen
+    fn var_decls(&self, namegen: &NameGenerator) -> String {
+        namegen
+            .num_var
+            .iter()
+            .sorted_by_key(|&(&t, _)| NameGenerator::gen_prefix(t))
+            .map(|(&t, &n)| {
+                let prefix = NameGenerator::gen_prefix(t);
+                format!(
+                    "  {} {};\n",
+                    t.c99(),
+                    (0..n).format_with(", ", |i, f| f(&format_args!("{}{}", prefix, i)))
+                )
+            })
+            .collect()
+    }
+
+    /// Declares block and thread indexes.  Thread dimensions are listed from the outermost to
+    /// the innermost, so the innermost one is mapped to the first work-item dimension.
+    fn decl_par_indexes(&mut self, function: &Function, name_map: &NameMap<'_>) {
+        for (ind, dim) in function.block_dims().iter().enumerate() {
+            unwrap!(writeln!(
+                self.buffer,
+                "  {} = get_group_id({});",
+                name_map.name_index(dim.id()).c99(),
+                ind
+            ));
+        }
+        for (ind, dim) in function.thread_dims().iter().rev().enumerate() {
+            unwrap!(writeln!(
+                self.buffer,
+                "  {} = get_local_id({});",
+                name_map.name_index(dim.id()).c99(),
+                ind
+            ));
+        }
+    }
+
+    /// Declares a shared memory block.
+    fn shared_mem_decl(&mut self, block: &MemoryRegion, name_map: &NameMap<'_>) {
+        unwrap!(writeln!(
+            self.buffer,
+            "  __local int8_t shmem{id}[{size}] __attribute__((aligned(16)));
+  {name} = (int64_t)shmem{id};",
+            id = block.id().0,
+            name = name_map.name_addr(block.id()).c99(),
+            size = unwrap!(block.alloc_size().as_int())
+        ));
+    }
+
+    /// Prints a `Function`.
+    pub fn function(&mut self, function: &Function) -> String {
+        let mut namegen = NameGenerator::default();
+        let interner = Interner::default();
+        let name_map = &mut NameMap::new(&interner, function, &mut namegen);
+        // INDEX LOADS
+        self.decl_par_indexes(function, name_map);
+        // LOAD PARAM
+        for val in function.device_code_args() {
+            let var_name = name_map.name_param_val(val.key());
+            unwrap!(writeln!(
+                self.buffer,
+                "  {var_name} = {cast}{name}; // {param}",
+                cast = if val.elem_t().is_some() {
+                    format!("({})", var_name.t().c99())
+                } else {
+                    "".to_string()
+                },
+                var_name = var_name.c99(),
+                name = val.key().ident(),
+                param = val.key(),
+            ));
+        }
+        // MEM DECL
+        for block in function.mem_blocks() {
+            match block.alloc_scheme() {
+                AllocationScheme::Shared => self.shared_mem_decl(block, name_map),
+                AllocationScheme::PrivatisedGlobal => {
+                    Printer::new(self, name_map).privatise_global_block(block, function)
+                }
+                AllocationScheme::Global => (),
+            }
+        }
+        // Compute size casts
+        for dim in function.dimensions() {
+            if !dim.kind().intersects(DimKind::UNROLL | DimKind::LOOP) {
+                continue;
+            }
+            for level in dim.induction_levels() {
+                if let Some((_, ref incr)) = level.increment {
+                    let reg = name_map.declare_size_cast(incr, level.t());
+                    if let Some(reg) = reg {
+                        let old_name = name_map.name_size(incr, Type::I(32));
+                        self.print_inst(
+                            llir::Instruction::cast(level.t(), reg, old_name)
+                                .unwrap()
+                                .into(),
+                        );
+                    }
+                }
+            }
+        }
+        // INIT
+        let ind_levels = function.init_induction_levels().iter().chain(
+            function
+                .block_dims()
+                .iter()
+                .flat_map(|d| d.induction_levels()),
+        );
+        let mut insts = InstBuffer::default();
+        for level in ind_levels {
+            Printer::new(&mut insts, name_map).parallel_induction_level(level);
+        }
+        // BODY
+        Printer::new(&mut insts, name_map).cfg(function, function.cfg());
+        self.optimization_stats = insts.optimize();
+        insts.print(self);
+        let mut body = self.var_decls(&namegen);
+        body.push_str(&self.buffer);
+        format!(
+            include_str!("template/kernel.cl.template"),
+            name = function.name(),
+            params = function
+                .device_code_args()
+                .format_with("", |v, f| f(&format_args!("\n  {},", self.param_decl(v))))
+                .to_string()
+                .trim_end_matches(','),
+            body = body,
+        )
+    }
+}
+
+impl<'a> InstPrinter<'a> for OpenClPrinter {
+    fn print_label(&mut self, label: llir::Label<'a>) {
+        unwrap!(writeln!(self.buffer, "{}", label.c99()))
+    }
+
+    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'a>) {
+        // Memory accesses need an address space qualifier and barriers are OpenCL builtins,
+        // all other instructions are printed as C99.
+        let op = match &inst.instruction {
+            llir::Instruction::Load(spec, d, a) => format!(
+                "{d} = *({ss} {t}*)({a});",
+                ss = address_space(spec.state_space()),
+                t = spec.t().c99(),
+                d = d.c99(),
+                a = a.c99(),
+            ),
+            llir::Instruction::Store(spec, a, [b]) => format!(
+                "*({ss} {t}*)({a}) = {b};",
+                ss = address_space(spec.state_space()),
+                t = spec.t().c99(),
+                a = a.c99(),
+                b = b.c99(),
+            ),
+            llir::Instruction::Sync => "barrier(CLK_LOCAL_MEM_FENCE);".to_string(),
+            _ => {
+                unwrap!(writeln!(self.buffer, "  {}", inst.c99()));
+                return;
+            }
+        };
+        match inst.predicate {
+            Some(predicate) => {
+                unwrap!(writeln!(self.buffer, "  if ({}) {}", predicate.c99(), op))
+            }
+            None => unwrap!(writeln!(self.buffer, "  {}", op)),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Checks the code generated for the kernels suite against reference outputs, without requiring
+//! an OpenCL runtime.
+//!
+//! Each kernel is lowered to a single implementation by always taking the first valid decision,
+//! which is deterministic.  Set `TELAMON_UPDATE_GOLDEN=1` to regenerate the reference outputs
+//! after an intended change of the printer.
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use telamon::codegen;
+use telamon::device::{fake, Device};
+use telamon_kernels::{linalg, Kernel, KernelBuilder};
+use telamon_opencl::{printer::OpenClPrinter, OpenCl};
+
+/// Generates the code of the first implementation of a kernel and compares it with the
+/// reference output `golden/<name>.cl`.
+fn check_kernel<'a, K>(name: &'a str, params: K::Parameters)
+where
+    K: Kernel<'a>,
+{
+    let mut context = fake::Context::new(OpenCl::default());
+    let code = KernelBuilder::new().name(name).print_first::<K, _, _>(
+        params,
+        &mut context,
+        print,
+    );
+    check_golden(name, &code);
+}
+
+/// Prints a function and checks the optimizer did not add instructions.
+fn print(function: &codegen::Function) -> String {
+    let stats = OpenCl::default()
+        .resource_report(function)
+        .optimization
+        .unwrap();
+    assert!(stats.num_insts_after <= stats.num_insts_before);
+    OpenClPrinter::default().function(function)
+}
+
+/// Compares code with the reference output `golden/<name>.cl`.
+fn check_golden(name: &str, code: &str) {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden"]
+        .iter()
+        .collect::<PathBuf>()
+        .join(format!("{}.cl", name));
+    if env::var_os("TELAMON_UPDATE_GOLDEN").is_some() {
+        fs::write(&path, code).unwrap();
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("cannot read {}: {}", path.display(), err));
+    assert!(
+        expected == code,
+        "Expected code:\n{}\nBut generated code was:\n{}",
+        expected,
+        code
+    );
+}
+
+/// Ensures block and thread dimensions are mapped to work-groups and work-items.
+#[test]
+fn block_thread_dims() {
+    let context = fake::Context::new(OpenCl::default());
+    let space = telamon_kernels::block_thread_dims(&context);
+    let code = telamon_kernels::print_first(&context, space, print);
+    check_golden("block_thread_dims", &code);
+}
+
+#[test]
+fn axpy() {
+    check_kernel::<linalg::Axpy<f32>>("axpy", (1 << 16, true));
+}
+
+#[test]
+fn mv() {
+    check_kernel::<linalg::MatVec<f32>>("mv", (64, 32, true));
+}
+
+#[test]
+fn gesummv() {
+    check_kernel::<linalg::Gesummv<f32>>("gesummv", (32, 32, true));
+}
+
+#[test]
+fn matmul() {
+    check_kernel::<linalg::FusedMM<f32>>("matmul", linalg::FusedMMP::new(32, 32, 32));
+}
+
+#[test]
+fn batchmm() {
+    check_kernel::<linalg::BatchMM<f32>>("batchmm", linalg::BatchMMP::new(4, 16, 16, 16));
+}
@@ -0,0 +1,120 @@
+typedef char int8_t;
+typedef short int16_t;
+typedef int int32_t;
+typedef long int64_t;
+typedef uint uint32_t;
+
+#define __max(a, b) ((a) > (b) ? (a) : (b))
+#define __min(a, b) ((a) < (b) ? (a) : (b))
+
+#define __mul32Hi(a, b) mul_hi((int)(a), (int)(b))
+#define __mul64Hi(a, b) mul_hi((long)(a), (long)(b))
+#define __mul32Wide(a, b) ((long)(a) * (long)(b))
+
+#define __mad32(a, b, c) (((a) * (b)) + (c))
+#define __mad64(a, b, c) (((a) * (b)) + (c))
+#define __mad32Hi(a, b, c) (__mul32Hi(a, b) + (c))
+#define __mad32Wide(a, b, c) (__mul32Wide(a, b) + (c))
+
+#define __fma(a, b, c) fma(a, b, c)
+#define expf(a) exp(a)
+
+__kernel void axpy(
+  uint32_t _n_4,
+  __global float* y,
+  __global float* x,
+  __global float* z,
+  float alpha)
+{
+  float f0, f1, f2, f3, f4, f5;
+  int8_t p0, p1, p2, p3, p4, p5, p6;
+  int32_t r0, r1, r2, r3, r4, r5, r6, r7;
+  int64_t rd0, rd1, rd2, rd3, rd4, rd5, rd6, rd7, rd8, rd9, rd10, rd11, rd12, rd13, rd14, rd15, rd16, rd17, rd18, rd19, rd20, rd21;
+  r0 = _n_4; // n/4
+  rd0 = (int64_t)y; // y
+  rd1 = (int64_t)x; // x
+  rd2 = (int64_t)z; // z
+  f0 = alpha; // alpha
+  __local int8_t shmem0[16] __attribute__((aligned(16)));
+  rd20 = (int64_t)shmem0;
+  __local int8_t shmem1[16] __attribute__((aligned(16)));
+  rd21 = (int64_t)shmem1;
+  r1 = (0);
+  rd17 = (rd1);
+  rd18 = (rd0);
+  rd19 = (rd2);
+LOOP_0:
+  r2 = (0);
+  rd5 = (rd17);
+  rd6 = (rd20);
+LOOP_1:
+  r3 = (0);
+  rd3 = (rd5);
+  rd4 = (rd6);
+LOOP_2:
+  f1 = *(__global float*)(rd3);
+  *(__local float*)(rd4) = f1;
+  rd3 = rd3 + 4;
+  rd4 = rd4 + 4;
+  r3 = r3 + 1;
+  p0 = r3 < 2;
+  if (p0) goto LOOP_2;
+  rd5 = rd5 + 8;
+  rd6 = rd6 + 8;
+  r2 = r2 + 1;
+  p1 = r2 < 2;
+  if (p1) goto LOOP_1;
+  r4 = (0);
+  rd9 = (rd18);
+  rd10 = (rd21);
+LOOP_3:
+  r5 = (0);
+  rd7 = (rd9);
+  rd8 = (rd10);
+LOOP_4:
+  f2 = *(__global float*)(rd7);
+  *(__local float*)(rd8) = f2;
+  rd7 = rd7 + 4;
+  rd8 = rd8 + 4;
+  r5 = r5 + 1;
+  p2 = r5 < 2;
+  if (p2) goto LOOP_4;
+  rd9 = rd9 + 8;
+  rd10 = rd10 + 8;
+  r4 = r4 + 1;
+  p3 = r4 < 2;
+  if (p3) goto LOOP_3;
+  r6 = (0);
+  rd14 = (rd19);
+  rd15 = (rd20);
+  rd16 = (rd21);
+LOOP_5:
+  r7 = (0);
+  rd11 = (rd14);
+  rd12 = (rd15);
+  rd13 = (rd16);
+LOOP_6:
+  f3 = *(__local float*)(rd12);
+  f4 = *(__local float*)(rd13);
+  f5 = __fma(f3, f0, f4);
+  *(__global float*)(rd11) = f5;
+  rd11 = rd11 + 4;
+  rd12 = rd12 + 4;
+  rd13 = rd13 + 4;
+  r7 = r7 + 1;
+  p4 = r7 < 2;
+  if (p4) goto LOOP_6;
+  rd14 = rd14 + 8;
+  rd15 = rd15 + 8;
+  rd16 = rd16 + 8;
+  r6 = r6 + 1;
+  p5 = r6 < 2;
+  if (p5) goto LOOP_5;
+  rd17 = rd17 + 16;
+  rd18 = rd18 + 16;
+  rd19 = rd19 + 16;
+  r1 = r1 + 1;
+  p6 = r1 < r0;
+  if (p6) goto LOOP_0;
+  barrier(CLK_LOCAL_MEM_FENCE);
+}
@@ -0,0 +1,180 @@
+typedef char int8_t;
+typedef short int16_t;
+typedef int int32_t;
+typedef long int64_t;
+typedef uint uint32_t;
+
+#define __max(a, b) ((a) > (b) ? (a) : (b))
+#define __min(a, b) ((a) < (b) ? (a) : (b))
+
+#define __mul32Hi(a, b) mul_hi((int)(a), (int)(b))
+#define __mul64Hi(a, b) mul_hi((long)(a), (long)(b))
+#define __mul32Wide(a, b) ((long)(a) * (long)(b))
+
+#define __mad32(a, b, c) (((a) * (b)) + (c))
+#define __mad64(a, b, c) (((a) * (b)) + (c))
+#define __mad32Hi(a, b, c) (__mul32Hi(a, b) + (c))
+#define __mad32Wide(a, b, c) (__mul32Wide(a, b) + (c))
+
+#define __fma(a, b, c) fma(a, b, c)
+#define expf(a) exp(a)
+
+__kernel void batchmm(
+  __global float* a,
+  uint32_t _m_2,
+  uint32_t _n_2,
+  uint32_t _8_k_m,
+  uint32_t _4_k,
+  uint32_t _batch_2,
+  uint32_t _4_n,
+  uint32_t _8_n_k,
+  __global float* b,
+  uint32_t _4_n_m,
+  uint32_t _8_n,
+  __global float* c,
+  uint32_t _4_k_m,
+  uint32_t _8_n_m,
+  uint32_t _8_k,
+  uint32_t _4_n_k,
+  uint32_t _k_2)
+{
+  float f0, f1, f2, f3, f4;
+  int8_t p0, p1, p2, p3, p4, p5, p6, p7, p8, p9;
+  int32_t r0, r1, r2, r3, r4, r5, r6, r7, r8, r9, r10, r11, r12, r13, r14, r15, r16, r17, r18, r19, r20, r21, r22, r23;
+  int64_t rd0, rd1, rd2, rd3, rd4, rd5, rd6, rd7, rd8, rd9, rd10, rd11, rd12, rd13, rd14, rd15, rd16, rd17, rd18, rd19, rd20, rd21, rd22, rd23, rd24, rd25, rd26, rd27, rd28, rd29, rd30, rd31, rd32, rd33, rd34, rd35, rd36;
+  rd0 = (int64_t)a; // a
+  r0 = _m_2; // m/2
+  r1 = _n_2; // n/2
+  r2 = _8_k_m; // 8*k*m
+  r3 = _4_k; // 4*k
+  r4 = _batch_2; // batch/2
+  r5 = _4_n; // 4*n
+  r6 = _8_n_k; // 8*n*k
+  rd1 = (int64_t)b; // b
+  r7 = _4_n_m; // 4*n*m
+  r8 = _8_n; // 8*n
+  rd2 = (int64_t)c; // c
+  r9 = _4_k_m; // 4*k*m
+  r10 = _8_n_m; // 8*n*m
+  r11 = _8_k; // 8*k
+  r12 = _4_n_k; // 4*n*k
+  r13 = _k_2; // k/2
+  __local int8_t shmem0[8] __attribute__((aligned(16)));
+  rd25 = (int64_t)shmem0;
+  __local int8_t shmem1[8] __attribute__((aligned(16)));
+  rd26 = (int64_t)shmem1;
+  rd27 = (int64_t)(r2);
+  rd28 = (int64_t)(r6);
+  rd29 = (int64_t)(r10);
+  rd30 = (int64_t)(r9);
+  rd31 = (int64_t)(r12);
+  rd32 = (int64_t)(r7);
+  rd33 = (int64_t)(r11);
+  rd34 = (int64_t)(r8);
+  rd35 = (int64_t)(r3);
+  rd36 = (int64_t)(r5);
+  r14 = (0);
+  rd22 = (rd0);
+  rd23 = (rd1);
+  rd24 = (rd2);
+LOOP_0:
+  r15 = (0);
+  rd19 = (rd22);
+  rd20 = (rd23);
+  rd21 = (rd24);
+LOOP_1:
+  r16 = (0);
+  rd17 = (rd19);
+  rd18 = (rd21);
+LOOP_2:
+  r17 = (0);
+  rd15 = (rd17);
+  rd16 = (rd18);
+LOOP_3:
+  r18 = (0);
+  rd13 = (rd20);
+  rd14 = (rd16);
+LOOP_4:
+  r19 = (0);
+  rd11 = (rd13);
+  rd12 = (rd14);
+LOOP_5:
+  f0 = (0x0p-1075f);
+  r20 = (0);
+  rd9 = (rd15);
+  rd10 = (rd11);
+LOOP_6:
+  r21 = (0);
+  rd3 = (rd9);
+  rd4 = (rd25);
+LOOP_7:
+  f1 = *(__global float*)(rd3);
+  *(__local float*)(rd4) = f1;
+  rd3 = rd3 + 4;
+  rd4 = rd4 + 4;
+  r21 = r21 + 1;
+  p0 = r21 < 2;
+  if (p0) goto LOOP_7;
+  r22 = (0);
+  rd5 = (rd10);
+  rd6 = (rd26);
+LOOP_8:
+  f2 = *(__global float*)(rd5);
+  *(__local float*)(rd6) = f2;
+  rd5 = rd5 + rd36;
+  rd6 = rd6 + 4;
+  r22 = r22 + 1;
+  p1 = r22 < 2;
+  if (p1) goto LOOP_8;
+  r23 = (0);
+  rd7 = (rd25);
+  rd8 = (rd26);
+LOOP_9:
+  f3 = *(__local float*)(rd7);
+  f4 = *(__local float*)(rd8);
+  f0 = __fma(f3, f4, f0);
+  rd7 = rd7 + 4;
+  rd8 = rd8 + 4;
+  r23 = r23 + 1;
+  p2 = r23 < 2;
+  if (p2) goto LOOP_9;
+  rd9 = rd9 + 8;
+  rd10 = rd10 + rd34;
+  r20 = r20 + 1;
+  p3 = r20 < r13;
+  if (p3) goto LOOP_6;
+  *(__global float*)(rd12) = f0;
+  rd11 = rd11 + 4;
+  rd12 = rd12 + 4;
+  r19 = r19 + 1;
+  p4 = r19 < 2;
+  if (p4) goto LOOP_5;
+  rd13 = rd13 + 8;
+  rd14 = rd14 + 8;
+  r18 = r18 + 1;
+  p5 = r18 < r1;
+  if (p5) goto LOOP_4;
+  rd15 = rd15 + rd35;
+  rd16 = rd16 + rd36;
+  r17 = r17 + 1;
+  p6 = r17 < 2;
+  if (p6) goto LOOP_3;
+  rd17 = rd17 + rd33;
+  rd18 = rd18 + rd34;
+  r16 = r16 + 1;
+  p7 = r16 < r0;
+  if (p7) goto LOOP_2;
+  rd19 = rd19 + rd30;
+  rd20 = rd20 + rd31;
+  rd21 = rd21 + rd32;
+  r15 = r15 + 1;
+  p8 = r15 < 2;
+  if (p8) goto LOOP_1;
+  rd22 = rd22 + rd27;
+  rd23 = rd23 + rd28;
+  rd24 = rd24 + rd29;
+  r14 = r14 + 1;
+  p9 = r14 < r4;
+  if (p9) goto LOOP_0;
+  barrier(CLK_LOCAL_MEM_FENCE);
+}
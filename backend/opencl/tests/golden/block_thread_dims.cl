@@ -0,0 +1,29 @@
+typedef char int8_t;
+typedef short int16_t;
+typedef int int32_t;
+typedef long int64_t;
+typedef uint uint32_t;
+
+#define __max(a, b) ((a) > (b) ? (a) : (b))
+#define __min(a, b) ((a) < (b) ? (a) : (b))
+
+#define __mul32Hi(a, b) mul_hi((int)(a), (int)(b))
+#define __mul64Hi(a, b) mul_hi((long)(a), (long)(b))
+#define __mul32Wide(a, b) ((long)(a) * (long)(b))
+
+#define __mad32(a, b, c) (((a) * (b)) + (c))
+#define __mad64(a, b, c) (((a) * (b)) + (c))
+#define __mad32Hi(a, b, c) (__mul32Hi(a, b) + (c))
+#define __mad32Wide(a, b, c) (__mul32Wide(a, b) + (c))
+
+#define __fma(a, b, c) fma(a, b, c)
+#define expf(a) exp(a)
+
+__kernel void block_thread_dims()
+{
+  int32_t r0, r1, r2, r3, r4, r5;
+  r0 = get_group_id(0);
+  r2 = get_local_id(0);
+  r1 = get_local_id(1);
+  barrier(CLK_LOCAL_MEM_FENCE);
+}
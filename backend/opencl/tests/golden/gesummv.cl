@@ -0,0 +1,237 @@
+typedef char int8_t;
+typedef short int16_t;
+typedef int int32_t;
+typedef long int64_t;
+typedef uint uint32_t;
+
+#define __max(a, b) ((a) > (b) ? (a) : (b))
+#define __min(a, b) ((a) < (b) ? (a) : (b))
+
+#define __mul32Hi(a, b) mul_hi((int)(a), (int)(b))
+#define __mul64Hi(a, b) mul_hi((long)(a), (long)(b))
+#define __mul32Wide(a, b) ((long)(a) * (long)(b))
+
+#define __mad32(a, b, c) (((a) * (b)) + (c))
+#define __mad64(a, b, c) (((a) * (b)) + (c))
+#define __mad32Hi(a, b, c) (__mul32Hi(a, b) + (c))
+#define __mad32Wide(a, b, c) (__mul32Wide(a, b) + (c))
+
+#define __fma(a, b, c) fma(a, b, c)
+#define expf(a) exp(a)
+
+__kernel void gesummv(
+  uint32_t _8_n,
+  __global float* a,
+  uint32_t _16_n,
+  uint32_t _n_2,
+  uint32_t _m_4,
+  __global float* x,
+  float beta,
+  __global float* y,
+  __global float* b,
+  uint32_t _4_n,
+  float alpha)
+{
+  float f0, f1, f2, f3, f4, f5, f6, f7, f8, f9, f10, f11, f12, f13, f14, f15;
+  int8_t p0, p1, p2, p3, p4, p5, p6, p7, p8, p9, p10, p11, p12, p13, p14;
+  int32_t r0, r1, r2, r3, r4, r5, r6, r7, r8, r9, r10, r11, r12, r13, r14, r15, r16, r17, r18, r19;
+  int64_t rd0, rd1, rd2, rd3, rd4, rd5, rd6, rd7, rd8, rd9, rd10, rd11, rd12, rd13, rd14, rd15, rd16, rd17, rd18, rd19, rd20, rd21, rd22, rd23, rd24, rd25, rd26, rd27, rd28, rd29, rd30, rd31, rd32, rd33, rd34, rd35, rd36, rd37, rd38, rd39, rd40, rd41, rd42, rd43, rd44, rd45, rd46, rd47, rd48;
+  r0 = _8_n; // 8*n
+  rd0 = (int64_t)a; // a
+  r1 = _16_n; // 16*n
+  r2 = _n_2; // n/2
+  r3 = _m_4; // m/4
+  rd1 = (int64_t)x; // x
+  f0 = beta; // beta
+  rd2 = (int64_t)y; // y
+  rd3 = (int64_t)b; // b
+  r4 = _4_n; // 4*n
+  f1 = alpha; // alpha
+  __local int8_t shmem0[8] __attribute__((aligned(16)));
+  rd39 = (int64_t)shmem0;
+  __local int8_t shmem1[8] __attribute__((aligned(16)));
+  rd40 = (int64_t)shmem1;
+  __local int8_t shmem2[16] __attribute__((aligned(16)));
+  rd41 = (int64_t)shmem2;
+  __local int8_t shmem3[8] __attribute__((aligned(16)));
+  rd42 = (int64_t)shmem3;
+  __local int8_t shmem4[8] __attribute__((aligned(16)));
+  rd43 = (int64_t)shmem4;
+  __local int8_t shmem5[16] __attribute__((aligned(16)));
+  rd44 = (int64_t)shmem5;
+  __local int8_t shmem6[16] __attribute__((aligned(16)));
+  rd45 = (int64_t)shmem6;
+  rd46 = (int64_t)(r1);
+  rd47 = (int64_t)(r0);
+  rd48 = (int64_t)(r4);
+  r5 = (0);
+  rd36 = (rd0);
+  rd37 = (rd3);
+  rd38 = (rd2);
+LOOP_0:
+  r6 = (0);
+  rd24 = (rd37);
+  rd25 = (rd44);
+LOOP_1:
+  r7 = (0);
+  rd22 = (rd24);
+  rd23 = (rd25);
+LOOP_2:
+  r8 = (0);
+  rd20 = (rd36);
+  rd21 = (rd41);
+LOOP_3:
+  r9 = (0);
+  rd18 = (rd20);
+  rd19 = (rd21);
+LOOP_4:
+  f2 = (0x0p-1075f);
+  f3 = (0x0p-1075f);
+  r10 = (0);
+  rd15 = (rd1);
+  rd16 = (rd18);
+  rd17 = (rd22);
+LOOP_5:
+  r11 = (0);
+  rd4 = (rd15);
+  rd5 = (rd40);
+  rd6 = (rd43);
+LOOP_6:
+  f4 = *(__global float*)(rd4);
+  *(__local float*)(rd5) = f4;
+  *(__local float*)(rd6) = f4;
+  rd4 = rd4 + 4;
+  rd5 = rd5 + 4;
+  rd6 = rd6 + 4;
+  r11 = r11 + 1;
+  p0 = r11 < 2;
+  if (p0) goto LOOP_6;
+  r12 = (0);
+  rd7 = (rd16);
+  rd8 = (rd39);
+LOOP_7:
+  f5 = *(__global float*)(rd7);
+  *(__local float*)(rd8) = f5;
+  rd7 = rd7 + 4;
+  rd8 = rd8 + 4;
+  r12 = r12 + 1;
+  p1 = r12 < 2;
+  if (p1) goto LOOP_7;
+  r13 = (0);
+  rd9 = (rd17);
+  rd10 = (rd42);
+LOOP_8:
+  f6 = *(__global float*)(rd9);
+  *(__local float*)(rd10) = f6;
+  rd9 = rd9 + 4;
+  rd10 = rd10 + 4;
+  r13 = r13 + 1;
+  p2 = r13 < 2;
+  if (p2) goto LOOP_8;
+  r14 = (0);
+  rd11 = (rd39);
+  rd12 = (rd40);
+LOOP_9:
+  f7 = *(__local float*)(rd11);
+  f8 = *(__local float*)(rd12);
+  f2 = __fma(f7, f8, f2);
+  rd11 = rd11 + 4;
+  rd12 = rd12 + 4;
+  r14 = r14 + 1;
+  p3 = r14 < 2;
+  if (p3) goto LOOP_9;
+  r15 = (0);
+  rd13 = (rd42);
+  rd14 = (rd43);
+LOOP_10:
+  f9 = *(__local float*)(rd13);
+  f10 = *(__local float*)(rd14);
+  f3 = __fma(f9, f10, f3);
+  rd13 = rd13 + 4;
+  rd14 = rd14 + 4;
+  r15 = r15 + 1;
+  p4 = r15 < 2;
+  if (p4) goto LOOP_10;
+  rd15 = rd15 + 8;
+  rd16 = rd16 + 8;
+  rd17 = rd17 + 8;
+  r10 = r10 + 1;
+  p5 = r10 < r2;
+  if (p5) goto LOOP_5;
+  *(__local float*)(rd19) = f2;
+  rd18 = rd18 + rd48;
+  rd19 = rd19 + 4;
+  r9 = r9 + 1;
+  p6 = r9 < 2;
+  if (p6) goto LOOP_4;
+  rd20 = rd20 + rd47;
+  rd21 = rd21 + 8;
+  r8 = r8 + 1;
+  p7 = r8 < 2;
+  if (p7) goto LOOP_3;
+  *(__local float*)(rd23) = f3;
+  rd22 = rd22 + rd48;
+  rd23 = rd23 + 4;
+  r7 = r7 + 1;
+  p8 = r7 < 2;
+  if (p8) goto LOOP_2;
+  rd24 = rd24 + rd47;
+  rd25 = rd25 + 8;
+  r6 = r6 + 1;
+  p9 = r6 < 2;
+  if (p9) goto LOOP_1;
+  r16 = (0);
+  rd28 = (rd41);
+  rd29 = (rd45);
+LOOP_11:
+  r17 = (0);
+  rd26 = (rd28);
+  rd27 = (rd29);
+LOOP_12:
+  f11 = *(__local float*)(rd26);
+  f12 = f11 * f1;
+  *(__local float*)(rd27) = f12;
+  rd26 = rd26 + 4;
+  rd27 = rd27 + 4;
+  r17 = r17 + 1;
+  p10 = r17 < 2;
+  if (p10) goto LOOP_12;
+  rd28 = rd28 + 8;
+  rd29 = rd29 + 8;
+  r16 = r16 + 1;
+  p11 = r16 < 2;
+  if (p11) goto LOOP_11;
+  r18 = (0);
+  rd33 = (rd38);
+  rd34 = (rd44);
+  rd35 = (rd45);
+LOOP_13:
+  r19 = (0);
+  rd30 = (rd33);
+  rd31 = (rd34);
+  rd32 = (rd35);
+LOOP_14:
+  f13 = *(__local float*)(rd31);
+  f14 = *(__local float*)(rd32);
+  f15 = __fma(f13, f0, f14);
+  *(__global float*)(rd30) = f15;
+  rd30 = rd30 + 4;
+  rd31 = rd31 + 4;
+  rd32 = rd32 + 4;
+  r19 = r19 + 1;
+  p12 = r19 < 2;
+  if (p12) goto LOOP_14;
+  rd33 = rd33 + 8;
+  rd34 = rd34 + 8;
+  rd35 = rd35 + 8;
+  r18 = r18 + 1;
+  p13 = r18 < 2;
+  if (p13) goto LOOP_13;
+  rd36 = rd36 + rd46;
+  rd37 = rd37 + rd46;
+  rd38 = rd38 + 16;
+  r5 = r5 + 1;
+  p14 = r5 < r3;
+  if (p14) goto LOOP_0;
+  barrier(CLK_LOCAL_MEM_FENCE);
+}
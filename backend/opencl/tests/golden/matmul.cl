@@ -0,0 +1,162 @@
+typedef char int8_t;
+typedef short int16_t;
+typedef int int32_t;
+typedef long int64_t;
+typedef uint uint32_t;
+
+#define __max(a, b) ((a) > (b) ? (a) : (b))
+#define __min(a, b) ((a) < (b) ? (a) : (b))
+
+#define __mul32Hi(a, b) mul_hi((int)(a), (int)(b))
+#define __mul64Hi(a, b) mul_hi((long)(a), (long)(b))
+#define __mul32Wide(a, b) ((long)(a) * (long)(b))
+
+#define __mad32(a, b, c) (((a) * (b)) + (c))
+#define __mad64(a, b, c) (((a) * (b)) + (c))
+#define __mad32Hi(a, b, c) (__mul32Hi(a, b) + (c))
+#define __mad32Wide(a, b, c) (__mul32Wide(a, b) + (c))
+
+#define __fma(a, b, c) fma(a, b, c)
+#define expf(a) exp(a)
+
+__kernel void matmul(
+  uint32_t _8_n,
+  __global float* a,
+  uint32_t _16_n,
+  __global float* c,
+  uint32_t _m_4,
+  uint32_t _4_k,
+  uint32_t _16_k,
+  uint32_t _8_k,
+  uint32_t _4_n,
+  uint32_t _k_2,
+  __global float* b,
+  uint32_t _n_4)
+{
+  float f0, f1, f2, f3, f4;
+  int8_t p0, p1, p2, p3, p4, p5, p6, p7, p8, p9;
+  int32_t r0, r1, r2, r3, r4, r5, r6, r7, r8, r9, r10, r11, r12, r13, r14, r15, r16, r17, r18;
+  int64_t rd0, rd1, rd2, rd3, rd4, rd5, rd6, rd7, rd8, rd9, rd10, rd11, rd12, rd13, rd14, rd15, rd16, rd17, rd18, rd19, rd20, rd21, rd22, rd23, rd24, rd25, rd26, rd27, rd28, rd29, rd30;
+  r0 = _8_n; // 8*n
+  rd0 = (int64_t)a; // a
+  r1 = _16_n; // 16*n
+  rd1 = (int64_t)c; // c
+  r2 = _m_4; // m/4
+  r3 = _4_k; // 4*k
+  r4 = _16_k; // 16*k
+  r5 = _8_k; // 8*k
+  r6 = _4_n; // 4*n
+  r7 = _k_2; // k/2
+  rd2 = (int64_t)b; // b
+  r8 = _n_4; // n/4
+  __local int8_t shmem0[8] __attribute__((aligned(16)));
+  rd23 = (int64_t)shmem0;
+  __local int8_t shmem1[8] __attribute__((aligned(16)));
+  rd24 = (int64_t)shmem1;
+  rd25 = (int64_t)(r4);
+  rd26 = (int64_t)(r1);
+  rd27 = (int64_t)(r5);
+  rd28 = (int64_t)(r0);
+  rd29 = (int64_t)(r3);
+  rd30 = (int64_t)(r6);
+  r9 = (0);
+  rd21 = (rd0);
+  rd22 = (rd1);
+LOOP_0:
+  r10 = (0);
+  rd19 = (rd21);
+  rd20 = (rd22);
+LOOP_1:
+  r11 = (0);
+  rd17 = (rd19);
+  rd18 = (rd20);
+LOOP_2:
+  r12 = (0);
+  rd15 = (rd2);
+  rd16 = (rd18);
+LOOP_3:
+  r13 = (0);
+  rd13 = (rd15);
+  rd14 = (rd16);
+LOOP_4:
+  r14 = (0);
+  rd11 = (rd13);
+  rd12 = (rd14);
+LOOP_5:
+  f0 = (0x0p-1075f);
+  r15 = (0);
+  rd9 = (rd17);
+  rd10 = (rd11);
+LOOP_6:
+  r16 = (0);
+  rd3 = (rd9);
+  rd4 = (rd23);
+LOOP_7:
+  f1 = *(__global float*)(rd3);
+  *(__local float*)(rd4) = f1;
+  rd3 = rd3 + 4;
+  rd4 = rd4 + 4;
+  r16 = r16 + 1;
+  p0 = r16 < 2;
+  if (p0) goto LOOP_7;
+  r17 = (0);
+  rd5 = (rd10);
+  rd6 = (rd24);
+LOOP_8:
+  f2 = *(__global float*)(rd5);
+  *(__local float*)(rd6) = f2;
+  rd5 = rd5 + rd30;
+  rd6 = rd6 + 4;
+  r17 = r17 + 1;
+  p1 = r17 < 2;
+  if (p1) goto LOOP_8;
+  r18 = (0);
+  rd7 = (rd23);
+  rd8 = (rd24);
+LOOP_9:
+  f3 = *(__local float*)(rd7);
+  f4 = *(__local float*)(rd8);
+  f0 = __fma(f3, f4, f0);
+  rd7 = rd7 + 4;
+  rd8 = rd8 + 4;
+  r18 = r18 + 1;
+  p2 = r18 < 2;
+  if (p2) goto LOOP_9;
+  rd9 = rd9 + 8;
+  rd10 = rd10 + rd28;
+  r15 = r15 + 1;
+  p3 = r15 < r7;
+  if (p3) goto LOOP_6;
+  *(__global float*)(rd12) = f0;
+  rd11 = rd11 + 4;
+  rd12 = rd12 + 4;
+  r14 = r14 + 1;
+  p4 = r14 < 2;
+  if (p4) goto LOOP_5;
+  rd13 = rd13 + 8;
+  rd14 = rd14 + 8;
+  r13 = r13 + 1;
+  p5 = r13 < 2;
+  if (p5) goto LOOP_4;
+  rd15 = rd15 + 16;
+  rd16 = rd16 + 16;
+  r12 = r12 + 1;
+  p6 = r12 < r8;
+  if (p6) goto LOOP_3;
+  rd17 = rd17 + rd29;
+  rd18 = rd18 + rd30;
+  r11 = r11 + 1;
+  p7 = r11 < 2;
+  if (p7) goto LOOP_2;
+  rd19 = rd19 + rd27;
+  rd20 = rd20 + rd28;
+  r10 = r10 + 1;
+  p8 = r10 < 2;
+  if (p8) goto LOOP_1;
+  rd21 = rd21 + rd25;
+  rd22 = rd22 + rd26;
+  r9 = r9 + 1;
+  p9 = r9 < r2;
+  if (p9) goto LOOP_0;
+  barrier(CLK_LOCAL_MEM_FENCE);
+}
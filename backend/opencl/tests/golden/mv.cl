@@ -0,0 +1,124 @@
+typedef char int8_t;
+typedef short int16_t;
+typedef int int32_t;
+typedef long int64_t;
+typedef uint uint32_t;
+
+#define __max(a, b) ((a) > (b) ? (a) : (b))
+#define __min(a, b) ((a) < (b) ? (a) : (b))
+
+#define __mul32Hi(a, b) mul_hi((int)(a), (int)(b))
+#define __mul64Hi(a, b) mul_hi((long)(a), (long)(b))
+#define __mul32Wide(a, b) ((long)(a) * (long)(b))
+
+#define __mad32(a, b, c) (((a) * (b)) + (c))
+#define __mad64(a, b, c) (((a) * (b)) + (c))
+#define __mad32Hi(a, b, c) (__mul32Hi(a, b) + (c))
+#define __mad32Wide(a, b, c) (__mul32Wide(a, b) + (c))
+
+#define __fma(a, b, c) fma(a, b, c)
+#define expf(a) exp(a)
+
+__kernel void mv(
+  uint32_t _8_n,
+  __global float* a,
+  uint32_t _16_n,
+  uint32_t _n_2,
+  __global float* x,
+  uint32_t _m_4,
+  __global float* y,
+  uint32_t _4_n)
+{
+  float f0, f1, f2, f3, f4;
+  int8_t p0, p1, p2, p3, p4, p5, p6;
+  int32_t r0, r1, r2, r3, r4, r5, r6, r7, r8, r9, r10, r11;
+  int64_t rd0, rd1, rd2, rd3, rd4, rd5, rd6, rd7, rd8, rd9, rd10, rd11, rd12, rd13, rd14, rd15, rd16, rd17, rd18, rd19, rd20, rd21;
+  r0 = _8_n; // 8*n
+  rd0 = (int64_t)a; // a
+  r1 = _16_n; // 16*n
+  r2 = _n_2; // n/2
+  rd1 = (int64_t)x; // x
+  r3 = _m_4; // m/4
+  rd2 = (int64_t)y; // y
+  r4 = _4_n; // 4*n
+  __local int8_t shmem0[8] __attribute__((aligned(16)));
+  rd17 = (int64_t)shmem0;
+  __local int8_t shmem1[8] __attribute__((aligned(16)));
+  rd18 = (int64_t)shmem1;
+  rd19 = (int64_t)(r1);
+  rd20 = (int64_t)(r0);
+  rd21 = (int64_t)(r4);
+  r5 = (0);
+  rd15 = (rd0);
+  rd16 = (rd2);
+LOOP_0:
+  r6 = (0);
+  rd13 = (rd15);
+  rd14 = (rd16);
+LOOP_1:
+  r7 = (0);
+  rd11 = (rd13);
+  rd12 = (rd14);
+LOOP_2:
+  f0 = (0x0p-1075f);
+  r8 = (0);
+  rd9 = (rd1);
+  rd10 = (rd11);
+LOOP_3:
+  r9 = (0);
+  rd3 = (rd9);
+  rd4 = (rd18);
+LOOP_4:
+  f1 = *(__global float*)(rd3);
+  *(__local float*)(rd4) = f1;
+  rd3 = rd3 + 4;
+  rd4 = rd4 + 4;
+  r9 = r9 + 1;
+  p0 = r9 < 2;
+  if (p0) goto LOOP_4;
+  r10 = (0);
+  rd5 = (rd10);
+  rd6 = (rd17);
+LOOP_5:
+  f2 = *(__global float*)(rd5);
+  *(__local float*)(rd6) = f2;
+  rd5 = rd5 + 4;
+  rd6 = rd6 + 4;
+  r10 = r10 + 1;
+  p1 = r10 < 2;
+  if (p1) goto LOOP_5;
+  r11 = (0);
+  rd7 = (rd17);
+  rd8 = (rd18);
+LOOP_6:
+  f3 = *(__local float*)(rd7);
+  f4 = *(__local float*)(rd8);
+  f0 = __fma(f3, f4, f0);
+  rd7 = rd7 + 4;
+  rd8 = rd8 + 4;
+  r11 = r11 + 1;
+  p2 = r11 < 2;
+  if (p2) goto LOOP_6;
+  rd9 = rd9 + 8;
+  rd10 = rd10 + 8;
+  r8 = r8 + 1;
+  p3 = r8 < r2;
+  if (p3) goto LOOP_3;
+  *(__global float*)(rd12) = f0;
+  rd11 = rd11 + rd21;
+  rd12 = rd12 + 4;
+  r7 = r7 + 1;
+  p4 = r7 < 2;
+  if (p4) goto LOOP_2;
+  rd13 = rd13 + rd20;
+  rd14 = rd14 + 8;
+  r6 = r6 + 1;
+  p5 = r6 < 2;
+  if (p5) goto LOOP_1;
+  rd15 = rd15 + rd19;
+  rd16 = rd16 + 16;
+  r5 = r5 + 1;
+  p6 = r5 < r3;
+  if (p6) goto LOOP_0;
+  barrier(CLK_LOCAL_MEM_FENCE);
+}
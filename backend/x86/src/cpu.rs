@@ -1,5 +1,5 @@
 //! Describes CUDA-enabled GPUs.
-use telamon::codegen::Function;
+use telamon::codegen::{Function, ResourceReport};
 use telamon::device;
 use telamon::ir::{self, Type};
 use telamon::model::{self, HwPressure};
@@ -30,6 +30,15 @@ impl device::Device for Cpu {
         write!(out, "{}", X86printer::default().wrapper_function(fun)).unwrap();
     }
 
+    fn resource_report(&self, fun: &Function) -> ResourceReport {
+        let mut printer = X86printer::default();
+        printer.function(fun);
+        ResourceReport {
+            optimization: Some(printer.optimization_stats()),
+            ..ResourceReport::new(fun)
+        }
+    }
+
     fn check_type(&self, t: Type) -> Result<(), ir::TypeError> {
         match t {
             Type::I(i) | Type::F(i) if i == 32 || i == 64 => Ok(()),
@@ -11,6 +11,8 @@ use utils::unwrap;
 #[derive(Default)]
 pub(crate) struct X86printer {
     buffer: String,
+    /// Effect of the optimizations on the last printed function body
+    optimization_stats: OptimizationStats,
 }
 
 fn param_t(param: &ParamVal) -> String {
@@ -28,6 +30,11 @@ fn param_t(param: &ParamVal) -> String {
 }
 
 impl X86printer {
+    /// Returns the effect of the optimizations on the last printed function body.
+    pub fn optimization_stats(&self) -> OptimizationStats {
+        self.optimization_stats
+    }
+
     /// Declares all parameters of the function with the appropriate type
     fn param_decl(&self, param: &ParamVal) -> String {
         format!("{} {}", param_t(param), param.key().ident())
@@ -151,11 +158,14 @@ impl X86printer {
                 .iter()
                 .flat_map(|d| d.induction_levels()),
         );
+        let mut insts = InstBuffer::default();
         for level in ind_levels {
-            Printer::new(self, name_map).parallel_induction_level(level);
+            Printer::new(&mut insts, name_map).parallel_induction_level(level);
         }
         // BODY
-        Printer::new(self, name_map).cfg(function, function.cfg());
+        Printer::new(&mut insts, name_map).cfg(function, function.cfg());
+        self.optimization_stats = insts.optimize();
+        insts.print(self);
         let var_decls = self.var_decls(&namegen);
         return_string.push_str(&var_decls);
         return_string.push_str(&self.buffer);
@@ -287,12 +297,64 @@ pthread_barrier_destroy(&barrier);
     }
 }
 
-impl InstPrinter for X86printer {
-    fn print_label(&mut self, label: llir::Label<'_>) {
+impl<'a> InstPrinter<'a> for X86printer {
+    fn print_label(&mut self, label: llir::Label<'a>) {
         writeln!(self.buffer, "{}", label.c99()).unwrap()
     }
 
-    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'_>) {
+    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'a>) {
         writeln!(self.buffer, "{}", inst.c99()).unwrap();
     }
+
+    fn print_branch(&mut self, label: llir::Label<'a>, cond: llir::Register<'a>) {
+        // Each thread runs in its own pthread, so branching never diverges.
+        writeln!(self.buffer, "if ({}) goto {};", cond.c99(), label.name()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Context;
+    use telamon::device::{Context as _, EvalMode};
+    use telamon::explorer::choice::fix_order;
+    use telamon::explorer::config::{ChoiceOrdering, NewNodeOrder};
+    use telamon::explorer::{local_selection, Candidate};
+    use telamon::search_space::{Action, ThreadGuard};
+    use telamon::{helper, ir, model};
+
+    /// Ensures threads can branch around a region of side-effects instead of executing it
+    /// under a predicate, and that the resulting code compiles and runs.
+    #[test]
+    fn branch_around_threads() {
+        let mut context = Context::default();
+        let signature = {
+            let mut builder =
+                helper::SignatureBuilder::new("branch_around", &mut context);
+            builder.array::<f32>("out", 1);
+            builder.get()
+        };
+        let mut builder = helper::Builder::new(signature.into(), context.device());
+        let d0 = builder.open_dim_ex(ir::Size::new_const(4), DimKind::THREAD);
+        builder.mov(&0f32);
+        builder.close_dim(&d0);
+        let pattern = ir::AccessPattern::Unknown(None);
+        builder.st(&"out", &1f32, pattern);
+        builder.action(Action::ThreadGuard(d0[0], ThreadGuard::BRANCH));
+        let space = builder.get();
+        let bound = model::bound(&space, &context);
+        let leaf = local_selection::descend(
+            &ChoiceOrdering::default(),
+            NewNodeOrder::Api,
+            &context,
+            Candidate::new(space, bound),
+            std::f64::INFINITY,
+        )
+        .expect("no implementation found");
+        let space = fix_order(leaf.space);
+        let function = Function::build(&space);
+        let code = X86printer::default().wrapper_function(&function);
+        assert!(code.contains(") goto SKIP"), "{}", code);
+        context.evaluate(&function, EvalMode::FindBest).unwrap();
+    }
 }
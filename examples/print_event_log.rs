@@ -21,8 +21,8 @@ struct Opt {
 fn main() -> io::Result<()> {
     let opt = Opt::from_args();
 
-    for record_bytes in EventLog::open(&opt.eventlog)?.records() {
-        let message: mcts::Message = bincode::deserialize(&record_bytes?).unwrap();
+    for message in EventLog::read_messages(&opt.eventlog)? {
+        let message: mcts::Message = message?;
         println!("{}", serde_json::to_string(&message).unwrap());
     }
 
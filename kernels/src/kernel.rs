@@ -110,6 +110,24 @@ impl<'a> KernelBuilder<'a> {
 
         (signature, kernel, context)
     }
+
+    /// Creates a kernel in the given context and prints the implementation of its first
+    /// candidate obtained by always taking the first valid decision.
+    pub fn print_first<'b, K, AM, F>(
+        &self,
+        params: K::Parameters,
+        context: &'b mut AM,
+        print: F,
+    ) -> String
+    where
+        AM: device::ArgMap<'a> + device::Context,
+        K: Kernel<'a> + 'b,
+        F: FnOnce(&codegen::Function) -> String,
+    {
+        let (signature, kernel, context) = self.build::<K, AM>(params, context);
+        let candidate = kernel.build_body(signature.into(), context).remove(0);
+        crate::print_first(context, candidate.space, print)
+    }
 }
 
 /// A kernel that can be compiled, benchmarked and used for correctness tests.
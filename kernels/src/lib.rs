@@ -14,7 +14,8 @@ pub use crate::kernel::{analyze_bounds, Kernel, KernelBuilder};
 use telamon::device::{self, ArgMap, Context};
 use telamon::helper::tensor::DimSize;
 use telamon::helper::{self, SignatureBuilder};
-use telamon::{explorer, model, search_space};
+use telamon::search_space::DimKind;
+use telamon::{codegen, explorer, ir, model, search_space};
 
 use ::ndarray::{ArrayBase, Data, Dimension, FoldWhile, Zip};
 
@@ -27,6 +28,36 @@ fn build_candidate(
     explorer::Candidate::new(space, bound)
 }
 
+/// Prints the implementation of a search space obtained by always taking the first valid
+/// decision.  This is deterministic, which makes it suitable to check the code generated by a
+/// backend without running it.
+pub fn print_first<F>(
+    context: &dyn device::Context,
+    space: search_space::SearchSpace,
+    print: F,
+) -> String
+where
+    F: FnOnce(&codegen::Function) -> String,
+{
+    let implementation = explorer::local_selection::first_implementation(context, space)
+        .expect("no implementation found");
+    print(&codegen::Function::build(&implementation.space))
+}
+
+/// Builds a function that moves the indexes of a block dimension and of two nested thread
+/// dimensions, to check how a backend maps them to the indexes of the device.
+pub fn block_thread_dims(context: &dyn device::Context) -> search_space::SearchSpace {
+    let signature = ir::Signature::new("block_thread_dims");
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    let d0 = builder.open_dim_ex(builder.cst_size(16), DimKind::BLOCK);
+    let d1 = builder.open_dim_ex(builder.cst_size(8), DimKind::THREAD);
+    let d2 = builder.open_dim_ex(builder.cst_size(4), DimKind::THREAD);
+    builder.mov(&d0);
+    builder.mov(&d1);
+    builder.mov(&d2);
+    builder.get()
+}
+
 /// Creates a `DimSize`. If the instantiate flag is true, it uses a constant size,
 /// otherwise it creates a parameter with the given name.
 fn create_size<'a, AM>(
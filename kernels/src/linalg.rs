@@ -9,6 +9,7 @@ use crate::compose::{
 use crate::kernel::Kernel;
 use crate::{build_candidate, check_output, create_size, infer_tiling, Scalar};
 use ::ndarray::{Array1, Array2, Array3, ArrayD};
+use itertools::Itertools;
 use rand;
 use serde::{Deserialize, Serialize};
 use telamon::explorer::Candidate;
@@ -618,6 +619,148 @@ impl<'a, S: Scalar> Kernel<'a> for BatchMM<'a, S> {
     }
 }
 
+/// Computes `C = A.B`, where `A` and `B` are half-precision matrices and `C` is a
+/// single-precision matrix, using warp-level matrix multiply-accumulate instructions.
+///
+/// `m` must be a multiple of 16 and `n` and `k` multiples of 8. Each dimension must span at
+/// least two tiles.
+pub struct MixedMM<'a> {
+    m: i32,
+    n: i32,
+    k: i32,
+    a: Tensor<'a, device::F16>,
+    b: Tensor<'a, device::F16>,
+    c: Tensor<'a, f32>,
+}
+
+impl<'a> Kernel<'a> for MixedMM<'a> {
+    type Parameters = (i32, i32, i32);
+    type ExpectedOutput = Array2<f32>;
+
+    fn name() -> &'static str {
+        "mixed_mm"
+    }
+
+    fn build_signature<AM>(
+        (m, n, k): (i32, i32, i32),
+        builder: &mut SignatureBuilder<AM>,
+    ) -> Self
+    where
+        AM: device::ArgMap<'a> + device::Context,
+    {
+        let (m_size, n_size, k_size) = (m as u32, n as u32, k as u32);
+        let a =
+            builder.tensor::<device::F16>("a", vec![m_size.into(), k_size.into()], true);
+        let b =
+            builder.tensor::<device::F16>("b", vec![k_size.into(), n_size.into()], true);
+        let c = builder.tensor::<f32>("c", vec![m_size.into(), n_size.into()], false);
+        MixedMM { m, n, k, a, b, c }
+    }
+
+    fn build_body<'b>(
+        &self,
+        signature: Arc<ir::Signature>,
+        ctx: &'b dyn device::Context,
+    ) -> Vec<Candidate> {
+        // Rows of `A` and `C` are split into tiles of 16 rows, with the rows of a tile
+        // indexed by `8*frag_rows + lane_rows`.  The reduction dimension and the columns of
+        // `C` are split into tiles of 8 columns, indexed by `2*lane_cols + frag_cols`.
+        let row_tiling = helper::TilingPattern::new_fixed(&[2, 8]);
+        let col_tiling = helper::TilingPattern::new_fixed(&[4, 2]);
+        let mut builder = helper::Builder::new(signature, ctx.device());
+        let ld_a = self
+            .a
+            .load(vec![row_tiling.clone(), col_tiling.clone()], &mut builder);
+        let b_tiling = helper::TilingPattern::new_fixed(&[8]);
+        let ld_b = self
+            .b
+            .load(vec![col_tiling.clone(), b_tiling], &mut builder);
+
+        let m_size = builder.cst_size(self.m as u32);
+        let n_size = builder.cst_size(self.n as u32);
+        let init_dim_m = builder.open_tiled_dim(m_size, row_tiling);
+        let init_dim_n = builder.open_tiled_dim(n_size, col_tiling);
+        let acc_init = builder.mov(&0f32);
+        let acc_dim_m = builder.open_mapped_dim(&init_dim_m);
+        let acc_dim_n = builder.open_mapped_dim(&init_dim_n);
+        let k_tiles = builder.cst_size(self.k as u32 / 8);
+        let acc_dim_k = builder.open_dim(k_tiles);
+        let (acc_m, acc_n) = (
+            acc_dim_m.iter().collect_vec(),
+            acc_dim_n.iter().collect_vec(),
+        );
+        // Within a tile, the reduction dimension is distributed as the columns of `C`.
+        let acc_k = [acc_dim_k[0], acc_n[1], acc_n[2]];
+        let a_op = real_dim_map(
+            &builder,
+            ld_a.inst(),
+            &[&ld_a[0], &ld_a[1]],
+            &[&acc_m, &acc_k],
+        );
+        let b_n = [acc_n[0], acc_m[2]];
+        let b_op = real_dim_map(
+            &builder,
+            ld_b.inst(),
+            &[&ld_b[0], &ld_b[1]],
+            &[&acc_k, &b_n],
+        );
+        let dims = ir::MmaDims {
+            lane_rows: acc_m[2],
+            lane_cols: acc_n[1],
+            frag_rows: acc_m[1],
+            frag_cols: acc_n[2],
+        };
+        let acc = builder.mma_sync(&a_op, &b_op, &helper::Reduce(acc_init), dims);
+        builder.close_dim(&acc_dim_k);
+
+        let acc = VirtualTensor::new(acc, vec![acc_dim_m, acc_dim_n]);
+        let st_c = acc.store(&self.c, &mut builder);
+
+        // Order for correctness.
+        builder.order(&st_c.inst(), &acc_dim_k, Order::AFTER);
+        vec![build_candidate(builder.get(), ctx)]
+    }
+
+    fn get_expected_output(&self, context: &dyn device::Context) -> Array2<f32> {
+        let (m, n, k) = (self.m as usize, self.n as usize, self.k as usize);
+        let a = self.a.read_to_host(context).mapv(|x| x.to_f32());
+        let b = self.b.read_to_host(context).mapv(|x| x.to_f32());
+        let a = unwrap!(a.into_shape((m, k)));
+        let b = unwrap!(b.into_shape((k, n)));
+        a.dot(&b)
+    }
+
+    fn check_result(
+        &self,
+        expected: &Self::ExpectedOutput,
+        context: &dyn device::Context,
+    ) -> Result<(), String> {
+        let c_shape = (self.m as usize, self.n as usize);
+        let c = unwrap!(self.c.read_to_host(context).into_shape(c_shape));
+        if let Err(invalid) = check_output(&c, expected) {
+            Err(format!("Invalid mixed_mm output: {}", invalid))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Creates an operand that maps the dimensions of `src`, grouped by logical dimension, to
+/// the given real dimensions, which may belong to different logical dimensions.
+fn real_dim_map(
+    builder: &helper::Builder,
+    src: ir::InstId,
+    src_dims: &[&helper::LogicalDim],
+    dst_dims: &[&[ir::DimId]],
+) -> ir::Operand<()> {
+    let dim_map = src_dims
+        .iter()
+        .flat_map(|dims| dims.iter())
+        .zip_eq(dst_dims.iter().flat_map(|dims| dims.iter().cloned()));
+    let inst = builder.function().inst(src);
+    ir::Operand::new_inst(inst, ir::DimMap::new(dim_map), GlobalScope(()))
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Fused2MMP {
     pub m: i32,
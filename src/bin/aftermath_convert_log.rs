@@ -55,8 +55,8 @@ fn main() -> io::Result<()> {
     tw.write_default_frame_ids()?;
 
     // Process log messages
-    for record_bytes in EventLog::open(&opt.eventlog)?.records() {
-        match bincode::deserialize(&record_bytes?).unwrap() {
+    for message in EventLog::read_messages(&opt.eventlog)? {
+        match message? {
             // Discovery of a new node
             Message::Node {
                 id,
@@ -229,6 +229,23 @@ fn main() -> io::Result<()> {
                             );
                         }
 
+                        // Selection of a child of the current candidate
+                        // which other threads were already exploring
+                        Event::ConcurrentSelect(child_idx, num_concurrent) => {
+                            t.get_node(curr_node_id)
+                                .child(usize::from(child_idx))
+                                .unwrap()
+                                .declare_concurrent_selection();
+
+                            debug!(
+                                "  ConcurrentSelect [idx: {}, concurrent: {}, interval: {:?} to {:?}]",
+                                u16::from(child_idx),
+                                num_concurrent,
+                                start,
+                                end
+                            );
+                        }
+
                         // Declaration of the current candidate as an
                         // implementation
                         Event::Implementation {} => {
@@ -9,76 +9,10 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io;
 use std::path::PathBuf;
-use telamon::explorer::{choice::ActionEx, eventlog::EventLog};
+use telamon::explorer::{choice::ActionEx, eventlog::EventLog, legacy_log::TreeEvent};
 
-use rpds::List;
-use serde::{Deserialize, Serialize};
 use structopt::StructOpt;
 
-#[derive(Serialize, Deserialize)]
-pub enum DeadEndSource {
-    /// Dead-end encountered in the tree
-    Tree {
-        /// List of actions defining the dead-end candidate
-        actions: List<ActionEx>,
-    },
-    /// Dead-end encountered in the rollout phase
-    Rollout {
-        /// List of actions defining the dead-end candidate
-        actions: List<ActionEx>,
-        /// Depth in the tree.  The remaining actions were selected during rollout.
-        depth: usize,
-        /// Performance model bound
-        bound: f64,
-        /// Current cut value
-        cut: f64,
-    },
-}
-
-/// The possible tree events.
-/// WARNING:  Changing the enums *will break* any pre-existing eventlog files.  Adding new cases
-/// *at the end only* is safe.
-#[derive(Serialize, Deserialize)]
-pub enum TreeEvent {
-    Evaluation {
-        actions: List<ActionEx>,
-        score: f64,
-    },
-
-    /// A fully-specified implementation was found and evaluated
-    EvaluationV2 {
-        /// List of actions defining the implementation
-        actions: List<ActionEx>,
-        /// Depth in the tree.  The remaining actions were selected during rollout.
-        depth: usize,
-        /// Execution time
-        score: f64,
-        /// Performance model lower bound
-        bound: f64,
-        /// Cut value when the implementation was found.  This is the best implementation at the
-        /// time the descent started from the root, as threads only synchronize the cut at the
-        /// root.
-        cut: f64,
-        /// Time at which the implementation was found
-        discovery_time: f64,
-        /// Time at which the evaluation finished.  Note that evaluations are performed by a
-        /// specific thread, not the one that found the implementation.
-        evaluation_end_time: f64,
-        /// ID of the thread that found this implementation
-        thread: String,
-    },
-
-    /// A dead-end was reached
-    DeadEnd {
-        /// Source of this deadend
-        source: DeadEndSource,
-        /// Time at which the deadend was found after the start of the program
-        discovery_time: f64,
-        /// ID of the thread that found the deadend
-        thread: String,
-    },
-}
-
 struct Edge {
     action: ActionEx,
     node: Box<Node>,
@@ -286,7 +220,15 @@ fn main() -> io::Result<()> {
 
     let mut evals = Vec::new();
 
-    for (id, record_bytes) in EventLog::open(&opt.eventlog)?.records().enumerate() {
+    let (version, records) = EventLog::open_versioned(&opt.eventlog)?;
+    if version != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a legacy event log, use `tlcli export-tree` instead",
+        ));
+    }
+
+    for (id, record_bytes) in records.enumerate() {
         match bincode::deserialize(&record_bytes?).unwrap() {
             TreeEvent::Evaluation { actions, score }
             | TreeEvent::EvaluationV2 { actions, score, .. } => {
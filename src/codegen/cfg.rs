@@ -1,6 +1,7 @@
 use crate::codegen::{Dimension, InductionLevel, Instruction};
 use crate::ir;
 use crate::search_space::*;
+use fxhash::FxHashSet;
 use itertools::Itertools;
 use log::debug;
 use std::{self, fmt};
@@ -20,6 +21,8 @@ pub enum Cfg<'a> {
         Vec<InductionLevel<'a>>,
         Vec<Cfg<'a>>,
     ),
+    /// Represents a sequential loop software-pipelined around multi-stage memory blocks.
+    Pipeline(Dimension<'a>, Pipeline<'a>),
 }
 
 impl<'a> Cfg<'a> {
@@ -34,6 +37,10 @@ impl<'a> Cfg<'a> {
                 let body_dims = body.iter().flat_map(|cfg| cfg.dimensions());
                 Box::new(std::iter::once(dim).chain(body_dims)) as _
             }
+            Cfg::Pipeline(dim, pipeline) => {
+                let body_dims = pipeline.body().flat_map(|cfg| cfg.dimensions());
+                Box::new(std::iter::once(dim).chain(body_dims)) as _
+            }
             Cfg::Instruction(dims, _) => Box::new(dims.iter().flatten()),
         }
     }
@@ -45,6 +52,9 @@ impl<'a> Cfg<'a> {
                 let iter = body.iter().flat_map(|cfg| cfg.instructions());
                 Box::new(iter) as Box<dyn Iterator<Item = _>>
             }
+            Cfg::Pipeline(_, pipeline) => {
+                Box::new(pipeline.body().flat_map(|cfg| cfg.instructions()))
+            }
             Cfg::Instruction(_, inst) => Box::new(std::iter::once(inst)) as _,
         }
     }
@@ -67,6 +77,12 @@ impl<'a> Cfg<'a> {
                     .flat_map(|c| c.induction_levels())
                     .chain(dim.induction_levels()),
             ),
+            Cfg::Pipeline(ref dim, ref pipeline) => Box::new(
+                pipeline
+                    .body()
+                    .flat_map(|c| c.induction_levels())
+                    .chain(dim.induction_levels()),
+            ),
             Cfg::Instruction(..) => Box::new(std::iter::empty()),
         }
     }
@@ -198,10 +214,255 @@ impl<'a> Cfg<'a> {
             Cfg::Root(ref inners) | Cfg::Loop(_, ref inners) => {
                 inners.iter().any(|c| c.handle_threads())
             }
+            Cfg::Pipeline(_, ref pipeline) => pipeline.body().any(|c| c.handle_threads()),
             Cfg::Threads(..) => true,
             Cfg::Instruction(..) => false,
         }
     }
+
+    /// Software-pipelines the sequential loops around the uses of multi-stage memory
+    /// blocks, starting from the innermost ones. Blocks whose loop cannot be pipelined only
+    /// use their first stage.
+    fn pipeline_loops(self, space: &SearchSpace) -> Self {
+        let pipeline_body = |body: Vec<Cfg<'a>>| {
+            body.into_iter()
+                .map(|cfg| cfg.pipeline_loops(space))
+                .collect_vec()
+        };
+        match self {
+            Cfg::Root(body) => Cfg::Root(pipeline_body(body)),
+            Cfg::Threads(dims, ind_levels, body) => {
+                Cfg::Threads(dims, ind_levels, pipeline_body(body))
+            }
+            Cfg::Loop(dim, body) => {
+                let body = pipeline_body(body);
+                if dim.kind() != DimKind::LOOP || dim.size().as_int() == Some(1) {
+                    return Cfg::Loop(dim, body);
+                }
+                match Pipeline::new(body, space) {
+                    Ok(pipeline) => Cfg::Pipeline(dim, pipeline),
+                    Err(body) => Cfg::Loop(dim, body),
+                }
+            }
+            cfg => cfg,
+        }
+    }
+
+    /// Indicates if an instruction of the `Cfg` is nested in a loop whose iterations are
+    /// not instantiated in separate registers.
+    fn in_sequential_loop(&self, inst: ir::InstId) -> bool {
+        match self {
+            Cfg::Root(body) | Cfg::Threads(_, _, body) => {
+                body.iter().any(|c| c.in_sequential_loop(inst))
+            }
+            Cfg::Loop(dim, body) if dim.kind() == DimKind::UNROLL => {
+                body.iter().any(|c| c.in_sequential_loop(inst))
+            }
+            Cfg::Loop(..) | Cfg::Pipeline(..) => {
+                self.instructions().any(|i| i.id() == inst)
+            }
+            Cfg::Instruction(..) => false,
+        }
+    }
+
+    /// Indicates if the `Cfg` contains a software-pipelined loop.
+    fn has_pipeline(&self) -> bool {
+        match self {
+            Cfg::Root(body) | Cfg::Loop(_, body) | Cfg::Threads(_, _, body) => {
+                body.iter().any(|c| c.has_pipeline())
+            }
+            Cfg::Pipeline(..) => true,
+            Cfg::Instruction(..) => false,
+        }
+    }
+}
+
+/// A loop whose body is split between a producer, that fills the stages of multi-stage
+/// memory blocks, and a consumer, that reads them.
+///
+/// The loop is printed as a prologue running the producer of the first iteration, a
+/// steady-state loop running the consumer of each iteration followed by the producer of the
+/// next one and an epilogue running the consumer of the last iteration. The statements
+/// thus execute in the same order as in the original loop, but consecutive iterations
+/// access different stages of the blocks: the barrier that separates the consumer of an
+/// iteration from the producer of the next one is not needed anymore.
+///
+/// Loads of the producer that define multi-stage variables are prefetched: the loads of
+/// the next iteration are issued before the consumer of the current one, so their latency
+/// overlaps with the consumer.
+pub struct Pipeline<'a> {
+    /// The part of the loop body that writes the multi-stage blocks.
+    pub producer: Vec<Cfg<'a>>,
+    /// The part of the loop body that reads the multi-stage blocks. It always ends with a
+    /// group of threads that loads from a multi-stage block.
+    pub consumer: Vec<Cfg<'a>>,
+    /// The pipelined memory blocks, with their number of stages.
+    pub stages: Vec<(ir::MemId, u32)>,
+    /// The loads of the producer issued one iteration ahead of the rest of the body.
+    pub prefetch: Vec<ir::InstId>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// Splits the body of a sequential loop to pipeline it around the multi-stage blocks
+    /// only used inside the loop. Returns the body unchanged if the loop does not use such
+    /// blocks or if it cannot be pipelined.
+    fn new(body: Vec<Cfg<'a>>, space: &SearchSpace) -> Result<Self, Vec<Cfg<'a>>> {
+        let fun = space.ir_instance();
+        let num_stages = |mem: ir::MemId| {
+            unwrap!(space
+                .domain()
+                .get_num_stages(mem)
+                .as_constrained(&ir::mem::NUM_STAGES))
+        };
+        let is_shared = |mem: Option<ir::MemId>| {
+            mem.map_or(false, |mem| {
+                space.domain().get_mem_space(mem) == MemSpace::SHARED
+            })
+        };
+        // Blocks of inner pipelines are already handled.
+        let body_insts = body
+            .iter()
+            .filter(|cfg| !cfg.has_pipeline())
+            .flat_map(|cfg| cfg.instructions())
+            .map(|inst| inst.id())
+            .collect::<FxHashSet<_>>();
+        let stages = fun
+            .mem_blocks()
+            .map(|block| block.mem_id())
+            .filter(|&mem| num_stages(mem) > 1)
+            .filter(|&mem| {
+                fun.insts()
+                    .filter(|inst| inst.operator().mem_used() == Some(mem))
+                    .all(|inst| body_insts.contains(&inst.id()))
+            })
+            .map(|mem| (mem, num_stages(mem)))
+            .collect_vec();
+        let is_staged = |mem: Option<ir::MemId>| {
+            mem.map_or(false, |mem| stages.iter().any(|&(id, _)| id == mem))
+        };
+        let is_store = |inst: &Instruction| match inst.operator() {
+            ir::Operator::St(..) => true,
+            _ => false,
+        };
+        // The producer stops after the last store to a staged block.
+        let num_produce = body
+            .iter()
+            .rposition(|cfg| {
+                cfg.instructions()
+                    .any(|inst| is_store(inst) && is_staged(inst.operator().mem_used()))
+            })
+            .map_or(0, |pos| pos + 1);
+        let (producer, consumer) = body.split_at(num_produce);
+        // Without the barrier, the producer of the next iteration may run concurrently with
+        // the consumer of the current one: it must only write the staged blocks and only
+        // read memory that the consumer does not write.
+        let producer_ok = producer.iter().flat_map(|c| c.instructions()).all(|inst| {
+            let mem = inst.operator().mem_used();
+            match inst.operator() {
+                ir::Operator::St(..) => is_staged(mem),
+                ir::Operator::Ld(..) => !is_shared(mem),
+                _ => true,
+            }
+        });
+        let consumer_ok = consumer.iter().flat_map(|c| c.instructions()).all(|inst| {
+            let mem = inst.operator().mem_used();
+            !is_store(inst) || (is_shared(mem) && !is_staged(mem))
+        });
+        // Only the barrier ending a group of threads that reads a staged block is removed,
+        // which the performance model relies on.
+        let removes_barrier = match consumer.last() {
+            Some(cfg @ Cfg::Threads(..)) => cfg.instructions().any(|inst| {
+                let is_load = match inst.operator() {
+                    ir::Operator::Ld(..) => true,
+                    _ => false,
+                };
+                is_load && is_staged(inst.operator().mem_used())
+            }),
+            _ => false,
+        };
+        if stages.is_empty()
+            || num_produce == 0
+            || !producer_ok
+            || !consumer_ok
+            || !removes_barrier
+        {
+            return Err(body);
+        }
+        let prefetch = Self::prefetched_loads(producer, consumer, space);
+        let mut body = body;
+        let consumer = body.split_off(num_produce);
+        Ok(Pipeline {
+            producer: body,
+            consumer,
+            stages,
+            prefetch,
+        })
+    }
+
+    /// Lists the loads of the producer that define multi-stage variables and that can be
+    /// issued before the consumer of the previous iteration. Such a load must only depend
+    /// on the loop indexes and its value must not be read by the consumer, which still
+    /// uses the value of the previous iteration.
+    fn prefetched_loads(
+        producer: &[Cfg<'a>],
+        consumer: &[Cfg<'a>],
+        space: &SearchSpace,
+    ) -> Vec<ir::InstId> {
+        let fun = space.ir_instance();
+        let num_stages = |var: ir::VarId| {
+            unwrap!(space
+                .domain()
+                .get_var_num_stages(var)
+                .as_constrained(&ir::mem::NUM_STAGES))
+        };
+        let producer_insts = producer
+            .iter()
+            .flat_map(|c| c.instructions())
+            .map(|inst| inst.id())
+            .collect::<FxHashSet<_>>();
+        let reads = |inst: &Instruction, ld: ir::InstId| {
+            inst.operator().operands().into_iter().any(|op| match *op {
+                ir::Operand::Inst(id, ..) | ir::Operand::Reduce(id, ..) => id == ld,
+                ir::Operand::Variable(var, _) => {
+                    fun.variable(var).def().production_inst(fun).0 == ld
+                }
+                _ => false,
+            })
+        };
+        fun.variables()
+            .filter(|var| num_stages(var.id()) > 1)
+            .flat_map(|var| match *var.def() {
+                ir::VarDef::Inst(inst) => Some(inst),
+                _ => None,
+            })
+            .filter(|inst| producer_insts.contains(inst))
+            .filter(|&inst| {
+                let operator = fun.inst(inst).operator();
+                let is_load = match operator {
+                    ir::Operator::Ld(..) => true,
+                    _ => false,
+                };
+                let is_independent = operator.operands().into_iter().all(|op| match op {
+                    ir::Operand::Inst(..)
+                    | ir::Operand::Reduce(..)
+                    | ir::Operand::Variable(..) => false,
+                    _ => true,
+                });
+                is_load
+                    && is_independent
+                    && !producer.iter().any(|c| c.in_sequential_loop(inst))
+                    && !consumer
+                        .iter()
+                        .flat_map(|c| c.instructions())
+                        .any(|consumer_inst| reads(consumer_inst, inst))
+            })
+            .collect()
+    }
+
+    /// Iterates over the body of the pipelined loop.
+    pub fn body(&self) -> impl Iterator<Item = &Cfg<'a>> {
+        self.producer.iter().chain(&self.consumer)
+    }
 }
 
 /// A struct to indent on new lines when writing formatting traits.
@@ -316,6 +577,24 @@ impl ir::IrDisplay for Cfg<'_> {
 
                 write!(fmt, "{}", inst.ir_instruction().display(fun))?;
             }
+            Cfg::Pipeline(dim, pipeline) => {
+                writeln!(
+                    fmt,
+                    "PIPELINE[{}]({:?}) stages {:?} prefetch {:?} {{",
+                    dim.size(),
+                    dim.dim_ids().format(" = "),
+                    pipeline.stages,
+                    pipeline.prefetch,
+                )?;
+                for inner in &pipeline.producer {
+                    writeln!(IndentAdapter::new(fmt), "{}", inner.display(fun))?;
+                }
+                writeln!(fmt, "}} then {{")?;
+                for inner in &pipeline.consumer {
+                    writeln!(IndentAdapter::new(fmt), "{}", inner.display(fun))?;
+                }
+                write!(fmt, "}}")?;
+            }
             Cfg::Threads(dims, _, inners) => {
                 writeln!(
                     fmt,
@@ -347,6 +626,14 @@ impl<'a> fmt::Debug for Cfg<'a> {
                 .field(&format_args!("[{:?}]", &dim.dim_ids().format(",")))
                 .field(inners)
                 .finish(),
+            Cfg::Pipeline(dim, pipeline) => f
+                .debug_tuple("Pipeline")
+                .field(&format_args!("[{:?}]", &dim.dim_ids().format(",")))
+                .field(&pipeline.stages)
+                .field(&pipeline.prefetch)
+                .field(&pipeline.producer)
+                .field(&pipeline.consumer)
+                .finish(),
             Cfg::Instruction(dims, inst) => write!(f, "{:?} {}", dims, inst),
             Cfg::Threads(dims, _, inners) => {
                 f.debug_tuple("Threads").field(dims).field(inners).finish()
@@ -365,7 +652,7 @@ pub fn build<'a>(
     let (block_dims, thread_dims, mut events) = gen_events(space, insts, dims);
     events.sort_by(|lhs, rhs| lhs.cmp(rhs, space));
     debug!("events: {:?}", events);
-    let cfg = Cfg::from_events(events, thread_dims.len());
+    let cfg = Cfg::from_events(events, thread_dims.len()).pipeline_loops(space);
     (block_dims, thread_dims, cfg)
 }
 
@@ -6,7 +6,7 @@ use crate::codegen::{
     self, cfg, dimension, Cfg, Dimension, InductionLevel, InductionVar,
 };
 use crate::ir::{self, IrDisplay};
-use crate::search_space::{self, DimKind, Domain, MemSpace, SearchSpace};
+use crate::search_space::{self, DimKind, Domain, MemSpace, NumSet, SearchSpace};
 use fxhash::FxHashSet;
 use utils::*;
 
@@ -286,6 +286,7 @@ pub struct MemoryRegion {
     id: ir::MemId,
     size: codegen::Size,
     num_private_copies: Option<codegen::Size>,
+    num_stages: u32,
     mem_space: MemSpace,
     ptr_type: ir::Type,
 }
@@ -317,6 +318,8 @@ impl MemoryRegion {
         } else {
             None
         };
+        let num_stages = space.domain().get_num_stages(block.mem_id());
+        let num_stages = unwrap!(num_stages.as_constrained(&ir::mem::NUM_STAGES));
         let ptr_type = ir::Type::PtrTo(block.mem_id());
         let ptr_type = unwrap!(space.ir_instance().device().lower_type(ptr_type, space));
         MemoryRegion {
@@ -324,6 +327,7 @@ impl MemoryRegion {
             size,
             mem_space,
             num_private_copies,
+            num_stages,
             ptr_type,
         }
     }
@@ -373,20 +377,27 @@ impl MemoryRegion {
         }
     }
 
-    /// Generates the size of the memory to allocate.
+    /// Generates the size of the memory to allocate, including all the stages.
     pub fn alloc_size(&self) -> codegen::Size {
         let mut out = self.size.clone();
         if let Some(ref s) = self.num_private_copies {
             out *= s
         }
+        out *= &codegen::Size::new(self.num_stages, vec![], 1);
         out
     }
 
-    /// Returns the size of the part of the allocated memory accessible by each thread.
+    /// Returns the size of the part of the allocated memory accessible by each thread, for
+    /// a single stage.
     pub fn local_size(&self) -> &codegen::Size {
         &self.size
     }
 
+    /// Returns the number of rotating buffers the block is allocated with.
+    pub fn num_stages(&self) -> u32 {
+        self.num_stages
+    }
+
     /// Returns the memory space the block is allocated in.
     pub fn mem_space(&self) -> MemSpace {
         self.mem_space
@@ -71,7 +71,7 @@ fn unify_ftype(types: impl IntoIterator<Item = ir::Type>) -> Result<ir::Type, Ty
 /// A named register.
 ///
 /// Registers are typed, and should only be used in instructions expecting the appropriate type.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Register<'a> {
     name: &'a str,
     t: ir::Type,
@@ -106,11 +106,12 @@ impl<'a> Register<'a> {
 }
 
 /// An operand which can be used as input argument of an instruction.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Operand<'a> {
     Register(Register<'a>),
     IntLiteral(Cow<'a, BigInt>, u16),
     FloatLiteral(Cow<'a, Ratio<BigInt>>, u16),
+    Bf16Literal(u16),
 }
 
 impl fmt::Display for Operand<'_> {
@@ -121,6 +122,7 @@ impl fmt::Display for Operand<'_> {
             &Operand::FloatLiteral(ref value, bits) => {
                 write!(fmt, "({}) as f{}", value, bits)
             }
+            Operand::Bf16Literal(bits) => write!(fmt, "0x{:04X}bf16", bits),
         }
     }
 }
@@ -146,6 +148,7 @@ impl<'a> Operand<'a> {
             Operand::Register(register) => register.t(),
             Operand::IntLiteral(_, bits) => ir::Type::I(bits),
             Operand::FloatLiteral(_, bits) => ir::Type::F(bits),
+            Operand::Bf16Literal(_) => ir::Type::Bf16,
         }
     }
 }
@@ -320,7 +323,7 @@ impl<'a> OpVec<'a> {
 }
 
 /// A typed unary operator.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum UnOp {
     Move { t: ir::Type },
     Cast { src_t: ir::Type, dst_t: ir::Type },
@@ -414,7 +417,7 @@ impl UnOp {
 }
 
 /// Comparison operators
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CmpOp {
     Eq,
     Ne,
@@ -438,7 +441,7 @@ impl fmt::Display for CmpOp {
 }
 
 /// A typed binary operator
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BinOp {
     // Integer Arithmetic Instructions
     IAdd { arg_t: ir::Type },
@@ -459,6 +462,7 @@ pub enum BinOp {
     And { t: ir::Type },
     Or { t: ir::Type },
     Xor { t: ir::Type },
+    Shl { t: ir::Type },
 }
 
 impl fmt::Display for BinOp {
@@ -485,6 +489,7 @@ impl fmt::Display for BinOp {
             And { t } => write!(fmt, "and.{}", t),
             Or { t } => write!(fmt, "or.{}", t),
             Xor { t } => write!(fmt, "xor.{}", t),
+            Shl { t } => write!(fmt, "shl.{}", t),
         }
     }
 }
@@ -519,7 +524,7 @@ impl BinOp {
                 return Err(InstructionError::invalid_rounding_for_type(rounding, arg_t))
             }
             ity::I(_) => (),
-            ity::F(_) => match op {
+            ity::F(_) | ity::Bf16 => match op {
                 iop::Add | iop::Sub | iop::Div => (),
                 iop::Max => {
                     if rounding != ir::op::Rounding::Exact {
@@ -540,15 +545,15 @@ impl BinOp {
             (iop::Div, ity::I(_)) => BinOp::IDiv { arg_t },
             (iop::And, ity::I(_)) => BinOp::And { t: arg_t },
             (iop::Or, ity::I(_)) => BinOp::Or { t: arg_t },
-            (iop::Add, ity::F(_)) => BinOp::FAdd {
+            (iop::Add, ity::F(_)) | (iop::Add, ity::Bf16) => BinOp::FAdd {
                 t: arg_t,
                 rounding: rounding.into(),
             },
-            (iop::Sub, ity::F(_)) => BinOp::FSub {
+            (iop::Sub, ity::F(_)) | (iop::Sub, ity::Bf16) => BinOp::FSub {
                 t: arg_t,
                 rounding: rounding.into(),
             },
-            (iop::Div, ity::F(_)) => BinOp::FDiv {
+            (iop::Div, ity::F(_)) | (iop::Div, ity::Bf16) => BinOp::FDiv {
                 t: arg_t,
                 rounding: rounding.into(),
             },
@@ -564,7 +569,7 @@ impl BinOp {
                 op: CmpOp::Eq,
                 arg_t,
             },
-            (iop::Max, ity::F(_)) => BinOp::FMax { t: arg_t },
+            (iop::Max, ity::F(_)) | (iop::Max, ity::Bf16) => BinOp::FMax { t: arg_t },
             (iop::Max, ity::I(_)) => BinOp::IMax { arg_t },
             _ => return Err(InstructionError::invalid_binop_for_type(op, arg_t)),
         })
@@ -594,7 +599,7 @@ impl BinOp {
                     })
                 }
             }
-            ir::Type::F(_) => Ok(BinOp::FMul {
+            ir::Type::F(_) | ir::Type::Bf16 => Ok(BinOp::FMul {
                 t: Self::unify_ftype(Some(ret_t), [lhs_t, rhs_t])?,
                 rounding: rounding.into(),
             }),
@@ -622,7 +627,8 @@ impl BinOp {
             | FMin { t }
             | And { t }
             | Or { t }
-            | Xor { t } => [t, t],
+            | Xor { t }
+            | Shl { t } => [t, t],
         }
     }
 
@@ -642,7 +648,8 @@ impl BinOp {
             | FMin { t }
             | And { t }
             | Or { t }
-            | Xor { t } => t,
+            | Xor { t }
+            | Shl { t } => t,
         }
     }
 
@@ -678,6 +685,7 @@ impl BinOp {
         infer_and, And { t }, unify_itype;
         infer_xor, Xor { t }, unify_itype;
         infer_or, Or { t }, unify_itype;
+        infer_shl, Shl { t }, unify_itype;
     }
 
     pub fn infer_imul(
@@ -702,7 +710,7 @@ impl BinOp {
 }
 
 /// A typed ternary operator (e.g. fma)
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum TernOp {
     IMad { arg_t: ir::Type, spec: MulSpec },
     FFma { t: ir::Type, rounding: FpRounding },
@@ -743,7 +751,7 @@ impl TernOp {
                     })
                 }
             }
-            ir::Type::F(_) => Ok(TernOp::FFma {
+            ir::Type::F(_) | ir::Type::Bf16 => Ok(TernOp::FFma {
                 t: Self::unify_ftype(None, [mlhs_t, mrhs_t, arhs_t])?,
                 rounding: rounding.into(),
             }),
@@ -809,6 +817,32 @@ impl TernOp {
     }
 }
 
+/// The types of a warp-level matrix multiply-accumulate.
+///
+/// The left-hand side, right-hand side and accumulator operands hold respectively 4, 2 and 4
+/// elements per lane, in the layout described by `ir::MmaDims`.
+#[derive(Debug, Copy, Clone)]
+pub struct MmaSpec {
+    /// The type of the elements of the multiplied matrices.
+    pub ab_t: ir::Type,
+    /// The type of the elements of the accumulator and of the result.
+    pub cd_t: ir::Type,
+}
+
+impl fmt::Display for MmaSpec {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "mma.{}.{}", self.ab_t, self.cd_t)
+    }
+}
+
+/// Returns the number of elements of a vector.
+fn vector_len<T>(vec: &ScalarOrVector<T>) -> usize {
+    match vec {
+        ScalarOrVector::Scalar(_) => 1,
+        ScalarOrVector::Vector(elems) => elems.len(),
+    }
+}
+
 /// A (possibly vectorized) instruction to execute.
 #[derive(Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
@@ -818,6 +852,7 @@ pub enum Instruction<'a> {
     Ternary(TernOp, RegVec<'a>, [OpVec<'a>; 3]),
     Load(LoadSpec, RegVec<'a>, Address<'a>),
     Store(StoreSpec, Address<'a>, [OpVec<'a>; 1]),
+    Mma(MmaSpec, RegVec<'a>, [OpVec<'a>; 3]),
     Jump(Label<'a>),
     Sync,
 }
@@ -834,6 +869,9 @@ impl fmt::Display for Instruction<'_> {
             }
             Load(spec, d, a) => write!(fmt, "{} = {}({})", d, spec, a),
             Store(spec, a, [b]) => write!(fmt, "{}({}, {})", spec, a, b),
+            Mma(spec, d, [a, b, c]) => {
+                write!(fmt, "{} = {}({}, {}, {})", d, spec, a, b, c)
+            }
             Jump(label) => write!(fmt, "jump {}", label),
             Sync => write!(fmt, "sync"),
         }
@@ -981,6 +1019,7 @@ impl<'a> Instruction<'a> {
         and(d, a, b), BinOp::infer_and, binary;
         xor(d, a, b), BinOp::infer_xor, binary;
         or(d, a, b), BinOp::infer_or, binary;
+        shl(d, a, b), BinOp::infer_shl, binary;
     }
 
     pub fn imul<D, A, B>(d: D, a: A, b: B) -> Result<Self, InstructionError>
@@ -1059,6 +1098,35 @@ impl<'a> Instruction<'a> {
         Ok(Instruction::Store(spec, a, [b]))
     }
 
+    /// Create a new warp-level matrix multiply-accumulate instruction.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the operand types do not match `spec`, or if the operands do not hold the
+    /// number of elements expected for each lane.
+    pub fn mma(
+        spec: MmaSpec,
+        d: RegVec<'a>,
+        a: OpVec<'a>,
+        b: OpVec<'a>,
+        c: OpVec<'a>,
+    ) -> Result<Self, InstructionError> {
+        if [a.t(), b.t()] != [spec.ab_t; 2] || [c.t(), d.t()] != [spec.cd_t; 2] {
+            return Err(InstructionError::incompatible_types());
+        }
+        for &len in &[vector_len(&a), vector_len(&c), vector_len(&d)] {
+            if len != 4 {
+                return Err(InstructionError::invalid_vector_factors([1, len as u32]));
+            }
+        }
+        if vector_len(&b) != 2 {
+            let len = vector_len(&b) as u32;
+            return Err(InstructionError::invalid_vector_factors([1, len]));
+        }
+
+        Ok(Instruction::Mma(spec, d, [a, b, c]))
+    }
+
     /// Create a new `jump` instruction.
     pub fn jump(label: Label<'a>) -> Self {
         Instruction::Jump(label)
@@ -1140,7 +1208,7 @@ impl<'a> Label<'a> {
 }
 
 /// Rounding mode for floating-point instructions
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum FpRounding {
     /// Mantissa LSB rounds to nearest even
     NearestEven,
@@ -1177,7 +1245,7 @@ impl From<ir::op::Rounding> for FpRounding {
 }
 
 /// Integer multiplication width specification
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum MulSpec {
     /// Keep the low bits of the result
     Low,
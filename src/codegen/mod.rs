@@ -4,15 +4,19 @@ mod dimension;
 mod function;
 pub mod llir;
 mod name_map;
+mod optimizer;
 mod printer;
+mod resources;
 mod size;
 mod variable;
 
-pub use self::cfg::Cfg;
+pub use self::cfg::{Cfg, Pipeline};
 pub use self::dimension::{Dimension, InductionLevel, InductionVar};
 pub use self::function::*;
 pub use self::name_map::{Interner, NameGenerator, NameMap, Operand};
+pub use self::optimizer::{InstBuffer, OptimizationStats};
 pub use self::printer::{IdentDisplay, InstPrinter, Printer};
+pub use self::resources::ResourceReport;
 pub use self::size::Size;
 pub use self::variable::Variable;
 
@@ -234,6 +234,7 @@ impl<'a> NameMap<'a> {
             ir::Operand::Float(val, len) => {
                 llir::Operand::FloatLiteral(Cow::Borrowed(val), *len)
             }
+            ir::Operand::Bf16(bits) => llir::Operand::Bf16Literal(*bits),
             ir::Operand::Inst(id, _, dim_map, _)
             | ir::Operand::Reduce(id, _, dim_map, _) => {
                 self.name_mapped_inst(*id, indexes, dim_map).into()
@@ -0,0 +1,749 @@
+//! Backend-agnostic optimizations on the stream of `llir` instructions.
+//!
+//! The printer lowers each IR instruction and each loop level independently, which results in
+//! redundant index computations, for instance once per unrolled instance.  The `InstBuffer`
+//! collects the instructions of a function body so that they can be cleaned up before being
+//! handed to the backend:
+//! - constant and copy propagation within straight-line code,
+//! - folding of integer operations on literals and of algebraic identities,
+//! - strength reduction of multiplications by powers of two into shifts,
+//! - elimination of common integer subexpressions within straight-line code,
+//! - elimination of pure instructions whose result is never read.
+use std::borrow::Cow;
+use std::fmt;
+
+use fxhash::{FxHashMap, FxHashSet};
+use log::debug;
+use num::bigint::BigInt;
+use num::{Integer, One, ToPrimitive, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::codegen::llir::{
+    self, BinOp, Instruction, MulSpec, Operand, PredicatedInstruction, Register,
+    ScalarOrVector, TernOp, UnOp,
+};
+use crate::codegen::InstPrinter;
+use crate::ir;
+use utils::unwrap;
+
+/// An element of the buffered instruction stream.
+#[allow(clippy::large_enum_variant)]
+enum Event<'a> {
+    Label(llir::Label<'a>),
+    LoopBegin(llir::Label<'a>),
+    LoopEnd(llir::Label<'a>, Register<'a>),
+    Branch(llir::Label<'a>, Register<'a>),
+    Inst(PredicatedInstruction<'a>),
+}
+
+/// Buffers the instructions of a function body so they can be optimized before being
+/// printed by a backend.
+#[derive(Default)]
+pub struct InstBuffer<'a> {
+    events: Vec<Event<'a>>,
+}
+
+impl<'a> InstPrinter<'a> for InstBuffer<'a> {
+    fn print_label(&mut self, label: llir::Label<'a>) {
+        self.events.push(Event::Label(label));
+    }
+
+    fn print_inst(&mut self, inst: PredicatedInstruction<'a>) {
+        self.events.push(Event::Inst(inst));
+    }
+
+    fn print_loop_begin(&mut self, label: llir::Label<'a>) {
+        self.events.push(Event::LoopBegin(label));
+    }
+
+    fn print_loop_end(&mut self, label: llir::Label<'a>, cond: Register<'a>) {
+        self.events.push(Event::LoopEnd(label, cond));
+    }
+
+    fn print_branch(&mut self, label: llir::Label<'a>, cond: Register<'a>) {
+        self.events.push(Event::Branch(label, cond));
+    }
+}
+
+impl<'a> InstBuffer<'a> {
+    /// Returns the number of buffered instructions.
+    pub fn num_instructions(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|event| match event {
+                Event::Inst(_) => true,
+                _ => false,
+            })
+            .count()
+    }
+
+    /// Optimizes the buffered instructions.  This assumes the buffer holds the end of the
+    /// function: registers defined by buffered instructions must not be read afterwards.
+    pub fn optimize(&mut self) -> OptimizationStats {
+        let mut stats = OptimizationStats {
+            num_insts_before: self.num_instructions(),
+            ..OptimizationStats::default()
+        };
+        self.simplify(&mut stats);
+        self.remove_dead_code(&mut stats);
+        stats.num_insts_after = self.num_instructions();
+        debug!("llir optimizations: {}", stats);
+        stats
+    }
+
+    /// Passes the buffered instructions to `printer`.
+    pub fn print(self, printer: &mut dyn InstPrinter<'a>) {
+        for event in self.events {
+            match event {
+                Event::Label(label) => printer.print_label(label),
+                Event::LoopBegin(label) => printer.print_loop_begin(label),
+                Event::LoopEnd(label, cond) => printer.print_loop_end(label, cond),
+                Event::Branch(label, cond) => printer.print_branch(label, cond),
+                Event::Inst(inst) => printer.print_inst(inst),
+            }
+        }
+    }
+
+    /// Propagates copies and constants, folds, strength-reduces and eliminates common
+    /// subexpressions in each straight-line section of code.
+    fn simplify(&mut self, stats: &mut OptimizationStats) {
+        let mut values = Values::default();
+        let events = std::mem::replace(&mut self.events, Vec::new());
+        for event in events {
+            let mut inst = match event {
+                Event::Inst(inst) => inst,
+                event => {
+                    // Control flow may reach labels from multiple places.
+                    values = Values::default();
+                    self.events.push(event);
+                    continue;
+                }
+            };
+            values.propagate(&mut inst.instruction);
+            if let Some((simplified, kind)) = simplify_inst(&inst.instruction) {
+                inst.instruction = simplified;
+                match kind {
+                    Simplification::Folded => stats.num_folded += 1,
+                    Simplification::StrengthReduced => stats.num_strength_reduced += 1,
+                }
+            }
+            if is_self_move(&inst.instruction) {
+                stats.num_folded += 1;
+                continue;
+            }
+            let expr = Expr::new(&inst.instruction).filter(|_| inst.predicate.is_none());
+            if let (Some(expr), Some(d)) = (&expr, scalar_dest(&inst.instruction)) {
+                if let Some(&reg) = values.exprs.get(expr) {
+                    inst.instruction = unwrap!(Instruction::mov(d, reg.into_operand()));
+                    stats.num_cse += 1;
+                }
+            }
+            for reg in defs(&inst.instruction) {
+                values.kill(reg);
+            }
+            if inst.predicate.is_none() {
+                values.record(&inst.instruction, expr);
+            }
+            self.events.push(Event::Inst(inst));
+        }
+    }
+
+    /// Removes pure instructions whose results are never read.
+    fn remove_dead_code(&mut self, stats: &mut OptimizationStats) {
+        loop {
+            let mut uses = FxHashSet::default();
+            for event in &self.events {
+                match event {
+                    Event::LoopEnd(_, cond) | Event::Branch(_, cond) => {
+                        uses.insert(cond.name());
+                    }
+                    Event::Inst(inst) => {
+                        uses.extend(inst.predicate.map(|p| p.name()));
+                        uses.extend(uses_of(&inst.instruction).map(|r| r.name()));
+                    }
+                    Event::Label(_) | Event::LoopBegin(_) => (),
+                }
+            }
+            let num_events = self.events.len();
+            self.events.retain(|event| match event {
+                Event::Inst(inst) => {
+                    !is_pure(&inst.instruction)
+                        || defs(&inst.instruction).any(|reg| uses.contains(reg.name()))
+                }
+                _ => true,
+            });
+            let num_removed = num_events - self.events.len();
+            if num_removed == 0 {
+                break;
+            }
+            stats.num_dead += num_removed;
+        }
+    }
+}
+
+/// Statistics about the optimizations applied to a function body.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptimizationStats {
+    /// Number of instructions before optimization.
+    pub num_insts_before: usize,
+    /// Number of instructions after optimization.
+    pub num_insts_after: usize,
+    /// Number of instructions folded into a move or removed because they were moves of a
+    /// register into itself.
+    pub num_folded: usize,
+    /// Number of instructions replaced by cheaper ones.
+    pub num_strength_reduced: usize,
+    /// Number of instructions replaced by a move of a previously computed value.
+    pub num_cse: usize,
+    /// Number of instructions removed because their result is not used.
+    pub num_dead: usize,
+}
+
+impl fmt::Display for OptimizationStats {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "{} -> {} instructions ({} folded, {} strength-reduced, {} common \
+             subexpressions, {} dead)",
+            self.num_insts_before,
+            self.num_insts_after,
+            self.num_folded,
+            self.num_strength_reduced,
+            self.num_cse,
+            self.num_dead,
+        )
+    }
+}
+
+/// A pure computation on integer scalars, that can be reused while its operands are not
+/// redefined.
+#[derive(PartialEq, Eq, Hash)]
+enum Expr<'a> {
+    Unary(UnOp, Operand<'a>),
+    Binary(BinOp, Operand<'a>, Operand<'a>),
+    Ternary(TernOp, Operand<'a>, Operand<'a>, Operand<'a>),
+}
+
+impl<'a> Expr<'a> {
+    /// Returns the computation performed by `inst`, if it can be reused.
+    fn new(inst: &Instruction<'a>) -> Option<Self> {
+        use ScalarOrVector::Scalar;
+
+        match inst {
+            Instruction::Unary(op @ UnOp::Cast { .. }, Scalar(_), [Scalar(a)])
+                if op.arg_t().iter().all(|&t| is_int(t)) && is_int(op.ret_t()) =>
+            {
+                Some(Expr::Unary(*op, a.clone()))
+            }
+            Instruction::Binary(op, Scalar(_), [Scalar(a), Scalar(b)])
+                if op.arg_t().iter().all(|&t| is_int(t)) =>
+            {
+                Some(Expr::Binary(*op, a.clone(), b.clone()))
+            }
+            Instruction::Ternary(op, Scalar(_), [Scalar(a), Scalar(b), Scalar(c)])
+                if op.arg_t().iter().all(|&t| is_int(t)) =>
+            {
+                Some(Expr::Ternary(*op, a.clone(), b.clone(), c.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Indicates if the computation reads `reg`.
+    fn reads(&self, reg: Register<'_>) -> bool {
+        match self {
+            Expr::Unary(_, a) => is_reg(a, reg),
+            Expr::Binary(_, a, b) => is_reg(a, reg) || is_reg(b, reg),
+            Expr::Ternary(_, a, b, c) => {
+                is_reg(a, reg) || is_reg(b, reg) || is_reg(c, reg)
+            }
+        }
+    }
+}
+
+/// The values known to be held by registers at a point of straight-line code.
+#[derive(Default)]
+struct Values<'a> {
+    /// Registers holding a copy of another register or of a literal.
+    copies: FxHashMap<&'a str, Operand<'a>>,
+    /// Registers holding the result of computations.
+    exprs: FxHashMap<Expr<'a>, Register<'a>>,
+}
+
+impl<'a> Values<'a> {
+    /// Replaces the operands of `inst` by the registers or literals they copy.  Literals are
+    /// only substituted in integer arithmetic, where all backends accept them.
+    fn propagate(&self, inst: &mut Instruction<'a>) {
+        for operand in operands_mut(inst) {
+            if let Some(reg) = operand.to_register() {
+                if let Some(copy @ Operand::Register(_)) = self.copies.get(reg.name()) {
+                    *operand = copy.clone();
+                }
+            }
+        }
+        match inst {
+            Instruction::Load(_, _, addr) | Instruction::Store(_, addr, _) => {
+                let llir::Address::Register(reg, _) = addr;
+                if let Some(&Operand::Register(copy)) = self.copies.get(reg.name()) {
+                    *reg = copy;
+                }
+            }
+            Instruction::Unary(UnOp::Move { t }, _, [ScalarOrVector::Scalar(a)])
+                if is_int(*t) =>
+            {
+                self.propagate_literal(a);
+            }
+            Instruction::Binary(
+                op,
+                _,
+                [ScalarOrVector::Scalar(a), ScalarOrVector::Scalar(b)],
+            ) if op.arg_t().iter().all(|&t| is_int(t)) => {
+                self.propagate_literal(b);
+                // Only substitute literals in the first operand if they can be moved to the second
+                // or if the operation can be folded.
+                if is_commutative(*op) || (is_literal(b) && is_foldable(*op)) {
+                    self.propagate_literal(a);
+                }
+                if is_commutative(*op) && is_literal(a) && !is_literal(b) {
+                    std::mem::swap(a, b);
+                }
+            }
+            Instruction::Ternary(
+                op @ TernOp::IMad { .. },
+                _,
+                [ScalarOrVector::Scalar(a), ScalarOrVector::Scalar(b), ScalarOrVector::Scalar(c)],
+            ) if op.arg_t().iter().all(|&t| is_int(t)) => {
+                self.propagate_literal(a);
+                self.propagate_literal(b);
+                self.propagate_literal(c);
+                if is_literal(a) && !is_literal(b) {
+                    std::mem::swap(a, b);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Replaces `operand` by the literal it holds, if known.
+    fn propagate_literal(&self, operand: &mut Operand<'a>) {
+        if let Some(reg) = operand.to_register() {
+            if let Some(lit @ Operand::IntLiteral(..)) = self.copies.get(reg.name()) {
+                *operand = lit.clone();
+            }
+        }
+    }
+
+    /// Forgets the values that depend on the content of `reg`.
+    fn kill(&mut self, reg: Register<'a>) {
+        self.copies.remove(reg.name());
+        self.copies.retain(|_, value| !is_reg(value, reg));
+        self.exprs
+            .retain(|expr, res| res.name() != reg.name() && !expr.reads(reg));
+    }
+
+    /// Records the value computed by an unpredicated instruction.
+    fn record(&mut self, inst: &Instruction<'a>, expr: Option<Expr<'a>>) {
+        let d = if let Some(d) = scalar_dest(inst) {
+            d
+        } else {
+            return;
+        };
+        match inst {
+            Instruction::Unary(UnOp::Move { .. }, _, [ScalarOrVector::Scalar(a)]) => {
+                if !is_reg(a, d) {
+                    self.copies.insert(d.name(), a.clone());
+                }
+            }
+            _ => {
+                if let Some(expr) = expr.filter(|expr| !expr.reads(d)) {
+                    self.exprs.insert(expr, d);
+                }
+            }
+        }
+    }
+}
+
+/// The kind of simplification applied to an instruction.
+enum Simplification {
+    Folded,
+    StrengthReduced,
+}
+
+/// Folds integer operations on literals, algebraic identities and multiplications by
+/// powers of two.
+fn simplify_inst<'a>(
+    inst: &Instruction<'a>,
+) -> Option<(Instruction<'a>, Simplification)> {
+    use ScalarOrVector::Scalar;
+
+    let folded = |inst| Some((unwrap!(inst), Simplification::Folded));
+    match inst {
+        Instruction::Binary(op, Scalar(d), [Scalar(a), Scalar(b)]) if is_int(d.t()) => {
+            let d = *d;
+            let t = d.t();
+            match (op, int_value(a), int_value(b)) {
+                (_, Some(x), Some(y)) => fold_binop(*op, x, y)
+                    .and_then(|value| folded(Instruction::mov(d, literal(value, t)))),
+                (BinOp::IAdd { .. }, _, Some(y)) | (BinOp::ISub { .. }, _, Some(y))
+                    if y.is_zero() =>
+                {
+                    folded(Instruction::mov(d, a.clone()))
+                }
+                (BinOp::IDiv { .. }, _, Some(y)) if y.is_one() => {
+                    folded(Instruction::mov(d, a.clone()))
+                }
+                (BinOp::IMul { .. }, _, Some(y)) if y.is_zero() => {
+                    folded(Instruction::mov(d, literal(BigInt::zero(), t)))
+                }
+                (
+                    BinOp::IMul {
+                        spec: MulSpec::Low, ..
+                    },
+                    _,
+                    Some(y),
+                ) => {
+                    if y.is_one() {
+                        return folded(Instruction::mov(d, a.clone()));
+                    }
+                    let shift = log2(y)?;
+                    let shift = literal(BigInt::from(shift), t);
+                    let inst = unwrap!(Instruction::shl(d, a.clone(), shift));
+                    Some((inst, Simplification::StrengthReduced))
+                }
+                _ => None,
+            }
+        }
+        Instruction::Ternary(
+            TernOp::IMad { arg_t, spec },
+            Scalar(d),
+            [Scalar(a), Scalar(b), Scalar(c)],
+        ) if is_int(*arg_t) && *spec != MulSpec::High => {
+            let d = *d;
+            let y = int_value(b)?;
+            if let Some(x) = int_value(a) {
+                let product = literal(x * y, d.t());
+                return if let Some(z) = int_value(c) {
+                    let sum = int_value(&product).unwrap() + z;
+                    folded(Instruction::mov(d, literal(sum, d.t())))
+                } else if int_value(&product).unwrap().is_zero() {
+                    folded(Instruction::mov(d, c.clone()))
+                } else {
+                    folded(Instruction::iadd(d, c.clone(), product))
+                };
+            }
+            if y.is_zero() {
+                folded(Instruction::mov(d, c.clone()))
+            } else if y.is_one() && *spec == MulSpec::Low {
+                folded(Instruction::iadd(d, a.clone(), c.clone()))
+            } else if int_value(c).map(Zero::is_zero).unwrap_or(false) {
+                let mul = unwrap!(Instruction::imul_ex(*spec, d, a.clone(), b.clone()));
+                simplify_inst(&mul).or(Some((mul, Simplification::Folded)))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Computes the result of an integer binary operator on literals, without wrapping.
+fn fold_binop(op: BinOp, x: &BigInt, y: &BigInt) -> Option<BigInt> {
+    match op {
+        BinOp::IAdd { .. } => Some(x + y),
+        BinOp::ISub { .. } => Some(x - y),
+        BinOp::IMul {
+            spec: MulSpec::Low, ..
+        }
+        | BinOp::IMul {
+            spec: MulSpec::Wide,
+            ..
+        } => Some(x * y),
+        // Division truncates towards zero in all backends, as does `BigInt`.
+        BinOp::IDiv { .. } if !y.is_zero() => Some(x / y),
+        BinOp::IMax { .. } => Some(std::cmp::max(x, y).clone()),
+        _ => None,
+    }
+}
+
+/// Indicates if `fold_binop` may fold the operator.
+fn is_foldable(op: BinOp) -> bool {
+    match op {
+        BinOp::IAdd { .. }
+        | BinOp::ISub { .. }
+        | BinOp::IDiv { .. }
+        | BinOp::IMax { .. } => true,
+        BinOp::IMul { spec, .. } => spec != MulSpec::High,
+        _ => false,
+    }
+}
+
+/// Indicates if the operands of the operator can be swapped.
+fn is_commutative(op: BinOp) -> bool {
+    match op {
+        BinOp::IAdd { .. }
+        | BinOp::IMul { .. }
+        | BinOp::IMax { .. }
+        | BinOp::And { .. }
+        | BinOp::Or { .. }
+        | BinOp::Xor { .. } => true,
+        _ => false,
+    }
+}
+
+/// Returns `k` if `value` is `2^k` with `k > 0`.
+fn log2(value: &BigInt) -> Option<u64> {
+    let value = value.to_u64()?;
+    if value > 1 && value.is_power_of_two() {
+        Some(u64::from(value.trailing_zeros()))
+    } else {
+        None
+    }
+}
+
+/// Creates an integer literal of type `t`, wrapping `value` around as two's complement.
+fn literal<'a>(value: BigInt, t: ir::Type) -> Operand<'a> {
+    let bits = unwrap!(t.bitwidth()) as u16;
+    let modulus = BigInt::one() << usize::from(bits);
+    let mut value = value.mod_floor(&modulus);
+    if value >= &modulus >> 1 {
+        value -= modulus;
+    }
+    Operand::IntLiteral(Cow::Owned(value), bits)
+}
+
+/// Returns the value of an integer literal operand.
+fn int_value<'b>(operand: &'b Operand<'_>) -> Option<&'b BigInt> {
+    match operand {
+        Operand::IntLiteral(value, _) => Some(value),
+        _ => None,
+    }
+}
+
+/// Indicates if the operand is a literal.
+fn is_literal(operand: &Operand<'_>) -> bool {
+    operand.to_register().is_none()
+}
+
+/// Indicates if the operand is the register `reg`.
+fn is_reg(operand: &Operand<'_>, reg: Register<'_>) -> bool {
+    operand.to_register().map(|r| r.name()) == Some(reg.name())
+}
+
+/// Indicates if values of type `t` are integers that literals can represent.  Predicates are
+/// excluded as backends handle them specially.
+fn is_int(t: ir::Type) -> bool {
+    match t {
+        ir::Type::I(bits) => bits > 1,
+        _ => false,
+    }
+}
+
+/// Indicates if the instruction is a move of a register into itself.
+fn is_self_move(inst: &Instruction<'_>) -> bool {
+    match inst {
+        Instruction::Unary(
+            UnOp::Move { .. },
+            ScalarOrVector::Scalar(d),
+            [ScalarOrVector::Scalar(a)],
+        ) => is_reg(a, *d),
+        _ => false,
+    }
+}
+
+/// Indicates if the only effect of the instruction is to write its destination registers.
+fn is_pure(inst: &Instruction<'_>) -> bool {
+    match inst {
+        Instruction::Unary(..) | Instruction::Binary(..) | Instruction::Ternary(..) => {
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Returns the destination of the instruction if it is a single register.
+fn scalar_dest<'a>(inst: &Instruction<'a>) -> Option<Register<'a>> {
+    match inst {
+        Instruction::Unary(_, ScalarOrVector::Scalar(d), _)
+        | Instruction::Binary(_, ScalarOrVector::Scalar(d), _)
+        | Instruction::Ternary(_, ScalarOrVector::Scalar(d), _) => Some(*d),
+        _ => None,
+    }
+}
+
+/// Returns the registers written by the instruction.
+fn defs<'a, 'b>(inst: &'b Instruction<'a>) -> impl Iterator<Item = Register<'a>> + 'b {
+    let d = match inst {
+        Instruction::Unary(_, d, _)
+        | Instruction::Binary(_, d, _)
+        | Instruction::Ternary(_, d, _)
+        | Instruction::Load(_, d, _)
+        | Instruction::Mma(_, d, _) => Some(d),
+        Instruction::Store(..) | Instruction::Jump(_) | Instruction::Sync => None,
+    };
+    d.into_iter().flat_map(elements).copied()
+}
+
+/// Returns the registers read by the instruction, excluding its predicate.
+fn uses_of<'a, 'b>(inst: &'b Instruction<'a>) -> impl Iterator<Item = Register<'a>> + 'b {
+    let (operands, addr): (&[llir::OpVec<'a>], _) = match inst {
+        Instruction::Unary(_, _, ops) => (ops, None),
+        Instruction::Binary(_, _, ops) => (ops, None),
+        Instruction::Ternary(_, _, ops) | Instruction::Mma(_, _, ops) => (ops, None),
+        Instruction::Load(_, _, addr) => (&[], Some(addr)),
+        Instruction::Store(_, addr, ops) => (ops, Some(addr)),
+        Instruction::Jump(_) | Instruction::Sync => (&[], None),
+    };
+    let addr = addr.map(|&llir::Address::Register(reg, _)| reg);
+    operands
+        .iter()
+        .flat_map(elements)
+        .filter_map(Operand::to_register)
+        .chain(addr)
+}
+
+/// Returns the operands read by the instruction, excluding addresses.
+fn operands_mut<'a, 'b>(
+    inst: &'b mut Instruction<'a>,
+) -> impl Iterator<Item = &'b mut Operand<'a>> + 'b {
+    let operands: &mut [llir::OpVec<'a>] = match inst {
+        Instruction::Unary(_, _, ops) => ops,
+        Instruction::Binary(_, _, ops) => ops,
+        Instruction::Ternary(_, _, ops) | Instruction::Mma(_, _, ops) => ops,
+        Instruction::Store(_, _, ops) => ops,
+        Instruction::Load(..) | Instruction::Jump(_) | Instruction::Sync => &mut [],
+    };
+    operands.iter_mut().flat_map(|op| match op {
+        ScalarOrVector::Scalar(op) => std::slice::from_mut(op),
+        ScalarOrVector::Vector(ops) => &mut ops[..],
+    })
+}
+
+/// Returns the elements of a scalar or a vector.
+fn elements<T>(vec: &ScalarOrVector<T>) -> &[T] {
+    match vec {
+        ScalarOrVector::Scalar(scalar) => std::slice::from_ref(scalar),
+        ScalarOrVector::Vector(elems) => elems,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codegen::llir::IntLiteral as _;
+    use crate::search_space::{InstFlag, MemSpace};
+
+    use super::*;
+
+    /// Collects the printed instructions as strings.
+    #[derive(Default)]
+    struct Collector(Vec<String>);
+
+    impl<'a> InstPrinter<'a> for Collector {
+        fn print_label(&mut self, label: llir::Label<'a>) {
+            self.0.push(label.to_string());
+        }
+
+        fn print_inst(&mut self, inst: PredicatedInstruction<'a>) {
+            self.0.push(inst.to_string());
+        }
+    }
+
+    fn reg(name: &str) -> Register<'_> {
+        Register::new(name, ir::Type::I(32))
+    }
+
+    fn op(name: &str) -> Operand<'_> {
+        reg(name).into_operand()
+    }
+
+    fn store<'a>(value: Register<'a>) -> PredicatedInstruction<'a> {
+        let spec = unwrap!(llir::StoreSpec::from_ir(
+            [1, 1],
+            ir::Type::I(32),
+            MemSpace::GLOBAL,
+            InstFlag::CACHE_SHARED,
+        ));
+        let addr = llir::Address::Register(Register::new("p", ir::Type::I(64)), 0);
+        unwrap!(Instruction::store(spec, addr, value.into_operand().into())).into()
+    }
+
+    /// Optimizes `insts` and returns the resulting instructions and statistics.
+    fn optimize(
+        insts: Vec<PredicatedInstruction<'_>>,
+    ) -> (Vec<String>, OptimizationStats) {
+        let mut buffer = InstBuffer::default();
+        for inst in insts {
+            buffer.print_inst(inst);
+        }
+        let stats = buffer.optimize();
+        let mut collector = Collector::default();
+        buffer.print(&mut collector);
+        (collector.0, stats)
+    }
+
+    /// Ensures constants are propagated and folded and that dead code is removed.
+    #[test]
+    fn fold_constants() {
+        let _ = ::env_logger::try_init();
+        let (x, y, z) = (reg("x"), reg("y"), reg("z"));
+        let (insts, stats) = optimize(vec![
+            unwrap!(Instruction::mov(x, 4i32.int_literal())).into(),
+            unwrap!(Instruction::iadd(y, x.into_operand(), 3i32.int_literal())).into(),
+            unwrap!(Instruction::imul(z, op("i"), y.into_operand())).into(),
+            store(z),
+        ]);
+        assert_eq!(insts[0], "z = mul.lo.i32(i, 7i32)");
+        assert_eq!(insts.len(), 2);
+        assert_eq!(stats.num_folded, 1);
+        assert_eq!(stats.num_dead, 2);
+    }
+
+    /// Ensures multiplications by powers of two are replaced by shifts.
+    #[test]
+    fn strength_reduction() {
+        let _ = ::env_logger::try_init();
+        let z = reg("z");
+        let (insts, stats) = optimize(vec![
+            unwrap!(Instruction::imul(z, op("i"), 8i32.int_literal())).into(),
+            store(z),
+        ]);
+        assert_eq!(insts[0], "z = shl.i32(i, 3i32)");
+        assert_eq!(stats.num_strength_reduced, 1);
+    }
+
+    /// Ensures common subexpressions are reused while their operands are not redefined.
+    #[test]
+    fn common_subexpressions() {
+        let _ = ::env_logger::try_init();
+        let (i, a, b, c) = (reg("i"), reg("a"), reg("b"), reg("c"));
+        let mad = |d| {
+            unwrap!(Instruction::imad(d, op("i"), 4i32.int_literal(), op("j"))).into()
+        };
+        let (insts, stats) = optimize(vec![
+            mad(a),
+            mad(b),
+            store(b),
+            unwrap!(Instruction::iadd(i, op("i"), 1i32.int_literal())).into(),
+            mad(c),
+            store(c),
+        ]);
+        assert_eq!(insts[1], "store.global.wb.i32([p], a)");
+        assert_eq!(insts[3], "c = mad.lo.i32(i, 4i32, j)");
+        assert_eq!(insts.len(), 5);
+        assert_eq!(stats.num_cse, 1);
+        assert_eq!(stats.num_dead, 1);
+    }
+
+    /// Ensures values are not reused across labels.
+    #[test]
+    fn labels_split_blocks() {
+        let _ = ::env_logger::try_init();
+        let (a, b) = (reg("a"), reg("b"));
+        let mut buffer = InstBuffer::default();
+        buffer.print_inst(unwrap!(Instruction::iadd(a, op("i"), op("i"))).into());
+        buffer.print_inst(store(a));
+        buffer.print_label(llir::Label::new("L"));
+        buffer.print_inst(unwrap!(Instruction::iadd(b, op("i"), op("i"))).into());
+        buffer.print_inst(store(b));
+        assert_eq!(buffer.optimize().num_cse, 0);
+    }
+}
@@ -1,6 +1,7 @@
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
 
+use fxhash::FxHashMap;
 use itertools::Itertools;
 
 use crate::codegen::llir::IntLiteral as _;
@@ -55,6 +56,7 @@ impl IdentDisplay for Type {
         match self {
             Type::I(s) => write!(fmt, "i{}", s),
             Type::F(s) => write!(fmt, "f{}", s),
+            Type::Bf16 => write!(fmt, "bf16"),
             Type::PtrTo(mem) => write!(fmt, "memptr{}", mem.0),
         }
     }
@@ -70,25 +72,44 @@ fn lower_type(t: ir::Type, fun: &Function) -> ir::Type {
         .unwrap()
 }
 
-pub trait InstPrinter {
+pub trait InstPrinter<'a> {
     /// print a label where to jump
-    fn print_label(&mut self, label: llir::Label<'_>);
+    fn print_label(&mut self, label: llir::Label<'a>);
 
-    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'_>);
+    fn print_inst(&mut self, inst: llir::PredicatedInstruction<'a>);
+
+    /// Prints the beginning of the body of a sequential loop.  Defaults to a label, so that
+    /// the end of the loop can jump back to it.
+    fn print_loop_begin(&mut self, label: llir::Label<'a>) {
+        self.print_label(label)
+    }
+
+    /// Prints the end of a sequential loop, whose body is executed again while `cond` holds.
+    /// Defaults to a conditional jump to the beginning of the loop.
+    fn print_loop_end(&mut self, label: llir::Label<'a>, cond: llir::Register<'a>) {
+        self.print_inst(llir::Instruction::jump(label).predicated(cond))
+    }
+
+    /// Prints a forward jump to `label`, taken by the threads for which `cond` holds.
+    /// Unlike the end of loops, threads may take different paths.  Defaults to a
+    /// conditional jump.
+    fn print_branch(&mut self, label: llir::Label<'a>, cond: llir::Register<'a>) {
+        self.print_inst(llir::Instruction::jump(label).predicated(cond))
+    }
 }
 
 /// Helper struct to provide useful methods wrapping an `InstPrinter` instance.
-struct InstPrinterHelper<'a> {
-    inst_printer: &'a mut dyn InstPrinter,
+struct InstPrinterHelper<'a, 'b> {
+    inst_printer: &'a mut dyn InstPrinter<'b>,
 }
 
-impl<'a> InstPrinterHelper<'a> {
+impl<'a, 'b> InstPrinterHelper<'a, 'b> {
     /// Prints a scalar addition on integers.
     fn print_add_int(
         &mut self,
-        result: llir::Register<'_>,
-        lhs: llir::Operand<'_>,
-        rhs: llir::Operand<'_>,
+        result: llir::Register<'b>,
+        lhs: llir::Operand<'b>,
+        rhs: llir::Operand<'b>,
     ) {
         self.inst_printer
             .print_inst(llir::Instruction::iadd(result, lhs, rhs).unwrap().into())
@@ -97,27 +118,38 @@ impl<'a> InstPrinterHelper<'a> {
     /// Prints a scalar less-than on integers.
     fn print_lt_int(
         &mut self,
-        result: llir::Register<'_>,
-        lhs: llir::Operand<'_>,
-        rhs: llir::Operand<'_>,
+        result: llir::Register<'b>,
+        lhs: llir::Operand<'b>,
+        rhs: llir::Operand<'b>,
     ) {
         self.inst_printer
             .print_inst(llir::Instruction::set_lt(result, lhs, rhs).unwrap().into())
     }
 
+    /// Prints an OR operation.
+    fn print_or(
+        &mut self,
+        result: llir::Register<'b>,
+        lhs: llir::Operand<'b>,
+        rhs: llir::Operand<'b>,
+    ) {
+        self.inst_printer
+            .print_inst(llir::Instruction::or(result, lhs, rhs).unwrap().into())
+    }
+
     /// Prints an AND operation.
     fn print_and(
         &mut self,
-        result: llir::Register<'_>,
-        lhs: llir::Operand<'_>,
-        rhs: llir::Operand<'_>,
+        result: llir::Register<'b>,
+        lhs: llir::Operand<'b>,
+        rhs: llir::Operand<'b>,
     ) {
         self.inst_printer
             .print_inst(llir::Instruction::and(result, lhs, rhs).unwrap().into())
     }
 
     /// Prints a move instruction.
-    fn print_move(&mut self, result: llir::Register<'_>, operand: llir::Operand<'_>) {
+    fn print_move(&mut self, result: llir::Register<'b>, operand: llir::Operand<'b>) {
         self.inst_printer
             .print_inst(llir::Instruction::mov(result, operand).unwrap().into())
     }
@@ -125,13 +157,24 @@ impl<'a> InstPrinterHelper<'a> {
     /// Prints a scalar equals instruction.
     fn print_equals(
         &mut self,
-        result: llir::Register<'_>,
-        lhs: llir::Operand<'_>,
-        rhs: llir::Operand<'_>,
+        result: llir::Register<'b>,
+        lhs: llir::Operand<'b>,
+        rhs: llir::Operand<'b>,
     ) {
         self.inst_printer
             .print_inst(llir::Instruction::set_eq(result, lhs, rhs).unwrap().into())
     }
+
+    /// Prints a scalar not-equals instruction.
+    fn print_not_equals(
+        &mut self,
+        result: llir::Register<'b>,
+        lhs: llir::Operand<'b>,
+        rhs: llir::Operand<'b>,
+    ) {
+        self.inst_printer
+            .print_inst(llir::Instruction::set_ne(result, lhs, rhs).unwrap().into())
+    }
 }
 
 /// High-level printer struct delegating to an `InstPrinter` instance the role of printing actual
@@ -140,30 +183,55 @@ impl<'a> InstPrinterHelper<'a> {
 /// The printer's task is to lower high(er) level construct into instructions, which get passed to
 /// the underlying `InstPrinter`.
 pub struct Printer<'a, 'b> {
-    helper: InstPrinterHelper<'a>,
+    helper: InstPrinterHelper<'a, 'b>,
     namer: &'a mut NameMap<'b>,
+    /// Registers holding the offset of the stage to access in multi-stage memory blocks.
+    stage_offsets: FxHashMap<ir::MemId, llir::Register<'b>>,
+    /// Splits the producer of a pipelined loop around its prefetched loads: only the loads
+    /// are printed if the flag is set and only the other instructions otherwise.
+    prefetch_filter: Option<(&'b [ir::InstId], bool)>,
 }
 
 impl<'a, 'b> Printer<'a, 'b> {
     pub fn new(
-        inst_printer: &'a mut dyn InstPrinter,
+        inst_printer: &'a mut dyn InstPrinter<'b>,
         namer: &'a mut NameMap<'b>,
     ) -> Self {
         Printer {
             helper: InstPrinterHelper { inst_printer },
             namer,
+            stage_offsets: FxHashMap::default(),
+            prefetch_filter: None,
         }
     }
 
-    /// Change the side-effect guards so that the specified threads are disabled.
-    fn disable_threads<'d, I>(&mut self, threads: I)
+    /// Change the side-effect guards so that the specified threads are disabled. Threads along
+    /// dimensions guarded by a branch are not predicated: instead, returns the predicate
+    /// indicating which threads must branch around the code, if any.
+    fn disable_threads<'d, I>(
+        &mut self,
+        threads: I,
+        space: &SearchSpace,
+    ) -> Option<llir::Register<'b>>
     where
         I: Iterator<Item = &'d Dimension<'d>>,
     {
         let mut guard: Option<llir::Register<'_>> = None;
+        let mut skip: Option<llir::Register<'_>> = None;
         for dim in threads {
-            let new_guard = self.namer.gen_name(ir::Type::I(1));
             let index = self.namer.name_index(dim.id());
+            if space.domain().get_thread_guard(dim.id()) == ThreadGuard::BRANCH {
+                let new_skip = self.namer.gen_name(ir::Type::I(1));
+                self.helper
+                    .print_not_equals(new_skip, index.into(), 0i32.int_literal());
+                if let Some(skip) = skip {
+                    self.helper.print_or(skip, skip.into(), new_skip.into());
+                } else {
+                    skip = Some(new_skip);
+                }
+                continue;
+            }
+            let new_guard = self.namer.gen_name(ir::Type::I(1));
             self.helper
                 .print_equals(new_guard, index.into(), 0i32.int_literal());
             if let Some(guard) = guard {
@@ -173,6 +241,7 @@ impl<'a, 'b> Printer<'a, 'b> {
             };
         }
         self.namer.set_side_effect_guard(guard);
+        skip
     }
 
     pub fn privatise_global_block(&mut self, block: &MemoryRegion, fun: &Function) {
@@ -211,12 +280,25 @@ impl<'a, 'b> Printer<'a, 'b> {
         dim: &Dimension<'b>,
         cfgs: &'b [Cfg<'b>],
     ) {
+        let ind_vars = self.init_loop(dim);
+        let loop_label = self.namer.gen_label("LOOP");
+        self.helper.inst_printer.print_loop_begin(loop_label);
+        self.cfg_vec(fun, cfgs);
+        self.increment_loop(dim, &ind_vars);
+        let idx = self.namer.name_index(dim.id());
+        let lt_cond = self.namer.gen_name(ir::Type::I(1));
+        let size = self.namer.name_size(dim.size(), Type::I(32));
+        self.helper.print_lt_int(lt_cond, idx.into(), size);
+        self.helper.inst_printer.print_loop_end(loop_label, lt_cond);
+    }
+
+    /// Initializes the index and the induction levels of a sequential loop. Returns the
+    /// registers holding the induction levels.
+    fn init_loop(&mut self, dim: &Dimension<'b>) -> Vec<llir::Register<'b>> {
         let idx = self.namer.name_index(dim.id());
         self.helper.print_move(idx, 0i32.int_literal());
         let mut ind_var_vec = vec![];
-        let loop_label = self.namer.gen_label("LOOP");
-        let ind_levels = dim.induction_levels();
-        for level in ind_levels.iter() {
+        for level in dim.induction_levels() {
             let dim_id = level.increment.as_ref().map(|&(dim, _)| dim);
             let ind_var = self
                 .namer
@@ -236,22 +318,153 @@ impl<'a, 'b> Printer<'a, 'b> {
             };
             ind_var_vec.push(ind_var);
         }
-        self.helper.inst_printer.print_label(loop_label);
-        self.cfg_vec(fun, cfgs);
-        for (level, ind_var) in ind_levels.iter().zip_eq(ind_var_vec) {
+        ind_var_vec
+    }
+
+    /// Moves the index and the induction levels of a sequential loop to the next iteration.
+    fn increment_loop(&mut self, dim: &Dimension<'b>, ind_vars: &[llir::Register<'b>]) {
+        for (level, &ind_var) in dim.induction_levels().iter().zip_eq(ind_vars) {
             if let Some((_, ref increment)) = level.increment {
                 let step = self.namer.name_size(increment, level.t());
                 self.helper.print_add_int(ind_var, ind_var.into(), step);
             };
         }
+        let idx = self.namer.name_index(dim.id());
         self.helper
             .print_add_int(idx, idx.into(), 1i32.int_literal());
+    }
+
+    /// Prints a software-pipelined loop: a prologue running the producer of the first
+    /// iteration, a steady-state loop running the consumer of iteration `i` and the
+    /// producer of iteration `i+1` and an epilogue running the consumer of the last
+    /// iteration. Producers write the stage following the one read by consumers. The
+    /// prefetched loads of iteration `i+1` are issued before the consumer of iteration `i`.
+    fn pipelined_loop(
+        &mut self,
+        fun: &Function,
+        dim: &Dimension<'b>,
+        pipeline: &'b Pipeline<'b>,
+    ) {
+        // Allocate the registers holding the offsets of the stages read and written.
+        let offsets = pipeline
+            .stages
+            .iter()
+            .map(|&(mem, num_stages)| {
+                let t = self.namer.name_addr(mem).t();
+                let block = fun.mem_blocks().find(|b| b.id() == mem).unwrap();
+                let stage_size = block.local_size().as_int().unwrap();
+                let read = self.namer.gen_name(t);
+                let write = self.namer.gen_name(t);
+                (mem, read, write, stage_size, num_stages, t)
+            })
+            .collect_vec();
+        // Instructions of the producer are only filtered by the innermost pipeline.
+        let outer_filter = self.prefetch_filter.take();
+        // Prologue.
+        let ind_vars = self.init_loop(dim);
+        for &(mem, _, write, _, _, t) in &offsets {
+            let zero = 0i32.typed_int_literal(t).unwrap();
+            self.helper.print_move(write, zero);
+            self.stage_offsets.insert(mem, write);
+        }
+        self.cfg_vec(fun, &pipeline.producer);
+        // Skip the steady state if the loop has a single iteration.
+        let end_label = self.namer.gen_label("PIPELINE_END");
+        if dim.size().as_int().is_none() {
+            let skip = self.namer.gen_name(ir::Type::I(1));
+            let size = self.namer.name_size(dim.size(), Type::I(32));
+            self.helper.print_lt_int(skip, size, 2i32.int_literal());
+            self.helper
+                .inst_printer
+                .print_inst(llir::Instruction::jump(end_label).predicated(skip));
+        }
+        // Steady state.
+        let loop_label = self.namer.gen_label("LOOP");
+        self.helper.inst_printer.print_loop_begin(loop_label);
+        for &(mem, read, write, stage_size, num_stages, t) in &offsets {
+            self.helper.print_move(read, write.into());
+            let step = (stage_size as i32).typed_int_literal(t).unwrap();
+            self.helper.print_add_int(write, write.into(), step);
+            let wrap = self.namer.gen_name(ir::Type::I(1));
+            let total_size = ((stage_size * num_stages) as i32)
+                .typed_int_literal(t)
+                .unwrap();
+            self.helper.print_equals(wrap, write.into(), total_size);
+            let zero = 0i32.typed_int_literal(t).unwrap();
+            self.helper.inst_printer.print_inst(
+                llir::Instruction::mov(write, zero)
+                    .unwrap()
+                    .predicated(wrap),
+            );
+            self.stage_offsets.insert(mem, read);
+        }
+        if !pipeline.prefetch.is_empty() {
+            self.prefetch(fun, dim, &ind_vars, pipeline);
+        }
+        // The last group of threads of the consumer does not need to wait for the other
+        // threads: the next producer writes a different stage.
+        let (last, consumer) = pipeline.consumer.split_last().unwrap();
+        self.cfg_vec(fun, consumer);
+        if let Cfg::Threads(dims, ind_levels, inner) = last {
+            self.threads(fun, dims, ind_levels, inner, false);
+        } else {
+            panic!("pipelined loops must end with a group of threads");
+        }
+        self.increment_loop(dim, &ind_vars);
+        for &(mem, _, write, ..) in &offsets {
+            self.stage_offsets.insert(mem, write);
+        }
+        self.prefetch_filter = Some((&pipeline.prefetch, false));
+        self.cfg_vec(fun, &pipeline.producer);
+        self.prefetch_filter = None;
+        let idx = self.namer.name_index(dim.id());
+        let next_idx = self.namer.gen_name(Type::I(32));
+        self.helper
+            .print_add_int(next_idx, idx.into(), 1i32.int_literal());
         let lt_cond = self.namer.gen_name(ir::Type::I(1));
         let size = self.namer.name_size(dim.size(), Type::I(32));
-        self.helper.print_lt_int(lt_cond, idx.into(), size);
-        self.helper
-            .inst_printer
-            .print_inst(llir::Instruction::jump(loop_label).predicated(lt_cond));
+        self.helper.print_lt_int(lt_cond, next_idx.into(), size);
+        self.helper.inst_printer.print_loop_end(loop_label, lt_cond);
+        if dim.size().as_int().is_none() {
+            self.helper.inst_printer.print_label(end_label);
+        }
+        // Epilogue.
+        for &(mem, read, write, ..) in &offsets {
+            self.helper.print_move(read, write.into());
+            self.stage_offsets.insert(mem, read);
+        }
+        self.cfg_vec(fun, &pipeline.consumer);
+        for &(mem, ..) in &offsets {
+            self.stage_offsets.remove(&mem);
+        }
+        self.prefetch_filter = outer_filter;
+    }
+
+    /// Issues the prefetched loads of the next iteration of a pipelined loop. The index
+    /// and the induction levels of the loop are restored afterward.
+    fn prefetch(
+        &mut self,
+        fun: &Function,
+        dim: &Dimension<'b>,
+        ind_vars: &[llir::Register<'b>],
+        pipeline: &'b Pipeline<'b>,
+    ) {
+        let idx = self.namer.name_index(dim.id());
+        let saved = std::iter::once(idx)
+            .chain(ind_vars.iter().cloned())
+            .map(|reg| {
+                let tmp = self.namer.gen_name(reg.t());
+                self.helper.print_move(tmp, reg.into());
+                (reg, tmp)
+            })
+            .collect_vec();
+        self.increment_loop(dim, ind_vars);
+        self.prefetch_filter = Some((&pipeline.prefetch, true));
+        self.cfg_vec(fun, &pipeline.producer);
+        self.prefetch_filter = None;
+        for (reg, tmp) in saved {
+            self.helper.print_move(reg, tmp.into());
+        }
     }
 
     /// Prints an unroll loop - loop without jumps
@@ -361,6 +574,12 @@ impl<'a, 'b> Printer<'a, 'b> {
 
     fn cfg_vec(&mut self, fun: &Function, cfgs: &'b [Cfg<'b>]) {
         for c in cfgs.iter() {
+            // Only print the structures around prefetched loads when issuing them.
+            if let Some((prefetch, true)) = self.prefetch_filter {
+                if !c.instructions().any(|inst| prefetch.contains(&inst.id())) {
+                    continue;
+                }
+            }
             self.cfg(fun, c);
         }
     }
@@ -370,28 +589,84 @@ impl<'a, 'b> Printer<'a, 'b> {
         match c {
             Cfg::Root(cfgs) => self.cfg_vec(fun, cfgs),
             Cfg::Loop(dim, cfgs) => self.gen_loop(fun, dim, cfgs),
+            Cfg::Pipeline(dim, pipeline) => self.pipelined_loop(fun, dim, pipeline),
             Cfg::Threads(dims, ind_levels, inner) => {
-                // Disable inactive threads
-                self.disable_threads(
-                    dims.iter().zip_eq(fun.thread_dims().iter()).filter_map(
-                        |(&active_dim_id, dim)| {
-                            if active_dim_id.is_none() {
-                                Some(dim)
-                            } else {
-                                None
-                            }
-                        },
-                    ),
-                );
-                for level in ind_levels {
-                    self.parallel_induction_level(level);
+                // Prefetched loads do not synchronize with other threads.
+                let sync = match self.prefetch_filter {
+                    Some((_, true)) => false,
+                    _ => true,
+                };
+                self.threads(fun, dims, ind_levels, inner, sync)
+            }
+            Cfg::Instruction(vec_dims, inst) => {
+                if let Some((prefetch, only_prefetch)) = self.prefetch_filter {
+                    if prefetch.contains(&inst.id()) != only_prefetch {
+                        return;
+                    }
                 }
-                self.cfg_vec(fun, inner);
-                self.helper
-                    .inst_printer
-                    .print_inst(llir::Instruction::sync().into());
+                self.inst(vec_dims, inst, fun)
             }
-            Cfg::Instruction(vec_dims, inst) => self.inst(vec_dims, inst, fun),
+        }
+    }
+
+    /// Prints a group of threads, followed by a barrier if `sync` is set.
+    fn threads(
+        &mut self,
+        fun: &Function,
+        dims: &[Option<ir::DimId>],
+        ind_levels: &'b [InductionLevel<'b>],
+        inner: &'b [Cfg<'b>],
+        sync: bool,
+    ) {
+        // Disable inactive threads
+        let skip = self.disable_threads(
+            dims.iter().zip_eq(fun.thread_dims().iter()).filter_map(
+                |(&active_dim_id, dim)| {
+                    if active_dim_id.is_none() {
+                        Some(dim)
+                    } else {
+                        None
+                    }
+                },
+            ),
+            fun.space(),
+        );
+        for level in ind_levels {
+            self.parallel_induction_level(level);
+        }
+        let skip_label = skip.map(|skip| {
+            let label = self.namer.gen_label("SKIP");
+            self.helper.inst_printer.print_branch(label, skip);
+            label
+        });
+        self.cfg_vec(fun, inner);
+        if let Some(label) = skip_label {
+            self.helper.inst_printer.print_label(label);
+        }
+        if sync {
+            self.helper
+                .inst_printer
+                .print_inst(llir::Instruction::sync().into());
+        }
+    }
+
+    /// Returns the address accessed by a memory instruction, offset to the current stage
+    /// if it accesses a multi-stage memory block.
+    fn address(
+        helper: &mut InstPrinterHelper<'_, 'b>,
+        namer: &mut NameMap<'b>,
+        stage_offsets: &FxHashMap<ir::MemId, llir::Register<'b>>,
+        addr: &'b ir::Operand,
+        pattern: &ir::AccessPattern,
+    ) -> llir::Address<'b> {
+        let addr = namer.name_op(addr);
+        let offset = pattern.mem_block().and_then(|mem| stage_offsets.get(&mem));
+        if let Some(&offset) = offset {
+            let staged = namer.gen_name(offset.t());
+            helper.print_add_int(staged, addr, offset.into());
+            staged.into_operand().try_into().unwrap()
+        } else {
+            addr.try_into().unwrap()
         }
     }
 
@@ -487,27 +762,33 @@ impl<'a, 'b> Printer<'a, 'b> {
                     .into(),
                 )
             }
-            &op::Ld(ld_type, ref addr, ref pattern) => helper.inst_printer.print_inst(
-                llir::Instruction::load(
-                    llir::LoadSpec::from_ir(
-                        vector_factors,
-                        lower_type(ld_type, fun),
-                        access_pattern_space(pattern, fun.space()),
-                        inst.mem_flag().unwrap(),
+            &op::Ld(ld_type, ref addr, ref pattern) => {
+                let addr =
+                    Self::address(helper, self.namer, &self.stage_offsets, addr, pattern);
+                helper.inst_printer.print_inst(
+                    llir::Instruction::load(
+                        llir::LoadSpec::from_ir(
+                            vector_factors,
+                            lower_type(ld_type, fun),
+                            access_pattern_space(pattern, fun.space()),
+                            inst.mem_flag().unwrap(),
+                        )
+                        .unwrap(),
+                        self.namer.vector_inst(vector_levels, inst.id()),
+                        addr,
                     )
-                    .unwrap(),
-                    self.namer.vector_inst(vector_levels, inst.id()),
-                    self.namer.name_op(addr).try_into().unwrap(),
+                    .unwrap()
+                    .into(),
                 )
-                .unwrap()
-                .into(),
-            ),
+            }
             op::St(addr, val, _, pattern) => {
                 let guard = if inst.has_side_effects() {
                     self.namer.side_effect_guard()
                 } else {
                     None
                 };
+                let addr =
+                    Self::address(helper, self.namer, &self.stage_offsets, addr, pattern);
                 helper.inst_printer.print_inst(
                     llir::Instruction::store(
                         llir::StoreSpec::from_ir(
@@ -517,13 +798,39 @@ impl<'a, 'b> Printer<'a, 'b> {
                             inst.mem_flag().unwrap(),
                         )
                         .unwrap(),
-                        self.namer.name_op(addr).try_into().unwrap(),
+                        addr,
                         self.namer.vector_operand(vector_levels, val),
                     )
                     .unwrap()
                     .predicated(guard),
                 )
             }
+            op::MmaSync(a, b, c, _) => {
+                let spec = llir::MmaSpec {
+                    ab_t: lower_type(a.t(), fun),
+                    cd_t: lower_type(c.t(), fun),
+                };
+                // The right-hand side does not depend on the outer fragment dimension, so
+                // its second half repeats the first.
+                let b = match self.namer.vector_operand(vector_levels, b) {
+                    llir::ScalarOrVector::Vector(mut b) => {
+                        b.truncate(2);
+                        llir::ScalarOrVector::Vector(b)
+                    }
+                    b => b,
+                };
+                helper.inst_printer.print_inst(
+                    llir::Instruction::mma(
+                        spec,
+                        self.namer.vector_inst(vector_levels, inst.id()),
+                        self.namer.vector_operand(vector_levels, a),
+                        b,
+                        self.namer.vector_operand(vector_levels, c),
+                    )
+                    .unwrap()
+                    .into(),
+                )
+            }
             op @ op::TmpLd(..) | op @ op::TmpSt(..) => {
                 panic!("non-printable instruction {:?}", op)
             }
@@ -0,0 +1,151 @@
+//! Static estimation of the resources used by the generated code.
+use crate::codegen::{Cfg, Function, Instruction, OptimizationStats};
+use crate::ir::{self, op};
+use crate::search_space::{access_pattern_space, register_pressure, DimKind, MemSpace};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Resources statically used by an implementation, to compare candidates without
+/// reading the generated code.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ResourceReport {
+    /// Number of 32-bit registers used by each thread
+    pub num_registers: u32,
+    /// Number of bytes of shared memory allocated by each block, including all the stages
+    pub shared_mem_bytes: u32,
+    /// Number of barriers in the code, once loops are unrolled
+    pub num_barriers: u32,
+    /// Number of instructions of the kernel, once loops are unrolled
+    pub code_size: u32,
+    /// Number of instructions of the kernel, once loops are unrolled, by operator
+    pub instruction_mix: BTreeMap<String, u32>,
+    /// Number of blocks that can run concurrently on a single multiprocessor, if the
+    /// device reports it
+    pub occupancy: Option<u32>,
+    /// Effect of the optimizations applied to the low-level instructions of the kernel
+    /// body, if the device reports it
+    pub optimization: Option<OptimizationStats>,
+}
+
+impl ResourceReport {
+    /// Computes the resources used by a `Function` from its memory blocks and its `Cfg`.
+    /// Instructions computing induction variables are not counted.
+    pub fn new(function: &Function) -> Self {
+        let shared_mem_bytes = function
+            .mem_blocks()
+            .filter(|block| block.mem_space() == MemSpace::SHARED)
+            .map(|block| block.alloc_size().as_int().unwrap())
+            .sum();
+        let mut report = ResourceReport {
+            num_registers: register_pressure(function.space()),
+            shared_mem_bytes,
+            ..ResourceReport::default()
+        };
+        report.add_cfg(function, function.cfg(), 1, true);
+        report
+    }
+
+    /// Accounts for the instructions and barriers of `cfg`, printed `factor` times.
+    /// `sync` indicates if groups of threads are followed by a barrier.
+    fn add_cfg(&mut self, function: &Function, cfg: &Cfg, factor: u32, sync: bool) {
+        match cfg {
+            Cfg::Root(body) => self.add_body(function, body, factor),
+            Cfg::Loop(dim, body) => {
+                let factor = if dim.kind() == DimKind::UNROLL {
+                    factor * dim.size().as_int().unwrap()
+                } else {
+                    factor
+                };
+                self.add_body(function, body, factor)
+            }
+            Cfg::Pipeline(_, pipeline) => {
+                // The producer is printed in the prologue and in the steady-state loop, and
+                // the consumer in the steady-state loop and in the epilogue.  The last group
+                // of threads of the consumer is only followed by a barrier in the epilogue.
+                self.add_body(function, &pipeline.producer, 2 * factor);
+                let (last, consumer) = pipeline.consumer.split_last().unwrap();
+                self.add_body(function, consumer, 2 * factor);
+                self.add_cfg(function, last, factor, false);
+                self.add_cfg(function, last, factor, true);
+            }
+            Cfg::Threads(_, _, body) => {
+                self.add_body(function, body, factor);
+                if sync {
+                    self.num_barriers += factor;
+                }
+            }
+            Cfg::Instruction(_, inst) => {
+                self.code_size += factor;
+                let name = operator_name(function, inst);
+                *self.instruction_mix.entry(name).or_insert(0) += factor;
+            }
+        }
+    }
+
+    /// Accounts for a list of `Cfg`, printed `factor` times.
+    fn add_body(&mut self, function: &Function, body: &[Cfg], factor: u32) {
+        for cfg in body {
+            self.add_cfg(function, cfg, factor, true);
+        }
+    }
+}
+
+impl fmt::Display for ResourceReport {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "registers: {}", self.num_registers)?;
+        writeln!(fmt, "shared memory: {} bytes", self.shared_mem_bytes)?;
+        writeln!(fmt, "barriers: {}", self.num_barriers)?;
+        if let Some(occupancy) = self.occupancy {
+            writeln!(fmt, "occupancy: {} blocks", occupancy)?;
+        }
+        writeln!(fmt, "instructions: {}", self.code_size)?;
+        for (name, count) in &self.instruction_mix {
+            writeln!(fmt, "  {}: {}", name, count)?;
+        }
+        if let Some(optimization) = &self.optimization {
+            writeln!(fmt, "optimizations: {}", optimization)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name under which an instruction is counted in the instruction mix: the
+/// operator followed by the memory space it accesses or the type it operates on.
+fn operator_name(function: &Function, inst: &Instruction) -> String {
+    let space = function.space();
+    let mem_space_name = |mem_space: MemSpace| {
+        if mem_space == MemSpace::SHARED {
+            "shared"
+        } else {
+            "global"
+        }
+    };
+    let block_space = |mem: ir::MemId| mem_space_name(space.domain().get_mem_space(mem));
+    let name = match inst.operator() {
+        op::BinOp(op, ..) => op.to_string(),
+        op::UnaryOp(ir::UnaryOp::Mov, _) => "mov".to_string(),
+        op::UnaryOp(ir::UnaryOp::Cast(_), _) => "cast".to_string(),
+        op::UnaryOp(ir::UnaryOp::Exp(_), _) => "exp".to_string(),
+        op::Mul(..) => "mul".to_string(),
+        op::Mad(..) => "mad".to_string(),
+        op::MmaSync(..) => "mma".to_string(),
+        op::Ld(_, _, pattern) => {
+            let mem_space = access_pattern_space(pattern, space);
+            return format!("ld.{}", mem_space_name(mem_space));
+        }
+        op::St(_, _, _, pattern) => {
+            let mem_space = access_pattern_space(pattern, space);
+            return format!("st.{}", mem_space_name(mem_space));
+        }
+        op::TmpLd(_, mem) => return format!("ld.{}", block_space(*mem)),
+        op::TmpSt(_, mem) => return format!("st.{}", block_space(*mem)),
+    };
+    match inst.t() {
+        Some(t) => {
+            let device = space.ir_instance().device();
+            format!("{}.{}", name, device.lower_type(t, space).unwrap_or(t))
+        }
+        None => name,
+    }
+}
@@ -117,6 +117,148 @@ int_scalar_argument!(unsafe impl ScalarArgument for i16 [(0) .. (100)]);
 int_scalar_argument!(unsafe impl ScalarArgument for i32 [(0) .. (100)]);
 int_scalar_argument!(unsafe impl ScalarArgument for i64 [(0) .. (100)]);
 
+/// A 16-bit IEEE 754 floating point value, stored as its bit pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct F16(pub u16);
+
+impl F16 {
+    /// Converts a single-precision value, rounding to the nearest even value.
+    pub fn from_f32(x: f32) -> Self {
+        let bits = x.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exp = ((bits >> 23) & 0xff) as i32;
+        let man = bits & 0x7f_ffff;
+        if exp == 0xff {
+            // Infinite and NaN values.
+            let nan = if man == 0 { 0 } else { 0x200 };
+            return F16(sign | 0x7c00 | nan);
+        }
+        let half_exp = exp - 127 + 15;
+        if half_exp >= 0x1f {
+            return F16(sign | 0x7c00);
+        }
+        let (man, shift, half) = if half_exp <= 0 {
+            // The value is either a subnormal half or rounds to zero.
+            if half_exp < -10 {
+                return F16(sign);
+            }
+            let man = man | 0x80_0000;
+            let shift = (14 - half_exp) as u32;
+            (man, shift, man >> shift)
+        } else {
+            (man, 13, (half_exp as u32) << 10 | man >> 13)
+        };
+        let round = (man >> (shift - 1)) & 1 == 1;
+        let sticky = man & ((1 << (shift - 1)) - 1) != 0;
+        let half = if round && (sticky || half & 1 == 1) {
+            half + 1
+        } else {
+            half
+        };
+        // A carry out of the mantissa correctly increments the exponent.
+        F16(sign | half as u16)
+    }
+
+    /// Converts the value to single-precision, which is always exact.
+    pub fn to_f32(self) -> f32 {
+        let sign = u32::from(self.0 & 0x8000) << 16;
+        let exp = u32::from((self.0 >> 10) & 0x1f);
+        let man = u32::from(self.0 & 0x3ff);
+        let bits = match exp {
+            0 => {
+                // Zero and subnormal values are scaled from their integer mantissa.
+                let value = man as f32 / (1 << 24) as f32;
+                return if sign == 0 { value } else { -value };
+            }
+            0x1f => sign | 0x7f80_0000 | man << 13,
+            _ => sign | (exp + 127 - 15) << 23 | man << 13,
+        };
+        f32::from_bits(bits)
+    }
+}
+
+impl std::fmt::Display for F16 {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.to_f32(), fmt)
+    }
+}
+
+unsafe impl ScalarArgument for F16 {
+    fn t() -> ir::Type {
+        ir::Type::F(16)
+    }
+
+    fn get_type(&self) -> ir::Type {
+        Self::t()
+    }
+
+    fn raw_ptr(&self) -> *const libc::c_void {
+        self as *const F16 as *const libc::c_void
+    }
+
+    fn as_operand<L>(&self) -> ir::Operand<L> {
+        let value = num::rational::Ratio::from_float(self.to_f32()).unwrap();
+        ir::Operand::new_float((value, 16))
+    }
+
+    fn gen_random<R: Rng>(rng: &mut R) -> Self {
+        F16::from_f32(rng.gen_range(0., 1.))
+    }
+}
+
+/// A 16-bit brain floating point value, stored as its bit pattern.  It has the exponent range
+/// of single-precision values, with 7 bits of mantissa.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[repr(transparent)]
+pub struct Bf16(pub u16);
+
+impl Bf16 {
+    /// Converts a single-precision value, rounding to the nearest even value.
+    pub fn from_f32(x: f32) -> Self {
+        let bits = x.to_bits();
+        if x.is_nan() {
+            // Keep the NaN quiet, even if the truncated mantissa is zero.
+            return Bf16((bits >> 16) as u16 | 0x40);
+        }
+        let rounding = 0x7fff + ((bits >> 16) & 1);
+        Bf16((bits.wrapping_add(rounding) >> 16) as u16)
+    }
+
+    /// Converts the value to single-precision, which is always exact.
+    pub fn to_f32(self) -> f32 {
+        f32::from_bits(u32::from(self.0) << 16)
+    }
+}
+
+impl std::fmt::Display for Bf16 {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.to_f32(), fmt)
+    }
+}
+
+unsafe impl ScalarArgument for Bf16 {
+    fn t() -> ir::Type {
+        ir::Type::Bf16
+    }
+
+    fn get_type(&self) -> ir::Type {
+        Self::t()
+    }
+
+    fn raw_ptr(&self) -> *const libc::c_void {
+        self as *const Bf16 as *const libc::c_void
+    }
+
+    fn as_operand<L>(&self) -> ir::Operand<L> {
+        ir::Operand::Bf16(self.0)
+    }
+
+    fn gen_random<R: Rng>(rng: &mut R) -> Self {
+        Bf16::from_f32(rng.gen_range(0., 1.))
+    }
+}
+
 /// Represents an array on the device.
 pub trait ArrayArgument: Send + Sync {
     // TODO(cc_perf): return a `Cow` instead of a `Vec` to avoid copying when testing
@@ -155,3 +297,42 @@ pub trait ArrayArgumentExt: ArrayArgument {
 }
 
 impl<A: ?Sized> ArrayArgumentExt for A where A: ArrayArgument {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ensures half-precision conversions round to the nearest even value.
+    #[test]
+    fn half_conversions() {
+        assert_eq!(F16::from_f32(1.0), F16(0x3c00));
+        assert_eq!(F16::from_f32(-2.0), F16(0xc000));
+        assert_eq!(F16::from_f32(65504.0), F16(0x7bff));
+        assert_eq!(F16::from_f32(1e6), F16(0x7c00));
+        assert_eq!(F16::from_f32(2f32.powi(-24)), F16(0x0001));
+        assert_eq!(F16::from_f32(2f32.powi(-26)), F16(0x0000));
+        // 1 + 2^-11 is halfway between 1 and the next half, and rounds to the even one.
+        assert_eq!(F16::from_f32(1.0 + 2f32.powi(-11)), F16(0x3c00));
+        assert_eq!(F16::from_f32(1.0 + 3.0 * 2f32.powi(-11)), F16(0x3c02));
+        for &bits in &[0x0001, 0x03ff, 0x0400, 0x3555, 0x7bff, 0xfbff] {
+            assert_eq!(F16::from_f32(F16(bits).to_f32()), F16(bits));
+        }
+
+        assert_eq!(Bf16::from_f32(1.0), Bf16(0x3f80));
+        assert_eq!(Bf16::from_f32(1.0 + 2f32.powi(-8)), Bf16(0x3f80));
+        assert_eq!(Bf16::from_f32(1.0 + 3.0 * 2f32.powi(-8)), Bf16(0x3f82));
+        assert_eq!(Bf16(0xc0a0).to_f32(), -5.0);
+        assert!(Bf16::from_f32(std::f32::NAN).to_f32().is_nan());
+    }
+
+    /// Ensures bf16 constants keep their bit pattern and type.
+    #[test]
+    fn bf16_operand() {
+        let operand: ir::Operand<()> = Bf16(0x3f80).as_operand();
+        assert_eq!(operand.t(), ir::Type::Bf16);
+        match operand {
+            ir::Operand::Bf16(bits) => assert_eq!(bits, 0x3f80),
+            _ => panic!("expected a bf16 literal, got {}", operand),
+        }
+    }
+}
@@ -4,13 +4,13 @@ pub mod fake;
 mod argument;
 mod context;
 
-pub use self::argument::{ArrayArgument, ArrayArgumentExt, ScalarArgument};
+pub use self::argument::{ArrayArgument, ArrayArgumentExt, Bf16, ScalarArgument, F16};
 pub use self::context::{
     ArgMap, ArgMapExt, AsyncCallback, AsyncEvaluator, Context, EvalMode, KernelEvaluator,
     Stabilizer,
 };
 
-use crate::codegen::Function;
+use crate::codegen::{Function, ResourceReport};
 use crate::ir;
 use crate::model::{self, HwPressure, Nesting};
 use crate::search_space::*;
@@ -61,6 +61,11 @@ pub trait Device: Send + Sync + 'static {
     /// Returns the pressure produced by a single iteration of a loop and the latency
     /// overhead of iterations.
     fn loop_iter_pressure(&self, kind: DimKind) -> (HwPressure, HwPressure);
+    /// Returns the pressure caused by a barrier synchronizing the threads of a block.
+    /// Defaults to no pressure.
+    fn barrier_pressure(&self) -> HwPressure {
+        HwPressure::new(0f64, self.bottlenecks().iter().map(|_| 0f64).collect())
+    }
     /// Returns the processing rates of a single thread, in units/ns
     fn thread_rates(&self) -> HwPressure;
     /// Returns the processing rates of a single block, in units/ns.
@@ -90,6 +95,30 @@ pub trait Device: Send + Sync + 'static {
     /// information is not yet specified.
     fn lower_type(&self, t: ir::Type, space: &SearchSpace) -> Option<ir::Type>;
 
+    /// Returns the number of 32-bit registers a thread can use without spilling, if the
+    /// device limits it.
+    fn max_registers_per_thread(&self) -> Option<u32> {
+        None
+    }
+
+    /// Indicates if the device supports warp-level matrix multiply-accumulate operators
+    /// on inputs of the given type.
+    fn supports_mma(&self, _: ir::Type) -> bool {
+        false
+    }
+
+    /// Indicates if the device supports additions, substractions and multiplications on
+    /// values of the given type.
+    fn supports_add_mul(&self, _: ir::Type) -> bool {
+        true
+    }
+
+    /// Returns the resources statically used by a `Function`.  Devices can override it to
+    /// account for the way they allocate resources.
+    fn resource_report(&self, function: &Function) -> ResourceReport {
+        ResourceReport::new(function)
+    }
+
     /// Builds and outputs a constrained IR instance.
     fn gen_code(&self, implementation: &SearchSpace, out: &mut dyn Write) {
         let code = Function::build(implementation);
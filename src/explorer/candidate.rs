@@ -92,6 +92,9 @@ impl Candidate {
             self
         )?;
 
+        self.space
+            .dump_resources(context, path.as_ref().join("resources.json"))?;
+
         self.space.dump_code(context, path.as_ref().join("code"))
     }
 
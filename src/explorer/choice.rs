@@ -181,6 +181,25 @@ pub fn list<'a>(
                         })
                     }))
                 }
+                ChoiceGroup::NumStages => {
+                    let mem_choices = fun.mem_blocks().flat_map(move |block| {
+                        let stages = space.domain().get_num_stages(block.mem_id());
+                        gen_choice(stages.list(), &|n| {
+                            Action::NumStages(block.mem_id(), n)
+                        })
+                    });
+                    let var_choices = fun.variables().flat_map(move |var| {
+                        let stages = space.domain().get_var_num_stages(var.id());
+                        gen_choice(stages.list(), &|n| Action::VarNumStages(var.id(), n))
+                    });
+                    Box::new(mem_choices.chain(var_choices))
+                }
+                ChoiceGroup::ThreadGuard => {
+                    Box::new(fun.static_dims().flat_map(move |dim| {
+                        let guards = space.domain().get_thread_guard(dim.id());
+                        gen_choice(guards.list(), &|g| Action::ThreadGuard(dim.id(), g))
+                    }))
+                }
                 ChoiceGroup::InstFlag => {
                     Box::new(fun.mem_insts().flat_map(move |inst| {
                         let flags = space.domain().get_inst_flag(inst.id()).list();
@@ -15,7 +15,9 @@ use num_cpus;
 use serde::{Deserialize, Serialize};
 use utils::{tfrecord, unwrap};
 
+use crate::explorer::eval_cache::EvalCache;
 use crate::explorer::eventlog::EventLog;
+use crate::explorer::pareto::Objective;
 
 /// Stores the configuration of the exploration.
 #[derive(Clone, Serialize, Deserialize)]
@@ -30,6 +32,10 @@ pub struct Config {
     /// Name of the file in which to store the binary event log.  If none is provided, the event
     /// log is not saved.
     pub event_log: Option<String>,
+    /// Name of the file in which to cache the runtimes of evaluated implementations.  The cache is
+    /// loaded before the search starts and saved when it ends, so that it can be shared across
+    /// searches on the same kernel and device.  If none is provided, no cache is used.
+    pub eval_cache: Option<String>,
     /// Number of exploration threads.
     pub num_workers: usize,
     /// Indicates the search must be stopped if a candidate with an execution time better
@@ -51,6 +57,20 @@ pub struct Config {
     ///
     /// Only supported by the MCTS search algorithm.
     pub restart_every_n_evals: Option<usize>,
+    /// Static resources to minimize in addition to the runtime.  The monitor keeps the Pareto
+    /// front of the implementations evaluated for those objectives and saves it in
+    /// `pareto_front.json`.  If empty (the default), the front only holds the best candidate.
+    pub pareto_objectives: Vec<Objective>,
+    /// Paths to implementations of related kernels (e.g. the same kernel on other sizes) to
+    /// warm-start the search from.  Each path is either an `actions.json` file or an event log,
+    /// in which case the best implementation of the log is used.  Their decisions are translated
+    /// onto the new search space and evaluated first.
+    ///
+    /// Only supported by the MCTS search algorithm.
+    pub warm_start: Vec<String>,
+    /// Address on which to serve the live metrics of the search over HTTP, for instance
+    /// `127.0.0.1:9898`.  If none is provided (the default), the metrics are not served.
+    pub metrics_address: Option<String>,
     /// Exploration algorithm to use. Needs to be last for TOML serialization, because it is a table.
     pub algorithm: SearchAlgorithm,
 }
@@ -117,6 +137,14 @@ impl Config {
             Ok(None)
         }
     }
+
+    pub fn load_eval_cache(&self) -> io::Result<Option<EvalCache>> {
+        if let Some(eval_cache) = &self.eval_cache {
+            EvalCache::load(self.output_path(eval_cache)?).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
 }
 
 impl fmt::Display for Config {
@@ -131,6 +159,7 @@ impl Default for Config {
             output_dir: ".".to_string(),
             log_file: "watch.log".to_string(),
             event_log: None,
+            eval_cache: None,
             check_all: false,
             num_workers: num_cpus::get(),
             algorithm: SearchAlgorithm::default(),
@@ -139,6 +168,9 @@ impl Default for Config {
             max_evaluations: None,
             distance_to_best: None,
             restart_every_n_evals: None,
+            pareto_objectives: vec![],
+            warm_start: vec![],
+            metrics_address: None,
         }
     }
 }
@@ -171,6 +203,15 @@ pub struct BanditConfig {
     /// Indicates whether we should backtrack locally when a dead-end is encountered.  If false,
     /// dead-ends will cause a restart from the root.
     pub backtrack_deadends: bool,
+    /// Number of virtual visits the tree policy accounts for on an edge for each descent which is
+    /// in progress across it, i.e. which selected the edge but whose evaluation was not
+    /// backpropagated yet.  Higher values spread concurrent workers across more branches instead of
+    /// having them all follow the same promising path until evaluations come back.  Zero disables
+    /// virtual losses; only the TAG, UCT and round-robin tree policies are affected.
+    pub virtual_loss: usize,
+    /// Probability for a descent to follow the decisions of a random warm-start implementation
+    /// wherever they apply, once each of them has been evaluated.  See [Config::warm_start].
+    pub warm_start_bias: f64,
     /// Indicates how to select between nodes of the search tree when none of their
     /// children have been evaluated.
     pub new_nodes_order: NewNodeOrder,
@@ -320,6 +361,8 @@ impl Default for BanditConfig {
             tree_policy: TreePolicy::default(),
             choice_ordering: ChoiceOrdering::default(),
             backtrack_deadends: false,
+            virtual_loss: 0,
+            warm_start_bias: 0.1,
         }
     }
 }
@@ -357,6 +400,8 @@ pub enum ChoiceGroup {
     DimMap,
     Order,
     MemSpace,
+    NumStages,
+    ThreadGuard,
     InstFlag,
     Threads,
     ThreadSize,
@@ -383,6 +428,8 @@ impl fmt::Display for ChoiceGroup {
             DimMap => "dim_map",
             Order => "order",
             MemSpace => "mem_space",
+            NumStages => "num_stages",
+            ThreadGuard => "thread_guard",
             InstFlag => "inst_flag",
             Threads => "threads",
             ThreadSize => "thread_size",
@@ -417,6 +464,8 @@ impl FromStr for ChoiceGroup {
             "dim_map" => DimMap,
             "order" => Order,
             "mem_space" => MemSpace,
+            "num_stages" => NumStages,
+            "thread_guard" => ThreadGuard,
             "inst_flag" => InstFlag,
             "threads" => Threads,
             "thread_size" => ThreadSize,
@@ -440,13 +489,15 @@ impl<'a> IntoIterator for &'a ChoiceOrdering {
     }
 }
 
-pub(super) const DEFAULT_ORDERING: [ChoiceGroup; 7] = [
+pub(super) const DEFAULT_ORDERING: [ChoiceGroup; 9] = [
     ChoiceGroup::LowerLayout,
     ChoiceGroup::Size,
     ChoiceGroup::DimKind,
     ChoiceGroup::DimMap,
     ChoiceGroup::MemSpace,
+    ChoiceGroup::NumStages,
     ChoiceGroup::Order,
+    ChoiceGroup::ThreadGuard,
     ChoiceGroup::InstFlag,
 ];
 
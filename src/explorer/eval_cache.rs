@@ -0,0 +1,185 @@
+//! A persistent cache of the runtimes of fully specified implementations.
+//!
+//! Different paths in the search tree, as well as repeated searches on the same kernel, often
+//! end up with the same implementation.  The cache maps a hash of the code generated for an
+//! implementation (and of the device and the sizes it runs with) to its measured runtime, so that
+//! it only needs to be compiled and benchmarked once.
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use fxhash::{FxHashMap, FxHasher64};
+use log::warn;
+use rpds::List;
+
+use crate::codegen;
+use crate::device::Context;
+use crate::explorer::candidate::Candidate;
+use crate::explorer::choice::ActionEx;
+use crate::explorer::pareto::Evaluation;
+use crate::explorer::store::Store;
+use crate::search_space::SearchSpace;
+
+/// Caches the runtimes of implementations, keyed by a hash of their generated code.
+pub struct EvalCache {
+    /// File from which the cache was loaded and to which it is saved.
+    path: PathBuf,
+    entries: Mutex<FxHashMap<u64, f64>>,
+    num_hits: AtomicUsize,
+    num_misses: AtomicUsize,
+}
+
+impl EvalCache {
+    /// Loads the cache from a file.  Starts with an empty cache if the file does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        let entries = if path.exists() {
+            serde_json::from_reader(BufReader::new(File::open(&path)?))
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+        } else {
+            FxHashMap::default()
+        };
+        Ok(EvalCache {
+            path,
+            entries: Mutex::new(entries),
+            num_hits: AtomicUsize::new(0),
+            num_misses: AtomicUsize::new(0),
+        })
+    }
+
+    /// Saves the cache to the file it was loaded from.
+    pub fn save(&self) -> io::Result<()> {
+        let entries = self.entries.lock().expect("entries: poisoned");
+        serde_json::to_writer(BufWriter::new(File::create(&self.path)?), &*entries)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+
+    /// Computes the key of a fully specified implementation.  Two implementations with the same
+    /// key generate the same code for the same device, and run with the same parameter sizes.
+    pub fn key(context: &dyn Context, space: &SearchSpace) -> u64 {
+        let device = context.device();
+        let mut code = Vec::new();
+        device.print(&codegen::Function::build(space), &mut code);
+        let mut hasher = FxHasher64::default();
+        hasher.write(device.name().as_bytes());
+        hasher.write(&code);
+        for param in &space.ir_instance().signature().params {
+            param.name.hash(&mut hasher);
+            context.param_as_size(&param.name).hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Returns the cached runtime for the given key, if any.
+    pub fn get(&self, key: u64) -> Option<f64> {
+        let eval = self
+            .entries
+            .lock()
+            .expect("entries: poisoned")
+            .get(&key)
+            .cloned();
+        if eval.is_some() {
+            self.num_hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.num_misses.fetch_add(1, Ordering::Relaxed);
+        }
+        eval
+    }
+
+    /// Records the runtime of an implementation.  Only finite runtimes are recorded, as infinite
+    /// ones denote evaluations that were cut or failed.
+    pub fn insert(&self, key: u64, eval: f64) {
+        if eval.is_finite() {
+            self.entries
+                .lock()
+                .expect("entries: poisoned")
+                .insert(key, eval);
+        }
+    }
+
+    /// Number of cached runtimes.
+    pub fn num_entries(&self) -> usize {
+        self.entries.lock().expect("entries: poisoned").len()
+    }
+
+    /// Number of lookups that found a cached runtime.
+    pub fn num_hits(&self) -> usize {
+        self.num_hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of lookups that did not find a cached runtime.
+    pub fn num_misses(&self) -> usize {
+        self.num_misses.load(Ordering::Relaxed)
+    }
+}
+
+/// A candidate store along with the evaluation cache of its implementations.  The statistics of
+/// the cache are displayed with the ones of the store.
+pub(super) struct CachedStore<S> {
+    store: S,
+    eval_cache: Option<EvalCache>,
+}
+
+impl<S> CachedStore<S> {
+    pub fn new(store: S, eval_cache: Option<EvalCache>) -> Self {
+        CachedStore { store, eval_cache }
+    }
+
+    /// Returns the evaluation cache, if any.
+    pub fn eval_cache(&self) -> Option<&EvalCache> {
+        self.eval_cache.as_ref()
+    }
+}
+
+impl<S: Store> Store for CachedStore<S> {
+    type PayLoad = S::PayLoad;
+    type Event = S::Event;
+
+    fn update_cut(&self, new_cut: f64) {
+        self.store.update_cut(new_cut)
+    }
+
+    fn stop_exploration(&self) {
+        self.store.stop_exploration()
+    }
+
+    fn commit_evaluation(
+        &self,
+        actions: &List<ActionEx>,
+        payload: Self::PayLoad,
+        eval: &Evaluation,
+    ) {
+        self.store.commit_evaluation(actions, payload, eval)
+    }
+
+    fn explore(&self, context: &dyn Context) -> Option<(Candidate, Self::PayLoad)> {
+        self.store.explore(context)
+    }
+
+    fn print_stats(&self) {
+        self.store.print_stats();
+        if let Some(eval_cache) = &self.eval_cache {
+            let (num_hits, num_misses) = (eval_cache.num_hits(), eval_cache.num_misses());
+            let num_lookups = num_hits + num_misses;
+            warn!(
+                "Evaluation cache: {} hits out of {} lookups ({:.2}%), {} entries in {}",
+                num_hits,
+                num_lookups,
+                if num_lookups == 0 {
+                    0.
+                } else {
+                    100. * num_hits as f64 / num_lookups as f64
+                },
+                eval_cache.num_entries(),
+                eval_cache.path.display(),
+            );
+        }
+    }
+
+    fn restart(&self) {
+        self.store.restart()
+    }
+}
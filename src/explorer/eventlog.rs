@@ -1,11 +1,36 @@
+//! Event logs of the search, stored as a sequence of bincode-encoded records in a tfrecord file,
+//! optionally compressed.
+//!
+//! Event logs start with a header record holding the version of the schema of the following
+//! records.  The successive versions are:
+//! - 0: `legacy_log::TreeEvent`s, without header;
+//! - 1: `mcts::Message`s, without header;
+//! - 2: the header followed by `mcts::Message`s.
+//!
+//! Readers should use `EventLog::read_messages`, which converts older versions to the current
+//! `mcts::Message` schema.
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::{self, Read, Write};
 use std::path::Path;
 
+use crate::explorer::legacy_log;
+use crate::explorer::mcts::Message;
 use flate2::{read, write, Compression};
 use utils::tfrecord;
 
+/// Magic bytes starting the header record of versioned event logs.
+const MAGIC: &[u8; 8] = b"TLMNELOG";
+
+/// Version of the schema of the event logs written by `EventLog::create`.
+pub const CURRENT_VERSION: u32 = 2;
+
+/// The records of an event log, after the header.
+pub type Records = std::iter::Chain<
+    std::option::IntoIter<io::Result<Vec<u8>>>,
+    tfrecord::Records<EventLog>,
+>;
+
 #[allow(clippy::large_enum_variant)]
 enum EventLogInner {
     Raw(File),
@@ -33,6 +58,7 @@ impl EventLog {
         EventLog { inner }
     }
 
+    /// Opens an event log for reading raw records, including the header if any.
     pub fn open<P: AsRef<Path>>(path: P) -> io::Result<tfrecord::Reader<Self>> {
         let extension = path
             .as_ref()
@@ -46,6 +72,7 @@ impl EventLog {
         )))
     }
 
+    /// Creates an event log and writes the header for the current version.
     pub fn create<P: AsRef<Path>>(path: P) -> io::Result<tfrecord::Writer<Self>> {
         let extension = path
             .as_ref()
@@ -53,10 +80,86 @@ impl EventLog {
             .and_then(OsStr::to_str)
             .map(str::to_string);
         let file = File::create(path)?;
-        Ok(tfrecord::Writer::from_writer(Self::wrap(
+        let mut writer = tfrecord::Writer::from_writer(Self::wrap(
             file,
             extension.as_ref().map(String::as_ref),
-        )))
+        ));
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&CURRENT_VERSION.to_le_bytes());
+        writer.write_record(&header)?;
+        Ok(writer)
+    }
+
+    /// Opens an event log and returns the version of its schema along with its records,
+    /// without the header.
+    pub fn open_versioned<P: AsRef<Path>>(path: P) -> io::Result<(u32, Records)> {
+        let mut records = Self::open(path)?.records();
+        let (version, first) = match records.next().transpose()? {
+            Some(ref record) if record.starts_with(MAGIC) => {
+                let mut version = [0; 4];
+                version.copy_from_slice(record.get(MAGIC.len()..).ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "truncated header")
+                })?);
+                (u32::from_le_bytes(version), None)
+            }
+            // Logs of `mcts::Message`s always start with the creation of the root.
+            Some(record) => match bincode::deserialize(&record) {
+                Ok(Message::Node { parent: None, .. }) => (1, Some(record)),
+                _ => (0, Some(record)),
+            },
+            None => (CURRENT_VERSION, None),
+        };
+        Ok((version, first.map(Ok).into_iter().chain(records)))
+    }
+
+    /// Reads the messages of an event log, converting them from older schema versions if
+    /// needed.
+    pub fn read_messages<P: AsRef<Path>>(
+        path: P,
+    ) -> io::Result<Box<dyn Iterator<Item = io::Result<Message>>>> {
+        let (version, records) = Self::open_versioned(path)?;
+        match version {
+            0 => {
+                let events = records
+                    .map(|record| deserialize(&record?))
+                    .collect::<io::Result<Vec<_>>>()?;
+                let messages = legacy_log::to_messages(events);
+                Ok(Box::new(messages.into_iter().map(Ok)))
+            }
+            1..=CURRENT_VERSION => {
+                Ok(Box::new(records.map(|record| deserialize(&record?))))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "event log version {} is newer than the supported version {}",
+                    version, CURRENT_VERSION
+                ),
+            )),
+        }
+    }
+
+    /// Rewrites an event log with the current schema version.  Returns the version of the
+    /// original log and the number of messages written.
+    pub fn migrate<P: AsRef<Path>, Q: AsRef<Path>>(
+        input: P,
+        output: Q,
+    ) -> io::Result<(u32, usize)> {
+        let (version, _) = Self::open_versioned(&input)?;
+        let mut writer = Self::create(output)?;
+        let mut num_messages = 0;
+        for message in Self::read_messages(input)? {
+            let record = bincode::serialize(&message?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            writer.write_record(&record)?;
+            num_messages += 1;
+        }
+        writer
+            .into_inner()
+            .map_err(io::Error::from)?
+            .finish()?
+            .flush()?;
+        Ok((version, num_messages))
     }
 
     pub fn finish(self) -> io::Result<File> {
@@ -68,6 +171,12 @@ impl EventLog {
     }
 }
 
+/// Deserializes a record.
+fn deserialize<'a, T: serde::Deserialize<'a>>(record: &'a [u8]) -> io::Result<T> {
+    bincode::deserialize(record)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
 impl Read for EventLog {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match &mut self.inner {
@@ -95,3 +204,83 @@ impl Write for EventLog {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::choice::ActionEx;
+    use crate::explorer::legacy_log::TreeEvent;
+    use crate::ir;
+    use crate::search_space::{Action, DimKind};
+    use std::path::PathBuf;
+
+    /// Returns a path to a temporary event log.
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.tfrecord", name, std::process::id()))
+    }
+
+    /// Writes records without header, as older versions did.
+    fn write_raw<T: serde::Serialize>(path: &Path, records: &[T]) {
+        let file = File::create(path).unwrap();
+        let mut writer = tfrecord::Writer::from_writer(EventLog::wrap(file, None));
+        for record in records {
+            writer
+                .write_record(&bincode::serialize(record).unwrap())
+                .unwrap();
+        }
+        writer
+            .into_inner()
+            .map_err(io::Error::from)
+            .unwrap()
+            .finish()
+            .unwrap();
+    }
+
+    /// Ensures legacy logs are converted to messages and migrated to the current version.
+    #[test]
+    fn migrate_legacy_log() {
+        let kind = |kind| ActionEx::Action(Action::DimKind(ir::DimId(0), kind));
+        let path = |first, second| rpds::List::new().push_front(first).push_front(second);
+        let events = vec![
+            TreeEvent::Evaluation {
+                actions: path(kind(DimKind::LOOP), kind(DimKind::UNROLL)),
+                score: 2.,
+            },
+            TreeEvent::Evaluation {
+                actions: path(kind(DimKind::LOOP), kind(DimKind::VECTOR)),
+                score: 1.,
+            },
+        ];
+        let (legacy, migrated) = (temp_path("legacy"), temp_path("migrated"));
+        write_raw(&legacy, &events);
+        assert_eq!(EventLog::open_versioned(&legacy).unwrap().0, 0);
+
+        // The root, the node of the common action and the two leaves, then the two traces and
+        // the two evaluations.
+        assert_eq!(EventLog::migrate(&legacy, &migrated).unwrap(), (0, 8));
+        assert_eq!(
+            EventLog::open_versioned(&migrated).unwrap().0,
+            CURRENT_VERSION
+        );
+        let messages = EventLog::read_messages(&migrated)
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        match &messages[1] {
+            Message::Node {
+                parent, children, ..
+            } => {
+                assert_eq!(parent.map(|(id, _)| u64::from(id)), Some(0));
+                assert_eq!(children, &[kind(DimKind::UNROLL), kind(DimKind::VECTOR)]);
+            }
+            _ => panic!("expected a node"),
+        }
+
+        // Logs of messages without header are still read.
+        write_raw(&legacy, &messages);
+        assert_eq!(EventLog::open_versioned(&legacy).unwrap().0, 1);
+        assert_eq!(EventLog::read_messages(&legacy).unwrap().count(), 8);
+        std::fs::remove_file(legacy).unwrap();
+        std::fs::remove_file(migrated).unwrap();
+    }
+}
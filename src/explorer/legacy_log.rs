@@ -0,0 +1,301 @@
+//! The event format written by the first versions of the explorer, along with its conversion
+//! to `mcts::Message`s.
+//!
+//! Legacy logs only record the actions leading to evaluated implementations and dead-ends, so
+//! the converted tree only contains the nodes along those paths.  The children of a node are
+//! the actions recorded from it, the bounds of intermediate nodes are unknown and set to zero,
+//! which is always a valid lower bound.
+use crate::explorer::choice::ActionEx;
+use crate::explorer::mcts::{
+    CauseOfDeath, EdgeIndex, Event, Message, NodeId, Policy, Selector, Timed,
+};
+use crate::model::Bound;
+use rpds::List;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum DeadEndSource {
+    /// Dead-end encountered in the tree
+    Tree {
+        /// List of actions defining the dead-end candidate
+        actions: List<ActionEx>,
+    },
+    /// Dead-end encountered in the rollout phase
+    Rollout {
+        /// List of actions defining the dead-end candidate
+        actions: List<ActionEx>,
+        /// Depth in the tree.  The remaining actions were selected during rollout.
+        depth: usize,
+        /// Performance model bound
+        bound: f64,
+        /// Current cut value
+        cut: f64,
+    },
+}
+
+/// The possible tree events.
+/// WARNING:  Changing the enums *will break* any pre-existing eventlog files.  Adding new cases
+/// *at the end only* is safe.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TreeEvent {
+    Evaluation {
+        actions: List<ActionEx>,
+        score: f64,
+    },
+
+    /// A fully-specified implementation was found and evaluated
+    EvaluationV2 {
+        /// List of actions defining the implementation
+        actions: List<ActionEx>,
+        /// Depth in the tree.  The remaining actions were selected during rollout.
+        depth: usize,
+        /// Execution time
+        score: f64,
+        /// Performance model lower bound
+        bound: f64,
+        /// Cut value when the implementation was found.  This is the best implementation at the
+        /// time the descent started from the root, as threads only synchronize the cut at the
+        /// root.
+        cut: f64,
+        /// Time at which the implementation was found
+        discovery_time: f64,
+        /// Time at which the evaluation finished.  Note that evaluations are performed by a
+        /// specific thread, not the one that found the implementation.
+        evaluation_end_time: f64,
+        /// ID of the thread that found this implementation
+        thread: String,
+    },
+
+    /// A dead-end was reached
+    DeadEnd {
+        /// Source of this deadend
+        source: DeadEndSource,
+        /// Time at which the deadend was found after the start of the program
+        discovery_time: f64,
+        /// ID of the thread that found the deadend
+        thread: String,
+    },
+}
+
+/// Thread name used for events that did not record their thread.
+const UNKNOWN_THREAD: &str = "ThreadId(0)";
+
+/// The path to a node recorded by an event, with the information needed to convert it.
+struct Path {
+    /// Actions from the root, in order
+    actions: Vec<ActionEx>,
+    /// Number of actions selected by the tree policy, the others being selected in rollout
+    depth: usize,
+    /// Bound of the last node, if recorded
+    bound: Option<f64>,
+    /// Time at which the last node was found
+    discovery_time: Duration,
+    thread: String,
+    /// Event ending the trace
+    end: Event,
+    /// Score and time at which the evaluation ended, for evaluated implementations
+    evaluation: Option<(f64, Duration)>,
+}
+
+impl Path {
+    fn new(event: TreeEvent) -> Self {
+        let actions = |list: List<ActionEx>| {
+            // Actions are stored from the last one to the first one.
+            let mut actions = list.iter().cloned().collect::<Vec<_>>();
+            actions.reverse();
+            actions
+        };
+        match event {
+            TreeEvent::Evaluation {
+                actions: list,
+                score,
+            } => {
+                let actions = actions(list);
+                Path {
+                    depth: actions.len(),
+                    actions,
+                    bound: None,
+                    discovery_time: Duration::default(),
+                    thread: UNKNOWN_THREAD.to_string(),
+                    end: Event::Implementation,
+                    evaluation: Some((score, Duration::default())),
+                }
+            }
+            TreeEvent::EvaluationV2 {
+                actions: list,
+                depth,
+                score,
+                bound,
+                discovery_time,
+                evaluation_end_time,
+                thread,
+                ..
+            } => Path {
+                actions: actions(list),
+                depth,
+                bound: Some(bound),
+                discovery_time: seconds(discovery_time),
+                thread,
+                end: Event::Implementation,
+                evaluation: Some((score, seconds(evaluation_end_time))),
+            },
+            TreeEvent::DeadEnd {
+                source,
+                discovery_time,
+                thread,
+            } => {
+                let (actions, depth, bound, cause) = match source {
+                    DeadEndSource::Tree { actions: list } => {
+                        let actions = actions(list);
+                        let depth = actions.len();
+                        (actions, depth, None, CauseOfDeath::Backtrack)
+                    }
+                    DeadEndSource::Rollout {
+                        actions: list,
+                        depth,
+                        bound,
+                        cut,
+                    } => (
+                        actions(list),
+                        depth,
+                        Some(bound),
+                        CauseOfDeath::PerfModel { cut },
+                    ),
+                };
+                Path {
+                    actions,
+                    depth,
+                    bound,
+                    discovery_time: seconds(discovery_time),
+                    thread,
+                    end: Event::Kill(cause),
+                    evaluation: None,
+                }
+            }
+        }
+    }
+}
+
+/// Converts a timestamp in seconds into a `Duration`.
+fn seconds(time: f64) -> Duration {
+    Duration::from_secs_f64(time.max(0.))
+}
+
+/// A node of the tree rebuilt from the paths.
+#[derive(Default)]
+struct TrieNode {
+    /// Actions leading to the children, along with the index of the children
+    children: Vec<(ActionEx, usize)>,
+    /// Bound of the node, if recorded
+    bound: Option<f64>,
+    /// Time at which the node was first found
+    discovery_time: Option<Duration>,
+}
+
+/// Converts legacy events into messages.  The messages creating the nodes come first, followed
+/// by one trace per event, in order, and the evaluations of the implementations.
+pub fn to_messages(events: Vec<TreeEvent>) -> Vec<Message> {
+    let paths = events.into_iter().map(Path::new).collect::<Vec<_>>();
+
+    // Build the tree of the recorded paths.
+    let mut nodes = vec![TrieNode::default()];
+    let mut path_nodes = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let mut node = 0;
+        let mut indexes = Vec::with_capacity(path.actions.len());
+        for action in &path.actions {
+            let index = nodes[node]
+                .children
+                .iter()
+                .position(|(other, _)| other == action);
+            let index = index.unwrap_or_else(|| {
+                nodes.push(TrieNode::default());
+                let child = nodes.len() - 1;
+                nodes[node].children.push((action.clone(), child));
+                nodes[node].children.len() - 1
+            });
+            indexes.push(index);
+            node = nodes[node].children[index].1;
+            let discovery_time = &mut nodes[node].discovery_time;
+            if discovery_time.map_or(true, |time| path.discovery_time < time) {
+                *discovery_time = Some(path.discovery_time);
+            }
+        }
+        if path.bound.is_some() {
+            nodes[node].bound = path.bound;
+        }
+        path_nodes.push((node, indexes));
+    }
+
+    // Create the nodes, parents first.  Node IDs are the indexes in `nodes`, which are already
+    // sorted in this order.
+    let mut parents = vec![None; nodes.len()];
+    for (id, node) in nodes.iter().enumerate() {
+        for (index, &(_, child)) in node.children.iter().enumerate() {
+            parents[child] =
+                Some((NodeId::from(id as u64), EdgeIndex::from(index as u16)));
+        }
+    }
+    let mut messages = nodes
+        .iter()
+        .zip(parents)
+        .enumerate()
+        .map(|(id, (node, parent))| Message::Node {
+            id: NodeId::from(id as u64),
+            parent,
+            children: node
+                .children
+                .iter()
+                .map(|(action, _)| action.clone())
+                .collect(),
+            bound: Some(Bound::unknown(node.bound.unwrap_or(0.))),
+            discovery_time: node.discovery_time.unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+
+    // Replay the events.  Implementations evaluated multiple times only keep their first
+    // evaluation, as each node is evaluated at most once in the current format.
+    let mut evaluated = vec![false; nodes.len()];
+    for (path, (node, indexes)) in paths.into_iter().zip(path_nodes) {
+        let timed = |value| Timed {
+            start_time: path.discovery_time,
+            end_time: path.discovery_time,
+            value,
+        };
+        let mut events = indexes
+            .iter()
+            .enumerate()
+            .map(|(depth, &index)| {
+                let index = EdgeIndex::from(index as u16);
+                let policy = if depth < path.depth {
+                    Policy::Bandit
+                } else {
+                    Policy::Default
+                };
+                timed(Event::SelectChild(
+                    index,
+                    policy,
+                    Selector::Exact { value: index },
+                ))
+            })
+            .collect::<Vec<_>>();
+        events.push(timed(path.end.clone()));
+        messages.push(Message::Trace {
+            thread: path.thread,
+            events,
+        });
+        match path.evaluation {
+            Some((score, result_time)) if !evaluated[node] => {
+                evaluated[node] = true;
+                messages.push(Message::Evaluation {
+                    id: NodeId::from(node as u64),
+                    value: Some(score),
+                    result_time,
+                });
+            }
+            _ => (),
+        }
+    }
+    messages
+}
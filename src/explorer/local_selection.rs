@@ -3,6 +3,8 @@ use crate::device::Context;
 use crate::explorer::candidate::Candidate;
 use crate::explorer::choice;
 use crate::explorer::config::{ChoiceOrdering, NewNodeOrder};
+use crate::model;
+use crate::search_space::SearchSpace;
 use rand::distributions::{Weighted, WeightedChoice};
 use rand::prelude::*;
 use std;
@@ -144,6 +146,23 @@ pub fn descend(
     .descend(candidate)
 }
 
+/// Returns the implementation obtained by always taking the first valid decision, without
+/// pruning candidates on their bound.  This is deterministic, which makes it suitable to check
+/// the code generated for a search space.
+pub fn first_implementation(
+    context: &dyn Context,
+    space: SearchSpace,
+) -> Option<Candidate> {
+    let bound = model::bound(&space, context);
+    descend(
+        &ChoiceOrdering::default(),
+        NewNodeOrder::Api,
+        context,
+        Candidate::new(space, bound),
+        std::f64::INFINITY,
+    )
+}
+
 impl NewNodeOrder {
     /// Called in montecarlo_descend, dispatch the choice of the next candidate according to our
     /// configuration
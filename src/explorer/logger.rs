@@ -4,9 +4,12 @@ use std::sync::mpsc;
 use std::time::Duration;
 
 use crate::explorer::config::Config;
+use crate::explorer::metrics::{MetricsServer, UpdateMetrics};
 use crate::explorer::monitor;
+use crate::model::roofline::Roofline;
 use bincode;
 use failure::Fail;
+use log::warn;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -17,11 +20,21 @@ pub enum LogMessage<E> {
         cpt: usize,
         timestamp: Duration,
     },
+    /// A candidate was evaluated.
+    Progress {
+        num_evaluations: usize,
+        cut: f64,
+    },
     Finished {
         reason: monitor::TerminationReason,
         timestamp: Duration,
         num_evaluations: usize,
     },
+    /// The roofline of the kernel, along with the runtime of the best candidate.
+    Roofline {
+        roofline: Roofline,
+        runtime: f64,
+    },
 }
 
 #[derive(Debug, Fail)]
@@ -52,15 +65,23 @@ impl From<mpsc::RecvError> for LogError {
     }
 }
 
-pub fn log<E: Send + Serialize>(
+pub fn log<E: Send + Serialize + UpdateMetrics>(
     config: &Config,
     recv: mpsc::Receiver<LogMessage<E>>,
 ) -> Result<(), LogError> {
     let mut record_writer = config.create_eventlog()?;
     let mut write_buffer = config.create_log()?;
+    let metrics_server = config.metrics_address.as_ref().and_then(|address| {
+        MetricsServer::start(address)
+            .map_err(|err| warn!("Unable to serve metrics on {}: {}", address, err))
+            .ok()
+    });
     while let Ok(message) = recv.recv() {
         match message {
             LogMessage::Event(event) => {
+                if let Some(server) = &metrics_server {
+                    server.update(|metrics| event.update_metrics(metrics));
+                }
                 if let Some(writer) = &mut record_writer {
                     writer.write_record(&bincode::serialize(&event)?)?;
                 }
@@ -70,13 +91,30 @@ pub fn log<E: Send + Serialize>(
                 cpt,
                 timestamp,
             } => {
+                if let Some(server) = &metrics_server {
+                    server.update(|metrics| metrics.best_runtime = Some(score));
+                }
                 log_monitor(score, cpt, timestamp, &mut write_buffer);
             }
+            LogMessage::Progress {
+                num_evaluations,
+                cut,
+            } => {
+                if let Some(server) = &metrics_server {
+                    server.update(|metrics| {
+                        metrics.num_evaluations = num_evaluations;
+                        metrics.cut = Some(cut);
+                    });
+                }
+            }
             LogMessage::Finished {
                 reason,
                 timestamp,
                 num_evaluations,
             } => {
+                if let Some(server) = &metrics_server {
+                    server.update(|metrics| metrics.finished = true);
+                }
                 let t_s = timestamp.as_secs();
                 let n_seconds = t_s % 60;
                 let n_minutes = (t_s / 60) % 60;
@@ -93,6 +131,17 @@ pub fn log<E: Send + Serialize>(
                 )?;
                 writeln!(write_buffer, "{}", reason)?;
             }
+            LogMessage::Roofline { roofline, runtime } => {
+                writeln!(write_buffer, "{}", roofline)?;
+                if let Some(efficiency) = roofline.efficiency(runtime) {
+                    writeln!(
+                        write_buffer,
+                        "best candidate: {:.4e}ns, at {:.2}% of the roofline",
+                        runtime,
+                        100. * efficiency
+                    )?;
+                }
+            }
         }
         // Flush after writing a message to ensure the log file does not end up empty in case of a
         // crash.
@@ -20,6 +20,7 @@ use std::sync::{
 };
 use std::{cmp, iter, ops, slice};
 
+use fxhash::FxHashSet;
 use rand::distributions::{Weighted, WeightedChoice};
 use rand::prelude::*;
 use rpds::List;
@@ -32,6 +33,7 @@ use crate::explorer::{
     choice::{self, ActionEx as Action},
     config::{self, BanditConfig, ChoiceOrdering, NewNodeOrder},
     logger::LogMessage,
+    pareto::Evaluation,
     store::Store,
 };
 use crate::model::{bound, Bound};
@@ -55,6 +57,12 @@ impl From<NodeId> for u64 {
     }
 }
 
+impl From<u64> for NodeId {
+    fn from(v: u64) -> Self {
+        NodeId(v)
+    }
+}
+
 /// Newtype wrapper to represent an edge index.  Like `NodeId`, we use a fixed-size representation
 /// for consistency of the serialization format.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -73,6 +81,12 @@ impl From<EdgeIndex> for u16 {
     }
 }
 
+impl From<u16> for EdgeIndex {
+    fn from(v: u16) -> Self {
+        EdgeIndex(v)
+    }
+}
+
 /// The possible causes for which a node can be killed.
 #[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum CauseOfDeath {
@@ -301,6 +315,10 @@ struct EdgeInner<N, E> {
     /// Action associated with the edge.
     action: Action,
 
+    /// Number of descents currently in progress across the edge, i.e. descents which selected the
+    /// edge and have not yet either reached a dead-end or backpropagated their evaluation.
+    in_flight: AtomicUsize,
+
     /// Additional algorithm-specific data associated with the edge.
     data: E,
 }
@@ -353,6 +371,36 @@ impl<N, E> Edge<N, E> {
     pub fn data(&self) -> &E {
         &self.inner.data
     }
+
+    /// Number of descents currently in progress across the edge.
+    pub fn num_in_flight(&self) -> usize {
+        self.inner.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Called when a descent selects the edge.  Returns the number of descents which were already
+    /// in progress across the edge.
+    fn enter(&self) -> usize {
+        self.inner.in_flight.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Called when a descent across the edge is over, either because it reached a dead-end or
+    /// because its evaluation was backpropagated.
+    fn leave(&self) {
+        // Descents started before a restart can finish after the counter was reset; saturate
+        // instead of underflowing in that case.
+        let mut current = self.inner.in_flight.load(Ordering::Relaxed);
+        while current > 0 {
+            match self.inner.in_flight.compare_exchange_weak(
+                current,
+                current - 1,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(actual) => current = actual,
+            }
+        }
+    }
 }
 
 impl<N: Reset, E: Reset> Reset for Edge<N, E> {
@@ -361,6 +409,7 @@ impl<N: Reset, E: Reset> Reset for Edge<N, E> {
             node.reset();
         }
 
+        self.inner.in_flight.store(0, Ordering::SeqCst);
         self.inner.data.reset();
     }
 }
@@ -443,6 +492,10 @@ pub enum Event {
     /// An implementation was found.  This should occur at most once per trace and will be the last
     /// event on the trace when it does occur.
     Implementation,
+    /// The `n`th child of the current node is about to be selected while the given number of other
+    /// descents are still in progress across it.  This is only logged when there is at least one
+    /// other descent in progress, immediately before the corresponding `SelectChild` event.
+    ConcurrentSelect(EdgeIndex, usize),
 }
 
 /// Wrapper struct to annotate events with timing information.
@@ -565,6 +618,7 @@ impl<'a> Tree<'a> {
                         node: RwLock::new(None),
                         index: EdgeIndex(ix as u16),
                         action,
+                        in_flight: AtomicUsize::new(0),
                         data: E::default(),
                     }),
                 })
@@ -617,6 +671,8 @@ pub struct NodeCursor<'a, N, E> {
     node: Node<N, E>,
     tree: Tree<'a>,
     helper: WalkHelper<'a>,
+    /// Decisions of the warm-start implementation the descent follows, if any.
+    guide: Option<&'a FxHashSet<Action>>,
 }
 
 impl<'a, N, E> NodeCursor<'a, N, E>
@@ -760,6 +816,24 @@ where
         })
     }
 
+    /// Selects a child whose action is one of the decisions of the warm-start implementation
+    /// followed by the descent, if any.
+    fn guided_pick(
+        &self,
+        children: &NodeView<'_, N, E>,
+    ) -> Option<(EdgeViewIndex, Selector<EdgeIndex>)> {
+        let guide = self.guide?;
+        children
+            .iter()
+            .find(|(_, edge, _)| guide.contains(edge.action()))
+            .map(|(index, _, _)| children.select_with(Selector::exact(index)))
+    }
+
+    /// A view on some of the children of the pointed-to node, for use by the tree policies.
+    fn view<'b>(&'b self, edges: &'b [ChildView<'b, N, E>]) -> NodeView<'b, N, E> {
+        NodeView::new(&self.node, edges, self.helper.config.virtual_loss)
+    }
+
     /// Kill the given node.
     fn kill_node<F>(&self, node: &Node<N, E>, cause: CauseOfDeath, event_fn: F)
     where
@@ -809,6 +883,7 @@ where
     }
 
     pub fn deadend(self) {
+        leave_path(&self.path);
         self.tree.log(Message::Trace {
             thread: format!("{:?}", std::thread::current().id()),
             events: self.events.into_inner(),
@@ -840,6 +915,7 @@ where
                     cursor.tree.epoch.elapsed(),
                     Event::SelectNode(checkpoint.id()),
                 );
+                leave_path(&cursor.path[path_len..]);
                 cursor.path.truncate(path_len);
                 cursor.node = checkpoint;
                 Err(cursor)
@@ -861,6 +937,10 @@ where
     {
         let start_time = self.tree.epoch.elapsed();
         if let Some((policy, selector, eindex, node, value)) = func(&self) {
+            let num_concurrent = self.node[eindex].enter();
+            if num_concurrent > 0 {
+                self.event(start_time, Event::ConcurrentSelect(eindex, num_concurrent));
+            }
             self.event(start_time, Event::SelectChild(eindex, policy, selector));
             self.path.push((policy, self.node.downgrade(), eindex));
             self.node = node;
@@ -918,6 +998,15 @@ where
     }
 }
 
+/// Mark the descents across the edges of `path` as finished.
+fn leave_path<N, E>(path: &[(Policy, WeakNode<N, E>, EdgeIndex)]) {
+    for (_policy, parent, index) in path {
+        if let Some(parent) = parent.upgrade() {
+            parent[*index].leave();
+        }
+    }
+}
+
 /// Errors which we can encounter during a descent
 enum Error<'a, N, E> {
     /// A dead-end was encountered
@@ -1008,9 +1097,10 @@ where
                             })
                             .unzip();
 
-                        if let Some((index, selector)) = self
-                            .policy
-                            .pick_child(cursor.cut, &NodeView::new(&cursor.node, &edges))
+                        let view = cursor.view(&edges);
+                        if let Some((index, selector)) = cursor
+                            .guided_pick(&view)
+                            .or_else(|| self.policy.pick_child(cursor.cut, &view))
                         {
                             let (edge, node) = edges.swap_remove(usize::from(index));
                             let child_candidate =
@@ -1106,52 +1196,61 @@ where
                             }
                         }
 
-                        if let Some((index, selector)) =
-                            self.default_walker.policy.pick_child(
-                                cursor.cut,
-                                &NodeView::new(&cursor.node, &unexpanded),
-                            )
-                        {
-                            let (edge, node) = unexpanded.swap_remove(usize::from(index));
+                        // Children on the path of the warm-start implementation come first,
+                        // whether they are expanded or not.
+                        let guided = cursor
+                            .guided_pick(&cursor.view(&unexpanded))
+                            .map(|pick| (true, pick))
+                            .or_else(|| {
+                                cursor
+                                    .guided_pick(&cursor.view(&expanded))
+                                    .map(|pick| (false, pick))
+                            });
+                        let picked = guided
+                            .or_else(|| {
+                                self.default_walker
+                                    .policy
+                                    .pick_child(cursor.cut, &cursor.view(&unexpanded))
+                                    .map(|pick| (true, pick))
+                            })
+                            .or_else(|| {
+                                assert!(
+                                    unexpanded
+                                        .iter()
+                                        .all(|(_edge, node)| !node.is_live()),
+                                    "live unexpanded child was not selected"
+                                );
+
+                                self.tree_policy
+                                    .pick_child(cursor.cut, &cursor.view(&expanded))
+                                    .map(|pick| (false, pick))
+                            });
+
+                        if let Some((expand, (index, selector))) = picked {
+                            let (policy, (edge, node)) = if expand {
+                                (
+                                    Policy::Default,
+                                    unexpanded.swap_remove(usize::from(index)),
+                                )
+                            } else {
+                                (Policy::Bandit, expanded.swap_remove(usize::from(index)))
+                            };
 
                             Some((
-                                Policy::Default,
+                                policy,
                                 selector,
                                 edge.index(),
                                 node,
-                                SelectedChild { expand: true },
+                                SelectedChild { expand },
                             ))
                         } else {
                             assert!(
-                                unexpanded
-                                    .into_iter()
-                                    .all(|(_edge, node)| !node.is_live()),
-                                "live unexpanded child was not selected"
+                                expanded.iter().all(|(_edge, node)| !node.is_live()),
+                                "live child was not selected"
                             );
 
-                            if let Some((index, selector)) = self.tree_policy.pick_child(
-                                cursor.cut,
-                                &NodeView::new(&cursor.node, &expanded),
-                            ) {
-                                let (edge, node) =
-                                    expanded.swap_remove(usize::from(index));
-
-                                Some((
-                                    Policy::Bandit,
-                                    selector,
-                                    edge.index(),
-                                    node,
-                                    SelectedChild { expand: false },
-                                ))
-                            } else {
-                                assert!(
-                                    expanded.iter().all(|(_edge, node)| !node.is_live()),
-                                    "live child was not selected"
-                                );
-
-                                cursor.kill(CauseOfDeath::Backtrack);
-                                None
-                            }
+                            cursor.kill(CauseOfDeath::Backtrack);
+                            None
                         }
                     })
                     .and_then(|(cursor, selected)| {
@@ -1214,6 +1313,13 @@ pub struct MctsStore<'a, N, E> {
     /// Bandit configuration
     config: &'a BanditConfig,
 
+    /// Decisions of the implementations to warm-start the search from.
+    warm_start: Vec<FxHashSet<Action>>,
+
+    /// Number of descents which started while there were warm-start implementations.  The first
+    /// descents each follow one of them.
+    num_warm_descents: AtomicUsize,
+
     /// Time at which the search started.  Used as an epoch for timestamps.
     epoch: std::time::Instant,
 }
@@ -1255,11 +1361,42 @@ where
             id_counter,
             logger,
             config,
+            warm_start: Vec::new(),
+            num_warm_descents: AtomicUsize::new(0),
             epoch,
         }
     }
 
-    fn cursor<'b>(&'b self, context: &'b dyn Context) -> NodeCursor<'b, N, E> {
+    /// Warm-starts the search from the decisions of prior implementations.  The first descents
+    /// each follow the decisions of one of them wherever they apply, so that they are evaluated
+    /// first.  Afterwards, descents follow a random one of them with probability
+    /// `warm_start_bias`.
+    pub fn with_warm_start(mut self, warm_start: Vec<FxHashSet<Action>>) -> Self {
+        self.warm_start = warm_start;
+        self
+    }
+
+    /// Selects the warm-start implementation a new descent should follow, if any.
+    fn pick_guide(&self) -> Option<&FxHashSet<Action>> {
+        if self.warm_start.is_empty() {
+            return None;
+        }
+
+        let index = self.num_warm_descents.fetch_add(1, Ordering::Relaxed);
+        if index < self.warm_start.len() {
+            Some(&self.warm_start[index])
+        } else if thread_rng().gen::<f64>() < self.config.warm_start_bias {
+            thread_rng().choose(&self.warm_start)
+        } else {
+            None
+        }
+    }
+
+    fn cursor<'b>(
+        &'b self,
+        context: &'b dyn Context,
+        guide: Option<&'b FxHashSet<Action>>,
+    ) -> NodeCursor<'b, N, E> {
         NodeCursor {
             events: Vec::new().into(),
             cut: *self.cut.read().expect("cut: poisoned"),
@@ -1278,6 +1415,7 @@ where
                 cut_epoch: &self.cut_epoch,
                 config: self.config,
             },
+            guide,
         }
     }
 
@@ -1323,17 +1461,25 @@ where
         &self,
         _actions: &List<choice::ActionEx>,
         payload: Self::PayLoad,
-        eval: f64,
+        eval: &Evaluation,
     ) {
-        // Discard old evaluations that were meant for a previous restart
+        // Discard old evaluations that were meant for a previous restart.  The restart already
+        // reset the descents in progress, so the path must not be left either.
         if self.restart_id.load(Ordering::SeqCst) > payload.restart_id {
             return;
         }
 
         let trace = payload.trace;
+        leave_path(&trace.path);
+
         let result_time = self.epoch.elapsed();
         let id = trace.node.id();
-        let eval = if eval.is_finite() { Some(eval) } else { None };
+        // The tree policies only optimize the runtime.
+        let eval = if eval.runtime.is_finite() {
+            Some(eval.runtime)
+        } else {
+            None
+        };
 
         // Backpropagate only when the parent is expanded
         for (policy, parent, index) in trace.path {
@@ -1359,8 +1505,9 @@ where
     }
 
     fn explore(&self, context: &dyn Context) -> Option<(Candidate, Self::PayLoad)> {
+        let guide = self.pick_guide();
         loop {
-            let cursor = self.cursor(context);
+            let cursor = self.cursor(context, guide);
             let walker = self.walker();
 
             // Stop if the root node is dead.
@@ -1587,11 +1734,28 @@ pub struct NodeView<'a, N, E> {
     #[allow(dead_code)]
     parent: &'a Node<N, E>,
     edges: &'a [ChildView<'a, N, E>],
+    /// Number of virtual visits to count for each descent in progress across an edge.
+    virtual_loss: usize,
 }
 
 impl<'a, N, E> NodeView<'a, N, E> {
-    fn new(parent: &'a Node<N, E>, edges: &'a [ChildView<'a, N, E>]) -> Self {
-        NodeView { parent, edges }
+    fn new(
+        parent: &'a Node<N, E>,
+        edges: &'a [ChildView<'a, N, E>],
+        virtual_loss: usize,
+    ) -> Self {
+        NodeView {
+            parent,
+            edges,
+            virtual_loss,
+        }
+    }
+
+    /// Number of additional visits the policies should assume for `edge` in order to account for
+    /// the descents which are currently in progress across it.  See
+    /// [config::BanditConfig::virtual_loss].
+    fn virtual_visits(&self, edge: &Edge<N, E>) -> usize {
+        self.virtual_loss * edge.num_in_flight()
     }
 
     fn iter(&'_ self) -> ChildViewIter<'_, 'a, N, E> {
@@ -1719,9 +1883,13 @@ impl<N> TreePolicy<N, UCTStats> for UCTPolicy {
         let stats = children
             .iter()
             .map(|(index, edge, node)| {
+                let (value, visits) = self.value(edge.data());
                 (
                     index,
-                    (node.bound().unwrap().value(), self.value(edge.data())),
+                    (
+                        node.bound().unwrap().value(),
+                        (value, visits + children.virtual_visits(edge)),
+                    ),
                 )
             })
             .collect::<Vec<_>>();
@@ -1935,7 +2103,9 @@ impl<N> TreePolicy<N, TAGStats> for TAGPolicy {
         let edges = children
             .iter()
             .map(|(index, edge, node)| {
-                (index, (edge, node, edge.data().common.num_visits()))
+                let num_visits =
+                    edge.data().common.num_visits() + children.virtual_visits(edge);
+                (index, (edge, node, num_visits))
             })
             .filter(|(_idx, (_edge, node, _num_visits))| {
                 node.bound().unwrap().value() < cut
@@ -2147,7 +2317,10 @@ impl<N> TreePolicy<N, CommonStats> for RoundRobinPolicy {
     ) -> Option<(EdgeViewIndex, Selector<EdgeIndex>)> {
         Selector::try_maximum(
             view.iter()
-                .map(|(index, edge, _node)| (index, -(edge.data().num_visits() as f64)))
+                .map(|(index, edge, _node)| {
+                    let num_visits = edge.data().num_visits() + view.virtual_visits(edge);
+                    (index, -(num_visits as f64))
+                })
                 .collect(),
         )
         .map(|selector| {
@@ -2165,3 +2338,62 @@ impl<N> TreePolicy<N, CommonStats> for RoundRobinPolicy {
     ) {
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::fake;
+    use crate::explorer::pareto::Evaluation;
+    use crate::{helper, ir};
+
+    /// Returns the edges crossed by a trace.
+    fn edges<N, E>(trace: &Trace<N, E>) -> Vec<(Node<N, E>, EdgeIndex)> {
+        trace
+            .path
+            .iter()
+            .map(|(_, parent, index)| (parent.upgrade().unwrap(), *index))
+            .collect()
+    }
+
+    /// Returns the number of descents in progress across each edge.
+    fn in_flight<N, E>(edges: &[(Node<N, E>, EdgeIndex)]) -> Vec<usize> {
+        edges
+            .iter()
+            .map(|(parent, index)| parent[*index].num_in_flight())
+            .collect()
+    }
+
+    /// Ensures evaluations committed after a restart do not leave the descents started
+    /// since.
+    #[test]
+    fn commit_after_restart() {
+        let context = fake::Context::<fake::Device>::default();
+        let signature = ir::Signature::new("empty");
+        let mut builder = helper::Builder::new(signature.into(), context.device());
+        let dim = builder.open_dim(ir::Size::new_const(16));
+        builder.mov(&0f32);
+        builder.close_dim(&dim);
+        let space = builder.get();
+        let config = BanditConfig::default();
+        let (log_sender, _log_receiver) = mpsc::sync_channel(1000);
+        let store = MctsStore::<(), ()>::new(
+            space,
+            &context,
+            &config,
+            Box::new(NewNodeOrder::Api),
+            Box::new(NewNodeOrder::Api),
+            log_sender,
+        );
+        let (candidate, stale) = store.explore(&context).unwrap();
+        let stale_edges = edges(&stale.trace);
+        assert!(!stale_edges.is_empty());
+        store.restart();
+        // A descent started after the restart crosses the same edges.
+        for (parent, index) in &stale_edges {
+            parent[*index].enter();
+        }
+        let eval = Evaluation::new(1., &candidate.space);
+        store.commit_evaluation(&candidate.actions, stale, &eval);
+        assert!(in_flight(&stale_edges).iter().all(|&n| n == 1));
+    }
+}
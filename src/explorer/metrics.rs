@@ -0,0 +1,398 @@
+//! Live metrics of a search, served over HTTP in JSON and in the Prometheus text format.
+//!
+//! The server answers `GET /metrics` with the Prometheus text format and any other path with
+//! a JSON snapshot of the `Metrics`.
+use crate::explorer::mcts::{self, CauseOfDeath};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A snapshot of the progress of a search.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Metrics {
+    /// Time elapsed since the start of the search, in seconds
+    pub elapsed: f64,
+    /// Number of candidates evaluated
+    pub num_evaluations: usize,
+    /// Average number of evaluations per second since the start of the search
+    pub evaluations_per_second: f64,
+    /// Runtime of the best candidate, in nanoseconds
+    pub best_runtime: Option<f64>,
+    /// Value above which candidates are pruned, in nanoseconds
+    pub cut: Option<f64>,
+    /// Number of nodes created in the search tree
+    pub num_nodes: usize,
+    /// Number of dead-ends, by cause of death
+    pub deadends: BTreeMap<String, usize>,
+    /// Indicates if the search is over
+    pub finished: bool,
+}
+
+impl Metrics {
+    /// Records a dead-end.
+    fn add_deadend(&mut self, cause: CauseOfDeath) {
+        let cause = match cause {
+            CauseOfDeath::Constraints => "constraints",
+            CauseOfDeath::PerfModel { .. } => "perf_model",
+            CauseOfDeath::Backtrack => "backtrack",
+        };
+        *self.deadends.entry(cause.to_string()).or_insert(0) += 1;
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        let mut metric =
+            |name: &str, kind: &str, help: &str, values: &[(String, f64)]| {
+                writeln!(out, "# HELP telamon_{} {}", name, help).unwrap();
+                writeln!(out, "# TYPE telamon_{} {}", name, kind).unwrap();
+                for (labels, value) in values {
+                    writeln!(
+                        out,
+                        "telamon_{}{} {}",
+                        name,
+                        labels,
+                        prometheus_value(*value)
+                    )
+                    .unwrap();
+                }
+            };
+        let value = |value: f64| vec![(String::new(), value)];
+        metric(
+            "elapsed_seconds",
+            "gauge",
+            "Time elapsed since the start of the search.",
+            &value(self.elapsed),
+        );
+        metric(
+            "evaluations_total",
+            "counter",
+            "Number of candidates evaluated.",
+            &value(self.num_evaluations as f64),
+        );
+        metric(
+            "evaluations_per_second",
+            "gauge",
+            "Average number of evaluations per second.",
+            &value(self.evaluations_per_second),
+        );
+        metric(
+            "best_runtime_nanoseconds",
+            "gauge",
+            "Runtime of the best candidate.",
+            &self.best_runtime.map(value).unwrap_or_default(),
+        );
+        metric(
+            "cut_nanoseconds",
+            "gauge",
+            "Value above which candidates are pruned.",
+            &self.cut.map(value).unwrap_or_default(),
+        );
+        metric(
+            "tree_nodes",
+            "gauge",
+            "Number of nodes created in the search tree.",
+            &value(self.num_nodes as f64),
+        );
+        let deadends = self
+            .deadends
+            .iter()
+            .map(|(cause, &count)| (format!("{{cause=\"{}\"}}", cause), count as f64))
+            .collect::<Vec<_>>();
+        metric(
+            "deadends_total",
+            "counter",
+            "Number of dead-ends, by cause of death.",
+            &deadends,
+        );
+        out
+    }
+}
+
+/// Formats a sample value as expected by the Prometheus text format, that spells infinite
+/// values `+Inf` and `-Inf`.
+fn prometheus_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() && value > 0. {
+        "+Inf".to_string()
+    } else if value.is_infinite() {
+        "-Inf".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let t_s = self.elapsed as u64;
+        writeln!(
+            f,
+            "elapsed: {}h {}m {}s{}",
+            t_s / 3600,
+            (t_s / 60) % 60,
+            t_s % 60,
+            if self.finished { " (finished)" } else { "" }
+        )?;
+        writeln!(
+            f,
+            "evaluations: {} ({:.2}/s)",
+            self.num_evaluations, self.evaluations_per_second
+        )?;
+        match self.best_runtime {
+            Some(best) => writeln!(f, "best runtime: {:.4e}ns", best)?,
+            None => writeln!(f, "best runtime: -")?,
+        }
+        match self.cut {
+            Some(cut) if cut.is_finite() => writeln!(f, "cut: {:.4e}ns", cut)?,
+            _ => writeln!(f, "cut: -")?,
+        }
+        write!(f, "tree nodes: {}", self.num_nodes)?;
+        for (cause, count) in &self.deadends {
+            write!(f, "\ndead-ends ({}): {}", cause, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Events of a search that update the metrics.
+pub trait UpdateMetrics {
+    /// Updates the metrics with the event.
+    fn update_metrics(&self, _metrics: &mut Metrics) {}
+}
+
+impl UpdateMetrics for () {}
+
+impl UpdateMetrics for mcts::Message {
+    fn update_metrics(&self, metrics: &mut Metrics) {
+        match self {
+            mcts::Message::Node { .. } => metrics.num_nodes += 1,
+            mcts::Message::Trace { events, .. } => {
+                for event in events {
+                    match event.value {
+                        mcts::Event::Kill(cause) | mcts::Event::KillChild(_, cause) => {
+                            metrics.add_deadend(cause)
+                        }
+                        _ => (),
+                    }
+                }
+            }
+            mcts::Message::Evaluation { .. } => (),
+        }
+    }
+}
+
+/// A server exposing the metrics of a search.  The server is stopped when dropped.
+pub struct MetricsServer {
+    address: SocketAddr,
+    metrics: Arc<Mutex<Metrics>>,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Interval at which the server checks whether it must stop.
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// Starts serving the metrics on the given address.
+    pub fn start<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        listener.set_nonblocking(true)?;
+        let metrics = Arc::new(Mutex::new(Metrics::default()));
+        let running = Arc::new(AtomicBool::new(true));
+        let start_time = Instant::now();
+        let thread = {
+            let metrics = Arc::clone(&metrics);
+            let running = Arc::clone(&running);
+            thread::Builder::new()
+                .name("Telamon - Metrics".to_string())
+                .spawn(move || {
+                    while running.load(Ordering::Relaxed) {
+                        match listener.accept() {
+                            Ok((stream, _)) => {
+                                let snapshot = snapshot(&metrics, start_time);
+                                serve(stream, &snapshot).unwrap_or_else(|err| {
+                                    warn!("Error while serving metrics: {}", err)
+                                });
+                            }
+                            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                                thread::sleep(Self::POLL_INTERVAL)
+                            }
+                            Err(err) => {
+                                warn!("Error while accepting connection: {}", err)
+                            }
+                        }
+                    }
+                })?
+        };
+        Ok(MetricsServer {
+            address,
+            metrics,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns the address the metrics are served on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Updates the metrics.
+    pub fn update<F: FnOnce(&mut Metrics)>(&self, update: F) {
+        update(&mut self.metrics.lock().unwrap())
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Returns a copy of the metrics with the rates computed at the current time.
+fn snapshot(metrics: &Mutex<Metrics>, start_time: Instant) -> Metrics {
+    let mut snapshot = metrics.lock().unwrap().clone();
+    snapshot.elapsed = start_time.elapsed().as_secs_f64();
+    if snapshot.elapsed > 0. {
+        snapshot.evaluations_per_second =
+            snapshot.num_evaluations as f64 / snapshot.elapsed;
+    }
+    snapshot
+}
+
+/// Answers a single HTTP request.
+fn serve(stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Skip the headers, which are not needed.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let (content_type, body) = if path == "/metrics" {
+        ("text/plain; version=0.0.4", metrics.to_prometheus())
+    } else {
+        ("application/json", serde_json::to_string(metrics)?)
+    };
+    write!(
+        reader.get_mut(),
+        "HTTP/1.0 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Retrieves the metrics served at the given address.
+pub fn fetch<A: ToSocketAddrs>(address: A) -> io::Result<Metrics> {
+    let mut stream = TcpStream::connect(address)?;
+    write!(stream, "GET /metrics.json HTTP/1.0\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let body = response
+        .find("\r\n\r\n")
+        .map(|pos| &response[pos + 4..])
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid response"))?;
+    Ok(serde_json::from_str(body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::mcts::{Event, Message, Timed};
+
+    /// Ensures the metrics are rendered in the Prometheus text format.
+    #[test]
+    fn prometheus_format() {
+        let mut metrics = Metrics {
+            num_evaluations: 3,
+            cut: Some(1. / 0.),
+            ..Metrics::default()
+        };
+        metrics.add_deadend(CauseOfDeath::Constraints);
+        let text = metrics.to_prometheus();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert!(lines.contains(&"# TYPE telamon_evaluations_total counter"));
+        assert!(lines.contains(&"telamon_evaluations_total 3"));
+        assert!(lines.contains(&"telamon_cut_nanoseconds +Inf"));
+        assert!(lines.contains(&"telamon_deadends_total{cause=\"constraints\"} 1"));
+        // Missing values have no sample.
+        assert!(!lines
+            .iter()
+            .any(|line| line.starts_with("telamon_best_runtime_nanoseconds")));
+        // Every sample is a metric name, optional labels and a value.
+        for line in lines.iter().filter(|line| !line.starts_with('#')) {
+            let value = line.rsplit(' ').next().unwrap();
+            assert!(value.parse::<f64>().is_ok() || value == "+Inf", "{}", line);
+        }
+    }
+
+    /// Ensures log messages update the metrics.
+    #[test]
+    fn update_from_messages() {
+        let mut metrics = Metrics::default();
+        let timed = |value| Timed {
+            start_time: Duration::from_secs(0),
+            end_time: Duration::from_secs(0),
+            value,
+        };
+        let messages = vec![
+            Message::Node {
+                id: 0.into(),
+                parent: None,
+                children: vec![],
+                bound: None,
+                discovery_time: Duration::from_secs(0),
+            },
+            Message::Trace {
+                thread: "test".to_string(),
+                events: vec![
+                    timed(Event::KillChild(0.into(), CauseOfDeath::Backtrack)),
+                    timed(Event::Kill(CauseOfDeath::PerfModel { cut: 1. })),
+                    timed(Event::Expand),
+                ],
+            },
+        ];
+        for message in &messages {
+            message.update_metrics(&mut metrics);
+        }
+        assert_eq!(metrics.num_nodes, 1);
+        assert_eq!(metrics.deadends["backtrack"], 1);
+        assert_eq!(metrics.deadends["perf_model"], 1);
+        assert_eq!(metrics.deadends.get("constraints"), None);
+    }
+
+    /// Ensures the metrics survive a round trip through the server.
+    #[test]
+    fn serve_and_fetch() {
+        let server = MetricsServer::start("127.0.0.1:0").unwrap();
+        server.update(|metrics| {
+            metrics.num_evaluations = 4;
+            metrics.best_runtime = Some(12.5);
+            metrics.finished = true;
+        });
+        let metrics = fetch(server.local_addr()).unwrap();
+        assert_eq!(metrics.num_evaluations, 4);
+        assert_eq!(metrics.best_runtime, Some(12.5));
+        assert!(metrics.finished);
+    }
+}
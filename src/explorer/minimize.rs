@@ -0,0 +1,215 @@
+//! Minimization of the decisions exposing a bug in the performance model.
+//!
+//! The performance model must return a lower bound on the execution time of every
+//! implementation a candidate contains.  When an implementation runs faster than the bound of a
+//! candidate built from a subset of its decisions, the model is wrong on that candidate.  This
+//! module shrinks the list of decisions of such an implementation to a minimal subset that still
+//! exhibits the violation, using the delta-debugging algorithm.
+//!
+//! The implementation is always contained in the candidates built from subsets of its
+//! decisions, so its runtime only needs to be measured once.
+use std::io::{self, Write};
+use std::path::Path;
+
+use log::{debug, info};
+
+use crate::codegen;
+use crate::device::{Context, EvalMode};
+use crate::explorer::choice::{ActionError, ActionEx};
+use crate::explorer::warm_start;
+use crate::model::{bound, Bound};
+use crate::search_space::SearchSpace;
+
+/// A minimized set of decisions for which the bound is above a measured runtime.
+pub struct BoundViolation {
+    /// The decisions of the minimized candidate, in the order they must be applied.
+    pub actions: Vec<ActionEx>,
+    /// The runtime of the original implementation, in nanoseconds.
+    pub runtime: f64,
+    /// The bound of the original implementation.
+    pub bound_before: Bound,
+    /// The bound of the minimized candidate.
+    pub bound_after: Bound,
+}
+
+impl BoundViolation {
+    /// Writes a reproducer in a directory: the minimized decisions in `actions.json` and the
+    /// explanations of the bounds before and after minimization in `bound_before.txt` and
+    /// `bound_after.txt`.
+    pub fn dump_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        std::fs::create_dir_all(path)?;
+        write!(
+            std::fs::File::create(path.join("actions.json"))?,
+            "{}",
+            serde_json::to_string(&self.actions)?
+        )?;
+        for (file_name, bound) in &[
+            ("bound_before.txt", &self.bound_before),
+            ("bound_after.txt", &self.bound_after),
+        ] {
+            writeln!(
+                std::fs::File::create(path.join(file_name))?,
+                "runtime: {:.2e}ns\nbound: {}",
+                self.runtime,
+                bound
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Measures the runtime of the implementation obtained by applying `actions` to `space`.
+/// Returns the minimum over `num_evals` evaluations, as the bound must be below every run.
+pub fn measure(
+    context: &dyn Context,
+    space: &SearchSpace,
+    actions: &[ActionEx],
+    num_evals: usize,
+) -> Result<f64, ActionError> {
+    let space = apply_all(space, actions)?;
+    let function = codegen::Function::build(&space);
+    Ok((0..num_evals)
+        .filter_map(|_| context.evaluate(&function, EvalMode::TestBound).ok())
+        .fold(std::f64::INFINITY, f64::min))
+}
+
+/// Shrinks `actions` to a minimal subset for which the bound of the resulting candidate is above
+/// `runtime`, the measured runtime of the implementation obtained by applying all of `actions`
+/// to `space`.  Returns `None` if the bounds of the original implementation and of the
+/// candidates along its path in the search tree are all below `runtime`.
+pub fn minimize_bound_violation(
+    context: &dyn Context,
+    space: &SearchSpace,
+    actions: &[ActionEx],
+    runtime: f64,
+) -> Result<Option<BoundViolation>, ActionError> {
+    // Find the shortest violating prefix first, as it is cheap and usually removes most of the
+    // decisions.
+    let mut current = space.clone();
+    let mut prefix_len = None;
+    for (idx, action) in std::iter::once(None)
+        .chain(actions.iter().map(Some))
+        .enumerate()
+    {
+        if let Some(action) = action {
+            current = action.apply_to(current)?;
+        }
+        if bound(&current, context).value() > runtime {
+            prefix_len = Some(idx);
+            break;
+        }
+    }
+    let prefix_len = match prefix_len {
+        Some(prefix_len) => prefix_len,
+        None => return Ok(None),
+    };
+    let bound_before = bound(&apply_all(space, actions)?, context);
+    info!(
+        "bound violated by a prefix of {} out of {} actions",
+        prefix_len,
+        actions.len()
+    );
+
+    let minimized = ddmin(actions[..prefix_len].to_vec(), |subset| {
+        apply_subset(space, subset)
+            .map(|space| bound(&space, context).value() > runtime)
+            .unwrap_or(false)
+    });
+    let bound_after = bound(&apply_all(space, &minimized)?, context);
+    info!("bound violated by a subset of {} actions", minimized.len());
+    Ok(Some(BoundViolation {
+        actions: minimized,
+        runtime,
+        bound_before,
+        bound_after,
+    }))
+}
+
+/// Applies a list of actions to a search space.
+fn apply_all(
+    space: &SearchSpace,
+    actions: &[ActionEx],
+) -> Result<SearchSpace, ActionError> {
+    actions
+        .iter()
+        .try_fold(space.clone(), |space, action| action.apply_to(space))
+}
+
+/// Applies a subset of the decisions of an implementation to a search space.  Returns `None` if
+/// a decision references an object created by a removed decision, such as the dimensions of a
+/// layout lowering, or if a decision fails to apply.
+fn apply_subset(space: &SearchSpace, actions: &[ActionEx]) -> Option<SearchSpace> {
+    let mut space = space.clone();
+    for action in actions {
+        if !warm_start::corresponds(space.ir_instance(), action) {
+            return None;
+        }
+        space = action.apply_to(space).ok()?;
+    }
+    Some(space)
+}
+
+/// Finds a minimal subset of `items` that passes `test`, which `items` must pass.  The subset
+/// is minimal in the sense that removing any single item makes it fail the test.  Items keep
+/// their relative order.
+fn ddmin<T: Clone, F>(mut items: Vec<T>, mut test: F) -> Vec<T>
+where
+    F: FnMut(&[T]) -> bool,
+{
+    if test(&[]) {
+        return vec![];
+    }
+    let mut num_chunks = 2;
+    while items.len() >= 2 {
+        let chunk_size = (items.len() + num_chunks - 1) / num_chunks;
+        let chunks = (0..items.len())
+            .step_by(chunk_size)
+            .map(|start| start..std::cmp::min(start + chunk_size, items.len()))
+            .collect::<Vec<_>>();
+        // Try to reduce to a single chunk, then to the complement of a chunk.
+        let subset = chunks
+            .iter()
+            .map(|range| items[range.clone()].to_vec())
+            .find(|subset| test(subset));
+        if let Some(subset) = subset {
+            debug!("reduced to a chunk of {} items", subset.len());
+            items = subset;
+            num_chunks = 2;
+            continue;
+        }
+        let complement = chunks
+            .iter()
+            .map(|range| {
+                let mut complement = items[..range.start].to_vec();
+                complement.extend_from_slice(&items[range.end..]);
+                complement
+            })
+            .find(|complement| test(complement));
+        if let Some(complement) = complement {
+            debug!("reduced to a complement of {} items", complement.len());
+            items = complement;
+            num_chunks = std::cmp::max(num_chunks - 1, 2);
+            continue;
+        }
+        if num_chunks >= items.len() {
+            break;
+        }
+        num_chunks = std::cmp::min(2 * num_chunks, items.len());
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ddmin;
+
+    /// Ensures `ddmin` finds the minimal subset when the test depends on a few items.
+    #[test]
+    fn ddmin_finds_minimal_subset() {
+        let items = (0..32).collect::<Vec<u32>>();
+        let test = |subset: &[u32]| subset.contains(&3) && subset.contains(&17);
+        assert_eq!(ddmin(items.clone(), test), vec![3, 17]);
+        assert_eq!(ddmin(items, |_| true), Vec::<u32>::new());
+    }
+}
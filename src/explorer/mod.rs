@@ -7,17 +7,29 @@ mod store;
 
 pub mod choice;
 pub mod config;
+pub mod eval_cache;
 pub mod eventlog;
+pub mod legacy_log;
 pub mod local_selection;
 pub mod mcts;
+pub mod metrics;
+pub mod minimize;
+pub mod pareto;
+pub mod scheduler;
+pub mod warm_start;
 
 pub use self::candidate::Candidate;
 pub use self::config::{BanditConfig, Config, SearchAlgorithm};
+pub use self::eval_cache::EvalCache;
 pub use self::logger::LogMessage;
+pub use self::pareto::ParetoFront;
+pub use self::scheduler::{find_best_scheduled, KernelSearch};
 
 use self::choice::fix_order;
+use self::eval_cache::CachedStore;
 use self::monitor::{monitor, MonitorMessage};
 use self::parallel_list::ParallelCandidateList;
+use self::scheduler::SchedulerHandle;
 use self::store::Store;
 
 use crate::device::{Context, EvalMode};
@@ -75,6 +87,7 @@ struct MctsBuilder<'a> {
     bandit_config: &'a BanditConfig,
     context: &'a dyn Context,
     check_result_fn: Option<&'a CheckResultFn<'a>>,
+    scheduler: Option<&'a SchedulerHandle<'a>>,
 }
 
 impl<'a> MctsBuilder<'a> {
@@ -82,7 +95,7 @@ impl<'a> MctsBuilder<'a> {
         self,
         tree_policy: Box<dyn mcts::TreePolicy<N, E>>,
         default_policy: Box<dyn mcts::TreePolicy<N, E>>,
-    ) -> Option<Candidate>
+    ) -> ParetoFront<Candidate>
     where
         N: Sync + Send + std::fmt::Debug + Default + mcts::Reset,
         E: Sync + Send + std::fmt::Debug + Default + mcts::Reset,
@@ -93,6 +106,7 @@ impl<'a> MctsBuilder<'a> {
             bandit_config,
             context,
             check_result_fn,
+            scheduler,
         } = self;
 
         crossbeam::scope(|scope| {
@@ -102,6 +116,7 @@ impl<'a> MctsBuilder<'a> {
                 .name("Telamon - Logger".to_string())
                 .spawn(|_| unwrap!(logger::log(config, log_receiver))));
 
+            let priors = warm_start::load_priors(config, &space);
             let store = mcts::MctsStore::new(
                 space,
                 context,
@@ -109,7 +124,8 @@ impl<'a> MctsBuilder<'a> {
                 tree_policy,
                 default_policy,
                 log_sender.clone(),
-            );
+            )
+            .with_warm_start(priors);
 
             unwrap!(scope
                 .builder()
@@ -119,7 +135,8 @@ impl<'a> MctsBuilder<'a> {
                     store,
                     context,
                     log_sender,
-                    check_result_fn
+                    check_result_fn,
+                    scheduler,
                 ))
                 .unwrap()
                 .join())
@@ -136,6 +153,28 @@ pub fn find_best_ex(
     candidates: Vec<Candidate>,
     check_result_fn: Option<&CheckResultFn<'_>>,
 ) -> Option<Candidate> {
+    find_pareto_front(config, context, candidates, check_result_fn).into_best()
+}
+
+/// Same as `find_best_ex`, but returns all the candidates that are not dominated for the runtime
+/// and the objectives listed in `config.pareto_objectives`, sorted by increasing runtime.
+pub fn find_pareto_front(
+    config: &Config,
+    context: &dyn Context,
+    candidates: Vec<Candidate>,
+    check_result_fn: Option<&CheckResultFn<'_>>,
+) -> ParetoFront<Candidate> {
+    find_pareto_front_ex(config, context, candidates, check_result_fn, None)
+}
+
+/// Same as `find_pareto_front`, but obtains the evaluation slots from `scheduler` if provided.
+fn find_pareto_front_ex(
+    config: &Config,
+    context: &dyn Context,
+    candidates: Vec<Candidate>,
+    check_result_fn: Option<&CheckResultFn<'_>>,
+    scheduler: Option<&SchedulerHandle<'_>>,
+) -> ParetoFront<Candidate> {
     match config.algorithm {
         config::SearchAlgorithm::Mcts(ref bandit_config) => {
             assert!(candidates.len() == 1);
@@ -146,6 +185,7 @@ pub fn find_best_ex(
                 bandit_config,
                 context,
                 check_result_fn,
+                scheduler,
             };
 
             let default_policy = Box::new(bandit_config.new_nodes_order);
@@ -177,6 +217,9 @@ pub fn find_best_ex(
             }
         }
         config::SearchAlgorithm::BoundOrder => crossbeam::scope(|scope| {
+            if !config.warm_start.is_empty() {
+                warn!("warm start is only supported by the MCTS search algorithm");
+            }
             let (log_sender, log_receiver) = sync::mpsc::sync_channel(100);
             unwrap!(scope
                 .builder()
@@ -193,7 +236,8 @@ pub fn find_best_ex(
                     candidate_list,
                     context,
                     log_sender,
-                    check_result_fn
+                    check_result_fn,
+                    scheduler,
                 ))
                 .unwrap()
                 .join())
@@ -203,16 +247,22 @@ pub fn find_best_ex(
 }
 
 /// Launch all threads needed for the search. wait for each one of them to finish. Monitor is
-/// supposed to return the best candidate found
+/// supposed to return the Pareto front of the candidates found
 fn launch_search<T: Store>(
     config: &Config,
     candidate_store: T,
     context: &dyn Context,
     log_sender: sync::mpsc::SyncSender<LogMessage<T::Event>>,
     check_result_fn: Option<&CheckResultFn<'_>>,
-) -> Option<Candidate> {
+    scheduler: Option<&SchedulerHandle<'_>>,
+) -> ParetoFront<Candidate> {
     let (monitor_sender, monitor_receiver) = futures::sync::mpsc::channel(100);
-    let maybe_candidate = crossbeam::scope(|scope| {
+    let eval_cache = config.load_eval_cache().unwrap_or_else(|err| {
+        error!("Unable to load the evaluation cache: {}", err);
+        None
+    });
+    let candidate_store = CachedStore::new(candidate_store, eval_cache);
+    let front = crossbeam::scope(|scope| {
         let best_cand_opt = scope
             .builder()
             .name("Telamon - Monitor".to_string())
@@ -232,6 +282,7 @@ fn launch_search<T: Store>(
             monitor_sender,
             context,
             check_result_fn,
+            scheduler,
         );
         unwrap!(best_cand_opt.join())
     })
@@ -240,17 +291,26 @@ fn launch_search<T: Store>(
     // exploring the candidate store anymore, so the stats printer
     // should have a consistent view on the tree.
     candidate_store.print_stats();
-    maybe_candidate
+    if let Some(eval_cache) = candidate_store.eval_cache() {
+        eval_cache
+            .save()
+            .unwrap_or_else(|err| error!("Unable to save the evaluation cache: {}", err));
+    }
+    front
 }
 
 /// Defines the work that explorer threads will do in a closure that will be passed to
-/// context.async_eval. Also defines a callback that will be executed by the evaluator
+/// context.async_eval. Also defines a callback that will be executed by the evaluator.
+/// Implementations found in the evaluation cache are sent to the monitor without being evaluated,
+/// unless their results must be checked.  When running under a scheduler, each candidate is
+/// explored only once an evaluation slot is granted.
 fn explore_space<T>(
     config: &Config,
-    candidate_store: &T,
-    eval_sender: futures::sync::mpsc::Sender<MonitorMessage<T>>,
+    candidate_store: &CachedStore<T>,
+    eval_sender: futures::sync::mpsc::Sender<MonitorMessage<CachedStore<T>>>,
     context: &dyn Context,
     check_result_fn: Option<&CheckResultFn<'_>>,
+    scheduler: Option<&SchedulerHandle<'_>>,
 ) where
     T: Store,
 {
@@ -262,10 +322,59 @@ fn explore_space<T>(
     let barrier = std::sync::Barrier::new(config.num_workers);
 
     context.async_eval(config.num_workers, EvalMode::FindBest, &|evaluator| {
-        while let Some((cand, payload)) = candidate_store.explore(context) {
+        loop {
+            let slot = match scheduler.map(SchedulerHandle::acquire) {
+                Some(None) => break,
+                Some(Some(slot)) => Some(slot),
+                None => None,
+            };
+            let (cand, payload) = match candidate_store.explore(context) {
+                Some(explored) => explored,
+                None => {
+                    // Nothing is left to evaluate with the slot.
+                    if let Some(slot) = slot {
+                        slot.cancel();
+                    }
+                    break;
+                }
+            };
             let space = fix_order(cand.space);
             let eval_sender = eval_sender.clone();
+            let eval_cache = candidate_store.eval_cache();
+            let cached = eval_cache.map(|eval_cache| {
+                let key = EvalCache::key(context, &space);
+                (key, eval_cache.get(key))
+            });
+            let cache_key = cached.map(|(key, _)| key);
+            if let Some((_, Some(eval))) = cached {
+                // Checking the results requires running the kernel, so cached runtimes are only
+                // used for candidates that would not be checked.
+                let mut best = best_mutex.lock().unwrap();
+                let is_checked = check_result_fn.is_some()
+                    && (config.check_all || best.is_none() || Some(eval) < *best);
+                if !is_checked {
+                    if best.is_none() || Some(eval) < *best {
+                        *best = Some(eval);
+                    }
+                    std::mem::drop(best);
+                    if let Some(slot) = slot {
+                        slot.cancel();
+                    }
+
+                    let leaf = Candidate { space, ..cand };
+                    if let Err(err) = executor::spawn(
+                        eval_sender.send((leaf, eval, payload, true)).map(|_| ()),
+                    )
+                    .wait_future()
+                    {
+                        warn!("Got disconnected , {:?}", err);
+                    }
+                    continue;
+                }
+            }
+
             evaluator.add_kernel(Candidate { space, ..cand }, move |leaf, compiled| {
+                let device_lock = slot.as_ref().map(|slot| slot.lock_device());
                 let mut best = best_mutex.lock().unwrap();
                 let n_evals = n_evals.fetch_add(1, Ordering::SeqCst);
 
@@ -316,9 +425,19 @@ fn explore_space<T>(
                     *best = Some(eval);
                 }
 
-                if let Err(err) =
-                    executor::spawn(eval_sender.send((leaf, eval, payload)).map(|_| ()))
-                        .wait_future()
+                if let (Some(eval_cache), Some(key)) = (eval_cache, cache_key) {
+                    eval_cache.insert(key, eval);
+                }
+
+                std::mem::drop(device_lock);
+                if let Some(slot) = slot {
+                    slot.finish(eval);
+                }
+
+                if let Err(err) = executor::spawn(
+                    eval_sender.send((leaf, eval, payload, false)).map(|_| ()),
+                )
+                .wait_future()
                 {
                     warn!("Got disconnected , {:?}", err);
                 }
@@ -3,12 +3,16 @@
 //! Store accordingly.
 use crate::device::Context;
 use crate::explorer::candidate::Candidate;
-use crate::explorer::config::Config;
+use crate::explorer::choice::ActionEx;
+use crate::explorer::config::{Config, SearchAlgorithm};
 use crate::explorer::logger::LogMessage;
+use crate::explorer::pareto::{Evaluation, ParetoFront};
 use crate::explorer::store::Store;
+use crate::model::roofline::Roofline;
 use futures::prelude::*;
 use futures::{executor, future, task, Async};
 use log::warn;
+use rpds::List;
 use serde::{Deserialize, Serialize};
 use std::io::Write;
 use std::sync::{
@@ -20,7 +24,9 @@ use std::time::{Duration, Instant};
 use std::{self, thread};
 use utils::unwrap;
 
-pub type MonitorMessage<T> = (Candidate, f64, <T as Store>::PayLoad);
+/// An evaluated candidate, with its runtime, the payload to commit to the store and whether the
+/// runtime comes from the evaluation cache.
+pub type MonitorMessage<T> = (Candidate, f64, <T as Store>::PayLoad, bool);
 
 /// Indicates why the exploration was terminated.
 #[derive(Serialize, Deserialize)]
@@ -46,34 +52,45 @@ impl std::fmt::Display for TerminationReason {
 
 struct Status {
     best_candidate: Option<(Candidate, f64)>,
+    /// Implementations which are not dominated for the configured objectives.
+    front: ParetoFront<Candidate>,
     num_evaluations: usize,
+    /// Value above which candidates are pruned.
+    cut: f64,
 }
 
-impl Default for Status {
-    fn default() -> Self {
+impl Status {
+    fn new(config: &Config) -> Self {
+        let cut = match &config.algorithm {
+            SearchAlgorithm::Mcts(bandit_config) => bandit_config.initial_cut,
+            SearchAlgorithm::BoundOrder => None,
+        };
         Status {
             best_candidate: None,
+            front: ParetoFront::new(config.pareto_objectives.clone()),
             num_evaluations: 0,
+            cut: cut.unwrap_or(std::f64::INFINITY),
         }
     }
 }
 
 /// This function is an interface supposed to make a connection between the
 /// Store and the evaluator. Retrieve evaluations, retains the results and
-/// update the store accordingly.
+/// update the store accordingly.  Returns the Pareto front of the evaluated
+/// candidates.
 pub fn monitor<T, E>(
     config: &Config,
     context: &dyn Context,
     candidate_store: &T,
     recv: futures::sync::mpsc::Receiver<MonitorMessage<T>>,
     log_sender: sync::mpsc::SyncSender<LogMessage<E>>,
-) -> Option<Candidate>
+) -> ParetoFront<Candidate>
 where
     T: Store,
 {
     warn!("Monitor waiting for evaluation results");
     let t0 = Instant::now();
-    let mut status = Status::default();
+    let mut status = Status::new(config);
 
     let res = {
         let log_sender_ref = &log_sender;
@@ -124,7 +141,46 @@ where
             }));
         }
     }
-    status.best_candidate.map(|x| x.0)
+    if let Some((best, runtime)) = &status.best_candidate {
+        let roofline = Roofline::new(best.space.ir_instance(), context);
+        if let Some(efficiency) = roofline.efficiency(*runtime) {
+            warn!(
+                "best candidate at {:.2}% of the roofline",
+                100. * efficiency
+            );
+        }
+        unwrap!(log_sender.send(LogMessage::Roofline {
+            roofline,
+            runtime: *runtime,
+        }));
+    }
+    if !config.pareto_objectives.is_empty() {
+        dump_front(config, &status.front)
+            .unwrap_or_else(|err| warn!("Error while dumping the Pareto front: {}", err));
+    }
+    status.front
+}
+
+/// Saves the evaluations and actions of the candidates in the front.
+fn dump_front(config: &Config, front: &ParetoFront<Candidate>) -> std::io::Result<()> {
+    #[derive(Serialize)]
+    struct Entry<'a> {
+        evaluation: &'a Evaluation,
+        actions: &'a List<ActionEx>,
+    }
+
+    let entries = front
+        .iter()
+        .map(|(evaluation, cand)| Entry {
+            evaluation,
+            actions: &cand.actions,
+        })
+        .collect::<Vec<_>>();
+    write!(
+        std::fs::File::create(config.output_path("pareto_front.json")?)?,
+        "{}",
+        serde_json::to_string(&entries).unwrap()
+    )
 }
 
 /// Depending on the value of the evaluation we just did, computes the new cut
@@ -156,7 +212,7 @@ fn handle_message<T, E>(
 where
     T: Store,
 {
-    let (cand, eval, payload) = message;
+    let (cand, eval, payload, is_cached) = message;
 
     let wall = start_time.elapsed();
     warn!("Got a new evaluation after {}, bound: {:.4e} score: {:.4e}, current best: {:.4e}",
@@ -165,7 +221,15 @@ where
           eval,
           status.best_candidate.as_ref().map_or(std::f64::INFINITY, |best:
                                                 &(Candidate, f64)| best.1 ));
-    candidate_store.commit_evaluation(&cand.actions, payload, eval);
+    let evaluation = Evaluation::new(eval, &cand.space);
+    candidate_store.commit_evaluation(&cand.actions, payload, &evaluation);
+
+    if !status.front.is_covered(&evaluation) {
+        if !config.pareto_objectives.is_empty() {
+            warn!("New candidate in the Pareto front: {}", evaluation);
+        }
+        status.front.insert(evaluation, cand.clone());
+    }
 
     let change = status
         .best_candidate
@@ -174,7 +238,8 @@ where
         .unwrap_or(true);
     if change {
         warn!("Got a new best candidate, score: {:.3e}, {}", eval, cand);
-        candidate_store.update_cut(get_new_cut(config, eval));
+        status.cut = get_new_cut(config, eval);
+        candidate_store.update_cut(status.cut);
         let log_message = LogMessage::NewBest {
             score: eval,
             cpt: status.num_evaluations,
@@ -193,6 +258,9 @@ where
                     serde_json::to_string(&cand.actions).unwrap()
                 )?;
 
+                cand.space
+                    .dump_resources(context, output_path.join("resources.json"))?;
+
                 cand.space.dump_code(context, output_path.join("code"))
             })
             .unwrap_or_else(|err| warn!("Error while dumping candidate: {}", err));
@@ -207,9 +275,14 @@ where
     // we don't count the corresponding evaluation towards the number
     // of evaluations performed (a sequential, non-parallel
     // implementation of the search algorithm would not have selected
-    // this candidate since it would get cut).
-    if !eval.is_infinite() {
+    // this candidate since it would get cut).  Runtimes found in the evaluation cache are not
+    // counted either, since the candidate was not evaluated.
+    if !eval.is_infinite() && !is_cached {
         status.num_evaluations += 1;
+        unwrap!(log_sender.send(LogMessage::Progress {
+            num_evaluations: status.num_evaluations,
+            cut: status.cut,
+        }));
         if let Some(max_evaluations) = config.max_evaluations {
             if status.num_evaluations >= max_evaluations {
                 return Err(TerminationReason::MaxEvaluations);
@@ -3,6 +3,7 @@ pub use crate::explorer::candidate::Candidate;
 
 use crate::device::Context;
 use crate::explorer::choice;
+use crate::explorer::pareto::Evaluation;
 use crate::explorer::store::Store;
 use interval_heap::IntervalHeap;
 use log::{info, warn};
@@ -24,7 +25,7 @@ impl Store for ParallelCandidateList {
         &self,
         _actions: &List<choice::ActionEx>,
         (): Self::PayLoad,
-        _: f64,
+        _: &Evaluation,
     ) {
     }
 
@@ -0,0 +1,256 @@
+//! Multi-objective evaluation of implementations.
+//!
+//! Besides the measured runtime, implementations can be compared on static resources read from
+//! the search space, such as the amount of shared memory or the number of threads they use.  The
+//! monitor keeps an archive of the implementations which are not dominated on the configured
+//! objectives: the Pareto front.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::search_space::SearchSpace;
+
+/// An objective to minimize, in addition to the runtime.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    /// The amount of shared memory used by a block, in bytes.
+    SharedMemUsed,
+    /// The number of threads in a block.
+    NumThreads,
+    /// The maximal unrolling factor across instructions.
+    UnrollFactor,
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Objective::SharedMemUsed => "shared_mem_used",
+            Objective::NumThreads => "num_threads",
+            Objective::UnrollFactor => "unroll_factor",
+        })
+    }
+}
+
+/// The vector-valued evaluation of a fully specified implementation.  All values are to be
+/// minimized.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Evaluation {
+    /// The measured execution time, in nanoseconds.
+    pub runtime: f64,
+    /// The amount of shared memory used by a block, in bytes.
+    pub shared_mem_used: u32,
+    /// The number of threads in a block.
+    pub num_threads: u32,
+    /// The maximal unrolling factor across instructions.
+    pub unroll_factor: u32,
+}
+
+impl Evaluation {
+    /// Creates the evaluation of a fully specified implementation from its runtime.  The static
+    /// resources are read from the search space.
+    pub fn new(runtime: f64, space: &SearchSpace) -> Self {
+        let domain = space.domain();
+        let unroll_factor = space
+            .ir_instance()
+            .insts()
+            .map(|inst| domain.get_unroll_factor(inst.id()).min)
+            .max()
+            .unwrap_or(1);
+        Evaluation {
+            runtime,
+            shared_mem_used: domain.get_shared_mem_used().min,
+            num_threads: domain.get_num_threads().min,
+            unroll_factor,
+        }
+    }
+
+    /// Returns the value of a secondary objective.
+    pub fn get(&self, objective: Objective) -> u32 {
+        match objective {
+            Objective::SharedMemUsed => self.shared_mem_used,
+            Objective::NumThreads => self.num_threads,
+            Objective::UnrollFactor => self.unroll_factor,
+        }
+    }
+
+    /// Indicates if `self` is at least as good as `other` on the runtime and on all the given
+    /// objectives, and strictly better on at least one of them.
+    pub fn dominates(&self, other: &Evaluation, objectives: &[Objective]) -> bool {
+        if self.runtime > other.runtime {
+            return false;
+        }
+        let mut strictly_better = self.runtime < other.runtime;
+        for &objective in objectives {
+            let (lhs, rhs) = (self.get(objective), other.get(objective));
+            if lhs > rhs {
+                return false;
+            }
+            strictly_better |= lhs < rhs;
+        }
+        strictly_better
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:.4e}ns (shared_mem_used: {}, num_threads: {}, unroll_factor: {})",
+            self.runtime, self.shared_mem_used, self.num_threads, self.unroll_factor
+        )
+    }
+}
+
+/// An archive of the non-dominated items seen so far, sorted by increasing runtime.
+#[derive(Clone, Debug)]
+pub struct ParetoFront<T> {
+    objectives: Vec<Objective>,
+    entries: Vec<(Evaluation, T)>,
+}
+
+impl<T> ParetoFront<T> {
+    /// Creates an empty front for the runtime and the given secondary objectives.
+    pub fn new(objectives: Vec<Objective>) -> Self {
+        ParetoFront {
+            objectives,
+            entries: Vec::new(),
+        }
+    }
+
+    /// The secondary objectives considered by the front.
+    pub fn objectives(&self) -> &[Objective] {
+        &self.objectives
+    }
+
+    /// Inserts an item in the front.  Returns `false` if the item was dominated by (or equivalent
+    /// to) an item already in the front, in which case the front is left unchanged.  Otherwise,
+    /// the items dominated by the new item are removed.
+    pub fn insert(&mut self, eval: Evaluation, item: T) -> bool {
+        if self.is_covered(&eval) {
+            return false;
+        }
+        let objectives = &self.objectives;
+        self.entries
+            .retain(|(other, _)| !eval.dominates(other, objectives));
+        let pos = self
+            .entries
+            .iter()
+            .position(|(other, _)| other.runtime > eval.runtime)
+            .unwrap_or_else(|| self.entries.len());
+        self.entries.insert(pos, (eval, item));
+        true
+    }
+
+    /// Indicates if an item with the given evaluation would be rejected by `insert`.
+    pub fn is_covered(&self, eval: &Evaluation) -> bool {
+        let objectives = &self.objectives;
+        !eval.runtime.is_finite()
+            || self.entries.iter().any(|(other, _)| {
+                other.dominates(eval, objectives)
+                    || (other.runtime == eval.runtime
+                        && objectives.iter().all(|&o| other.get(o) == eval.get(o)))
+            })
+    }
+
+    /// The number of items in the front.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Indicates if the front is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates on the items of the front, by increasing runtime.
+    pub fn iter(&self) -> impl Iterator<Item = &(Evaluation, T)> {
+        self.entries.iter()
+    }
+
+    /// Returns the fastest item of the front.
+    pub fn best(&self) -> Option<&(Evaluation, T)> {
+        self.entries.first()
+    }
+
+    /// Consumes the front and returns its fastest item.
+    pub fn into_best(self) -> Option<T> {
+        self.entries.into_iter().next().map(|(_, item)| item)
+    }
+
+    /// Returns the fastest item satisfying the given resource budget.
+    pub fn best_with<F>(&self, mut budget: F) -> Option<&(Evaluation, T)>
+    where
+        F: FnMut(&Evaluation) -> bool,
+    {
+        self.entries.iter().find(|(eval, _)| budget(eval))
+    }
+
+    /// Applies a function to the items of the front.
+    pub fn map<U, F>(self, mut f: F) -> ParetoFront<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        ParetoFront {
+            objectives: self.objectives,
+            entries: self
+                .entries
+                .into_iter()
+                .map(|(eval, item)| (eval, f(item)))
+                .collect(),
+        }
+    }
+}
+
+impl<T> IntoIterator for ParetoFront<T> {
+    type Item = (Evaluation, T);
+    type IntoIter = std::vec::IntoIter<(Evaluation, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(runtime: f64, shared_mem_used: u32, num_threads: u32) -> Evaluation {
+        Evaluation {
+            runtime,
+            shared_mem_used,
+            num_threads,
+            unroll_factor: 1,
+        }
+    }
+
+    /// Ensures only the fastest item is kept when there are no secondary objectives.
+    #[test]
+    fn runtime_only() {
+        let mut front = ParetoFront::new(vec![]);
+        assert!(front.insert(eval(10., 0, 32), 0));
+        assert!(!front.insert(eval(12., 0, 16), 1));
+        assert!(front.insert(eval(8., 1024, 64), 2));
+        assert!(!front.insert(eval(8., 0, 64), 3));
+        assert_eq!(front.iter().map(|&(_, i)| i).collect::<Vec<_>>(), vec![2]);
+    }
+
+    /// Ensures trade-offs are kept and dominated items are removed.
+    #[test]
+    fn trade_offs() {
+        let objectives = vec![Objective::SharedMemUsed, Objective::NumThreads];
+        let mut front = ParetoFront::new(objectives);
+        assert!(front.insert(eval(10., 1024, 128), 0));
+        assert!(front.insert(eval(12., 0, 128), 1));
+        assert!(front.insert(eval(20., 0, 32), 2));
+        assert!(!front.insert(eval(21., 0, 64), 3));
+        assert!(front.insert(eval(9., 512, 128), 4));
+        assert_eq!(
+            front.iter().map(|&(_, i)| i).collect::<Vec<_>>(),
+            vec![4, 1, 2]
+        );
+        let within_budget = front.best_with(|eval| eval.num_threads <= 64);
+        assert_eq!(within_budget.map(|&(_, i)| i), Some(2));
+        assert_eq!(front.into_best(), Some(4));
+    }
+}
@@ -0,0 +1,402 @@
+//! Scheduling of several searches over a shared evaluation budget.
+//!
+//! Each kernel is explored by its own search, with its own candidate store, output directory and
+//! event log.  The searches run concurrently but must obtain an evaluation slot from the
+//! scheduler before exploring a new candidate.  Slots are shared across all kernels and are
+//! allocated by a UCB bandit over the recent relative improvements of each kernel, so that the
+//! evaluation time flows to the kernels that are still improving.
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+use utils::unwrap;
+
+use crate::device::Context;
+use crate::explorer::candidate::Candidate;
+use crate::explorer::config::Config;
+use crate::explorer::CheckResultFn;
+
+/// Weight of past improvements in the moving average of the improvements of a kernel.
+const IMPROVEMENT_DECAY: f64 = 0.9;
+/// Exploration constant of the UCB formula used to allocate slots.
+const EXPLORATION_CONSTANT: f64 = 0.05;
+
+/// A search to run under the scheduler.
+pub struct KernelSearch<'a> {
+    /// The configuration of the search.  The output directory and event log must be specific
+    /// to the kernel.  The timeout and the maximal number of evaluations are ignored in favor of
+    /// the shared ones.
+    pub config: Config,
+    pub context: &'a dyn Context,
+    pub candidates: Vec<Candidate>,
+    pub check_result_fn: Option<&'a CheckResultFn<'a>>,
+}
+
+/// Runs several searches concurrently, sharing `config.num_workers` evaluation slots, the
+/// timeout and the maximal number of evaluations from `config`.  Returns the best candidate
+/// found for each search.
+pub fn find_best_scheduled(
+    config: &Config,
+    searches: Vec<KernelSearch<'_>>,
+) -> Vec<Option<Candidate>> {
+    let scheduler = Scheduler::new(config, searches.len());
+    let best = crossbeam::scope(|scope| {
+        let handles = searches
+            .into_iter()
+            .enumerate()
+            .map(|(index, search)| {
+                let scheduler = &scheduler;
+                unwrap!(scope
+                    .builder()
+                    .name(format!("Telamon - Kernel {}", index))
+                    .spawn(move |_| {
+                        let mut config = search.config;
+                        config.timeout = None;
+                        config.max_evaluations = None;
+                        super::find_pareto_front_ex(
+                            &config,
+                            search.context,
+                            search.candidates,
+                            search.check_result_fn,
+                            Some(&scheduler.handle(index)),
+                        )
+                        .into_best()
+                    }))
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| unwrap!(handle.join()))
+            .collect()
+    })
+    .unwrap();
+    scheduler.print_stats();
+    best
+}
+
+/// Statistics about the evaluations of a kernel.
+#[derive(Default)]
+struct KernelStats {
+    /// Number of threads waiting for a slot.
+    num_waiting: usize,
+    /// Number of slots granted to the kernel.
+    num_granted: usize,
+    /// Best runtime found so far.
+    best: Option<f64>,
+    /// Moving average of the relative improvements of the best runtime.
+    improvement: f64,
+}
+
+impl KernelStats {
+    /// Records the runtime of an implementation of the kernel.
+    fn record(&mut self, eval: f64) {
+        let improvement = match self.best {
+            _ if !eval.is_finite() => 0.,
+            None => 1.,
+            Some(best) if eval < best => (best - eval) / best,
+            Some(_) => 0.,
+        };
+        if eval.is_finite() && self.best.map(|best| eval < best).unwrap_or(true) {
+            self.best = Some(eval);
+        }
+        self.improvement =
+            IMPROVEMENT_DECAY * self.improvement + (1. - IMPROVEMENT_DECAY) * improvement;
+    }
+
+    /// Computes the UCB score of the kernel.
+    fn score(&self, total_granted: usize) -> f64 {
+        if self.num_granted == 0 {
+            std::f64::INFINITY
+        } else {
+            let exploration = (total_granted as f64).ln() / self.num_granted as f64;
+            self.improvement + EXPLORATION_CONSTANT * exploration.sqrt()
+        }
+    }
+}
+
+struct State {
+    num_free_slots: usize,
+    num_granted: usize,
+    kernels: Vec<KernelStats>,
+}
+
+impl State {
+    /// Selects the kernel to which the next free slot goes, among the waiting ones.
+    fn select(&self) -> Option<usize> {
+        let mut selected: Option<(usize, f64)> = None;
+        for (index, kernel) in self.kernels.iter().enumerate() {
+            if kernel.num_waiting == 0 {
+                continue;
+            }
+            let score = kernel.score(self.num_granted);
+            if selected.map(|(_, best)| score > best).unwrap_or(true) {
+                selected = Some((index, score));
+            }
+        }
+        selected.map(|(index, _)| index)
+    }
+}
+
+/// Allocates evaluation slots to several searches.
+pub struct Scheduler {
+    state: Mutex<State>,
+    condvar: Condvar,
+    /// Ensures only one implementation is measured at a time, as the searches may share the
+    /// same device.
+    device_lock: Mutex<()>,
+    deadline: Option<Instant>,
+    max_evaluations: Option<usize>,
+}
+
+impl Scheduler {
+    /// Creates a scheduler for `num_kernels` searches, with the shared budget from `config`.
+    pub fn new(config: &Config, num_kernels: usize) -> Self {
+        let kernels = (0..num_kernels).map(|_| KernelStats::default()).collect();
+        Scheduler {
+            state: Mutex::new(State {
+                num_free_slots: config.num_workers,
+                num_granted: 0,
+                kernels,
+            }),
+            condvar: Condvar::new(),
+            device_lock: Mutex::new(()),
+            deadline: config
+                .timeout
+                .map(|mins| Instant::now() + Duration::from_secs(mins * 60)),
+            max_evaluations: config.max_evaluations,
+        }
+    }
+
+    /// Returns the handle through which the search on the given kernel obtains its slots.
+    pub fn handle(&self, kernel: usize) -> SchedulerHandle<'_> {
+        SchedulerHandle {
+            scheduler: self,
+            kernel,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("state: poisoned")
+    }
+
+    /// Indicates if the shared budget is exhausted.
+    fn is_over(&self, state: &State) -> bool {
+        self.deadline.map(|d| Instant::now() >= d).unwrap_or(false)
+            || self
+                .max_evaluations
+                .map(|max| state.num_granted >= max)
+                .unwrap_or(false)
+    }
+
+    /// Blocks until the kernel is granted a slot.  Returns `None` once the budget is exhausted.
+    fn acquire(&self, kernel: usize) -> Option<EvalSlot<'_>> {
+        let mut state = self.lock();
+        state.kernels[kernel].num_waiting += 1;
+        loop {
+            if self.is_over(&state) {
+                state.kernels[kernel].num_waiting -= 1;
+                self.condvar.notify_all();
+                return None;
+            }
+            if state.num_free_slots > 0 && state.select() == Some(kernel) {
+                state.num_free_slots -= 1;
+                state.num_granted += 1;
+                let stats = &mut state.kernels[kernel];
+                stats.num_waiting -= 1;
+                stats.num_granted += 1;
+                self.condvar.notify_all();
+                return Some(EvalSlot {
+                    scheduler: self,
+                    kernel,
+                    eval: None,
+                    is_counted: true,
+                });
+            }
+            state = match self.deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    self.condvar
+                        .wait_timeout(state, timeout)
+                        .expect("state: poisoned")
+                        .0
+                }
+                None => self.condvar.wait(state).expect("state: poisoned"),
+            };
+        }
+    }
+
+    /// Displays the number of slots granted to each kernel and its best runtime.
+    pub fn print_stats(&self) {
+        let state = self.lock();
+        for (index, kernel) in state.kernels.iter().enumerate() {
+            warn!(
+                "Kernel {}: {} evaluations, best {:?}",
+                index, kernel.num_granted, kernel.best
+            );
+        }
+    }
+}
+
+/// Obtains evaluation slots for the search on a kernel.
+#[derive(Copy, Clone)]
+pub struct SchedulerHandle<'a> {
+    scheduler: &'a Scheduler,
+    kernel: usize,
+}
+
+impl<'a> SchedulerHandle<'a> {
+    /// Blocks until an evaluation slot is available for the kernel.  Returns `None` once the
+    /// shared budget is exhausted, in which case the search must stop.
+    pub fn acquire(&self) -> Option<EvalSlot<'a>> {
+        self.scheduler.acquire(self.kernel)
+    }
+}
+
+/// The permission to evaluate one candidate.  The slot is released when dropped.
+pub struct EvalSlot<'a> {
+    scheduler: &'a Scheduler,
+    kernel: usize,
+    eval: Option<f64>,
+    /// Indicates if the slot counts towards the evaluation budget.
+    is_counted: bool,
+}
+
+impl<'a> EvalSlot<'a> {
+    /// Prevents other searches from measuring implementations while the guard is alive.
+    pub fn lock_device(&self) -> MutexGuard<'a, ()> {
+        self.scheduler
+            .device_lock
+            .lock()
+            .expect("device_lock: poisoned")
+    }
+
+    /// Records the runtime of the evaluated candidate and releases the slot.
+    pub fn finish(mut self, eval: f64) {
+        self.eval = Some(eval);
+    }
+
+    /// Releases the slot without counting it towards the evaluation budget, when no candidate
+    /// was explored or when the runtime of the candidate was already known.
+    pub fn cancel(mut self) {
+        self.is_counted = false;
+    }
+}
+
+impl<'a> Drop for EvalSlot<'a> {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.lock();
+        state.num_free_slots += 1;
+        if !self.is_counted {
+            state.num_granted -= 1;
+            state.kernels[self.kernel].num_granted -= 1;
+        }
+        if let Some(eval) = self.eval {
+            let stats = &mut state.kernels[self.kernel];
+            stats.record(eval);
+            info!(
+                "Kernel {}: evaluation {:.4e}ns, improvement {:.4}",
+                self.kernel, eval, stats.improvement
+            );
+        }
+        self.scheduler.condvar.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::fake;
+    use crate::explorer::config::SearchAlgorithm;
+    use crate::{helper, ir, model};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Ensures only the slots used to evaluate a candidate count towards the budget.
+    #[test]
+    fn budget_accounting() {
+        let config = Config {
+            num_workers: 1,
+            max_evaluations: Some(2),
+            ..Config::default()
+        };
+        let scheduler = Scheduler::new(&config, 1);
+        let handle = scheduler.handle(0);
+        handle.acquire().unwrap().cancel();
+        assert_eq!(scheduler.lock().num_granted, 0);
+        handle.acquire().unwrap().finish(1.);
+        std::mem::drop(handle.acquire().unwrap());
+        {
+            let state = scheduler.lock();
+            assert_eq!(state.num_granted, 2);
+            assert_eq!(state.num_free_slots, 1);
+            assert_eq!(state.kernels[0].num_granted, 2);
+            assert_eq!(state.kernels[0].best, Some(1.));
+        }
+        assert!(handle.acquire().is_none());
+    }
+
+    /// Ensures a search that runs out of candidates does not consume budget.
+    #[test]
+    fn exhausted_search() {
+        let context = fake::Context::<fake::Device>::default();
+        let signature = ir::Signature::new("exhausted");
+        let mut builder = helper::Builder::new(signature.into(), context.device());
+        builder.mov(&0f32);
+        let space = builder.get();
+        let bound = model::bound(&space, &context);
+        let output_dir = std::env::temp_dir()
+            .join(format!("telamon_scheduler_{}", std::process::id()));
+        let config = Config {
+            output_dir: output_dir.to_str().unwrap().to_string(),
+            num_workers: 1,
+            algorithm: SearchAlgorithm::BoundOrder,
+            check_all: true,
+            ..Config::default()
+        };
+        let num_evals = AtomicUsize::new(0);
+        let count = |_: &Candidate, _: &dyn Context| {
+            num_evals.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        };
+        let scheduler = Scheduler::new(&config, 1);
+        crate::explorer::find_pareto_front_ex(
+            &config,
+            &context,
+            vec![Candidate::new(space, bound)],
+            Some(&count),
+            Some(&scheduler.handle(0)),
+        );
+        assert!(num_evals.load(Ordering::SeqCst) > 0);
+        assert_eq!(
+            scheduler.lock().num_granted,
+            num_evals.load(Ordering::SeqCst)
+        );
+        std::fs::remove_dir_all(output_dir).unwrap();
+    }
+
+    /// Ensures slots go to unexplored kernels first, then to the kernels that improve.
+    #[test]
+    fn select_improving() {
+        let mut state = State {
+            num_free_slots: 1,
+            num_granted: 2,
+            kernels: (0..3).map(|_| KernelStats::default()).collect(),
+        };
+        for kernel in &mut state.kernels {
+            kernel.num_waiting = 1;
+        }
+        state.kernels[0].num_granted = 1;
+        state.kernels[1].num_granted = 1;
+        assert_eq!(state.select(), Some(2));
+
+        state.kernels[2].num_waiting = 0;
+        for &eval in &[10., 9., 5.] {
+            state.kernels[0].record(eval);
+        }
+        for &eval in &[10., 10., 10.] {
+            state.kernels[1].record(eval);
+        }
+        assert_eq!(state.select(), Some(0));
+        state.kernels[0].num_waiting = 0;
+        assert_eq!(state.select(), Some(1));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::device::Context;
 use crate::explorer::candidate::Candidate;
 use crate::explorer::choice::ActionEx;
+use crate::explorer::pareto::Evaluation;
 use rpds::List;
 use serde::Serialize;
 
@@ -18,12 +19,13 @@ pub trait Store: Sync {
     fn stop_exploration(&self) {
         self.update_cut(0.0);
     }
-    /// Commit the result of an evaluation back to Store
+    /// Commit the result of an evaluation back to Store. The evaluation holds both the
+    /// runtime and the static resources used by the implementation.
     fn commit_evaluation(
         &self,
         actions: &List<ActionEx>,
         payload: Self::PayLoad,
-        eval: f64,
+        eval: &Evaluation,
     );
     /// Retrieve a Candidate for evaluation, returns `None` if no candidate remains.
     fn explore(&self, context: &dyn Context) -> Option<(Candidate, Self::PayLoad)>;
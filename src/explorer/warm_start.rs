@@ -0,0 +1,170 @@
+//! Warm-starting the search from implementations of related kernels.
+//!
+//! When the sizes of a kernel change slightly, most decisions of the best implementation found
+//! for the previous sizes are usually still legal.  The decisions of prior implementations are
+//! translated onto the new search space so that the search can evaluate them first and bias its
+//! descents toward them.
+//!
+//! Kernels built by the same generator allocate their dimensions, instructions and memory blocks
+//! in the same order, so decisions are translated by matching identifiers.  Sizes are matched by
+//! their position in the list of possible sizes of the dimension.
+use std::io;
+use std::path::Path;
+
+use fxhash::FxHashSet;
+use log::{debug, error, info};
+use rpds::List;
+
+use crate::explorer::choice::ActionEx;
+use crate::explorer::config::Config;
+use crate::explorer::eventlog::EventLog;
+use crate::explorer::mcts;
+use crate::ir;
+use crate::offline_analysis::tree::CandidateTree;
+use crate::search_space::{Action, SearchSpace};
+
+/// Loads the decisions of a prior implementation.  `path` is either an event log, in which case
+/// the best implementation it contains is used, or a JSON list of actions such as the
+/// `actions.json` files written by the search.
+pub fn load_actions<P: AsRef<Path>>(path: P) -> io::Result<Vec<ActionEx>> {
+    let path = path.as_ref();
+    let is_event_log = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.contains(".tfrecord"))
+        .unwrap_or(false);
+    if !is_event_log {
+        let actions: List<ActionEx> =
+            serde_json::from_reader(std::fs::File::open(path)?)?;
+        return Ok(actions.iter().cloned().collect());
+    }
+
+    let mut tree = CandidateTree::new();
+    let mut best: Option<(f64, Vec<ActionEx>)> = None;
+    for message in EventLog::read_messages(path)? {
+        match message? {
+            mcts::Message::Node {
+                id,
+                parent,
+                mut children,
+                bound,
+                discovery_time,
+            } => tree.extend(id, discovery_time, parent, bound, &mut children),
+            mcts::Message::Trace { .. } => (),
+            mcts::Message::Evaluation { id, value, .. } => {
+                if let Some(value) = value {
+                    if best.as_ref().map(|&(b, _)| value < b).unwrap_or(true) {
+                        best = Some((value, tree.get_node(id).actions()));
+                    }
+                }
+            }
+        }
+    }
+    best.map(|(_, actions)| actions).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "no implementation in event log")
+    })
+}
+
+/// Indicates if the objects referenced by an action exist in `fun`.
+pub(super) fn corresponds(fun: &ir::Function, action: &ActionEx) -> bool {
+    let has_dim = |id: ir::DimId| fun.dims().any(|dim| dim.id() == id);
+    let has_inst = |id: ir::InstId| fun.insts().any(|inst| inst.id() == id);
+    let has_stmt = |id: ir::StmtId| match id {
+        ir::StmtId::Inst(id) => has_inst(id),
+        ir::StmtId::Dim(id) => has_dim(id),
+    };
+    let has_mem = |id: ir::MemId| fun.mem_blocks().any(|block| block.mem_id() == id);
+    match *action {
+        ActionEx::Action(Action::Size(dim, _)) => {
+            fun.static_dims().any(|static_dim| static_dim.id() == dim)
+        }
+        ActionEx::Action(Action::DimKind(dim, _))
+        | ActionEx::Action(Action::ThreadGuard(dim, _)) => has_dim(dim),
+        ActionEx::Action(Action::ThreadMapping(lhs, rhs, _)) => {
+            has_dim(lhs) && has_dim(rhs)
+        }
+        ActionEx::Action(Action::Order(lhs, rhs, _)) => has_stmt(lhs) && has_stmt(rhs),
+        ActionEx::Action(Action::MemSpace(mem, _))
+        | ActionEx::Action(Action::NumStages(mem, _)) => has_mem(mem),
+        ActionEx::Action(Action::VarNumStages(var, _)) => {
+            fun.variables().any(|variable| variable.id() == var)
+        }
+        ActionEx::Action(Action::InstFlag(inst, _)) => {
+            fun.mem_insts().any(|mem_inst| mem_inst.id() == inst)
+        }
+        ActionEx::LowerLayout {
+            mem,
+            ref st_dims,
+            ref ld_dims,
+        } => {
+            fun.layouts_to_lower().contains(&mem)
+                && st_dims.iter().chain(ld_dims).all(|&dim| has_dim(dim))
+        }
+        // Other actions are never taken by the explorer.
+        ActionEx::Action(_) => false,
+    }
+}
+
+/// Translates the decisions of a prior implementation onto `space`.  Decisions which reference
+/// objects that do not exist in `space`, or which fail to apply, are skipped.  Returns the
+/// decisions that could be applied, in the order they were applied.
+pub fn translate(space: &SearchSpace, actions: Vec<ActionEx>) -> Vec<ActionEx> {
+    let mut space = space.clone();
+    let mut applied = Vec::new();
+    let mut remaining = actions;
+    // The actions may be listed in any order, while a layout lowering must be applied before the
+    // decisions on the dimensions it creates.  Retry the failed actions as long as some progress
+    // is made.
+    loop {
+        let num_remaining = remaining.len();
+        remaining = remaining
+            .into_iter()
+            .filter(|action| {
+                if !corresponds(space.ir_instance(), action) {
+                    return true;
+                }
+                match action.apply_to(space.clone()) {
+                    Ok(new_space) => {
+                        space = new_space;
+                        applied.push(action.clone());
+                        false
+                    }
+                    Err(_) => true,
+                }
+            })
+            .collect();
+        if remaining.is_empty() || remaining.len() == num_remaining {
+            break;
+        }
+    }
+    for action in &remaining {
+        debug!("skipping warm-start action {:?}", action);
+    }
+    applied
+}
+
+/// Loads and translates onto `space` the prior implementations listed in `config.warm_start`.
+/// Priors which fail to load are reported and ignored.
+pub fn load_priors(config: &Config, space: &SearchSpace) -> Vec<FxHashSet<ActionEx>> {
+    config
+        .warm_start
+        .iter()
+        .filter_map(|path| match load_actions(path) {
+            Ok(actions) => {
+                let num_actions = actions.len();
+                let actions = translate(space, actions);
+                info!(
+                    "warm start from {}: {} out of {} decisions translated",
+                    path,
+                    actions.len(),
+                    num_actions
+                );
+                Some(actions.into_iter().collect())
+            }
+            Err(err) => {
+                error!("Unable to load warm start {}: {}", path, err);
+                None
+            }
+        })
+        .collect()
+}
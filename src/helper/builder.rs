@@ -107,6 +107,20 @@ impl Builder {
         self.inst(op)
     }
 
+    /// Adds a warp-level matrix multiply-accumulate instruction to the function.
+    pub fn mma_sync(
+        &mut self,
+        a: &dyn AutoOperand,
+        b: &dyn AutoOperand,
+        c: &dyn AutoOperand,
+        dims: ir::MmaDims,
+    ) -> InstId {
+        let a_op = self.get_op(a);
+        let b_op = self.get_op(b);
+        let c_op = self.get_op(c);
+        self.inst(op::MmaSync(a_op, b_op, c_op, dims))
+    }
+
     /// Adds a `Max` instruction to the fuction.
     pub fn max(&mut self, lhs: &dyn AutoOperand, rhs: &dyn AutoOperand) -> InstId {
         let lhs_op = self.get_op(lhs);
@@ -102,6 +102,15 @@ pub enum Error {
     MissingIterationDim { dim: ir::DimId },
     #[fail(display = "no mapping found between dimensions {} and {}", lhs, rhs)]
     MissingDimMapping { lhs: ir::DimId, rhs: ir::DimId },
+    #[fail(display = "dimension {} must have a fixed size of {}", dim, size)]
+    InvalidMmaDim { dim: ir::DimId, size: u32 },
+    #[fail(
+        display = "operator {} is not supported by the targeted device",
+        operator
+    )]
+    UnsupportedOperator { operator: &'static str },
+    #[fail(display = "the operand must not depend on dimension {}", dim)]
+    UnexpectedOperandDim { dim: ir::DimId },
 }
 
 impl From<TypeError> for Error {
@@ -310,6 +310,16 @@ impl<L> Function<L> {
         self.body.mem_blocks.block(id)
     }
 
+    /// Lists the stages memory blocks can be allocated with.
+    pub fn buffer_stages(&self) -> impl Iterator<Item = &'static mem::BufferStage> {
+        mem::BufferStage::all()
+    }
+
+    /// Retrieves a buffer stage given its ID.
+    pub fn buffer_stage(&self, id: mem::BufferStage) -> &mem::BufferStage {
+        mem::BufferStage::get(id)
+    }
+
     /// Retrieves an induction variable given its Id.
     pub fn induction_var(&self, id: ir::IndVarId) -> &ir::InductionVar<L> {
         &self.body.induction_vars[id.0 as usize]
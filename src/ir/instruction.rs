@@ -38,6 +38,7 @@ pub struct Instruction<L = LoweringMap> {
     operator: Operator<L>,
     id: InstId,
     iter_dims: FxHashSet<ir::DimId>,
+    initial_dims: FxHashSet<ir::DimId>,
     variable: Option<ir::VarId>,
     defined_vars: VecSet<ir::VarId>,
     used_vars: VecSet<ir::VarId>,
@@ -75,6 +76,7 @@ impl<L> Instruction<L> {
         Ok(Instruction {
             operator,
             id,
+            initial_dims: iter_dims.clone(),
             iter_dims,
             variable: None,
             defined_vars: VecSet::default(),
@@ -180,6 +182,15 @@ impl<L> Instruction<L> {
             .unwrap_or(false)
     }
 
+    /// Returns the dimension with the given role if the instruction is a warp-level
+    /// matrix multiply-accumulate.
+    pub fn mma_dim(&self, role: ir::MmaRole) -> Option<ir::DimId> {
+        match self.operator {
+            Operator::MmaSync(.., ref dims) => Some(dims.get(role)),
+            _ => None,
+        }
+    }
+
     /// Rename a dimension to another ID.
     pub fn merge_dims(&mut self, lhs: ir::DimId, rhs: ir::DimId) {
         self.operator.merge_dims(lhs, rhs);
@@ -190,6 +201,12 @@ impl<L> Instruction<L> {
         &self.iter_dims
     }
 
+    /// The iteration dimensions of the instruction when it was created, before the
+    /// search space nests it in other dimensions.
+    pub fn initial_dims(&self) -> &FxHashSet<ir::DimId> {
+        &self.initial_dims
+    }
+
     /// Adds a new iteration dimension. Indicates if the dimension was not already an
     /// iteration dimension.
     pub fn add_iteration_dimension(&mut self, dim: ir::DimId) -> bool {
@@ -214,6 +231,7 @@ impl Instruction<()> {
             operator: self.operator.freeze(cnt),
             id: self.id,
             iter_dims: self.iter_dims,
+            initial_dims: self.initial_dims,
             variable: self.variable,
             used_vars: self.used_vars,
             defined_vars: self.defined_vars,
@@ -28,6 +28,53 @@ impl fmt::Display for MemId {
     }
 }
 
+/// The number of rotating buffers a memory block can be allocated with.
+pub const NUM_STAGES: [u32; 2] = [1, 2];
+
+/// The stages a memory block can be allocated with, indexed by `BufferStage`.
+static BUFFER_STAGES: [BufferStage; 2] = [BufferStage(0), BufferStage(1)];
+
+/// Identifies one of the rotating buffers of a memory block.
+#[derive(
+    Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize, Ord, PartialOrd,
+)]
+#[repr(transparent)]
+pub struct BufferStage(pub u32);
+
+impl BufferStage {
+    /// Lists the possible stages of memory blocks.
+    pub fn all() -> impl Iterator<Item = &'static BufferStage> {
+        BUFFER_STAGES.iter()
+    }
+
+    /// Returns the stage with the given ID.
+    pub fn get(id: BufferStage) -> &'static BufferStage {
+        &BUFFER_STAGES[id.0 as usize]
+    }
+
+    /// The ID of the stage.
+    pub fn id(&self) -> BufferStage {
+        *self
+    }
+
+    /// The position of the stage among the buffers of a block.
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for BufferStage {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "stage{}", self.0)
+    }
+}
+
+impl From<BufferStage> for usize {
+    fn from(id: BufferStage) -> usize {
+        id.0 as usize
+    }
+}
+
 /// A block of memory allocated on the device by the kernel.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Block {
@@ -30,7 +30,7 @@ pub use self::induction_var::{IndVarId, InductionVar};
 pub use self::instruction::{InstId, Instruction};
 pub use self::mem::MemId;
 pub use self::operand::{DimMapScope, FloatLiteral, IntLiteral, LoweringMap, Operand};
-pub use self::operator::{BinOp, Operator, UnaryOp};
+pub use self::operator::{BinOp, MmaDims, MmaRole, Operator, UnaryOp};
 pub use self::size::{PartialSize, Size};
 pub use self::statement::{Statement, StmtId};
 pub use self::types::Type;
@@ -78,6 +78,8 @@ pub struct NewObjs {
     pub def_statements: Vec<(VarId, StmtId)>,
     pub var_dims: Vec<(VarId, DimId)>,
     pub var_mappings: Vec<(VarId, DimMappingId)>,
+    /// Buffer stages do not depend on the function, so this list always stays empty.
+    pub buffer_stages: Vec<mem::BufferStage>,
 }
 
 impl NewObjs {
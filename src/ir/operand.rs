@@ -187,6 +187,8 @@ pub enum Operand<L = LoweringMap> {
     Int(BigInt, u16),
     /// A float constant, on a given number of bits.
     Float(Ratio<BigInt>, u16),
+    /// A brain floating point constant, given by its bit pattern.
+    Bf16(u16),
     /// A value produced by an instruction. The boolean indicates if the `DimMap` can be
     /// lowered.
     Inst(InstId, Type, DimMap, DimMapScope<L>),
@@ -210,6 +212,7 @@ impl<L> Operand<L> {
         match self {
             Int(_, n_bit) => Type::I(*n_bit),
             Float(_, n_bit) => Type::F(*n_bit),
+            Bf16(_) => Type::Bf16,
             Addr(mem) => ir::Type::PtrTo(*mem),
             Index(..) => Type::I(32),
             Param(p) => p.t,
@@ -289,7 +292,7 @@ impl<L> Operand<L> {
     /// Indicates if the operand stays constant during the execution.
     pub fn is_constant(&self) -> bool {
         match self {
-            Int(..) | Float(..) | Addr(..) | Param(..) => true,
+            Int(..) | Float(..) | Bf16(..) | Addr(..) | Param(..) => true,
             Index(..) | Inst(..) | Reduce(..) | InductionVar(..) | Variable(..) => false,
         }
     }
@@ -308,6 +311,7 @@ impl Operand<()> {
         match self {
             Int(val, len) => Int(val, len),
             Float(val, len) => Float(val, len),
+            Bf16(bits) => Bf16(bits),
             Inst(id, t, dim_map, DimMapScope::Global(())) => {
                 let lowering_map = LoweringMap::for_dim_map(&dim_map, cnt);
                 Inst(id, t, dim_map, DimMapScope::Global(lowering_map))
@@ -333,6 +337,7 @@ impl<L> fmt::Display for Operand<L> {
         match self {
             Int(val, len) => write!(fmt, "{}u{}", val, len),
             Float(val, len) => write!(fmt, "{}f{}", val, len),
+            Bf16(bits) => write!(fmt, "0x{:04X}bf16", bits),
             Inst(id, _t, dim_map, _scope) => write!(fmt, "{:?} [{}]", id, dim_map),
             Index(id) => write!(fmt, "{}", id),
             Param(param) => write!(fmt, "{}", param),
@@ -351,6 +356,7 @@ impl<L> ir::IrDisplay<L> for Operand<L> {
         match self {
             Int(val, len) => write!(fmt, "{}u{}", val, len),
             Float(val, len) => write!(fmt, "{}f{}", val, len),
+            Bf16(bits) => write!(fmt, "0x{:04X}bf16", bits),
             Inst(id, _t, dim_map, _scope) => {
                 let source_dims = fun
                     .inst(*id)
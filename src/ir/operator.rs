@@ -141,6 +141,88 @@ impl UnaryOp {
     }
 }
 
+/// The role of a dimension in a warp-level matrix multiply-accumulate.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum MmaRole {
+    /// Thread dimension iterating on groups of lanes that hold the same rows.
+    LaneRows,
+    /// Thread dimension iterating on lanes within a group.
+    LaneCols,
+    /// Vector dimension iterating on the rows held by a lane.
+    FragRows,
+    /// Vector dimension iterating on the columns held by a lane.
+    FragCols,
+}
+
+impl MmaRole {
+    /// Lists all the roles.
+    pub const ALL: [MmaRole; 4] = [
+        MmaRole::LaneRows,
+        MmaRole::LaneCols,
+        MmaRole::FragRows,
+        MmaRole::FragCols,
+    ];
+
+    /// Returns the size the dimension with the role must have.
+    pub fn size(self) -> u32 {
+        match self {
+            MmaRole::LaneRows => 8,
+            MmaRole::LaneCols => 4,
+            MmaRole::FragRows | MmaRole::FragCols => 2,
+        }
+    }
+}
+
+/// The dimensions on which a warp-level matrix multiply-accumulate is distributed.
+///
+/// The operation computes a 16x8 tile of a matrix product over 8 reduction steps. Lane
+/// `(g, t)` of the warp, where `g` is the index along `lane_rows` and `t` the index along
+/// `lane_cols`, holds:
+/// * the elements `(g+8r, 2t+c)` of the left-hand side, of the accumulator and of the
+///   result, where `r` and `c` are the indexes along `frag_rows` and `frag_cols`,
+/// * the elements `(2t+c, g)` of the right-hand side, which thus cannot depend on
+///   `frag_rows`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MmaDims {
+    pub lane_rows: ir::DimId,
+    pub lane_cols: ir::DimId,
+    pub frag_rows: ir::DimId,
+    pub frag_cols: ir::DimId,
+}
+
+impl MmaDims {
+    /// Returns the dimension with the given role.
+    pub fn get(&self, role: MmaRole) -> ir::DimId {
+        match role {
+            MmaRole::LaneRows => self.lane_rows,
+            MmaRole::LaneCols => self.lane_cols,
+            MmaRole::FragRows => self.frag_rows,
+            MmaRole::FragCols => self.frag_cols,
+        }
+    }
+
+    /// Ensures the dimensions iterate on the instruction and have the right sizes.
+    fn check<L>(
+        &self,
+        iter_dims: &FxHashSet<ir::DimId>,
+        fun: &ir::Function<L>,
+    ) -> Result<(), ir::Error> {
+        for &role in &MmaRole::ALL {
+            let dim = self.get(role);
+            if !iter_dims.contains(&dim) {
+                Err(ir::Error::MissingIterationDim { dim })?;
+            }
+            if fun.dim(dim).possible_sizes() != Some(&[role.size()]) {
+                Err(ir::Error::InvalidMmaDim {
+                    dim,
+                    size: role.size(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// The operation performed by an instruction.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Operator<L = LoweringMap> {
@@ -163,6 +245,11 @@ pub enum Operator<L = LoweringMap> {
     TmpLd(Type, ir::MemId),
     /// Represents a store to a temporary memory that is not fully defined yet.
     TmpSt(Operand<L>, ir::MemId),
+    /// Multiplies a 16x8 half-precision matrix by a 8x8 half-precision matrix and adds
+    /// the result to a single-precision accumulator, cooperatively across a warp. The
+    /// operands are the left-hand side, the right-hand side and the accumulator, that
+    /// vary along the dimensions described in `MmaDims`.
+    MmaSync(Operand<L>, Operand<L>, Operand<L>, MmaDims),
 }
 
 impl<L> Operator<L> {
@@ -197,9 +284,21 @@ impl<L> Operator<L> {
                     })?;
                 }
                 ir::TypeError::check_equals(lhs.t(), rhs.t())?;
+                let is_add = match operator {
+                    ir::BinOp::Add | ir::BinOp::Sub => true,
+                    _ => false,
+                };
+                if is_add && !fun.device().supports_add_mul(lhs.t()) {
+                    Err(ir::Error::UnsupportedOperator {
+                        operator: operator.name(),
+                    })?;
+                }
             }
             Mul(ref lhs, ref rhs, rounding, res_type) => {
                 rounding.check(lhs.t())?;
+                if !fun.device().supports_add_mul(lhs.t()) {
+                    Err(ir::Error::UnsupportedOperator { operator: "mul" })?;
+                }
                 ir::TypeError::check_equals(lhs.t(), rhs.t())?;
                 match (lhs.t(), res_type) {
                     (x, z) if x == z => (),
@@ -226,6 +325,32 @@ impl<L> Operator<L> {
                 let pointer_type = pattern.pointer_type(fun.device());
                 ir::TypeError::check_equals(addr.t(), pointer_type)?;
             }
+            MmaSync(ref a, ref b, ref c, ref dims) => {
+                dims.check(iter_dims, fun)?;
+                let ab_t = a.t();
+                if ab_t != Type::F(16) && ab_t != Type::Bf16 {
+                    Err(ir::TypeError::UnexpectedType { t: ab_t })?;
+                }
+                if !fun.device().supports_mma(ab_t) {
+                    Err(ir::Error::UnsupportedOperator {
+                        operator: "mma_sync",
+                    })?;
+                }
+                ir::TypeError::check_equals(b.t(), ab_t)?;
+                ir::TypeError::check_equals(c.t(), Type::F(32))?;
+                let depends_on_rows = match *b {
+                    Operand::Inst(_, _, ref dim_map, _) => {
+                        dim_map.iter().any(|&(_, rhs)| rhs == dims.frag_rows)
+                    }
+                    Operand::Index(dim) => dim == dims.frag_rows,
+                    _ => false,
+                };
+                if depends_on_rows {
+                    Err(ir::Error::UnexpectedOperandDim {
+                        dim: dims.frag_rows,
+                    })?;
+                }
+            }
             TmpLd(..) | UnaryOp(..) | TmpSt(..) => (),
         }
         Ok(())
@@ -234,7 +359,7 @@ impl<L> Operator<L> {
     /// Returns the type of the value produced.
     pub fn t(&self) -> Option<Type> {
         match self {
-            Mad(_, _, op, _) => Some(op.t()),
+            Mad(_, _, op, _) | MmaSync(_, _, op, _) => Some(op.t()),
             Ld(t, ..) | TmpLd(t, _) | Mul(.., t) => Some(*t),
             BinOp(operator, lhs, ..) => Some(operator.t(lhs.t())),
             UnaryOp(operator, operand) => Some(operator.t(operand.t())),
@@ -251,6 +376,7 @@ impl<L> Operator<L> {
             Mad(mul_lhs, mul_rhs, add_rhs, _) => vec![mul_lhs, mul_rhs, add_rhs],
             UnaryOp(_, op) | Ld(_, op, _) | TmpSt(op, _) => vec![op],
             TmpLd(..) => vec![],
+            MmaSync(a, b, c, _) => vec![a, b, c],
         }
     }
 
@@ -263,6 +389,7 @@ impl<L> Operator<L> {
             Mad(mul_lhs, mul_rhs, add_rhs, _) => vec![mul_lhs, mul_rhs, add_rhs],
             UnaryOp(_, op, ..) | Ld(_, op, ..) | TmpSt(op, _) => vec![op],
             TmpLd(..) => vec![],
+            MmaSync(a, b, c, _) => vec![a, b, c],
         }
     }
 
@@ -271,7 +398,7 @@ impl<L> Operator<L> {
         match self {
             St(_, _, b, _) => *b,
             BinOp(..) | UnaryOp(..) | Mul(..) | Mad(..) | Ld(..) | TmpLd(..)
-            | TmpSt(..) => false,
+            | TmpSt(..) | MmaSync(..) => false,
         }
     }
 
@@ -344,6 +471,12 @@ impl<L> Operator<L> {
                 let oper1 = f(oper1);
                 TmpSt(oper1, id)
             }
+            MmaSync(a, b, c, dims) => {
+                let a = f(a);
+                let b = f(b);
+                let c = f(c);
+                MmaSync(a, b, c, dims)
+            }
         }
     }
 }
@@ -381,6 +514,13 @@ impl<L> ir::IrDisplay<L> for Operator<L> {
             ),
             TmpLd(_t, mem) => write!(fmt, "load({})", mem),
             TmpSt(src, mem) => write!(fmt, "store({}, {})", mem, src.display(function)),
+            MmaSync(a, b, c, _) => write!(
+                fmt,
+                "mma_sync({}, {}, {})",
+                a.display(function),
+                b.display(function),
+                c.display(function)
+            ),
         }
     }
 }
@@ -404,6 +544,7 @@ impl<L> std::fmt::Display for Operator<L> {
             St(dst, src, _side_effects, _ap) => write!(fmt, "Store({}, {})", dst, src),
             TmpLd(_t, mem) => write!(fmt, "TempLoad({})", mem),
             TmpSt(src, mem) => write!(fmt, "TempStore({}, {})", mem, src),
+            MmaSync(a, b, c, _) => write!(fmt, "MmaSync({}, {}, {})", a, b, c),
         }
     }
 }
@@ -11,6 +11,9 @@ pub enum Type {
     I(u16),
     /// Type for floating point values, with a fixed number of bits.
     F(u16),
+    /// Type for 16-bit brain floating point values, with 8 bits of exponent and 7 bits of
+    /// mantissa.
+    Bf16,
     /// Pointer type of the given memory space.
     PtrTo(ir::MemId),
 }
@@ -20,14 +23,14 @@ impl Type {
     pub fn is_integer(self) -> bool {
         match self {
             Type::I(_) | Type::PtrTo(_) => true,
-            Type::F(_) => false,
+            Type::F(_) | Type::Bf16 => false,
         }
     }
 
     /// Returns true if the type is a float.
     pub fn is_float(self) -> bool {
         match self {
-            Type::F(_) => true,
+            Type::F(_) | Type::Bf16 => true,
             Type::I(_) | Type::PtrTo(..) => false,
         }
     }
@@ -36,6 +39,7 @@ impl Type {
     pub fn bitwidth(self) -> Option<u32> {
         match self {
             Type::I(bits) | Type::F(bits) => Some(u32::from(bits)),
+            Type::Bf16 => Some(16),
             _ => None,
         }
     }
@@ -51,6 +55,7 @@ impl fmt::Display for Type {
         match self {
             Type::I(s) => write!(f, "i{}", s),
             Type::F(s) => write!(f, "f{}", s),
+            Type::Bf16 => write!(f, "bf16"),
             Type::PtrTo(mem) => write!(f, "ptr to {:?}", mem),
         }
     }
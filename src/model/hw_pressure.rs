@@ -32,6 +32,11 @@ impl<ORIGIN> ExplainedBound<ORIGIN> {
         self.value
     }
 
+    /// Returns the justification of the bound.
+    pub fn origin(&self) -> &ORIGIN {
+        &self.origin
+    }
+
     /// Indicates if the bound should be used instead of another.
     pub fn is_better_than(&self, other: &ExplainedBound<ORIGIN>) -> bool {
         const F: f64 = 1.0 + 1.0e-6;
@@ -161,6 +166,16 @@ impl Bound {
             size: 1,
         }
     }
+
+    /// Creates a bound whose justification is unknown, for instance when only its value was
+    /// recorded.
+    pub fn unknown(value: f64) -> Self {
+        Bound {
+            value,
+            origin: Origin::Unknown,
+            size: 1,
+        }
+    }
 }
 
 impl fmt::Display for Bound {
@@ -332,6 +347,8 @@ pub enum Origin {
     Scale { inner: Box<Origin>, factor: f64 },
     /// The bound was measured on hardware.
     HardwareEvaluation,
+    /// The justification of the bound was not recorded.
+    Unknown,
 }
 
 impl Origin {
@@ -341,7 +358,8 @@ impl Origin {
         match self {
             x @ Origin::Latency
             | x @ Origin::Bottleneck(..)
-            | x @ Origin::HardwareEvaluation => (false, x, false),
+            | x @ Origin::HardwareEvaluation
+            | x @ Origin::Unknown => (false, x, false),
             Origin::Loop { iterations: 0, .. } => (true, Origin::Latency, true),
             Origin::Loop {
                 dims,
@@ -409,6 +427,7 @@ impl fmt::Display for Origin {
                 write!(f, "the pressure on {} at the {}", name, level)
             }
             Origin::HardwareEvaluation => write!(f, "the evaluation on the hardware"),
+            Origin::Unknown => write!(f, "an unknown reason"),
             Origin::Loop {
                 ref dims,
                 iterations,
@@ -499,6 +518,12 @@ impl HwPressure {
         }
     }
 
+    /// Ensures the latency is at least the one of another computation, without adding its
+    /// pressure on bottlenecks.
+    pub fn max_latency(&mut self, other: &HwPressure) {
+        self.latency = f64::max(self.latency, other.latency);
+    }
+
     /// Computes the pressure obtained by duplicating this one in parallel.
     pub fn repeat_parallel(&mut self, factor: f64) {
         for b in &mut self.bottlenecks {
@@ -543,7 +568,6 @@ impl HwPressure {
     }
 
     /// Returns the pressure on a bottleneck.
-    #[cfg(test)]
     pub fn get_bottleneck(&self, index: usize) -> f64 {
         self.bottlenecks[index]
     }
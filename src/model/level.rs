@@ -97,6 +97,12 @@ pub fn sum_pressure(
     let mut pressure =
         HwPressure::min(nest.iter().map(|d| &local_info.dim_overhead[d].0))
             .unwrap_or_else(|| HwPressure::zero(&*ctx.device()));
+    // Barriers are executed sequentially by each thread, so their latencies add up.
+    let mut num_barriers = nest
+        .iter()
+        .map(|d| local_info.dim_barriers.get(d).map_or(0, |b| b.0))
+        .min()
+        .unwrap_or(local_info.root_barriers);
     if nest.is_empty() {
         let min_num_threads = match bound_level {
             BottleneckLevel::Global => local_info.parallelism.min_num_threads,
@@ -161,7 +167,8 @@ pub fn sum_pressure(
         // From parallel levels, we must take into account the thread dimensions that re
         // not mapped to a dimension outside of the block. Predicated instructions require
         // special care as they are only active on the dimensions they are nested on. Other
-        // threads just skip the instruction.
+        // threads just skip the instruction. Threads that branch around the instruction do
+        // not execute it at all.
         if bound_level <= BottleneckLevel::Block {
             let unmapped_threads = &nesting.num_unmapped_threads;
             let max_threads = nesting.max_threads_per_block.clone() * unmapped_threads;
@@ -176,9 +183,10 @@ pub fn sum_pressure(
             if is_predicated {
                 max_active_threads =
                     size::factors(&nesting.max_threads_per_block, space, ctx);
-                predication_factor = size::bounds(unmapped_threads, space, ctx);
+                predication_factor =
+                    size::bounds(&nesting.num_predicated_threads, space, ctx);
             } else {
-                num_instances *= unmapped_threads;
+                num_instances *= &nesting.num_predicated_threads;
                 predication_factor = size::Range::ONE;
                 max_active_threads = max_threads;
             }
@@ -191,6 +199,17 @@ pub fn sum_pressure(
         }
         let num_instances = size::bounds(&num_instances, space, ctx).min;
         pressure.repeat_and_add_bottlenecks(num_instances as f64, &stmt_pressure);
+        if let ir::StmtId::Dim(dim) = stmt {
+            if let Some(&(_, barriers)) = local_info.dim_barriers.get(&dim) {
+                num_barriers += num_instances * barriers;
+            }
+        }
+    }
+    // Only threads are sure to execute barriers one after the other.
+    if bound_level == BottleneckLevel::Thread {
+        let mut barriers = ctx.device().barrier_pressure();
+        barriers.repeat_sequential(num_barriers as f64);
+        pressure.max_latency(&barriers);
     }
     pressure
 }
@@ -2,7 +2,9 @@
 use crate::device::{Context, Device};
 use crate::ir::{self, Statement};
 use crate::model::{size, HwPressure};
-use crate::search_space::{DimKind, Domain, Order, SearchSpace, ThreadMapping};
+use crate::search_space::{
+    DimKind, Domain, NumSet, Order, SearchSpace, ThreadGuard, ThreadMapping,
+};
 use fxhash::FxHashMap;
 use itertools::Itertools;
 use num::integer::lcm;
@@ -21,6 +23,11 @@ pub struct LocalInfo {
     pub dim_overhead: FxHashMap<ir::DimId, (HwPressure, HwPressure)>,
     /// The overhead to initialize a thread.
     pub thread_overhead: HwPressure,
+    /// The number of barriers each thread is sure to execute in a single iteration of each
+    /// dimension and in the whole loop, not counting the barriers of nested statements.
+    pub dim_barriers: FxHashMap<ir::DimId, (u64, u64)>,
+    /// The number of barriers each thread is sure to execute outside of any dimension.
+    pub root_barriers: u64,
     /// Available parallelism in the kernel.
     pub parallelism: Parallelism,
 }
@@ -48,15 +55,18 @@ impl LocalInfo {
                     false
                 };
                 // Only keep the pressure of innermost thread dimensions. Otherwise it
-                // will be taken multiple times into account.
-                let pressure =
-                    if is_thread && nesting[&stmt.stmt_id()].has_inner_thread_dims {
-                        HwPressure::zero(&*context.device())
-                    } else {
-                        context
-                            .device()
-                            .hw_pressure(space, &dim_sizes, &nesting, stmt, context)
-                    };
+                // will be taken multiple times into account. The pressure is not kept
+                // either if the barrier ending the dimension may be removed.
+                let pressure = if is_thread
+                    && (nesting[&stmt.stmt_id()].has_inner_thread_dims
+                        || may_remove_barrier(space, unwrap!(stmt.as_dim()).id()))
+                {
+                    HwPressure::zero(&*context.device())
+                } else {
+                    context
+                        .device()
+                        .hw_pressure(space, &dim_sizes, &nesting, stmt, context)
+                };
                 (stmt.stmt_id(), pressure)
             })
             .collect();
@@ -65,10 +75,13 @@ impl LocalInfo {
             .dims()
             .map(|d| {
                 let kind = space.domain().get_dim_kind(d.id());
-                if kind == DimKind::THREAD && nesting[&d.stmt_id()].has_inner_thread_dims
+                if kind == DimKind::THREAD
+                    && (nesting[&d.stmt_id()].has_inner_thread_dims
+                        || may_remove_barrier(space, d.id()))
                 {
                     // Only keep the overhead on innermost thread dimensions. Otherwise it
-                    // will be taken multiple times into account.
+                    // will be taken multiple times into account. Barriers that may be
+                    // removed are not accounted for either.
                     let zero = HwPressure::zero(&*context.device());
                     (d.id(), (zero.clone(), zero))
                 } else {
@@ -90,11 +103,22 @@ impl LocalInfo {
                 &mut thread_overhead,
             );
         }
+        let (dim_barriers, root_barriers) = add_barrier_pressure(
+            &*context.device(),
+            space,
+            &dim_sizes,
+            &nesting,
+            &mut hw_pressure,
+            &mut dim_overhead,
+            &mut thread_overhead,
+        );
         LocalInfo {
             nesting,
             hw_pressure,
             dim_overhead,
             thread_overhead,
+            dim_barriers,
+            root_barriers,
             parallelism,
         }
     }
@@ -140,6 +164,143 @@ fn add_indvar_pressure(
     }
 }
 
+/// Adds the pressure of the barriers ending the groups of instructions that are not nested
+/// in thread dimensions and counts the barriers each thread is sure to execute. Returns the
+/// number of barriers of each dimension, per iteration and for the whole loop, and the
+/// number of barriers outside of any dimension.
+///
+/// Code generation ends each group of thread dimensions with a barrier, already accounted
+/// for in the pressure of the innermost thread dimensions. It also groups instructions that
+/// are not nested in thread dimensions and ends each group with a barrier, executed once per
+/// iteration of the innermost loop containing thread dimensions. Only the latter barriers
+/// that are sure to be generated are accounted for: a loop is charged a barrier per
+/// iteration if it is sure to contain thread dimensions and an instruction outside of thread
+/// dimensions, with no loop that may contain thread dimensions in between.
+fn add_barrier_pressure(
+    device: &dyn Device,
+    space: &SearchSpace,
+    dim_sizes: &FxHashMap<ir::DimId, size::Range>,
+    nesting: &FxHashMap<ir::StmtId, Nesting>,
+    hw_pressure: &mut FxHashMap<ir::StmtId, HwPressure>,
+    dim_overhead: &mut FxHashMap<ir::DimId, (HwPressure, HwPressure)>,
+    thread_overhead: &mut HwPressure,
+) -> (FxHashMap<ir::DimId, (u64, u64)>, u64) {
+    let fun = space.ir_instance();
+    let domain = space.domain();
+    let order = |lhs: ir::DimId, rhs: ir::StmtId| domain.get_order(lhs.into(), rhs);
+    let may_contain_threads = |dim: ir::DimId| {
+        domain
+            .get_dim_kind(dim)
+            .intersects(DimKind::LOOP | DimKind::UNROLL)
+            && fun.dims().any(|other| {
+                other.id() != dim
+                    && domain.get_dim_kind(other.id()).intersects(DimKind::THREAD)
+                    && order(dim, other.stmt_id()).intersects(Order::OUTER)
+            })
+    };
+    let contains_threads = |dim: ir::DimId| {
+        (DimKind::LOOP | DimKind::UNROLL).contains(domain.get_dim_kind(dim))
+            && !fun.dims().any(|other| {
+                other.id() != dim && order(dim, other.stmt_id()).intersects(Order::MERGED)
+            })
+            && fun.dims().any(|other| {
+                other.id() != dim
+                    && domain.get_dim_kind(other.id()) == DimKind::THREAD
+                    && order(dim, other.stmt_id()) == Order::OUTER
+            })
+    };
+    let mut barrier_loops = VecSet::default();
+    let mut root_barrier = false;
+    for inst in fun.insts() {
+        let stmt = inst.stmt_id();
+        let outside_threads = fun.dims().all(|dim| {
+            !domain.get_dim_kind(dim.id()).intersects(DimKind::THREAD)
+                || !order(dim.id(), stmt).intersects(Order::OUTER)
+        });
+        if !outside_threads {
+            continue;
+        }
+        // Find the innermost loop sure to contain both the instruction and thread dimensions.
+        let loops = fun
+            .dims()
+            .map(|dim| dim.id())
+            .filter(|&dim| order(dim, stmt) == Order::OUTER && contains_threads(dim))
+            .collect_vec();
+        let innermost = loops.iter().cloned().find(|&dim| {
+            loops
+                .iter()
+                .all(|&other| other == dim || order(other, dim.into()) == Order::OUTER)
+        });
+        if innermost.is_none() && !loops.is_empty() {
+            continue;
+        }
+        // Ensure no loop between the innermost one and the instruction may contain thread
+        // dimensions, otherwise the barrier might be shared with other iterations.
+        let may_be_nested = fun.dims().map(|dim| dim.id()).any(|dim| {
+            Some(dim) != innermost
+                && order(dim, stmt).intersects(Order::OUTER)
+                && innermost.map_or(true, |outer| {
+                    order(outer, dim.into()).intersects(Order::OUTER)
+                })
+                && may_contain_threads(dim)
+        });
+        if may_be_nested {
+            continue;
+        }
+        match innermost {
+            Some(dim) if may_remove_barrier(space, dim) => (),
+            Some(dim) => {
+                barrier_loops.insert(dim);
+            }
+            None => root_barrier = true,
+        }
+    }
+    let barrier = device.barrier_pressure();
+    if root_barrier {
+        thread_overhead.add_parallel(&barrier);
+    }
+    // Innermost thread dimensions end with a barrier.
+    let mut dim_barriers: FxHashMap<_, _> = fun
+        .dims()
+        .map(|dim| dim.id())
+        .filter(|&dim| {
+            domain.get_dim_kind(dim) == DimKind::THREAD
+                && !nesting[&dim.into()].has_inner_thread_dims
+                && !may_remove_barrier(space, dim)
+        })
+        .map(|dim| (dim, (1, 1)))
+        .collect();
+    for dim in barrier_loops {
+        let size = dim_sizes[&dim].min;
+        unwrap!(dim_overhead.get_mut(&dim)).0.add_parallel(&barrier);
+        let mut pressure = barrier.clone();
+        pressure.repeat_sequential(size as f64);
+        unwrap!(hw_pressure.get_mut(&dim.into())).add_parallel(&pressure);
+        dim_barriers.insert(dim, (1, size));
+    }
+    (dim_barriers, if root_barrier { 1 } else { 0 })
+}
+
+/// Indicates if code generation may remove the barrier ending the groups of threads nested
+/// in `dim`. It does so when it pipelines a loop around the loads from multi-stage memory
+/// blocks, so the barriers of dimensions that may contain such loads are not accounted for.
+fn may_remove_barrier(space: &SearchSpace, dim: ir::DimId) -> bool {
+    let domain = space.domain();
+    space.ir_instance().insts().any(|inst| {
+        let staged_mem = match *inst.operator() {
+            ir::Operator::Ld(..) => inst.operator().mem_used().filter(|&mem| {
+                let num_stages = domain.get_num_stages(mem);
+                num_stages.max_value(&ir::mem::NUM_STAGES) > 1
+            }),
+            _ => None,
+        };
+        staged_mem.is_some()
+            && domain
+                .get_order(dim.into(), inst.stmt_id())
+                .intersects(Order::OUTER)
+    })
+}
+
 /// Nesting of an object.
 #[derive(Debug)]
 pub struct Nesting {
@@ -160,6 +321,12 @@ pub struct Nesting {
     has_inner_thread_dims: bool,
     /// Number of threads that are not represented in the active dimensions of the block.
     pub num_unmapped_threads: ir::PartialSize,
+    /// Number of threads that are not represented in the active dimensions of the block and
+    /// that are sure to execute it under a predicate instead of branching around it.
+    pub num_predicated_threads: ir::PartialSize,
+    /// Indicates if some of the threads that are not represented in the active dimensions
+    /// of the block are sure to branch around it.
+    pub is_branched_around: bool,
     /// Maximal number of threads this block can be in, considering only outer dimensions
     /// (an not mapped out dimensions).
     pub max_threads_per_block: ir::PartialSize,
@@ -167,7 +334,7 @@ pub struct Nesting {
 
 impl Nesting {
     /// Computes the nesting of a `Statement`.
-    fn compute(space: &SearchSpace, stmt: ir::StmtId) -> Self {
+    pub fn compute(space: &SearchSpace, stmt: ir::StmtId) -> Self {
         let mut inner_dims = Vec::new();
         let mut inner_stmts = Vec::new();
         let mut before_self = Vec::new();
@@ -202,7 +369,7 @@ impl Nesting {
             }
         }
         let outer_dims = Self::get_iteration_dims(space, stmt);
-        let num_unmapped_threads = space
+        let unmapped_threads = space
             .ir_instance()
             .thread_dims()
             .filter(|dim| {
@@ -217,8 +384,21 @@ impl Nesting {
                     mapping.intersects(ThreadMapping::MAPPED)
                 })
             })
+            .collect_vec();
+        let num_unmapped_threads = unmapped_threads
+            .iter()
+            .map(|d| d.size())
+            .product::<ir::PartialSize>();
+        let num_predicated_threads = unmapped_threads
+            .iter()
+            .filter(|d| {
+                space.domain().get_thread_guard(d.id()) == ThreadGuard::PREDICATED
+            })
             .map(|d| d.size())
             .product::<ir::PartialSize>();
+        let is_branched_around = unmapped_threads
+            .iter()
+            .any(|d| space.domain().get_thread_guard(d.id()) == ThreadGuard::BRANCH);
         let max_threads_per_block = outer_dims
             .iter()
             .cloned()
@@ -234,6 +414,8 @@ impl Nesting {
             bigger_merged_dims: VecSet::new(bigger_merged_dims),
             has_inner_thread_dims,
             num_unmapped_threads,
+            num_predicated_threads,
+            is_branched_around,
             max_threads_per_block,
         }
     }
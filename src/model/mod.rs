@@ -6,9 +6,10 @@ mod hw_pressure;
 mod level;
 mod local_info;
 
+pub mod roofline;
 pub mod size;
 
-pub use self::hw_pressure::{BottleneckLevel, Bound, HwPressure};
+pub use self::hw_pressure::{BottleneckLevel, Bound, HwPressure, Origin};
 pub use self::local_info::Nesting;
 
 use log::{debug, trace};
@@ -24,9 +25,6 @@ use log::{debug, trace};
 //  - FIXME: should not be taken into account for inner latency of unrolled loops
 //    * account for unrolled induction variables only in the hw pressure and not in the
 //      iteration overhead => also check regular loops
-// TODO(model): take syncthread overhead into account
-// TODO(model): the block parallelism is overestimated because we do not account for the
-//  number of registers used per threads.
 // FIXME: to avoid error, distinguish the issue and consumption of instructions.
 //  For example, a loop might issue loads, but the loads can end after the end of the loop
 //  is issued. For this, either double the nodes or subtract the size of buffers to the next
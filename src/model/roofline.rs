@@ -0,0 +1,262 @@
+//! Roofline analysis of a kernel.
+//!
+//! The roofline of a kernel compares its arithmetic intensity, the number of floating-point
+//! operations per byte transferred from and to the DRAM, against the peak compute rate and
+//! the peak bandwidth of a device.  It tells whether the kernel is compute- or memory-bound
+//! and gives a lower bound on its execution time that does not depend on the implementation.
+use std::fmt;
+
+use crate::device::Context;
+use crate::ir;
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+
+/// The resource limiting the execution time of a kernel in the roofline model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Limit {
+    /// The kernel is limited by the rate of floating-point operations.
+    Compute,
+    /// The kernel is limited by the DRAM bandwidth.
+    Memory,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Compute => fmt.write_str("compute-bound"),
+            Limit::Memory => fmt.write_str("memory-bound"),
+        }
+    }
+}
+
+/// The floating-point operations and the minimal DRAM traffic of a kernel, along with the
+/// peak rates of a device.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Roofline {
+    /// Number of floating-point operations performed by the kernel.
+    pub flops: f64,
+    /// Minimal number of bytes the kernel reads from and writes to the DRAM.
+    pub dram_bytes: f64,
+    /// Peak number of floating-point operations per nanosecond, if the device exposes an
+    /// `alu` bottleneck.  A fused multiply-add counts as two operations.
+    pub peak_flops: Option<f64>,
+    /// Peak number of bytes transferred from and to the DRAM per nanosecond, if the device
+    /// exposes a `bandwidth` bottleneck.
+    pub peak_bandwidth: Option<f64>,
+}
+
+impl Roofline {
+    /// Computes the roofline of a function on the device of the context.
+    pub fn new<L>(fun: &ir::Function<L>, context: &dyn Context) -> Self {
+        let device = context.device();
+        let rates = device.total_rates();
+        let peak_rate = |name| {
+            device
+                .bottlenecks()
+                .iter()
+                .position(|&bottleneck| bottleneck == name)
+                .map(|idx| rates.get_bottleneck(idx))
+                .filter(|&rate| rate > 0.)
+        };
+        Roofline {
+            flops: fun
+                .insts()
+                .map(|inst| flops(inst.operator()) * num_instances(fun, inst, context))
+                .sum(),
+            dram_bytes: dram_bytes(fun, context),
+            peak_flops: peak_rate("alu").map(|rate| 2. * rate),
+            peak_bandwidth: peak_rate("bandwidth"),
+        }
+    }
+
+    /// Returns the number of floating-point operations per byte of DRAM traffic, or `None` if
+    /// the kernel does not access the DRAM.
+    pub fn arithmetic_intensity(&self) -> Option<f64> {
+        if self.dram_bytes > 0. {
+            Some(self.flops / self.dram_bytes)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the arithmetic intensity above which the kernel is compute-bound.
+    pub fn ridge_point(&self) -> Option<f64> {
+        Some(self.peak_flops? / self.peak_bandwidth?)
+    }
+
+    /// Returns the minimal time spent computing, in nanoseconds.
+    pub fn compute_time(&self) -> Option<f64> {
+        self.peak_flops.map(|peak| self.flops / peak)
+    }
+
+    /// Returns the minimal time spent transferring data from and to the DRAM, in
+    /// nanoseconds.
+    pub fn memory_time(&self) -> Option<f64> {
+        self.peak_bandwidth.map(|peak| self.dram_bytes / peak)
+    }
+
+    /// Returns the resource limiting the kernel, if the device exposes both peak rates.
+    pub fn limit(&self) -> Option<Limit> {
+        if self.compute_time()? >= self.memory_time()? {
+            Some(Limit::Compute)
+        } else {
+            Some(Limit::Memory)
+        }
+    }
+
+    /// Returns the lower bound on the execution time given by the roofline, in nanoseconds.
+    pub fn min_time(&self) -> Option<f64> {
+        match (self.compute_time(), self.memory_time()) {
+            (Some(compute), Some(memory)) => Some(f64::max(compute, memory)),
+            (compute, memory) => compute.or(memory),
+        }
+    }
+
+    /// Returns the fraction of the roofline attained by an implementation running in
+    /// `runtime` nanoseconds.
+    pub fn efficiency(&self, runtime: f64) -> Option<f64> {
+        self.min_time().map(|min_time| min_time / runtime)
+    }
+}
+
+impl fmt::Display for Roofline {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(fmt, "floating-point operations: {:.4e}", self.flops)?;
+        writeln!(fmt, "minimal DRAM traffic: {:.4e}B", self.dram_bytes)?;
+        if let Some(intensity) = self.arithmetic_intensity() {
+            write!(fmt, "arithmetic intensity: {:.3} flop/B", intensity)?;
+        } else {
+            write!(fmt, "arithmetic intensity: unbounded, no DRAM traffic")?;
+        }
+        if let Some(ridge_point) = self.ridge_point() {
+            write!(fmt, " (ridge point: {:.3} flop/B)", ridge_point)?;
+        }
+        if let Some(compute_time) = self.compute_time() {
+            write!(fmt, "\ncompute time: {:.4e}ns", compute_time)?;
+        }
+        if let Some(memory_time) = self.memory_time() {
+            write!(fmt, "\nmemory time: {:.4e}ns", memory_time)?;
+        }
+        if let Some(limit) = self.limit() {
+            write!(fmt, "\nthe kernel is {}", limit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of floating-point operations performed by an instance of an operator.
+fn flops<L>(operator: &ir::Operator<L>) -> f64 {
+    if !operator.t().map(ir::Type::is_float).unwrap_or(false) {
+        return 0.;
+    }
+    match operator {
+        ir::Operator::BinOp(op, ..) => match op {
+            ir::BinOp::Add | ir::BinOp::Sub | ir::BinOp::Div | ir::BinOp::Max => 1.,
+            _ => 0.,
+        },
+        ir::Operator::Mul(..) | ir::Operator::UnaryOp(ir::UnaryOp::Exp(..), _) => 1.,
+        ir::Operator::Mad(..) => 2.,
+        _ => 0.,
+    }
+}
+
+/// Returns the minimal number of instances of an instruction.
+fn num_instances<L>(
+    fun: &ir::Function<L>,
+    inst: &ir::Instruction<L>,
+    context: &dyn Context,
+) -> f64 {
+    iteration_space(fun, inst.iteration_dims().iter().cloned(), context)
+}
+
+/// Returns the minimal number of points in the iteration space of a set of dimensions.
+/// Logical dimensions fully contained in the set contribute their total size, regardless of
+/// how they are tiled.
+fn iteration_space<L, IT>(fun: &ir::Function<L>, dims: IT, context: &dyn Context) -> f64
+where
+    IT: IntoIterator<Item = ir::DimId>,
+{
+    let dims = dims.into_iter().collect::<FxHashSet<_>>();
+    let mut logical_dims = FxHashSet::default();
+    let mut total = 1.;
+    for &dim in &dims {
+        let dim = fun.dim(dim);
+        let logical_dim = dim.logical_dim().map(|id| fun.logical_dim(id));
+        match logical_dim {
+            Some(logical_dim)
+                if logical_dim.dimensions().all(|other| dims.contains(&other)) =>
+            {
+                if logical_dims.insert(logical_dim.id()) {
+                    let size = ir::PartialSize::from(logical_dim.total_size().clone());
+                    total *= eval_size(&size, context).unwrap_or(1) as f64;
+                }
+            }
+            _ => {
+                let size = dim
+                    .possible_sizes()
+                    .and_then(|sizes| sizes.iter().cloned().min())
+                    .map(u64::from)
+                    .or_else(|| eval_size(dim.size(), context));
+                total *= size.unwrap_or(1) as f64;
+            }
+        }
+    }
+    total
+}
+
+/// Evaluates a size that only depends on the parameters of the function.
+fn eval_size(size: &ir::PartialSize, context: &dyn Context) -> Option<u64> {
+    let (factor, params, dims) = size.factors();
+    if !dims.is_empty() || !size.divisors().is_empty() {
+        return None;
+    }
+    params.iter().try_fold(u64::from(factor), |total, param| {
+        context
+            .param_as_size(&param.name)
+            .map(|value| total * u64::from(value))
+    })
+}
+
+/// Returns the minimal number of bytes a function reads from and writes to the DRAM.  Each
+/// array is read and written at least once, and an access covers at least the elements
+/// selected by the dimensions of its access pattern, as accesses along different dimensions
+/// do not overlap.  Accesses to the same array are assumed to overlap.
+fn dram_bytes<L>(fun: &ir::Function<L>, context: &dyn Context) -> f64 {
+    let mut loads = FxHashMap::default();
+    let mut stores = FxHashMap::default();
+    for inst in fun.insts() {
+        let (accesses, addr, t, pattern) = match inst.operator() {
+            ir::Operator::Ld(t, addr, pattern) => (&mut loads, addr, *t, pattern),
+            ir::Operator::St(addr, value, _, pattern) => {
+                (&mut stores, addr, value.t(), pattern)
+            }
+            _ => continue,
+        };
+        // Memory blocks are allocated in the shared memory or in registers.
+        if pattern.mem_block().is_some() {
+            continue;
+        }
+        let footprint = match pattern {
+            ir::AccessPattern::Tensor { dims, .. } => {
+                iteration_space(fun, dims.keys().cloned(), context)
+            }
+            ir::AccessPattern::Unknown(..) => 1.,
+        };
+        let bytes = footprint * f64::from(t.len_byte().unwrap_or(0));
+        let array = array_name(fun, addr);
+        let entry = accesses.entry(array).or_insert(0.);
+        *entry = f64::max(*entry, bytes);
+    }
+    loads.values().chain(stores.values()).sum()
+}
+
+/// Returns the name of the parameter an address is computed from, if any.
+fn array_name<L>(fun: &ir::Function<L>, addr: &ir::Operand<L>) -> Option<String> {
+    match addr {
+        ir::Operand::Param(param) => Some(param.name.clone()),
+        ir::Operand::InductionVar(id, _) => {
+            array_name(fun, fun.induction_var(*id).base())
+        }
+        _ => None,
+    }
+}
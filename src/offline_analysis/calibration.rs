@@ -0,0 +1,312 @@
+//! Comparison of the bounds of the performance model against the evaluations recorded in an
+//! event log, to find where the model is loose or wrong.
+use crate::explorer::choice::ActionEx as Action;
+use crate::explorer::eventlog::EventLog;
+use crate::explorer::mcts::Message;
+use crate::model::{BottleneckLevel, Bound, Origin};
+use crate::offline_analysis::tree::CandidateTree;
+use crate::search_space::Action as SpaceAction;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+/// An evaluated implementation, paired with its bound.
+#[derive(Clone, Debug, Serialize)]
+pub struct Sample {
+    /// ID of the implementation in the event log
+    pub id: u64,
+    /// Number of decisions from the root to the implementation
+    pub depth: usize,
+    /// Measured execution time, in nanoseconds
+    pub runtime: f64,
+    /// Bound from the performance model, in nanoseconds
+    pub bound: f64,
+    /// Ratio of the bound over the runtime.  The closer to 1 the better, and above 1 when the
+    /// bound is violated.
+    pub tightness: f64,
+    /// Kind of the origin of the dominant term of the bound
+    pub origin: &'static str,
+    /// Bottleneck responsible for the dominant term of the bound, if any
+    pub bottleneck: Option<String>,
+    /// Level at which the bottleneck is computed, if any
+    pub level: Option<BottleneckLevel>,
+    /// `DimKind` and `MemSpace` decisions along the path to the implementation
+    pub decisions: Vec<String>,
+}
+
+impl Sample {
+    /// Creates a sample from the evaluation of an implementation.
+    fn new(id: u64, actions: &[Action], bound: &Bound, runtime: f64) -> Self {
+        let (origin, bottleneck, level) = match dominant_origin(bound) {
+            Origin::Bottleneck(name, level) => {
+                ("bottleneck", Some(name.to_string()), Some(*level))
+            }
+            Origin::HardwareEvaluation => ("hardware", None, None),
+            _ => ("latency", None, None),
+        };
+        let mut decisions = actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::Action(SpaceAction::DimKind(_, kind)) => {
+                    Some(format!("dim_kind:{}", kind))
+                }
+                Action::Action(SpaceAction::MemSpace(_, space)) => {
+                    Some(format!("mem_space:{}", space))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        decisions.sort();
+        decisions.dedup();
+        Sample {
+            id,
+            depth: actions.len(),
+            runtime,
+            bound: bound.value(),
+            tightness: bound.value() / runtime,
+            origin,
+            bottleneck,
+            level,
+            decisions,
+        }
+    }
+}
+
+/// Returns the origin of the term of the bound with the highest value.
+fn dominant_origin(bound: &Bound) -> &Origin {
+    let mut origin = bound.origin();
+    loop {
+        origin = match origin {
+            Origin::Loop { inner, .. } | Origin::Scale { inner, .. } => inner,
+            Origin::Chain { before, after, .. } => {
+                if before.value() >= after.value() {
+                    before.origin()
+                } else {
+                    after.origin()
+                }
+            }
+            _ => return origin,
+        };
+    }
+}
+
+/// Statistics on the tightness of a group of samples.
+#[derive(Clone, Debug, Serialize)]
+pub struct Tightness {
+    /// Number of samples in the group
+    pub count: usize,
+    /// Number of samples for which the bound is above the runtime
+    pub num_violations: usize,
+    pub min: f64,
+    pub median: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+impl Tightness {
+    /// Computes the statistics on a group of tightness ratios. Ratios that are not a number,
+    /// for example when both the bound and the runtime are null, are ignored. Returns `None`
+    /// if no ratio remains.
+    fn new(mut ratios: Vec<f64>) -> Option<Self> {
+        ratios.retain(|ratio| !ratio.is_nan());
+        if ratios.is_empty() {
+            return None;
+        }
+        ratios.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).unwrap());
+        let count = ratios.len();
+        Some(Tightness {
+            count,
+            num_violations: ratios.iter().filter(|&&ratio| ratio > 1.).count(),
+            min: ratios[0],
+            median: ratios[count / 2],
+            mean: ratios.iter().sum::<f64>() / count as f64,
+            max: ratios[count - 1],
+        })
+    }
+
+    /// Computes the statistics of each group of samples.
+    fn group_by<K, F, IT>(samples: &[Sample], keys: F) -> BTreeMap<K, Self>
+    where
+        K: Ord,
+        F: Fn(&Sample) -> IT,
+        IT: IntoIterator<Item = K>,
+    {
+        let mut groups = BTreeMap::new();
+        for sample in samples {
+            for key in keys(sample) {
+                groups
+                    .entry(key)
+                    .or_insert_with(Vec::new)
+                    .push(sample.tightness);
+            }
+        }
+        groups
+            .into_iter()
+            .filter_map(|(key, ratios)| Some((key, Tightness::new(ratios)?)))
+            .collect()
+    }
+}
+
+/// The tightness of the bounds of the evaluated implementations in an event log.
+#[derive(Clone, Debug, Serialize)]
+pub struct CalibrationReport {
+    /// Statistics over all the samples
+    pub overall: Option<Tightness>,
+    /// Statistics by kind of origin of the dominant term of the bound
+    pub by_origin: BTreeMap<&'static str, Tightness>,
+    /// Statistics by bottleneck, for bounds dominated by a bottleneck
+    pub by_bottleneck: BTreeMap<String, Tightness>,
+    /// Statistics by bottleneck level, for bounds dominated by a bottleneck
+    pub by_level: BTreeMap<String, Tightness>,
+    /// Statistics by `DimKind` and `MemSpace` decision along the path
+    pub by_decision: BTreeMap<String, Tightness>,
+    /// Statistics by depth of the implementation
+    pub by_depth: BTreeMap<usize, Tightness>,
+    /// The evaluated implementations
+    pub samples: Vec<Sample>,
+}
+
+impl CalibrationReport {
+    /// Builds the report from the samples.
+    pub fn new(samples: Vec<Sample>) -> Self {
+        let overall = Tightness::new(samples.iter().map(|s| s.tightness).collect());
+        CalibrationReport {
+            overall,
+            by_origin: Tightness::group_by(&samples, |s| Some(s.origin)),
+            by_bottleneck: Tightness::group_by(&samples, |s| s.bottleneck.clone()),
+            by_level: Tightness::group_by(&samples, |s| {
+                s.level.map(|level| format!("{:?}", level))
+            }),
+            by_decision: Tightness::group_by(&samples, |s| s.decisions.clone()),
+            by_depth: Tightness::group_by(&samples, |s| Some(s.depth)),
+            samples,
+        }
+    }
+
+    /// Reads the evaluations in an event log and pairs them with the bounds of the evaluated
+    /// nodes.  Evaluations without a result are ignored.
+    pub fn from_event_log<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut tree = CandidateTree::new();
+        let mut samples = Vec::new();
+        for message in EventLog::read_messages(path)? {
+            match message? {
+                Message::Node {
+                    id,
+                    parent,
+                    mut children,
+                    bound,
+                    discovery_time,
+                } => tree.extend(id, discovery_time, parent, bound, &mut children),
+                Message::Trace { .. } => (),
+                Message::Evaluation { id, value, .. } => {
+                    let node = tree.get_node(id);
+                    let bound = node.bound();
+                    match (value, bound.as_ref()) {
+                        (Some(runtime), Some(bound)) if runtime.is_finite() => {
+                            let actions = node.actions();
+                            samples.push(Sample::new(id.into(), &actions, bound, runtime))
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+        Ok(Self::new(samples))
+    }
+
+    /// Writes one line per sample in CSV format.  Decisions are separated by semicolons.
+    pub fn write_csv<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        let mut writer = csv::Writer::from_writer(writer);
+        writer.write_record(&[
+            "id",
+            "depth",
+            "runtime",
+            "bound",
+            "tightness",
+            "origin",
+            "bottleneck",
+            "level",
+            "decisions",
+        ])?;
+        for sample in &self.samples {
+            writer.write_record(&[
+                sample.id.to_string(),
+                sample.depth.to_string(),
+                sample.runtime.to_string(),
+                sample.bound.to_string(),
+                sample.tightness.to_string(),
+                sample.origin.to_string(),
+                sample.bottleneck.clone().unwrap_or_default(),
+                sample
+                    .level
+                    .map(|level| format!("{:?}", level))
+                    .unwrap_or_default(),
+                sample.decisions.join(";"),
+            ])?;
+        }
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir;
+    use crate::search_space::DimKind;
+
+    /// Returns the actions setting the kind of consecutive dimensions.
+    fn dim_kinds(kinds: &[DimKind]) -> Vec<Action> {
+        kinds
+            .iter()
+            .enumerate()
+            .map(|(id, &kind)| {
+                Action::Action(SpaceAction::DimKind(ir::DimId(id as u32), kind))
+            })
+            .collect()
+    }
+
+    /// Ensures the statistics of the report match synthetic measurements.
+    #[test]
+    fn report() {
+        let samples = vec![
+            Sample::new(0, &dim_kinds(&[DimKind::LOOP]), &Bound::unknown(1.), 2.),
+            Sample::new(
+                1,
+                &dim_kinds(&[DimKind::LOOP, DimKind::UNROLL]),
+                &Bound::unknown(3.),
+                4.,
+            ),
+            Sample::new(
+                2,
+                &dim_kinds(&[DimKind::VECTOR]),
+                &Bound::from_actual_time(6.),
+                4.,
+            ),
+            // Both the bound and the runtime are null, so the tightness is ignored.
+            Sample::new(3, &[], &Bound::unknown(0.), 0.),
+        ];
+        let report = CalibrationReport::new(samples);
+
+        let overall = report.overall.as_ref().unwrap();
+        assert_eq!(overall.count, 3);
+        assert_eq!(overall.num_violations, 1);
+        assert_eq!(overall.min, 0.5);
+        assert_eq!(overall.median, 0.75);
+        assert_eq!(overall.max, 1.5);
+        assert!((overall.mean - 2.75 / 3.).abs() < 1e-9);
+
+        assert_eq!(report.by_origin["latency"].count, 2);
+        assert_eq!(report.by_origin["hardware"].num_violations, 1);
+        assert!(report.by_bottleneck.is_empty());
+        let loop_kind = format!("dim_kind:{}", DimKind::LOOP);
+        assert_eq!(report.by_decision[&loop_kind].count, 2);
+        assert_eq!(report.by_decision[&loop_kind].max, 0.75);
+        assert_eq!(report.by_depth.keys().cloned().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(report.by_depth[&1].median, 1.5);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 5);
+    }
+}
@@ -0,0 +1,296 @@
+//! Export of a reconstructed candidate tree to Graphviz and JSON, for visualization.
+use crate::explorer::mcts::CauseOfDeath;
+use crate::offline_analysis::tree::{CandidateNode, CandidateTree};
+use serde::Serialize;
+use std::io;
+
+/// Criteria for hiding parts of the tree.  The root is always exported.
+#[derive(Clone, Debug, Default)]
+pub struct Pruning {
+    /// Nodes deeper than `max_depth` are hidden.
+    pub max_depth: Option<usize>,
+    /// Nodes visited by less than `min_visits` descents are hidden, along with their subtree.
+    pub min_visits: usize,
+    /// Nodes whose subtree contains no evaluation below `max_score` are hidden, along with
+    /// their subtree.
+    pub max_score: Option<f64>,
+}
+
+/// A node of the exported tree.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedNode {
+    /// ID of the node in the event log
+    pub id: u64,
+    /// Action leading from the parent to the node, empty for the root
+    pub action: String,
+    /// Number of actions from the root to the node
+    pub depth: usize,
+    /// Bound from the performance model, in nanoseconds
+    pub bound: Option<f64>,
+    /// Score of the evaluation of the node, if it is an evaluated implementation
+    pub score: Option<f64>,
+    /// Best score of the evaluated implementations in the subtree
+    pub best_score: Option<f64>,
+    /// Number of descents that went through the node
+    pub num_visits: usize,
+    /// Reason for which the node was declared a deadend, if any
+    pub cause_of_death: Option<CauseOfDeath>,
+    /// Number of explored children that were hidden by the pruning criteria
+    pub num_hidden_children: usize,
+    /// Children that were not hidden
+    pub children: Vec<ExportedNode>,
+}
+
+impl ExportedNode {
+    /// Exports the tree, hiding nodes according to `pruning`.
+    pub fn new(tree: &CandidateTree, pruning: &Pruning) -> Self {
+        Self::from_node(&tree.get_root(), 0, pruning)
+    }
+
+    /// Exports the subtree rooted at `node`.  The whole subtree is traversed so that the best
+    /// score accounts for hidden nodes.
+    fn from_node(node: &CandidateNode, depth: usize, pruning: &Pruning) -> Self {
+        let mut best_score = node.score();
+        let mut children = Vec::new();
+        let mut num_hidden_children = 0;
+        for child in node.children().flatten() {
+            let child = Self::from_node(&child, depth + 1, pruning);
+            best_score = min_score(best_score, child.best_score);
+            if child.is_visible(pruning) {
+                children.push(child);
+            } else {
+                num_hidden_children += 1;
+            }
+        }
+        ExportedNode {
+            id: node.id().into(),
+            action: node.action_str(),
+            depth,
+            bound: node.bound().as_ref().map(|bound| bound.value()),
+            score: node.score(),
+            best_score,
+            num_visits: node.num_visits(),
+            cause_of_death: node.cause_of_death(),
+            num_hidden_children,
+            children,
+        }
+    }
+
+    /// Indicates if the pruning criteria keep this node.
+    fn is_visible(&self, pruning: &Pruning) -> bool {
+        pruning.max_depth.map_or(true, |max| self.depth <= max)
+            && self.num_visits >= pruning.min_visits
+            && pruning.max_score.map_or(true, |max| {
+                self.best_score.map_or(false, |score| score <= max)
+            })
+    }
+
+    /// Returns the number of exported nodes in the subtree.
+    pub fn num_nodes(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(ExportedNode::num_nodes)
+            .sum::<usize>()
+    }
+
+    /// Writes the tree in Graphviz DOT format.
+    pub fn write_dot<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut graph = Graph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        graph.add(self);
+        dot::render(&graph, writer)
+    }
+
+    /// Returns the label of the node in the DOT output.
+    fn dot_label(&self) -> String {
+        let mut label = format!("#{}\nvisits: {}", self.id, self.num_visits);
+        if let Some(bound) = self.bound {
+            label += &format!("\nbound: {:.4e}ns", bound);
+        }
+        if let Some(best_score) = self.best_score {
+            label += &format!("\nbest: {:.4e}ns", best_score);
+        }
+        if let Some(cause) = self.cause_of_death {
+            label += &format!("\ndead: {:?}", cause);
+        }
+        if self.num_hidden_children > 0 {
+            label += &format!("\n({} hidden children)", self.num_hidden_children);
+        }
+        label
+    }
+}
+
+/// Returns the lowest of two optional scores.
+fn min_score(lhs: Option<f64>, rhs: Option<f64>) -> Option<f64> {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => Some(f64::min(lhs, rhs)),
+        (lhs, rhs) => lhs.or(rhs),
+    }
+}
+
+/// Flattened view of an exported tree, for the DOT renderer.
+struct Graph<'a> {
+    nodes: Vec<&'a ExportedNode>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl<'a> Graph<'a> {
+    /// Adds a subtree to the graph and returns the index of its root.
+    fn add(&mut self, node: &'a ExportedNode) -> usize {
+        let idx = self.nodes.len();
+        self.nodes.push(node);
+        for child in &node.children {
+            let child_idx = self.add(child);
+            self.edges.push((idx, child_idx));
+        }
+        idx
+    }
+}
+
+impl<'a> dot::GraphWalk<'a, usize, (usize, usize)> for Graph<'a> {
+    fn nodes(&'a self) -> dot::Nodes<'a, usize> {
+        (0..self.nodes.len()).collect()
+    }
+
+    fn edges(&'a self) -> dot::Edges<'a, (usize, usize)> {
+        self.edges.iter().cloned().collect()
+    }
+
+    fn source(&'a self, edge: &(usize, usize)) -> usize {
+        edge.0
+    }
+
+    fn target(&'a self, edge: &(usize, usize)) -> usize {
+        edge.1
+    }
+}
+
+impl<'a> dot::Labeller<'a, usize, (usize, usize)> for Graph<'a> {
+    fn graph_id(&'a self) -> dot::Id<'a> {
+        dot::Id::new("telamon").unwrap()
+    }
+
+    fn node_id(&'a self, node: &usize) -> dot::Id<'a> {
+        dot::Id::new(format!("N{}", self.nodes[*node].id)).unwrap()
+    }
+
+    fn node_label(&'a self, node: &usize) -> dot::LabelText<'a> {
+        dot::LabelText::label(self.nodes[*node].dot_label())
+    }
+
+    fn node_color(&'a self, node: &usize) -> Option<dot::LabelText<'a>> {
+        let node = self.nodes[*node];
+        if node.cause_of_death.is_some() {
+            Some(dot::LabelText::label("red"))
+        } else if node.score.is_some() {
+            Some(dot::LabelText::label("darkgreen"))
+        } else {
+            None
+        }
+    }
+
+    fn edge_label(&'a self, edge: &(usize, usize)) -> dot::LabelText<'a> {
+        dot::LabelText::label(self.nodes[edge.1].action.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::choice::ActionEx;
+    use crate::ir;
+    use crate::search_space::{Action, DimKind};
+    use std::time::Duration;
+
+    /// Returns a tree where the root has an explored child leading to an implementation and a
+    /// deadend child, visited once.
+    fn tree() -> CandidateTree {
+        let kind = |kind| ActionEx::Action(Action::DimKind(ir::DimId(0), kind));
+        let time = Duration::from_secs(0);
+        let mut tree = CandidateTree::new();
+        let mut root_actions = vec![kind(DimKind::LOOP), kind(DimKind::UNROLL)];
+        tree.extend(0.into(), time, None, None, &mut root_actions);
+        tree.extend(
+            1.into(),
+            time,
+            Some((0.into(), 0.into())),
+            None,
+            &mut vec![kind(DimKind::VECTOR)],
+        );
+        tree.extend(
+            2.into(),
+            time,
+            Some((0.into(), 1.into())),
+            None,
+            &mut vec![],
+        );
+        tree.extend(
+            3.into(),
+            time,
+            Some((1.into(), 0.into())),
+            None,
+            &mut vec![],
+        );
+        for &id in &[0, 0, 1, 1, 2, 3, 3] {
+            tree.get_node(id.into()).declare_visit();
+        }
+        tree.get_node(2.into()).declare_deadend(time);
+        tree.get_node(2.into())
+            .set_cause_of_death(CauseOfDeath::Constraints);
+        tree.get_node(3.into()).declare_score(2.);
+        tree
+    }
+
+    /// Ensures pruned nodes are hidden while still accounting for their best score.
+    #[test]
+    fn pruning() {
+        let tree = tree();
+        let all = ExportedNode::new(&tree, &Pruning::default());
+        assert_eq!(all.num_nodes(), 4);
+        assert_eq!(all.best_score, Some(2.));
+
+        let visited = Pruning {
+            min_visits: 2,
+            ..Pruning::default()
+        };
+        let exported = ExportedNode::new(&tree, &visited);
+        assert_eq!(exported.num_nodes(), 3);
+        assert_eq!(exported.num_hidden_children, 1);
+        assert_eq!(exported.children[0].children[0].score, Some(2.));
+
+        let shallow = Pruning {
+            max_depth: Some(1),
+            ..Pruning::default()
+        };
+        let exported = ExportedNode::new(&tree, &shallow);
+        assert_eq!(exported.num_nodes(), 3);
+        assert_eq!(exported.children[0].num_hidden_children, 1);
+        assert_eq!(exported.children[0].best_score, Some(2.));
+
+        let fast = Pruning {
+            max_score: Some(1.),
+            ..Pruning::default()
+        };
+        assert_eq!(ExportedNode::new(&tree, &fast).num_nodes(), 1);
+    }
+
+    /// Ensures the DOT output contains the visible nodes and the labels of their edges.
+    #[test]
+    fn write_dot() {
+        let visited = Pruning {
+            min_visits: 2,
+            ..Pruning::default()
+        };
+        let exported = ExportedNode::new(&tree(), &visited);
+        let mut dot = Vec::new();
+        exported.write_dot(&mut dot).unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.contains("N3"));
+        assert!(!dot.contains("N2"));
+        assert!(dot.contains("(1 hidden children)"));
+        assert!(dot.contains(&exported.children[0].action));
+    }
+}
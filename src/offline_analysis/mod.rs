@@ -1,2 +1,4 @@
 pub mod aftermath;
+pub mod calibration;
+pub mod export;
 pub mod tree;
@@ -1,11 +1,14 @@
 ///! Data structures and function that allow for the recreation of a
 ///! candidate tree from a log file
 use crate::explorer::choice::ActionEx as Action;
-use crate::explorer::mcts::{EdgeIndex, NodeId};
+use crate::explorer::eventlog::EventLog;
+use crate::explorer::mcts::{CauseOfDeath, EdgeIndex, Event, Message, NodeId, Policy};
 use crate::model::Bound;
 use fxhash::FxHashMap;
 use std::borrow::Cow;
 use std::cell::{Ref, RefCell};
+use std::io;
+use std::path::Path;
 use std::rc::{Rc, Weak};
 use std::time::Duration;
 
@@ -64,6 +67,16 @@ struct CandidateNodeInner {
 
     /// Score from the evaluation
     score: Option<f64>,
+
+    /// Number of times the node was selected while other descents were already in progress
+    /// across its incoming edge
+    num_concurrent_selections: usize,
+
+    /// Number of descents that went through the node
+    num_visits: usize,
+
+    /// Reason for which the node was first declared as a deadend
+    cause_of_death: Option<CauseOfDeath>,
 }
 
 trait ReplaceDurationIfLower {
@@ -124,6 +137,23 @@ impl CandidateNode {
         self.inner.borrow().score
     }
 
+    /// Returns the number of times this node was selected while
+    /// another thread was already descending through it
+    pub fn num_concurrent_selections(&self) -> usize {
+        self.inner.borrow().num_concurrent_selections
+    }
+
+    /// Returns the number of descents that went through this node
+    pub fn num_visits(&self) -> usize {
+        self.inner.borrow().num_visits
+    }
+
+    /// Returns the reason for which this node was first declared as
+    /// a deadend, if it is known
+    pub fn cause_of_death(&self) -> Option<CauseOfDeath> {
+        self.inner.borrow().cause_of_death
+    }
+
     /// Returns the parent node or None if this is the root node
     pub fn parent(&self) -> Option<CandidateNode> {
         self.inner
@@ -308,6 +338,26 @@ impl CandidateNode {
             .replace_if_lower(timestamp);
     }
 
+    /// Records that this node was selected while at least one other
+    /// descent was in progress through it
+    pub fn declare_concurrent_selection(&mut self) {
+        self.inner.borrow_mut().num_concurrent_selections += 1;
+    }
+
+    /// Records that a descent went through this node
+    pub fn declare_visit(&mut self) {
+        self.inner.borrow_mut().num_visits += 1;
+    }
+
+    /// Records the reason for which this node is a deadend. Only the
+    /// first cause is kept.
+    pub fn set_cause_of_death(&mut self, cause: CauseOfDeath) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.cause_of_death.is_none() {
+            inner.cause_of_death = Some(cause);
+        }
+    }
+
     /// Sets the score from an evaluation
     ///
     /// # Panics
@@ -320,6 +370,16 @@ impl CandidateNode {
         );
         self.inner.borrow_mut().score.replace(score);
     }
+
+    /// Records the score of an evaluation. If the node was evaluated
+    /// several times, e.g. across restarts, only the best score is
+    /// kept.
+    pub fn declare_score(&mut self, score: f64) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.score.map_or(true, |best| score < best) {
+            inner.score = Some(score);
+        }
+    }
 }
 
 /// A reconstructed tree
@@ -338,6 +398,71 @@ impl CandidateTree {
         Self::default()
     }
 
+    /// Rebuilds the tree recorded in an event log, along with the
+    /// visits, deadends and scores of its nodes. Timestamps of deadends
+    /// and node kinds are taken from the end of the corresponding
+    /// events.
+    pub fn from_event_log<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut tree = CandidateTree::new();
+        for message in EventLog::read_messages(path)? {
+            match message? {
+                Message::Node {
+                    id,
+                    parent,
+                    mut children,
+                    bound,
+                    discovery_time,
+                } => tree.extend(id, discovery_time, parent, bound, &mut children),
+                Message::Trace { events, .. } => {
+                    let mut node = tree.get_root();
+                    node.declare_visit();
+                    for event in &events {
+                        let time = event.end_time;
+                        match event.value {
+                            Event::SelectNode(id) => node = tree.get_node(id),
+                            Event::SelectChild(idx, policy, _) => {
+                                node = node.child(idx.into()).ok_or_else(|| {
+                                    io::Error::new(
+                                        io::ErrorKind::InvalidData,
+                                        "selected an unknown child",
+                                    )
+                                })?;
+                                node.declare_visit();
+                                match policy {
+                                    Policy::Bandit => node.declare_internal(time),
+                                    Policy::Default => node.declare_rollout(time),
+                                }
+                            }
+                            Event::Expand => node.declare_internal(time),
+                            Event::Kill(cause) => {
+                                node.declare_deadend(time);
+                                node.set_cause_of_death(cause);
+                            }
+                            Event::KillChild(idx, cause) => {
+                                if let Some(mut child) = node.child(idx.into()) {
+                                    child.declare_deadend(time);
+                                    child.set_cause_of_death(cause);
+                                }
+                            }
+                            Event::ConcurrentSelect(idx, _) => {
+                                if let Some(mut child) = node.child(idx.into()) {
+                                    child.declare_concurrent_selection();
+                                }
+                            }
+                            Event::Implementation => node.declare_implementation(time),
+                        }
+                    }
+                }
+                Message::Evaluation { id, value, .. } => {
+                    if let Some(score) = value {
+                        tree.get_node(id).declare_score(score);
+                    }
+                }
+            }
+        }
+        Ok(tree)
+    }
+
     /// Creates a new inner node
     fn new_node(
         &mut self,
@@ -367,6 +492,9 @@ impl CandidateTree {
                 .collect(),
             id: node_id,
             score: None,
+            num_concurrent_selections: 0,
+            num_visits: 0,
+            cause_of_death: None,
         }
     }
 
@@ -485,3 +613,92 @@ impl CandidateTree {
         self.add_node_mapping(node_id, new_node);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::mcts::{Selector, Timed};
+    use crate::ir;
+    use crate::search_space::{Action as SpaceAction, DimKind};
+
+    /// Returns an event ending at `time` seconds.
+    fn timed(time: u64, value: Event) -> Timed<Event> {
+        let time = Duration::from_secs(time);
+        Timed {
+            start_time: time,
+            end_time: time,
+            value,
+        }
+    }
+
+    /// Returns a message selecting the child `idx` of the root, followed by `event`.
+    fn trace(idx: u16, policy: Policy, event: Event) -> Message {
+        let idx = EdgeIndex::from(idx);
+        Message::Trace {
+            thread: "test".to_string(),
+            events: vec![
+                timed(
+                    1,
+                    Event::SelectChild(idx, policy, Selector::Exact { value: idx }),
+                ),
+                timed(2, event),
+            ],
+        }
+    }
+
+    /// Ensures the visits, deadends and best scores are rebuilt from an event log where the
+    /// same implementation is evaluated twice.
+    #[test]
+    fn from_event_log() {
+        let kind = |kind| Action::Action(SpaceAction::DimKind(ir::DimId(0), kind));
+        let node = |id: u64, parent: Option<u16>, children| Message::Node {
+            id: id.into(),
+            parent: parent.map(|idx| (0.into(), idx.into())),
+            children,
+            bound: None,
+            discovery_time: Duration::from_secs(0),
+        };
+        let evaluation = |score| Message::Evaluation {
+            id: 1.into(),
+            value: Some(score),
+            result_time: Duration::from_secs(3),
+        };
+        let messages = vec![
+            node(0, None, vec![kind(DimKind::LOOP), kind(DimKind::UNROLL)]),
+            node(1, Some(0), vec![]),
+            node(2, Some(1), vec![]),
+            trace(0, Policy::Default, Event::Implementation),
+            evaluation(3.),
+            trace(1, Policy::Bandit, Event::Kill(CauseOfDeath::Constraints)),
+            trace(0, Policy::Default, Event::Implementation),
+            evaluation(2.),
+        ];
+        let path =
+            std::env::temp_dir().join(format!("tree-{}.tfrecord", std::process::id()));
+        let mut writer = EventLog::create(&path).unwrap();
+        for message in &messages {
+            writer
+                .write_record(&bincode::serialize(message).unwrap())
+                .unwrap();
+        }
+        writer
+            .into_inner()
+            .map_err(io::Error::from)
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        let tree = CandidateTree::from_event_log(&path).unwrap();
+        assert_eq!(tree.get_root().num_visits(), 3);
+        let implementation = tree.get_root().child(0).unwrap();
+        assert_eq!(implementation.num_visits(), 2);
+        assert!(implementation.is_implementation());
+        assert_eq!(implementation.score(), Some(2.));
+        let deadend = tree.get_root().child(1).unwrap();
+        assert!(deadend.is_deadend());
+        match deadend.cause_of_death() {
+            Some(CauseOfDeath::Constraints) => (),
+            cause => panic!("unexpected cause of death: {:?}", cause),
+        }
+    }
+}
@@ -10,14 +10,17 @@ use std::sync::Arc;
 
 mod dim_map;
 mod operand;
+mod registers;
 use utils::generated_file;
 generated_file!(choices);
 
 pub use self::choices::{
-    Action, Bool, Choice, DimKind, Domain, DomainStore, InstFlag, MemSpace, NumSet,
-    Order, ThreadMapping,
+    Action, Bool, Choice, DimKind, Domain, DomainStore, InstFlag, MemSpace, NumDomain,
+    NumSet, NumericSet, Order, ThreadGuard, ThreadMapping,
 };
 
+pub use self::registers::register_pressure;
+
 use self::choices::{apply_action, init_domain, DomainDiff};
 
 /// A partially specified implementation.
@@ -50,7 +53,8 @@ impl SearchSpace {
             ir_instance: Arc::new(ir_instance),
             domain,
         };
-        space.apply_decisions(actions)?;
+        choices::apply_decisions(actions, &mut space.ir_instance, &mut space.domain)?;
+        space.check_registers()?;
         Ok(space)
     }
 
@@ -71,7 +75,28 @@ impl SearchSpace {
 
     /// Applies a list of decisions to the domain and propagate constraints.
     pub fn apply_decisions(&mut self, actions: Vec<Action>) -> Result<(), ()> {
-        choices::apply_decisions(actions, &mut self.ir_instance, &mut self.domain)
+        // The register estimate is only recomputed after decisions it depends on.  Other
+        // decisions only affect it through propagation, which is accounted for as soon as one of
+        // these decisions is taken.
+        let check_registers = actions.iter().any(registers::affects_pressure);
+        choices::apply_decisions(actions, &mut self.ir_instance, &mut self.domain)?;
+        if check_registers {
+            self.check_registers()?;
+        }
+        Ok(())
+    }
+
+    /// Ensures the implementation fits in the registers of the device, as spilling registers to
+    /// memory is never profitable.
+    fn check_registers(&self) -> Result<(), ()> {
+        let max_registers = self.ir_instance.device().max_registers_per_thread();
+        match max_registers {
+            Some(max) if register_pressure(self) > max => {
+                debug!("pruning candidate using more than {} registers", max);
+                Err(())
+            }
+            _ => Ok(()),
+        }
     }
 
     /// Triggers a layout lowering.
@@ -85,7 +110,9 @@ impl SearchSpace {
             let ir_instance = Arc::make_mut(&mut self.ir_instance);
             dim_map::lower_layout(ir_instance, mem, st_dims, ld_dims, &self.domain)?
         };
-        self.apply_decisions(actions)
+        // Lowering adds instructions, whose registers must be accounted for.
+        choices::apply_decisions(actions, &mut self.ir_instance, &mut self.domain)?;
+        self.check_registers()
     }
 
     /// Dump the code associated with this candidate.
@@ -111,6 +138,20 @@ impl SearchSpace {
 
         Ok(())
     }
+
+    /// Dump the resources statically used by the code associated with this candidate.
+    pub fn dump_resources<P: AsRef<Path>>(
+        &self,
+        context: &dyn Context,
+        path: P,
+    ) -> io::Result<()> {
+        let code = codegen::Function::build(self);
+        write!(
+            std::fs::File::create(path)?,
+            "{}",
+            serde_json::to_string(&context.device().resource_report(&code)).unwrap()
+        )
+    }
 }
 
 /// Update the domain after a lowering.
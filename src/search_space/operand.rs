@@ -52,7 +52,7 @@ fn order_reduce_dims(fun: &ir::Function, inst_id: ir::InstId, actions: &mut Vec<
 /// Generates actions to enforce operands invariants.
 pub fn invariants(fun: &ir::Function, op: &ir::Operand, user: ir::StmtId) -> Vec<Action> {
     match *op {
-        Int(..) | Float(..) | Param(..) | Addr(..) | Variable(..) => vec![],
+        Int(..) | Float(..) | Bf16(..) | Param(..) | Addr(..) | Variable(..) => vec![],
         Inst(src, _, ref dim_map, ref scope) => {
             // Order dimensions in the dim map.
             let order = Order::BEFORE | Order::MERGED;
@@ -115,3 +115,98 @@ pub fn inst_invariants(fun: &ir::Function, inst: &ir::Instruction) -> Vec<Action
         .flat_map(move |op| invariants(fun, op, inst.stmt_id()))
         .collect()
 }
+
+/// Indicates if `inst` computes the same value at each iteration of `dim`, and thus never
+/// needs to be nested in `dim` if it is a sequential loop. This is the case when the
+/// instruction has no side effects, does not access memory and does not depend on `dim`.
+/// Instructions that initialize a reduction are excluded as they must be nested in the
+/// same dimensions as the reduction.
+pub fn is_loop_invariant(
+    fun: &ir::Function,
+    inst: &ir::Instruction,
+    dim: &ir::Dimension,
+) -> bool {
+    !inst.has_side_effects()
+        && !inst.operator().is_mem_access()
+        && !depends_on_dim(fun, inst, dim)
+        && !fun.insts().any(|other| {
+            other
+                .as_reduction()
+                .map(|(init, ..)| init == inst.id())
+                .unwrap_or(false)
+        })
+}
+
+/// Indicates if the value computed by `inst` may vary with the iterations of `dim`. The
+/// instruction only depends on the dimensions it was created in and on the dimensions its
+/// operands depend on, as the search space only nests it in other dimensions to
+/// recompute the same value.
+fn depends_on_dim(
+    fun: &ir::Function,
+    inst: &ir::Instruction,
+    dim: &ir::Dimension,
+) -> bool {
+    inst.initial_dims()
+        .iter()
+        .any(|&inst_dim| may_merge(fun, fun.dim(inst_dim), dim))
+        || inst
+            .operands()
+            .into_iter()
+            .any(|op| operand_depends_on_dim(fun, op, dim))
+}
+
+/// Indicates if the value of an operand may vary with the iterations of `dim`.
+fn operand_depends_on_dim(
+    fun: &ir::Function,
+    op: &ir::Operand,
+    dim: &ir::Dimension,
+) -> bool {
+    let maps_dim = |dim_map: &ir::DimMap| {
+        dim_map
+            .iter()
+            .any(|&(lhs, rhs)| lhs == dim.id() || rhs == dim.id())
+    };
+    match *op {
+        Int(..) | Float(..) | Bf16(..) | Param(..) | Addr(..) => false,
+        Inst(src, _, ref dim_map, _) => {
+            maps_dim(dim_map) || depends_on_dim(fun, fun.inst(src), dim)
+        }
+        Reduce(init, _, ref dim_map, ref reduce_dims) => {
+            maps_dim(dim_map)
+                || reduce_dims
+                    .iter()
+                    .any(|&red_dim| may_merge(fun, fun.dim(red_dim), dim))
+                || depends_on_dim(fun, fun.inst(init), dim)
+        }
+        Index(index_dim) => may_merge(fun, fun.dim(index_dim), dim),
+        InductionVar(ind_var, _) => {
+            let ind_var = fun.induction_var(ind_var);
+            ind_var
+                .dims()
+                .iter()
+                .any(|&(ind_dim, _)| may_merge(fun, fun.dim(ind_dim), dim))
+                || operand_depends_on_dim(fun, ind_var.base(), dim)
+        }
+        Variable(..) => true,
+    }
+}
+
+/// Indicates if two dimensions may be merged, or are the same dimension. Merged
+/// dimensions must have the same size and, for tiled dimensions, tile logical
+/// dimensions of the same size.
+fn may_merge(fun: &ir::Function, lhs: &ir::Dimension, rhs: &ir::Dimension) -> bool {
+    if lhs.id() == rhs.id() {
+        return true;
+    }
+    if let (Some(lhs), Some(rhs)) = (lhs.logical_dim(), rhs.logical_dim()) {
+        let lhs_size = fun.logical_dim(lhs).total_size();
+        if lhs_size != fun.logical_dim(rhs).total_size() {
+            return false;
+        }
+    }
+    match (lhs.possible_sizes(), rhs.possible_sizes()) {
+        (Some(lhs), Some(rhs)) => lhs.iter().any(|size| rhs.contains(size)),
+        (None, None) => true,
+        _ => false,
+    }
+}
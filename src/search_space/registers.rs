@@ -0,0 +1,127 @@
+//! Estimation of the number of registers used by each thread.
+//!
+//! The estimate is a lower bound: it only accounts for values that must be live at the same time
+//! given the decisions already taken, so that candidates can be safely pruned when it exceeds
+//! the number of registers available.
+use crate::ir;
+use crate::search_space::{Action, DimKind, Domain, NumSet, Order, SearchSpace};
+
+/// Returns a lower bound on the number of 32-bit registers used by each thread.
+///
+/// The registers needed by an instruction are the values of its operands, which must be live
+/// when it executes.  Values are replicated along the dimensions they are vectorized on, and
+/// along unrolled dimensions when they are consumed in a different loop nest.  Accumulators of
+/// reductions are replicated along the unrolled dimensions nested in the reduction loops.
+pub fn register_pressure(space: &SearchSpace) -> u32 {
+    let fun = space.ir_instance();
+    fun.insts()
+        .map(|inst| {
+            inst.operands()
+                .into_iter()
+                .map(|operand| operand_registers(space, inst, operand))
+                .sum::<u32>()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Indicates if an action directly restricts a choice the register estimate depends on.
+pub fn affects_pressure(action: &Action) -> bool {
+    match action {
+        Action::Size(..)
+        | Action::DimKind(..)
+        | Action::Order(..)
+        | Action::DimMapping(..)
+        | Action::MemSpace(..) => true,
+        _ => false,
+    }
+}
+
+/// Returns the number of registers an operand of `inst` keeps live.
+fn operand_registers(
+    space: &SearchSpace,
+    inst: &ir::Instruction,
+    operand: &ir::Operand,
+) -> u32 {
+    match operand {
+        ir::Operand::Inst(src, t, dim_map, ir::DimMapScope::Local)
+        | ir::Operand::Inst(src, t, dim_map, ir::DimMapScope::Thread) => {
+            // Values mapped along unrolled dimensions that are not merged must all be computed
+            // before the first one is consumed.
+            let replication = dim_map
+                .iter()
+                .filter(|&&(src_dim, dst_dim)| {
+                    src_dim != dst_dim
+                        && is_unrolled(space, src_dim)
+                        && !space
+                            .domain()
+                            .get_order(src_dim.into(), dst_dim.into())
+                            .intersects(Order::MERGED)
+                })
+                .map(|&(src_dim, _)| min_size(space, src_dim))
+                .product::<u32>();
+            type_registers(space, *t) * vector_factor(space, *src) * replication
+        }
+        ir::Operand::Reduce(_, t, _, reduction_dims) => {
+            // An accumulator is needed for each instance of the unrolled dimensions nested in
+            // the reduction loops.
+            let replication = inst
+                .iteration_dims()
+                .iter()
+                .filter(|&&dim| {
+                    !reduction_dims.contains(&dim)
+                        && is_unrolled(space, dim)
+                        && reduction_dims.iter().any(|&red_dim| {
+                            space.domain().get_order(red_dim.into(), dim.into())
+                                == Order::OUTER
+                        })
+                })
+                .map(|&dim| min_size(space, dim))
+                .product::<u32>();
+            type_registers(space, *t) * vector_factor(space, inst.id()) * replication
+        }
+        ir::Operand::InductionVar(_, t) | ir::Operand::Variable(_, t) => {
+            type_registers(space, *t)
+        }
+        // Values transmitted through memory are loaded by other instructions, while constants,
+        // parameters and indexes may be encoded in the instruction.
+        ir::Operand::Inst(.., ir::DimMapScope::Global(..))
+        | ir::Operand::Int(..)
+        | ir::Operand::Float(..)
+        | ir::Operand::Bf16(..)
+        | ir::Operand::Index(..)
+        | ir::Operand::Param(..)
+        | ir::Operand::Addr(..) => 0,
+    }
+}
+
+/// Returns the number of 32-bit registers needed to hold a value of the given type.  Pointers
+/// whose memory space is not yet known are assumed to be 32 bits wide.
+fn type_registers(space: &SearchSpace, t: ir::Type) -> u32 {
+    let lowered = space.ir_instance().device().lower_type(t, space);
+    match lowered.unwrap_or(t) {
+        // Predicates are stored in dedicated registers.
+        ir::Type::I(1) => 0,
+        ir::Type::I(bits) | ir::Type::F(bits) => (u32::from(bits) + 31) / 32,
+        ir::Type::Bf16 | ir::Type::PtrTo(..) => 1,
+    }
+}
+
+/// Returns the minimal vectorization factor of an instruction.
+fn vector_factor(space: &SearchSpace, inst: ir::InstId) -> u32 {
+    let domain = space.domain();
+    domain.get_inner_vector_factor(inst).min * domain.get_outer_vector_factor(inst).min
+}
+
+/// Indicates if a dimension is known to be unrolled.
+fn is_unrolled(space: &SearchSpace, dim: ir::DimId) -> bool {
+    space.domain().get_dim_kind(dim) == DimKind::UNROLL
+}
+
+/// Returns the minimal size of a dimension.
+fn min_size(space: &SearchSpace, dim: ir::DimId) -> u32 {
+    let universe = space.ir_instance().dim(dim).possible_sizes();
+    universe.map_or(1, |universe| {
+        space.domain().get_size(dim).min_value(universe)
+    })
+}
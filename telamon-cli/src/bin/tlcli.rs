@@ -15,15 +15,21 @@ use telamon::explorer::{
     self,
     choice::{default_list, ActionEx as Action, Choice},
     config,
-    eventlog::EventLog,
-    mcts, Candidate,
+    eventlog::{self, EventLog},
+    mcts, metrics, Candidate,
+};
+use telamon::model::{bound, roofline::Roofline, Bound};
+use telamon::offline_analysis::{
+    calibration::CalibrationReport,
+    export::{ExportedNode, Pruning},
+    tree::CandidateTree,
 };
-use telamon::model::{bound, Bound};
-use telamon::offline_analysis::tree::CandidateTree;
 use telamon::search_space::SearchSpace;
 use telamon_kernels::statistics::estimate_mean;
 
-use telamon_cli::{Bench, CommonOpt, KernelBundle, KernelParam, Platform, ReplayPath};
+use telamon_cli::{
+    Bench, CodeFormat, CommonOpt, KernelBundle, KernelParam, Platform, ReplayPath,
+};
 
 /// Run a full search for a given kernel
 #[derive(StructOpt)]
@@ -43,64 +49,174 @@ struct Search {
     /// Number of times to run the generated code to evaluate its performance.
     #[structopt(long = "num-code-runs", default_value = "40")]
     num_code_runs: usize,
+
+    /// Share a cache of the evaluated implementations across the runs on each kernel.  The cache
+    /// is stored in the kernel's output directory, with one file per device.
+    #[structopt(long = "eval-cache")]
+    eval_cache: bool,
+
+    /// Search all the kernels concurrently, sharing the evaluation workers, the timeout and the
+    /// maximal number of evaluations.  Evaluations are allocated in priority to the kernels that
+    /// are still improving.
+    #[structopt(long = "schedule")]
+    schedule: bool,
 }
 
 impl Search {
     fn run(&self, _args: &Opt) -> io::Result<()> {
         let builder = self.platform.to_builder();
-        let mut config = self.common.config().unwrap().clone();
+        let config = self.common.config().unwrap();
         let output_base = std::path::Path::new(&config.output_dir).to_owned();
 
         for idx in 0..self.repeat {
-            for kernel in &self.kernels {
-                config.output_dir = output_base
-                    .join(kernel.to_string())
-                    .join(idx.to_string())
-                    .to_str()
-                    .unwrap()
-                    .to_string();
-
-                let mut context = builder.build_context();
-                let (bundle, context) = context.kernel_bundle(kernel);
-
-                let best = explorer::find_best_ex(
-                    &config,
-                    context,
-                    bundle.candidates,
-                    Some({
+            if self.schedule {
+                let mut contexts = self
+                    .kernels
+                    .iter()
+                    .map(|_| builder.build_context())
+                    .collect::<Vec<_>>();
+                let bundles = self
+                    .kernels
+                    .iter()
+                    .zip(&mut contexts)
+                    .map(|(kernel, context)| {
+                        let (bundle, context) = context.kernel_bundle(kernel);
+                        let kernel_config = self.kernel_config(
+                            &config,
+                            &output_base,
+                            kernel,
+                            idx,
+                            context,
+                        )?;
+                        Ok((kernel, kernel_config, bundle, context))
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                let check_fns = bundles
+                    .iter()
+                    .map(|(_, _, bundle, _)| {
                         let check_fn = &bundle.check_fn;
-                        &move |_, context| check_fn(context)
-                    }),
-                )
-                .unwrap_or_else(|| panic!("no candidates found for kernel {}", kernel));
-
-                let best_fn = telamon::codegen::Function::build(&best.space);
-                let runtime = context.benchmark(&best_fn, self.num_code_runs);
-
-                let ref_runtime = Bench::default()
-                    .runs(self.num_code_runs)
-                    .benchmark_fn(&bundle.reference_fn);
-
-                let mut f =
-                    std::fs::File::create(config.output_path("benchmark.txt").unwrap())
-                        .unwrap();
-                writeln!(f, "runtimes: {:?}", runtime).unwrap();
-                let mean = estimate_mean(runtime, 0.95, "ns");
-                let ref_mean = estimate_mean(ref_runtime, 0.95, "ns");
-                writeln!(
-                    f,
-                    "{}: {}, reference: {}, speedup: {:.2}",
-                    kernel,
-                    mean,
-                    ref_mean,
-                    ref_mean.value / mean.value
-                )
-                .unwrap();
+                        move |_: &Candidate, context: &dyn device::Context| {
+                            check_fn(context)
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let searches = bundles
+                    .iter()
+                    .zip(&check_fns)
+                    .map(|((_, kernel_config, bundle, context), check_fn)| {
+                        explorer::KernelSearch {
+                            config: kernel_config.clone(),
+                            context: *context,
+                            candidates: bundle.candidates.clone(),
+                            check_result_fn: Some(check_fn),
+                        }
+                    })
+                    .collect();
+                let bests = explorer::find_best_scheduled(&config, searches);
+                for ((kernel, kernel_config, bundle, context), best) in
+                    bundles.iter().zip(bests)
+                {
+                    let best = best.unwrap_or_else(|| {
+                        panic!("no candidates found for kernel {}", kernel)
+                    });
+                    self.benchmark(
+                        kernel_config,
+                        kernel,
+                        &best,
+                        *context,
+                        &bundle.reference_fn,
+                    );
+                }
+            } else {
+                for kernel in &self.kernels {
+                    let mut context = builder.build_context();
+                    let (bundle, context) = context.kernel_bundle(kernel);
+                    let config =
+                        self.kernel_config(&config, &output_base, kernel, idx, context)?;
+
+                    let best = explorer::find_best_ex(
+                        &config,
+                        context,
+                        bundle.candidates,
+                        Some({
+                            let check_fn = &bundle.check_fn;
+                            &move |_, context| check_fn(context)
+                        }),
+                    )
+                    .unwrap_or_else(|| {
+                        panic!("no candidates found for kernel {}", kernel)
+                    });
+
+                    self.benchmark(&config, kernel, &best, context, &bundle.reference_fn);
+                }
             }
         }
 
         Ok(())
     }
+
+    /// Returns the configuration for the `idx`-th search on `kernel`.
+    fn kernel_config(
+        &self,
+        config: &config::Config,
+        output_base: &std::path::Path,
+        kernel: &KernelParam,
+        idx: usize,
+        context: &dyn device::Context,
+    ) -> io::Result<config::Config> {
+        let mut config = config.clone();
+        config.output_dir = output_base
+            .join(kernel.to_string())
+            .join(idx.to_string())
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        if self.eval_cache {
+            let device_name = context
+                .device()
+                .name()
+                .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+            let cache_path = std::env::current_dir()?
+                .join(output_base)
+                .join(kernel.to_string())
+                .join(format!("eval_cache.{}.json", device_name));
+            config.eval_cache = Some(cache_path.to_str().unwrap().to_string());
+        }
+        Ok(config)
+    }
+
+    /// Benchmarks the best candidate found for a kernel against the reference implementation.
+    fn benchmark(
+        &self,
+        config: &config::Config,
+        kernel: &KernelParam,
+        best: &Candidate,
+        context: &dyn device::Context,
+        reference_fn: &dyn Fn() -> f64,
+    ) {
+        let best_fn = telamon::codegen::Function::build(&best.space);
+        let runtime = context.benchmark(&best_fn, self.num_code_runs);
+
+        let ref_runtime = Bench::default()
+            .runs(self.num_code_runs)
+            .benchmark_fn(reference_fn);
+
+        let mut f =
+            std::fs::File::create(config.output_path("benchmark.txt").unwrap()).unwrap();
+        writeln!(f, "runtimes: {:?}", runtime).unwrap();
+        let mean = estimate_mean(runtime, 0.95, "ns");
+        let ref_mean = estimate_mean(ref_runtime, 0.95, "ns");
+        writeln!(
+            f,
+            "{}: {}, reference: {}, speedup: {:.2}",
+            kernel,
+            mean,
+            ref_mean,
+            ref_mean.value / mean.value
+        )
+        .unwrap();
+    }
 }
 
 /// Compute the bound for a given candidate.
@@ -146,6 +262,46 @@ impl ComputeBound {
     }
 }
 
+/// Print the roofline of a kernel.
+#[derive(StructOpt)]
+struct ComputeRoofline {
+    #[structopt(long = "platform", default_value = "cuda")]
+    platform: Platform,
+
+    /// Kernel specification to use.
+    #[structopt(short = "k", long = "kernel")]
+    kernel: KernelParam,
+
+    /// Runtime of an implementation, in nanoseconds, to compare against the roofline.
+    #[structopt(long = "runtime")]
+    runtime: Option<f64>,
+}
+
+impl ComputeRoofline {
+    fn run(&self, _args: &Opt) -> io::Result<()> {
+        let builder = self.platform.to_builder();
+        let mut context = builder.build_context();
+        let (bundle, context) = context.kernel_bundle(&self.kernel);
+
+        for candidate in &bundle.candidates {
+            let roofline = Roofline::new(candidate.space.ir_instance(), context);
+            println!("{}", roofline);
+            if let Some(runtime) = self.runtime {
+                match roofline.efficiency(runtime) {
+                    Some(efficiency) => println!(
+                        "{:.4e}ns is at {:.2}% of the roofline",
+                        runtime,
+                        100. * efficiency
+                    ),
+                    None => println!("the device does not expose peak rates"),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Compute bounds.csv
 #[derive(StructOpt)]
 struct Bounds {
@@ -282,6 +438,84 @@ impl Bounds {
     }
 }
 
+/// Shrinks the decisions of an implementation running faster than its bound to a minimal subset
+/// that still violates the bound.  Writes a reproducer in the output directory.
+#[derive(StructOpt)]
+struct MinimizeBound {
+    /// Path to the replay file of the implementation violating the bound.  Must be compatible
+    /// with the provided kernel.
+    #[structopt(parse(from_os_str))]
+    replay: ReplayPath,
+
+    #[structopt(long = "platform", default_value = "cuda")]
+    platform: Platform,
+
+    /// Kernel specification to use.
+    #[structopt(short = "k", long = "kernel")]
+    kernel: KernelParam,
+
+    /// Runtime of the implementation, in nanoseconds.  The implementation is evaluated if no
+    /// runtime is provided.
+    #[structopt(long = "runtime")]
+    runtime: Option<f64>,
+
+    /// Number of evaluations of the implementation.  The minimal runtime is used.
+    #[structopt(long = "num-evals", default_value = "10")]
+    num_evals: usize,
+
+    /// Directory where the reproducer is written.
+    #[structopt(
+        parse(from_os_str),
+        short = "o",
+        long = "output",
+        default_value = "bound_violation"
+    )]
+    output: PathBuf,
+}
+
+impl MinimizeBound {
+    fn run(&self, _args: &Opt) -> io::Result<()> {
+        let builder = self.platform.to_builder();
+        let mut context = builder.build_context();
+        let (bundle, context) = context.kernel_bundle(&self.kernel);
+        let mut candidates = bundle.candidates;
+        assert!(candidates.len() == 1);
+
+        let space = candidates.swap_remove(0).space;
+        let actions = self.replay.load()?;
+        let to_io_error = |err| io::Error::new(io::ErrorKind::Other, err);
+        let runtime = match self.runtime {
+            Some(runtime) => runtime,
+            None => {
+                explorer::minimize::measure(context, &space, &actions, self.num_evals)
+                    .map_err(to_io_error)?
+            }
+        };
+
+        let violation = explorer::minimize::minimize_bound_violation(
+            context, &space, &actions, runtime,
+        )
+        .map_err(to_io_error)?
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("the bound is below the runtime ({:.2e}ns)", runtime),
+            )
+        })?;
+        violation.dump_to(&self.output)?;
+        println!(
+            "Reduced from {} to {} actions (runtime: {:.2e}ns)",
+            actions.len(),
+            violation.actions.len(),
+            runtime
+        );
+        println!("Bound before: {}", violation.bound_before);
+        println!("Bound after: {}", violation.bound_after);
+
+        Ok(())
+    }
+}
+
 /// Prints code to stdout for a given kernel.
 #[derive(StructOpt)]
 struct Codegen {
@@ -296,14 +530,21 @@ struct Codegen {
     /// Platform to generate code for.
     #[structopt(long = "platform", short = "p", default_value = "cuda")]
     platform: Platform,
+
+    /// Language of the generated code: `native` for the code compiled by the platform, or
+    /// `cuda-c` for CUDA C++ source on CUDA platforms.
+    #[structopt(long = "format", default_value = "native")]
+    format: CodeFormat,
 }
 
 impl Codegen {
     fn run(&self, _args: &Opt) -> io::Result<()> {
         let builder = self.platform.to_builder();
-        let mut context = builder.build_context();
-        let (bundle, context) = context.kernel_bundle(&self.kernel);
-        let mut candidates = bundle.candidates;
+        let mut platform_context = builder.build_context();
+        let (mut candidates, device) = {
+            let (bundle, context) = platform_context.kernel_bundle(&self.kernel);
+            (bundle.candidates, context.device())
+        };
         assert!(candidates.len() == 1);
 
         let mut candidate = candidates.swap_remove(0).space;
@@ -314,7 +555,12 @@ impl Codegen {
         }
 
         let code = telamon::codegen::Function::build(&candidate);
-        context.device().print(&code, &mut std::io::stdout());
+        match self.format {
+            CodeFormat::Native => device.print(&code, &mut std::io::stdout()),
+            CodeFormat::CudaC => {
+                platform_context.print_cuda_c(&code, &mut std::io::stdout())?
+            }
+        }
 
         Ok(())
     }
@@ -510,10 +756,8 @@ impl Rebuild {
         target.dedup();
         target.reverse();
 
-        for record_bytes in EventLog::open(&self.eventlog)?.records() {
-            match bincode::deserialize(&record_bytes?)
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
-            {
+        for message in EventLog::read_messages(&self.eventlog)? {
+            match message? {
                 mcts::Message::Node {
                     id,
                     parent,
@@ -552,6 +796,312 @@ impl Rebuild {
     }
 }
 
+/// Compute the resources used by the implementations evaluated in an eventlog
+///
+/// Writes one line per evaluation with its runtime, the number of registers, the shared memory,
+/// the number of barriers, the code size and the instruction mix of the implementation.
+#[derive(StructOpt)]
+struct Resources {
+    /// Path to the eventlog to read the evaluations from
+    #[structopt(
+        parse(from_os_str),
+        short = "i",
+        long = "input",
+        default_value = "eventlog.tfrecord.gz"
+    )]
+    eventlog: PathBuf,
+
+    /// Kernel specification the eventlog was produced with.
+    #[structopt(short = "k", long = "kernel")]
+    kernel: KernelParam,
+
+    /// Platform the eventlog was produced on.
+    #[structopt(long = "platform", short = "p", default_value = "cuda")]
+    platform: Platform,
+
+    /// Path of the CSV file to write.
+    #[structopt(
+        parse(from_os_str),
+        short = "o",
+        long = "output",
+        default_value = "resources.csv"
+    )]
+    output: PathBuf,
+}
+
+impl Resources {
+    fn run(&self, _args: &Opt) -> io::Result<()> {
+        let builder = self.platform.to_builder();
+        let mut platform_context = builder.build_context();
+        let (mut candidates, device) = {
+            let (bundle, context) = platform_context.kernel_bundle(&self.kernel);
+            (bundle.candidates, context.device())
+        };
+        assert!(candidates.len() == 1);
+        let root = candidates.swap_remove(0).space;
+
+        let mut tree = CandidateTree::new();
+        let mut rows = Vec::new();
+        for message in EventLog::read_messages(&self.eventlog)? {
+            match message? {
+                mcts::Message::Node {
+                    id,
+                    parent,
+                    mut children,
+                    bound,
+                    discovery_time,
+                } => tree.extend(id, discovery_time, parent, bound, &mut children),
+                mcts::Message::Trace { .. } => (),
+                mcts::Message::Evaluation { id, value, .. } => {
+                    let mut candidate = root.clone();
+                    for action in tree.get_node(id).actions() {
+                        candidate = action
+                            .apply_to(candidate)
+                            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+                    }
+                    let code = telamon::codegen::Function::build(&candidate);
+                    rows.push((id, value, device.resource_report(&code)));
+                }
+            }
+        }
+
+        let operators = rows
+            .iter()
+            .flat_map(|(_, _, report)| report.instruction_mix.keys())
+            .unique()
+            .sorted()
+            .collect::<Vec<_>>();
+        let mut out = io::BufWriter::new(fs::File::create(&self.output)?);
+        write!(
+            out,
+            "id,runtime,registers,shared_mem_bytes,barriers,occupancy,code_size,\
+             llir_insts,optimized_llir_insts"
+        )?;
+        for operator in &operators {
+            write!(out, ",{}", operator)?;
+        }
+        writeln!(out)?;
+        for (id, value, report) in &rows {
+            write!(
+                out,
+                "{},{},{},{},{},{},{},{},{}",
+                id,
+                value.map(|value| value.to_string()).unwrap_or_default(),
+                report.num_registers,
+                report.shared_mem_bytes,
+                report.num_barriers,
+                report
+                    .occupancy
+                    .map(|occupancy| occupancy.to_string())
+                    .unwrap_or_default(),
+                report.code_size,
+                report
+                    .optimization
+                    .map(|stats| stats.num_insts_before.to_string())
+                    .unwrap_or_default(),
+                report
+                    .optimization
+                    .map(|stats| stats.num_insts_after.to_string())
+                    .unwrap_or_default(),
+            )?;
+            for &operator in &operators {
+                let count = report.instruction_mix.get(operator).cloned().unwrap_or(0);
+                write!(out, ",{}", count)?;
+            }
+            writeln!(out)?;
+        }
+
+        println!(
+            "Wrote the resources of {} evaluations to {}",
+            rows.len(),
+            self.output.display()
+        );
+        Ok(())
+    }
+}
+
+/// Compare the bounds of the performance model against the evaluations of an eventlog
+#[derive(StructOpt)]
+struct Calibrate {
+    /// Path to the eventlog to compare the bounds for
+    #[structopt(
+        parse(from_os_str),
+        short = "i",
+        long = "input",
+        default_value = "eventlog.tfrecord.gz"
+    )]
+    eventlog: PathBuf,
+
+    /// Prefix of the output files.  One line per evaluation is written to `<output>.csv` and the
+    /// statistics, grouped by origin of the bound, decisions and depth, to `<output>.json`.
+    #[structopt(short = "o", long = "output", default_value = "calibration")]
+    output: String,
+}
+
+impl Calibrate {
+    fn run(&self, _args: &Opt) -> io::Result<()> {
+        let report = CalibrationReport::from_event_log(&self.eventlog)?;
+        report.write_csv(fs::File::create(format!("{}.csv", self.output))?)?;
+        serde_json::to_writer(
+            fs::File::create(format!("{}.json", self.output))?,
+            &report,
+        )?;
+
+        if let Some(overall) = &report.overall {
+            println!(
+                "{} evaluations, {} violations, tightness: min {:.3}, median {:.3}, max {:.3}",
+                overall.count,
+                overall.num_violations,
+                overall.min,
+                overall.median,
+                overall.max
+            );
+        } else {
+            println!("No evaluation found");
+        }
+        for (origin, tightness) in &report.by_origin {
+            println!(
+                "  {}: {} evaluations, median tightness {:.3}",
+                origin, tightness.count, tightness.median
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Export the search tree of an eventlog for visualization
+#[derive(StructOpt)]
+struct ExportTree {
+    /// Path to the eventlog to export the tree from
+    #[structopt(
+        parse(from_os_str),
+        short = "i",
+        long = "input",
+        default_value = "eventlog.tfrecord.gz"
+    )]
+    eventlog: PathBuf,
+
+    /// Prefix of the output files.  The tree is written in Graphviz format to `<output>.dot`
+    /// and in hierarchical JSON format to `<output>.json`.
+    #[structopt(short = "o", long = "output", default_value = "tree")]
+    output: String,
+
+    /// Maximum depth after which nodes are hidden.
+    #[structopt(long = "max-depth")]
+    max_depth: Option<usize>,
+
+    /// Minimum number of visits below which nodes are hidden, along with their children.
+    #[structopt(long = "min-visits", default_value = "0")]
+    min_visits: usize,
+
+    /// Nodes without any evaluation below this score in their subtree are hidden, along with
+    /// their children.
+    #[structopt(long = "max-score")]
+    max_score: Option<f64>,
+}
+
+impl ExportTree {
+    fn run(&self, _args: &Opt) -> io::Result<()> {
+        let tree = CandidateTree::from_event_log(&self.eventlog)?;
+        let pruning = Pruning {
+            max_depth: self.max_depth,
+            min_visits: self.min_visits,
+            max_score: self.max_score,
+        };
+        let root = ExportedNode::new(&tree, &pruning);
+        root.write_dot(&mut fs::File::create(format!("{}.dot", self.output))?)?;
+        serde_json::to_writer(fs::File::create(format!("{}.json", self.output))?, &root)?;
+        println!(
+            "Exported {} nodes to {}.dot and {}.json",
+            root.num_nodes(),
+            self.output,
+            self.output
+        );
+        Ok(())
+    }
+}
+
+/// Upgrade an eventlog to the current schema version
+///
+/// Logs written by older versions of the explorer, including the legacy format read by
+/// `parse_event_log`, are converted so that the other subcommands can analyze them.
+#[derive(StructOpt)]
+struct MigrateLog {
+    /// Path to the eventlog to upgrade
+    #[structopt(parse(from_os_str), short = "i", long = "input")]
+    input: PathBuf,
+
+    /// Path to the upgraded eventlog
+    #[structopt(
+        parse(from_os_str),
+        short = "o",
+        long = "output",
+        default_value = "eventlog.tfrecord.gz"
+    )]
+    output: PathBuf,
+}
+
+impl MigrateLog {
+    fn run(&self, _args: &Opt) -> io::Result<()> {
+        if self.input == self.output {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the output must differ from the input",
+            ));
+        }
+        let (version, num_messages) = EventLog::migrate(&self.input, &self.output)?;
+        println!(
+            "Migrated {} messages from version {} to version {}",
+            num_messages,
+            version,
+            eventlog::CURRENT_VERSION
+        );
+        Ok(())
+    }
+}
+
+/// Follow the live metrics of a running search
+///
+/// The search must be configured to serve its metrics with the `metrics_address` option.
+#[derive(StructOpt)]
+struct Watch {
+    /// Address on which the search serves its metrics
+    #[structopt(long = "address", default_value = "127.0.0.1:9898")]
+    address: String,
+
+    /// Interval between two refreshes, in seconds
+    #[structopt(long = "interval", default_value = "1")]
+    interval: f64,
+}
+
+impl Watch {
+    fn run(&self, _args: &Opt) -> io::Result<()> {
+        let interval = std::time::Duration::from_secs_f64(self.interval);
+        let mut connected = false;
+        loop {
+            match metrics::fetch(&self.address) {
+                Ok(metrics) => {
+                    connected = true;
+                    // Clear the terminal and move the cursor to the top-left corner.
+                    print!("\x1b[2J\x1b[H");
+                    println!("search on {}\n{}", self.address, metrics);
+                    io::stdout().flush()?;
+                    if metrics.finished {
+                        return Ok(());
+                    }
+                }
+                Err(_) if connected => {
+                    println!("search finished");
+                    return Ok(());
+                }
+                Err(_) => println!("waiting for a search on {}...", self.address),
+            }
+            std::thread::sleep(interval);
+        }
+    }
+}
+
 /// Compute statistics on an eventlog
 #[derive(StructOpt)]
 struct Stats {
@@ -572,6 +1122,7 @@ struct Stats {
 impl Stats {
     fn run(&self, _args: &Opt) -> io::Result<()> {
         let (mut nimpl, mut impld) = (0, 0u64);
+        let (mut nselect, mut nconcurrent, mut concurrentd) = (0u64, 0u64, 0u64);
 
         #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
         enum Cause {
@@ -595,10 +1146,8 @@ impl Stats {
         let mut evalns = self.limit.map(Vec::with_capacity).unwrap_or_default();
         let mut tree = CandidateTree::new();
 
-        for record_bytes in EventLog::open(&self.eventlog)?.records() {
-            match bincode::deserialize(&record_bytes?)
-                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?
-            {
+        for message in EventLog::read_messages(&self.eventlog)? {
+            match message? {
                 mcts::Message::Node {
                     id,
                     parent,
@@ -629,6 +1178,11 @@ impl Stats {
                                     has_size = true
                                 }
                                 len += 1;
+                                nselect += 1;
+                            }
+                            mcts::Event::ConcurrentSelect(_index, num_concurrent) => {
+                                nconcurrent += 1;
+                                concurrentd += num_concurrent as u64;
                             }
                             mcts::Event::KillChild(_index, cause_) => {
                                 let info = deadinfo
@@ -684,6 +1238,18 @@ impl Stats {
             impld as f64 / nimpl as f64
         );
 
+        if nconcurrent > 0 {
+            println!(
+                "Concurrent selections: {} out of {} ({:.2}%, avg other descents: {:.2})",
+                nconcurrent,
+                nselect,
+                100. * nconcurrent as f64 / nselect as f64,
+                concurrentd as f64 / nconcurrent as f64,
+            );
+        } else {
+            println!("Concurrent selections: 0 out of {}", nselect);
+        }
+
         let ((ddepth, ndead), (ddepth_size, ndead_size)) = deadinfo.iter().fold(
             ((0, 0), (0, 0)),
             |((ddepth, ndead), (ddepth_size, ndead_size)),
@@ -734,12 +1300,33 @@ enum Command {
     #[structopt(name = "bounds")]
     Bounds(Bounds),
 
+    #[structopt(name = "minimize-bound")]
+    MinimizeBound(MinimizeBound),
+
     #[structopt(name = "stats")]
     Stats(Stats),
 
+    #[structopt(name = "calibrate")]
+    Calibrate(Calibrate),
+
+    #[structopt(name = "resources")]
+    Resources(Resources),
+
+    #[structopt(name = "migrate-log")]
+    MigrateLog(MigrateLog),
+
+    #[structopt(name = "watch")]
+    Watch(Watch),
+
+    #[structopt(name = "export-tree")]
+    ExportTree(ExportTree),
+
     #[structopt(name = "bound")]
     Bound(ComputeBound),
 
+    #[structopt(name = "roofline")]
+    Roofline(ComputeRoofline),
+
     #[structopt(name = "search")]
     Search(Search),
 }
@@ -760,8 +1347,15 @@ fn main() {
         Command::Codegen(codegen) => codegen.run(&args),
         Command::Rebuild(rebuild) => rebuild.run(&args),
         Command::Bounds(bounds) => bounds.run(&args),
+        Command::MinimizeBound(minimize) => minimize.run(&args),
         Command::Stats(stats) => stats.run(&args),
+        Command::Calibrate(calibrate) => calibrate.run(&args),
+        Command::Resources(resources) => resources.run(&args),
+        Command::ExportTree(export) => export.run(&args),
+        Command::MigrateLog(migrate) => migrate.run(&args),
+        Command::Watch(watch) => watch.run(&args),
         Command::Bound(bound) => bound.run(&args),
+        Command::Roofline(roofline) => roofline.run(&args),
         Command::Search(search) => search.run(&args),
     };
 
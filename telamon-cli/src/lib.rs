@@ -431,6 +431,27 @@ mod x86_reference {
 #[cfg(feature = "x86")]
 pub use x86_reference::X86Reference;
 
+/// A reference for the GPUs evaluated with the performance model.  There is no reference
+/// implementation to run on such GPUs.
+#[cfg(feature = "cuda-model")]
+#[derive(Default)]
+pub struct ModelReference {
+    _priv: (),
+}
+
+#[cfg(feature = "cuda-model")]
+impl<'a, K> Reference<'a, K> for ModelReference
+where
+    K: Kernel<'a>,
+{
+    type Context = telamon_cuda::ModelContext;
+
+    fn eval_reference(&self, _: &K::Parameters, _: &Self::Context) -> f64 {
+        log::warn!("no reference on modeled GPUs");
+        1.
+    }
+}
+
 /// A wrapper type containing a (list of) candidates; a checking function to ensure that an
 /// implementation's output is valid, and a reference function to compare to.
 pub struct KernelBundle<'a> {
@@ -725,10 +746,13 @@ impl std::str::FromStr for KernelParam {
 }
 
 /// Available platforms for running kernels on.
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub enum Platform {
     X86,
     Cuda,
+    /// A GPU bundled with telamon-cuda, evaluated with the performance model.  Parsed from
+    /// `cuda:<name>` or `cuda:<name>@v<version>`.
+    CudaModel(String),
     __Unsupported,
 }
 
@@ -739,6 +763,24 @@ impl std::str::FromStr for Platform {
         Ok(match s {
             "x86" => Platform::X86,
             "cuda" => Platform::Cuda,
+            _ if s.starts_with("cuda:") => {
+                let name = &s["cuda:".len()..];
+                #[cfg(feature = "cuda-model")]
+                {
+                    if telamon_cuda::find_bundled_gpu(name).is_none() {
+                        let available = telamon_cuda::bundled_gpus()
+                            .iter()
+                            .map(|gpu| gpu.to_string())
+                            .collect::<Vec<_>>();
+                        return Err(format!(
+                            "unknown GPU: {} (available: {})",
+                            name,
+                            available.join(", ")
+                        ));
+                    }
+                }
+                Platform::CudaModel(name.to_string())
+            }
             _ => return Err(format!("invalid platform: {}", s)),
         })
     }
@@ -748,7 +790,7 @@ impl Platform {
     /// Convert the platform into the appropriate context builder.  This initializes any internal
     /// ressources of the platform; for instance, requesting a Cuda context builder will setup the
     /// connection to the GPU.
-    pub fn to_builder(self) -> PlatformContextBuilder {
+    pub fn to_builder(&self) -> PlatformContextBuilder {
         match self {
             #[cfg(feature = "x86")]
             Platform::X86 => PlatformContextBuilder::X86,
@@ -756,6 +798,12 @@ impl Platform {
             Platform::Cuda => {
                 PlatformContextBuilder::Cuda(telamon_cuda::Executor::init())
             }
+            #[cfg(feature = "cuda-model")]
+            Platform::CudaModel(name) => {
+                let bundled = telamon_cuda::find_bundled_gpu(name)
+                    .unwrap_or_else(|| panic!("unknown GPU: {}", name));
+                PlatformContextBuilder::CudaModel(bundled.gpu())
+            }
             _ => panic!("platform is not supported"),
         }
     }
@@ -766,6 +814,8 @@ pub enum PlatformContextBuilder {
     X86,
     #[cfg(feature = "cuda")]
     Cuda(telamon_cuda::Executor),
+    #[cfg(feature = "cuda-model")]
+    CudaModel(telamon_cuda::Gpu),
 }
 
 impl PlatformContextBuilder {
@@ -783,6 +833,11 @@ impl PlatformContextBuilder {
             PlatformContextBuilder::Cuda(executor) => {
                 PlatformContext::Cuda(telamon_cuda::Context::new(executor))
             }
+            #[cfg(feature = "cuda-model")]
+            PlatformContextBuilder::CudaModel(gpu) => PlatformContext::CudaModel(
+                telamon_cuda::ModelContext::new(gpu.clone()),
+                std::marker::PhantomData,
+            ),
         }
     }
 }
@@ -793,6 +848,8 @@ pub enum PlatformContext<'a> {
     X86(telamon_x86::Context, std::marker::PhantomData<&'a ()>),
     #[cfg(feature = "cuda")]
     Cuda(telamon_cuda::Context<'a>),
+    #[cfg(feature = "cuda-model")]
+    CudaModel(telamon_cuda::ModelContext, std::marker::PhantomData<&'a ()>),
 }
 
 impl<'a> PlatformContext<'a> {
@@ -814,10 +871,65 @@ impl<'a> PlatformContext<'a> {
                 let (bundle, context) = kernel.to_bundle(context, CublasHandle::new());
                 (bundle, context as &dyn Context)
             }
+            #[cfg(feature = "cuda-model")]
+            PlatformContext::CudaModel(context, _) => {
+                let (bundle, context) =
+                    kernel.to_bundle(context, ModelReference::default());
+                (bundle, context as &dyn Context)
+            }
+        }
+    }
+
+    /// Prints a function as CUDA C++ code.  Fails if the platform does not target CUDA GPUs.
+    pub fn print_cuda_c(
+        &self,
+        function: &telamon::codegen::Function,
+        out: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "cuda")]
+            PlatformContext::Cuda(context) => {
+                context.gpu().print_cuda_c(function, out);
+                Ok(())
+            }
+            #[cfg(feature = "cuda-model")]
+            PlatformContext::CudaModel(context, _) => {
+                context.gpu().print_cuda_c(function, out);
+                Ok(())
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = (function, out);
+                Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "CUDA C code can only be generated for CUDA platforms",
+                ))
+            }
         }
     }
 }
 
+/// Language of the code printed for a kernel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodeFormat {
+    /// The code the platform compiles: PTX for CUDA GPUs, C for x86.
+    Native,
+    /// CUDA C++ source, that can be compiled with NVRTC.  Only valid for CUDA platforms.
+    CudaC,
+}
+
+impl std::str::FromStr for CodeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "native" => CodeFormat::Native,
+            "cuda-c" => CodeFormat::CudaC,
+            _ => return Err(format!("invalid code format: {}", s)),
+        })
+    }
+}
+
 /// Path to a replay file.
 ///
 /// Replay files are .json files containing a serialized representation of actions to apply.  They
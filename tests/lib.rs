@@ -1,9 +1,11 @@
 //! Contains integration tests for Telamon.
 
+use telamon::codegen;
 use telamon::device::{fake, Context};
 use telamon::explorer;
 use telamon::helper;
 use telamon::ir::{self, Size, Type};
+use telamon::model::{self, roofline::Roofline};
 use telamon::search_space::*;
 
 /// Find the best candidate for a function and outputs it.
@@ -169,6 +171,44 @@ fn last_variable_order() {
     );
 }
 
+/// Ensures loop-invariant instructions are not recomputed in sequential loops.
+#[test]
+fn loop_invariant_order() {
+    let _ = env_logger::try_init();
+    let context = fake::Context::<fake::Device>::default();
+    let signature = ir::Signature::new("empty");
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    let inst0 = builder.mov(&1i32);
+    let inst1 = builder.add(&inst0, &2i32);
+    let dim0 = builder.open_dim(Size::new_const(4));
+    let inst2 = builder.mul(&inst1, &3i32);
+    builder.close_dim(&dim0);
+    let dim1 = builder.open_dim_ex(Size::new_const(16), DimKind::LOOP);
+    let _ = builder.add(&inst1, &dim1);
+    let mut space = builder.get();
+    assert_eq!(
+        space.domain().get_order(inst0.into(), dim1[0].into()),
+        Order::BEFORE
+    );
+    assert_eq!(
+        space.domain().get_order(inst1.into(), dim1[0].into()),
+        Order::BEFORE
+    );
+    // Instructions placed in a dimension by the kernel are not moved.
+    assert_eq!(
+        space.domain().get_order(inst2.into(), dim0[0].into()),
+        Order::INNER
+    );
+    // Invariant instructions can only be recomputed in parallel dimensions.
+    let action = Action::Order(inst1.into(), dim0[0].into(), Order::INNER);
+    space.apply_decisions(vec![action]).unwrap();
+    assert!(!space
+        .domain()
+        .get_dim_kind(dim0[0])
+        .intersects(DimKind::SEQUENTIAL));
+    gen_best(&context, space);
+}
+
 /// Ensures nested thread dimensions are packed and that their number is limited.
 #[test]
 fn nested_thread_dims() {
@@ -579,3 +619,129 @@ fn two_level_vectorization() {
     // Try to generate a fully specified candidate.
     gen_best(&context, space);
 }
+
+/// Ensures warm-start decisions are translated onto a space of a different size.
+#[test]
+fn warm_start_translate() {
+    use telamon::explorer::{choice::ActionEx, warm_start};
+
+    let _ = env_logger::try_init();
+    let context = fake::Context::<fake::Device>::default();
+    let signature = ir::Signature::new("empty");
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    let d0 = builder.open_dim(Size::new_const(8));
+    builder.mov(&0i32);
+    let space = builder.get();
+
+    let unroll = ActionEx::Action(Action::DimKind(d0[0], DimKind::UNROLL));
+    let actions = vec![
+        unroll.clone(),
+        ActionEx::Action(Action::DimKind(ir::DimId(42), DimKind::LOOP)),
+        ActionEx::Action(Action::DimKind(d0[0], DimKind::LOOP)),
+    ];
+    assert_eq!(warm_start::translate(&space, actions), vec![unroll]);
+}
+
+/// Ensures values consumed outside of the unrolled loop that produces them are counted once
+/// per iteration in the register pressure.
+#[test]
+fn register_pressure_unrolled() {
+    let _ = env_logger::try_init();
+    let context = fake::Context::<fake::Device>::default();
+    let signature = ir::Signature::new("empty");
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    let d0 = builder.open_dim_ex(Size::new_const(4), DimKind::UNROLL);
+    let inst0 = builder.mov(&0i32);
+    let d1 = builder.open_mapped_dim(&d0);
+    builder.mov(&inst0);
+    let space = builder.get_clone();
+    assert_eq!(register_pressure(&space), 1);
+    builder.order(&d0, &d1, !Order::MERGED);
+    assert_eq!(register_pressure(&builder.get()), 4);
+}
+
+/// Ensures the roofline counts the floating-point operations of all the tiles of a
+/// dimension, and ignores integer operations.
+#[test]
+fn roofline_flops() {
+    let _ = env_logger::try_init();
+    let context = fake::Context::<fake::Device>::default();
+    let signature = ir::Signature::new("empty");
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    builder.open_tiled_dim(Size::new_const(64), helper::TilingPattern::new_fixed(&[4]));
+    builder.mad(&1f32, &2f32, &3f32);
+    builder.add(&1i32, &2i32);
+    let space = builder.get();
+    let roofline = Roofline::new(space.ir_instance(), &context);
+    assert_eq!(roofline.flops, 128.);
+    assert_eq!(roofline.dram_bytes, 0.);
+    assert_eq!(roofline.arithmetic_intensity(), None);
+}
+
+/// Ensures the resource report counts the instructions of unrolled loops once per iteration.
+#[test]
+fn resource_report_unrolled() {
+    let _ = env_logger::try_init();
+    let context = fake::Context::<fake::Device>::default();
+    let signature = ir::Signature::new("empty");
+    let mut builder = helper::Builder::new(signature.into(), context.device());
+    builder.open_dim_ex(Size::new_const(4), DimKind::UNROLL);
+    builder.add(&1i32, &2i32);
+    let space = builder.get();
+    let bound = model::bound(&space, &context);
+    let implementation = explorer::local_selection::descend(
+        &explorer::config::ChoiceOrdering::default(),
+        explorer::config::NewNodeOrder::Api,
+        &context,
+        explorer::Candidate::new(space, bound),
+        std::f64::INFINITY,
+    )
+    .unwrap();
+    let function = codegen::Function::build(&implementation.space);
+    let report = context.device().resource_report(&function);
+    assert_eq!(report.code_size, 4);
+    assert_eq!(report.instruction_mix["add.i32"], 4);
+    assert_eq!(report.shared_mem_bytes, 0);
+    assert_eq!(report.num_barriers, 1);
+}
+
+/// Ensures implementations found in the evaluation cache are still checked when all
+/// implementations must be.
+#[test]
+fn eval_cache_check_all() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let _ = env_logger::try_init();
+    let context = fake::Context::<fake::Device>::default();
+    let signature = ir::Signature::new("eval_cache");
+    let space = {
+        let mut builder = helper::Builder::new(signature.into(), context.device());
+        builder.mov(&1f32);
+        builder.get()
+    };
+    let output_dir =
+        std::env::temp_dir().join(format!("telamon_eval_cache_{}", std::process::id()));
+    let config = explorer::Config {
+        output_dir: output_dir.to_str().unwrap().to_string(),
+        eval_cache: Some("eval_cache.json".to_string()),
+        check_all: true,
+        num_workers: 1,
+        algorithm: explorer::SearchAlgorithm::BoundOrder,
+        ..explorer::Config::default()
+    };
+    let num_checks = AtomicUsize::new(0);
+    let check = |_: &explorer::Candidate, _: &dyn Context| {
+        num_checks.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    };
+    // The second search only finds implementations from the first one in the cache.
+    for _ in 0..2 {
+        num_checks.store(0, Ordering::SeqCst);
+        explorer::find_best(&config, &context, vec![space.clone()], Some(&check));
+        assert!(num_checks.load(Ordering::SeqCst) > 0);
+    }
+    let eval_cache =
+        explorer::EvalCache::load(output_dir.join("eval_cache.json")).unwrap();
+    assert!(eval_cache.num_entries() > 0);
+    std::fs::remove_dir_all(output_dir).unwrap();
+}